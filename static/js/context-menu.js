@@ -1372,6 +1372,26 @@ var ContextMenu = new (class ContextMenu extends ContextMenuOrSubMenu {
             section: "jumps",
           }));
         }
+
+        // Explain why the edge exists.  Hits with a path are already covered
+        // by the jump above, so we only call out the other provenances.
+        for (const prov of edgeExtra.provenance || []) {
+          if (prov.path) {
+            continue;
+          }
+          const why = [prov.source, prov.detail, prov.rule && `rule ${prov.rule}`]
+            .filter(x => x).join(", ");
+          jumpMenuItems.push(new MenuItem({
+            html: this.fmt("Edge from _", why),
+            section: "jumps",
+          }));
+        }
+        for (const limit of edgeExtra.limits || []) {
+          jumpMenuItems.push(new MenuItem({
+            html: this.fmt("Traversal stopped: _", limit),
+            section: "jumps",
+          }));
+        }
       }
 
       const findJSModuleGlobalDef = sym => {
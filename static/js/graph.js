@@ -650,7 +650,7 @@ class Graph {
     const keyToEdge = new Map();
 
     let nextEdgeId = 0;
-    for (const { from: fromSym, to: toSym, kind, jumps, hovers } of input.edges) {
+    for (const { from: fromSym, to: toSym, kind, jumps, hovers, provenance = [], limits = [] } of input.edges) {
       const fromBlock = symToBlock.get(fromSym);
       const toBlock = symToBlock.get(toSym);
 
@@ -659,6 +659,8 @@ class Graph {
         const edge = keyToEdge.get(key);
         edge.jumps.push(...jumps);
         edge.hovers.push(...hovers);
+        edge.provenance.push(...provenance);
+        edge.limits.push(...limits);
         continue;
       }
 
@@ -674,6 +676,8 @@ class Graph {
         kind,
         jumps: jumps,
         hovers,
+        provenance: [...provenance],
+        limits: [...limits],
 
         predSide: "",
         succSide: "",
@@ -988,6 +992,8 @@ class Graph {
 
       extra.edges["Gide" + edge.id] = {
         jump: mergeJumps(edge.jumps),
+        provenance: edge.provenance,
+        limits: [...new Set(edge.limits)],
       };
     }
 
@@ -1079,6 +1079,8 @@ var Diagram = new (class Diagram {
         return "Binding slots are not traversed";
       case "DepthLimitOnOntologySlot":
         return "Ontology slots are not traversed ";
+      case "DepthLimitOnIpdlProtocol":
+        return "Managed protocols and messages are not traversed";
       case "DepthLimitOnSubclass":
         return "Subclasses are not traversed";
       case "DepthLimitOnSuper":
@@ -0,0 +1,21 @@
+#include <stdint.h>
+
+namespace field_layout {
+
+namespace platform_specific_size {
+
+using T1 = uint32_t;
+
+struct S {
+  T1 f1;
+  T1 f2;
+};
+
+S f() {
+  S s;
+  return s;
+}
+
+}  // namespace platform_specific_size
+
+}  // namespace field_layout
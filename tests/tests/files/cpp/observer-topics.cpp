@@ -0,0 +1,45 @@
+#include <string.h>
+
+// Minimal stand-ins for the observer service interfaces so that the
+// `[topics.observer]` ontology rule can connect notifications to their
+// handlers by way of the topic string literals.
+
+class nsIObserver {
+ public:
+  virtual void Observe(void* aSubject, const char* aTopic,
+                       const char16_t* aData) = 0;
+};
+
+class nsIObserverService {
+ public:
+  virtual void AddObserver(nsIObserver* aObserver, const char* aTopic,
+                           bool aOwnsWeak) = 0;
+  virtual void NotifyObservers(void* aSubject, const char* aTopic,
+                               const char16_t* aData) = 0;
+};
+
+class ShutdownObserver final : public nsIObserver {
+ public:
+  void Observe(void* aSubject, const char* aTopic,
+               const char16_t* aData) override {
+    if (!strcmp(aTopic, "test-shutdown")) {
+      mShutdown = true;
+    }
+  }
+
+  bool mShutdown = false;
+};
+
+void RegisterShutdownObserver(nsIObserverService* aService,
+                              ShutdownObserver* aObserver) {
+  aService->AddObserver(aObserver, "test-shutdown", false);
+}
+
+void NotifyShutdown(nsIObserverService* aService) {
+  aService->NotifyObservers(nullptr, "test-shutdown", nullptr);
+}
+
+// Nothing handles this topic, so it gets a symbol without handlers.
+void NotifyIdle(nsIObserverService* aService) {
+  aService->NotifyObservers(nullptr, "test-idle", nullptr);
+}
@@ -0,0 +1,27 @@
+// Minimal stand-ins for the necko listener interfaces.  The listener methods
+// are matched by the "request-observer" `[[rules]]` selector in
+// ontology-mapping.toml rather than by exact pretty identifiers.
+
+class nsIRequest {};
+
+class nsIRequestObserver {
+ public:
+  virtual void OnStartRequest(nsIRequest* aRequest) = 0;
+  virtual void OnStopRequest(nsIRequest* aRequest, int aStatus) = 0;
+};
+
+class DownloadListener final : public nsIRequestObserver {
+ public:
+  DownloadListener() = default;
+
+  void OnStartRequest(nsIRequest* aRequest) override { mStarted = true; }
+
+  void OnStopRequest(nsIRequest* aRequest, int aStatus) override {
+    mStatus = aStatus;
+  }
+
+  bool mStarted = false;
+  int mStatus = 0;
+};
+
+nsIRequestObserver* CreateDownloadListener() { return new DownloadListener(); }
@@ -0,0 +1,17 @@
+#include "mozilla/_ipdltest/TestReplyChild.h"
+#include "mozilla/_ipdltest/TestReplyParent.h"
+
+namespace mozilla::_ipdltest {
+
+void TestReplyChild::RecvPing(void (*aResolve)(int32_t)) { aResolve(42); }
+
+static void OnPing(int32_t aValue) {}
+
+static void OnPingRejected(int aReason) {}
+
+void TestReply(TestReplyParent* aActor) {
+  aActor->SendPing(OnPing, OnPingRejected);
+  aActor->SendPing();
+}
+
+}  // namespace mozilla::_ipdltest
@@ -0,0 +1,15 @@
+#ifndef mozilla_ipdltest_TestReplyChild_h
+#define mozilla_ipdltest_TestReplyChild_h
+
+#include <stdint.h>
+
+namespace mozilla::_ipdltest {
+
+class TestReplyChild {
+ public:
+  void RecvPing(void (*aResolve)(int32_t));
+};
+
+}  // namespace mozilla::_ipdltest
+
+#endif  // mozilla_ipdltest_TestReplyChild_h
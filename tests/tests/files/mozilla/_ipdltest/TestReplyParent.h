@@ -0,0 +1,23 @@
+#ifndef mozilla_ipdltest_TestReplyParent_h
+#define mozilla_ipdltest_TestReplyParent_h
+
+#include <stdint.h>
+
+namespace mozilla::_ipdltest {
+
+class PingPromise;
+
+// The tests tree doesn't run the IPDL compiler, so this stands in for the
+// generated PTestReplyParent.h.  Like the generated actor, a message with a
+// reply gets both a callback-taking and a promise-returning Send overload.
+class PTestReplyParent {
+ public:
+  void SendPing(void (*aResolve)(int32_t), void (*aReject)(int));
+  PingPromise* SendPing();
+};
+
+class TestReplyParent : public PTestReplyParent {};
+
+}  // namespace mozilla::_ipdltest
+
+#endif  // mozilla_ipdltest_TestReplyParent_h
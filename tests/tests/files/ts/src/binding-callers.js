@@ -0,0 +1,4 @@
+// tsMethod is bound to an XPIDL method, so calls to it end up in C++.
+function callTsMethod() {
+  return tsMethod(true, false);
+}
@@ -0,0 +1,9 @@
+(comment) @comment
+
+;; string_literal only has children for the quotes, so we need the whole node.
+[
+  (string_literal)
+  (raw_string_literal)
+  (char_literal)
+  (system_lib_string)
+] @string
@@ -0,0 +1,10 @@
+(comment) @comment
+
+(string) @string
+
+;; Only the fragments so that `${...}` substitutions, which can themselves
+;; contain template strings, get highlighted as code.
+(template_string
+  (string_fragment) @string)
+
+(regex) @regex
@@ -0,0 +1,11 @@
+[
+  (line_comment)
+  (block_comment)
+] @comment
+
+;; Only the content so that `$name` and `${...}` string templates get
+;; highlighted as code.
+[
+  (string_content)
+  (character_literal)
+] @string
@@ -0,0 +1,8 @@
+(comment) @comment
+
+;; Only the pieces so that f-string interpolations get highlighted as code.
+[
+  (string_start)
+  (string_content)
+  (string_end)
+] @string
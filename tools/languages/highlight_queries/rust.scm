@@ -0,0 +1,13 @@
+[
+  (line_comment)
+  (block_comment)
+] @comment
+
+[
+  (string_literal)
+  (raw_string_literal)
+  (char_literal)
+] @string
+
+;; The hand-rolled tokenizer thinks `'a` starts a char literal.
+(lifetime) @identifier
@@ -0,0 +1,22 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; Derived from the tags.scm file:
+; https://github.com/tree-sitter/tree-sitter-c-sharp/blob/master/queries/tags.scm
+;
+; The tags only cover classes, interfaces, methods and namespaces but the other
+; type declarations have the same shape.
+
+[
+  (namespace_declaration name: (_) @name body: (_) @body)
+  (class_declaration name: (_) @name body: (_) @body)
+  (interface_declaration name: (_) @name body: (_) @body)
+  (struct_declaration name: (_) @name body: (_) @body)
+  (record_declaration name: (_) @name body: (_) @body)
+  (enum_declaration name: (_) @name body: (_) @body)
+]
+
+[
+  (method_declaration name: (_) @name body: (_) @body)
+  (constructor_declaration name: (_) @name body: (_) @body)
+]
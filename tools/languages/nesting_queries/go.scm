@@ -0,0 +1,14 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; Derived from the tags.scm file:
+; https://github.com/tree-sitter/tree-sitter-go/blob/master/queries/tags.scm
+
+[
+  (function_declaration name: (_) @name body: (_) @body)
+  (method_declaration name: (_) @name body: (_) @body)
+]
+
+; Only struct and interface types have a body worth nesting; the tags also only
+; call these out.
+(type_spec name: (_) @name type: [(struct_type) (interface_type)] @body)
@@ -0,0 +1,11 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; Derived from the tags.scm file:
+; https://github.com/tree-sitter/tree-sitter-java/blob/master/queries/tags.scm
+
+(class_declaration name: (_) @name body: (_) @body)
+
+(method_declaration name: (_) @name body: (_) @body)
+
+(interface_declaration name: (_) @name body: (_) @body)
@@ -0,0 +1,8 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; There's no tags.scm in tree-sitter-kotlin and the bodies aren't fields.
+
+(class_declaration name: (_) @name (class_body) @body)
+
+(function_declaration name: (_) @name (function_body) @body)
@@ -0,0 +1,9 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; Derived from the tags.scm file:
+; https://github.com/tree-sitter/tree-sitter-python/blob/master/queries/tags.scm
+
+(class_definition name: (_) @name body: (_) @body)
+
+(function_definition name: (_) @name body: (_) @body)
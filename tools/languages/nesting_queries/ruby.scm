@@ -0,0 +1,20 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; Derived from the tags.scm file:
+; https://github.com/tree-sitter/tree-sitter-ruby/blob/master/queries/tags.scm
+
+; For `class Foo::Bar` SCIP defines `Bar`, so that's the name we want.
+[
+  (class
+    name: [(constant) @name (scope_resolution name: (_) @name)]
+    body: (_) @body)
+  (module
+    name: [(constant) @name (scope_resolution name: (_) @name)]
+    body: (_) @body)
+]
+
+[
+  (method name: (_) @name body: (_) @body)
+  (singleton_method name: (_) @name body: (_) @body)
+]
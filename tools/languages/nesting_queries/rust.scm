@@ -0,0 +1,21 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; Derived from the tags.scm file:
+; https://github.com/tree-sitter/tree-sitter-rust/blob/master/queries/tags.scm
+
+[
+  (struct_item name: (_) @name body: (_) @body)
+  (enum_item name: (_) @name body: (_) @body)
+  (union_item name: (_) @name body: (_) @body)
+  (function_item name: (_) @name body: (_) @body)
+  (trait_item name: (_) @name body: (_) @body)
+  (mod_item name: (_) @name body: (_) @body)
+]
+
+; TODO macro_definition lacks a body so the body needs to be the parent node
+; maybe?
+;
+; impl can also have a "trait" field, but symbol-wise I think the important
+; symbol for context is the struct type not the trait type.
+(impl_item type: (_) @name body: (_) @body)
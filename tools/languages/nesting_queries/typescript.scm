@@ -0,0 +1,42 @@
+; Nesting ranges for scip-indexer.  See the comment on `NestingQueries` in
+; `scip-indexer.rs` for the captures and properties used here.
+;
+; The typescript languages (typescript and tsx) extend the javascript language
+; so this is derived from both base tags.scm files:
+; https://github.com/tree-sitter/tree-sitter-javascript/blob/master/queries/tags.scm
+; https://github.com/tree-sitter/tree-sitter-typescript/blob/master/queries/tags.scm
+
+; ### from the JS tags
+(method_definition name: (_) @name body: (_) @body)
+
+; class_declaration becomes "class" if we add a "let blah = " ahead of it (and
+; it stops being a declaration).
+[
+  (class name: (_) @name body: (_) @body)
+  (class_declaration name: (_) @name body: (_) @body)
+]
+
+[
+  (function_expression name: (_) @name body: (_) @body)
+  (function_declaration name: (_) @name body: (_) @body)
+  (generator_function name: (_) @name body: (_) @body)
+  (generator_function_declaration name: (_) @name body: (_) @body)
+]
+
+; TODO: tags.scm has logic for lexical binds on arrow functions and this is
+; worth considering, although arguably this might also resemble the lambda
+; case.  Conceptually, the arrow functions should already exist within a
+; nesting scope, which raises the question of whether it's actually desirable
+; to treat them like C++ lambdas which we also currently fold in.  If we do,
+; the binding will be a local whose SCIP pretty is useless, so this would want
+; `(#set! nesting.pretty "source")`.
+; TODO: There's also property-defined arrow functions.
+
+; ### from the TS tags
+; function_signature and (abstract_)method_signature are skipped because they
+; lack a body.
+(abstract_class_declaration name: (_) @name body: (_) @body)
+
+(module name: (_) @name body: (_) @body)
+
+(interface_declaration name: (_) @name body: (_) @body)
@@ -0,0 +1,40 @@
+;; derived from the following plus our scip-indexer.rs decisions:
+;; - https://github.com/tree-sitter/tree-sitter-java/blob/master/queries/tags.scm
+;;
+;; double-comments like this are distinct from the original source.
+
+(((class_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "class"))
+
+(((interface_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "class"))
+
+;; records are just classes with some sugar as far as an outline is concerned.
+(((record_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "class"))
+
+(((annotation_type_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "class"))
+
+(((enum_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "enum"))
+
+(((method_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "method"))
+
+(((constructor_declaration
+    name: (identifier) @name) @container)
+    (#set! structure.kind "method"))
+
+;; Like for C++ fields, a declaration like `int a, b;` will only get the first
+;; declarator's name, which is fine.
+(((field_declaration
+    declarator: (variable_declarator
+      name: (identifier) @name)) @container)
+    (#set! structure.kind "field"))
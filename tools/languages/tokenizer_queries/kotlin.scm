@@ -0,0 +1,18 @@
+;; tree-sitter-kotlin-ng doesn't have a tags.scm, so this is derived from our
+;; scip-indexer.rs KOTLIN_NESTING decisions.
+;;
+;; `class_declaration` covers classes, interfaces, and enum classes since the
+;; grammar just distinguishes them by keyword.
+
+(((class_declaration
+    name: (_) @name) @container)
+    (#set! structure.kind "class"))
+
+;; `object` declarations are singleton classes.
+(((object_declaration
+    name: (_) @name) @container)
+    (#set! structure.kind "class"))
+
+(((function_declaration
+    name: (_) @name) @container)
+    (#set! structure.kind "method"))
@@ -1,10 +1,11 @@
 use async_trait::async_trait;
 use flate2::read::GzDecoder;
 use futures_core::stream::BoxStream;
-use serde_json::{Value, from_str};
+use serde_json::Value;
 use std::collections::BTreeMap;
-use std::fs;
 use std::io::Read;
+use std::ops::RangeInclusive;
+use std::sync::{Arc, OnceLock, Weak};
 use std::time::Instant;
 use tokio::fs::File;
 use tokio::io::AsyncReadExt;
@@ -18,10 +19,13 @@ use super::server_interface::{
 use super::{CommitInfo, TextMatches, TextMatchesByFile, TreeInfo};
 
 use crate::blame;
-use crate::file_format::analysis::{read_analyses, read_source};
+use crate::file_format::analysis::{AnalysisUnion, WithLocation, read_analyses, read_source};
+use crate::file_format::analysis_reader::{
+    AnalysisPredicate, read_matching_analysis, read_matching_raw_analysis,
+};
 use crate::file_format::bisectable_mmap::BisectableMmap;
 use crate::file_format::code_coverage_report;
-use crate::file_format::config::{TreeConfig, TreeConfigPaths, git_data, load};
+use crate::file_format::config::{GitData, TreeConfig, TreeConfigPaths, git_data, load};
 use crate::file_format::crossref::CrossrefData;
 use crate::file_format::identifiers::IdentMap;
 use crate::file_format::jumpref::JumprefData;
@@ -76,41 +80,6 @@ impl From<tonic::transport::Error> for ServerError {
     }
 }
 
-/// Read newline-delimited JSON that's possibly been gzip-compressed.
-async fn read_maybe_gzipped_ndjson_from_file(path: &str) -> Result<Vec<Value>> {
-    let used_path = if path.ends_with(".gz") && !fs::exists(path).unwrap_or(false) {
-        &path[..path.len() - 3]
-    } else {
-        path
-    };
-
-    let mut f = File::open(used_path).await?;
-
-    let mut raw_str = String::new();
-
-    // We read the entirety to a buffer because
-    // https://github.com/serde-rs/json/issues/160 suggests that the buffered
-    // reader performance is likely to be much worse.
-    //
-    // When we want to go async here,
-    // https://github.com/rust-lang/flate2-rs/pull/214 suggests that we want to
-    // use the `async-compression` crate.
-    if used_path.ends_with(".gz") {
-        let mut buffer = Vec::new();
-        f.read_to_end(&mut buffer).await?;
-        let mut gz = GzDecoder::new(&buffer[..]);
-
-        gz.read_to_string(&mut raw_str)?;
-    } else {
-        f.read_to_string(&mut raw_str).await?;
-    }
-
-    raw_str
-        .lines()
-        .map(|s| from_str(s).map_err(ServerError::from))
-        .collect()
-}
-
 /// Helper to ensure that our path-ish use of &str's does not ever try and do
 /// something that can escape a hackily constructed path.  We probably should
 /// move to using path types more directly.
@@ -140,14 +109,37 @@ pub struct LocalIndex {
     // likely the model we should use.
     config_paths: TreeConfigPaths,
     config_repo_path: String,
+    // The config file we were created from, so we can create servers for the
+    // other trees it defines.
+    config_path: String,
+    // The servers created alongside us by `make_all_local_servers`, which
+    // `other_tree` reuses instead of creating a new server.  Weak because the
+    // map holds us.
+    sibling_servers: Weak<LocalServerMap>,
     tree_name: String,
     // Note: IdentMap internally handles the identifiers db not existing
     ident_map: Option<IdentMap>,
+    // Same deal for the ontology topics db, which most trees won't have.
+    topic_map: Option<IdentMap>,
     // But for crossref, it's on us.
     crossref_lookup_map: Option<BisectableMmap<CrossrefData>>,
     jumpref_lookup_map: Option<BisectableMmap<JumprefData>>,
     file_lookup_map: FileLookupMap,
     head_info: Option<CommitInfo>,
+    blame_git: LazyGitData,
+}
+
+/// The `GitData` (with blame indexes) used by `get_blame_json`, loaded on first
+/// use and shared by all clones of the `LocalIndex`.
+#[derive(Clone, Default)]
+struct LazyGitData(Arc<OnceLock<Option<GitData>>>);
+
+impl std::fmt::Debug for LazyGitData {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_tuple("LazyGitData")
+            .field(&self.0.get().map(Option::is_some))
+            .finish()
+    }
 }
 
 impl LocalIndex {
@@ -179,7 +171,23 @@ impl AbstractServer for LocalIndex {
         Ok(self.head_info.clone())
     }
 
+    fn other_tree(&self, tree_name: &str) -> Result<Box<dyn AbstractServer + Send + Sync>> {
+        if let Some(servers) = self.sibling_servers.upgrade() {
+            return match servers.get(tree_name) {
+                Some(server) => Ok(server.clonify()),
+                None => Err(ServerError::StickyProblem(ErrorDetails {
+                    layer: ErrorLayer::BadInput,
+                    message: format!("bad tree name: {}", tree_name),
+                })),
+            };
+        }
+        make_local_server(&self.config_path, tree_name)
+    }
+
     fn translate_path(&self, root: SearchfoxIndexRoot, sf_path: &str) -> Result<String> {
+        // Commands like `validate-analysis` pass user-supplied paths straight
+        // through, so this has to be just as strict as the fetch methods.
+        let sf_path = self.normalize_and_validate_path(sf_path)?;
         match root {
             SearchfoxIndexRoot::CompressedAnalysis => Ok(format!(
                 "{}/analysis/{}.gz",
@@ -225,11 +233,32 @@ impl AbstractServer for LocalIndex {
         Ok(coverage_summary(Some(&git), &coverage_rev, norm_path))
     }
 
-    async fn fetch_raw_analysis<'a>(&self, sf_path: &str) -> Result<BoxStream<'a, Value>> {
+    async fn fetch_analysis<'a>(
+        &self,
+        sf_path: &str,
+        predicate: &AnalysisPredicate,
+    ) -> Result<BoxStream<'a, WithLocation<AnalysisUnion>>> {
         let norm_path = self.normalize_and_validate_path(sf_path)?;
         let full_path = self.translate_path(SearchfoxIndexRoot::CompressedAnalysis, norm_path)?;
-        let values = read_maybe_gzipped_ndjson_from_file(&full_path).await?;
-        Ok(Box::pin(tokio_stream::iter(values)))
+        let predicate = predicate.clone();
+        let records =
+            tokio::task::spawn_blocking(move || read_matching_analysis(&full_path, &predicate))
+                .await??;
+        Ok(Box::pin(tokio_stream::iter(records)))
+    }
+
+    async fn fetch_raw_analysis<'a>(
+        &self,
+        sf_path: &str,
+        predicate: &AnalysisPredicate,
+    ) -> Result<BoxStream<'a, Value>> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?;
+        let full_path = self.translate_path(SearchfoxIndexRoot::CompressedAnalysis, norm_path)?;
+        let predicate = predicate.clone();
+        let records =
+            tokio::task::spawn_blocking(move || read_matching_raw_analysis(&full_path, &predicate))
+                .await??;
+        Ok(Box::pin(tokio_stream::iter(records)))
     }
 
     async fn fetch_raw_source(&self, sf_path: &str) -> Result<String> {
@@ -333,6 +362,42 @@ impl AbstractServer for LocalIndex {
         Ok(raw_str)
     }
 
+    async fn fetch_blame(
+        &self,
+        sf_path: &str,
+        rev: Option<&str>,
+        lines: RangeInclusive<u32>,
+    ) -> Result<Value> {
+        let norm_path = self.normalize_and_validate_path(sf_path)?.to_string();
+        let rev = match (rev, &self.head_info) {
+            (Some(rev), _) => rev.to_string(),
+            (None, Some(head)) => head.rev.clone(),
+            (None, None) => "HEAD".to_string(),
+        };
+        let paths = self.config_paths.clone();
+        let blame_git = self.blame_git.clone();
+        // Unlike coverage, blame needs the blame indexes to map revisions to
+        // blame commits.  Those are expensive to load, so we load them the
+        // first time they're needed and keep them around.
+        tokio::task::spawn_blocking(move || {
+            let Some(git) = blame_git.0.get_or_init(|| git_data(&paths, true)) else {
+                return Err(ServerError::Unsupported);
+            };
+            blame::get_blame_json(git, &rev, &norm_path, lines).map_err(|err| {
+                let layer = match err {
+                    blame::BlameError::BadRevision => ErrorLayer::BadInput,
+                    blame::BlameError::NoSuchPath => ErrorLayer::DataLayer,
+                    blame::BlameError::BrokenBlame(_) => ErrorLayer::ServerLayer,
+                };
+                ServerError::StickyProblem(ErrorDetails {
+                    layer,
+                    message: err.to_string(),
+                })
+            })
+        })
+        .await?
+    }
+
     async fn crossref_lookup(&self, symbol: &str) -> Result<Option<CrossrefData>> {
         let now = Instant::now();
         let result = match &self.crossref_lookup_map {
@@ -394,6 +459,24 @@ impl AbstractServer for LocalIndex {
         }
     }
 
+    async fn search_topics(
+        &self,
+        needle: &str,
+        exact_match: bool,
+        ignore_case: bool,
+        match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        if let Some(topic_map) = &self.topic_map {
+            Ok(topic_map
+                .lookup(needle, exact_match, ignore_case, match_limit)
+                .into_iter()
+                .map(|ir| (ir.symbol, ir.id))
+                .collect())
+        } else {
+            Ok(vec![])
+        }
+    }
+
     async fn search_text(
         &self,
         pattern: &str,
@@ -489,10 +572,14 @@ impl AbstractServer for LocalIndex {
 fn fab_server(
     tree_config: TreeConfig,
     tree_name: &str,
+    config_path: &str,
     config_repo_path: &str,
+    sibling_servers: Weak<LocalServerMap>,
 ) -> Result<Box<dyn AbstractServer + Send + Sync>> {
     let ident_path = format!("{}/identifiers", tree_config.paths.index_path);
     let ident_map = IdentMap::new(&ident_path);
+    let topic_path = format!("{}/topics", tree_config.paths.index_path);
+    let topic_map = IdentMap::new(&topic_path);
 
     let crossref_path = format!("{}/crossref", tree_config.paths.index_path);
     let crossref_extra_path = format!("{}/crossref-extra", tree_config.paths.index_path);
@@ -535,12 +622,16 @@ fn fab_server(
         // We don't need the blame_map and hg_map (yet)
         config_paths: tree_config.paths,
         config_repo_path: config_repo_path.to_string(),
+        config_path: config_path.to_string(),
+        sibling_servers,
         tree_name: tree_name.to_string(),
         ident_map,
+        topic_map,
         crossref_lookup_map,
         jumpref_lookup_map,
         file_lookup_map,
         head_info,
+        blame_git: LazyGitData::default(),
     }))
 }
 
@@ -559,17 +650,46 @@ pub fn make_local_server(
         }
     };
 
-    fab_server(tree_config, tree_name, &config.config_repo_path)
+    fab_server(
+        tree_config,
+        tree_name,
+        config_path,
+        &config.config_repo_path,
+        Weak::new(),
+    )
 }
 
-pub fn make_all_local_servers(
-    config_path: &str,
-) -> Result<BTreeMap<String, Box<dyn AbstractServer + Send + Sync>>> {
+/// The servers for every tree in a config file, keyed by tree name.
+pub type LocalServerMap = BTreeMap<String, Box<dyn AbstractServer + Send + Sync>>;
+
+pub fn make_all_local_servers(config_path: &str) -> Result<Arc<LocalServerMap>> {
     let config = load(config_path, false, None, None, None);
-    let mut servers = BTreeMap::new();
-    for (tree_name, tree_config) in config.trees {
-        let server = fab_server(tree_config, &tree_name, &config.config_repo_path)?;
-        servers.insert(tree_name, server);
+    let mut error = None;
+    // Each server gets a handle to the map so that `other_tree` can hand out
+    // the existing servers.
+    let servers = Arc::new_cyclic(|sibling_servers| {
+        let mut servers = BTreeMap::new();
+        for (tree_name, tree_config) in config.trees {
+            match fab_server(
+                tree_config,
+                &tree_name,
+                config_path,
+                &config.config_repo_path,
+                sibling_servers.clone(),
+            ) {
+                Ok(server) => {
+                    servers.insert(tree_name, server);
+                }
+                Err(e) => {
+                    error = Some(e);
+                    break;
+                }
+            }
+        }
+        servers
+    });
+    match error {
+        Some(e) => Err(e),
+        None => Ok(servers),
     }
-    Ok(servers)
 }
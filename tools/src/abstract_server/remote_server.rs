@@ -1,11 +1,21 @@
 use async_trait::async_trait;
 use futures_core::stream::BoxStream;
 use serde_json::{Value, from_str};
+use std::io::Cursor;
+use std::ops::RangeInclusive;
 use url::{ParseError, Url};
 use ustr::Ustr;
 
 use crate::{
-    file_format::{code_coverage_report, crossref::CrossrefData, jumpref::JumprefData},
+    file_format::{
+        analysis::{AnalysisUnion, WithLocation},
+        analysis_reader::{
+            AnalysisPredicate, read_matching_analysis_from, read_matching_raw_analysis_from,
+        },
+        code_coverage_report,
+        crossref::CrossrefData,
+        jumpref::JumprefData,
+    },
     git_ops::RevisionCoverage,
 };
 
@@ -111,6 +121,10 @@ impl AbstractServer for RemoteServer {
         Ok(None)
     }
 
+    fn other_tree(&self, tree_name: &str) -> Result<Box<dyn AbstractServer + Send + Sync>> {
+        make_remote_server(self.server_base_url.clone(), tree_name)
+    }
+
     fn translate_path(&self, _root: SearchfoxIndexRoot, _sf_path: &str) -> Result<String> {
         // Remote servers don't have local filesystem paths.
         Err(ServerError::Unsupported)
@@ -127,14 +141,40 @@ impl AbstractServer for RemoteServer {
         Ok(None)
     }
 
-    async fn fetch_raw_analysis<'a>(&self, sf_path: &str) -> Result<BoxStream<'a, Value>> {
+    async fn fetch_analysis<'a>(
+        &self,
+        sf_path: &str,
+        predicate: &AnalysisPredicate,
+    ) -> Result<BoxStream<'a, WithLocation<AnalysisUnion>>> {
+        let url = self.raw_analysis_base_url.join(sf_path)?;
+        let raw_bytes = get(url).await?.bytes().await?;
+        let records =
+            read_matching_analysis_from(Box::new(Cursor::new(raw_bytes)), sf_path, predicate)
+                .map_err(|err| {
+                    ServerError::StickyProblem(ErrorDetails {
+                        layer: ErrorLayer::DataLayer,
+                        message: err.to_string(),
+                    })
+                })?;
+        Ok(Box::pin(tokio_stream::iter(records)))
+    }
+
+    async fn fetch_raw_analysis<'a>(
+        &self,
+        sf_path: &str,
+        predicate: &AnalysisPredicate,
+    ) -> Result<BoxStream<'a, Value>> {
         let url = self.raw_analysis_base_url.join(sf_path)?;
-        let raw_str = get(url).await?.text().await?;
-        let values: Result<Vec<Value>> = raw_str
-            .lines()
-            .map(|s| from_str(s).map_err(ServerError::from))
-            .collect();
-        Ok(Box::pin(tokio_stream::iter(values?)))
+        let raw_bytes = get(url).await?.bytes().await?;
+        let records =
+            read_matching_raw_analysis_from(Box::new(Cursor::new(raw_bytes)), sf_path, predicate)
+                .map_err(|err| {
+                ServerError::StickyProblem(ErrorDetails {
+                    layer: ErrorLayer::DataLayer,
+                    message: err.to_string(),
+                })
+            })?;
+        Ok(Box::pin(tokio_stream::iter(records)))
     }
 
     async fn fetch_formatted_lines(&self, _sf_path: &str) -> Result<(Vec<String>, String)> {
@@ -146,6 +186,15 @@ impl AbstractServer for RemoteServer {
         Err(ServerError::Unsupported)
     }
 
+    async fn fetch_blame(
+        &self,
+        _sf_path: &str,
+        _rev: Option<&str>,
+        _lines: RangeInclusive<u32>,
+    ) -> Result<Value> {
+        Err(ServerError::Unsupported)
+    }
+
     async fn fetch_html(&self, root: HtmlFileRoot, sf_path: &str) -> Result<String> {
         // We don't have access to raw templates, so just call that unsupported.
         // Note that we could special-case for "help.html" here since it does
@@ -202,6 +251,17 @@ impl AbstractServer for RemoteServer {
         Err(ServerError::Unsupported)
     }
 
+    async fn search_topics(
+        &self,
+        _needle: &str,
+        _exact_match: bool,
+        _ignore_case: bool,
+        _match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>> {
+        // Same rationale as crossref_lookup.
+        Err(ServerError::Unsupported)
+    }
+
     async fn search_text(
         &self,
         _pattern: &str,
@@ -1,12 +1,16 @@
 use async_trait::async_trait;
+use axum::Json;
 use axum::http::StatusCode;
 use axum::response::{IntoResponse, Response};
 use chrono::{DateTime, FixedOffset};
 use futures_core::stream::BoxStream;
 use serde::Serialize;
-use serde_json::Value;
+use serde_json::{Value, json};
+use std::ops::RangeInclusive;
 use ustr::{Ustr, ustr};
 
+use crate::file_format::analysis::{AnalysisUnion, WithLocation};
+use crate::file_format::analysis_reader::AnalysisPredicate;
 use crate::file_format::code_coverage_report;
 use crate::file_format::crossref::CrossrefData;
 use crate::file_format::jumpref::JumprefData;
@@ -65,10 +69,34 @@ impl From<liquid::Error> for ServerError {
     }
 }
 
+/// Errors are reported as a JSON object of the form
+/// `{ "error": { "kind", "layer", "message" } }` with a status code derived
+/// from the `ErrorLayer` so that clients can distinguish bad input and missing
+/// data from server problems.
 impl IntoResponse for ServerError {
     fn into_response(self) -> Response {
-        let body = format!("Error: {:#?}", self);
-        (StatusCode::INTERNAL_SERVER_ERROR, body).into_response()
+        let (status, kind, details) = match &self {
+            ServerError::StickyProblem(details) => {
+                let status = match details.layer {
+                    ErrorLayer::BadInput => StatusCode::BAD_REQUEST,
+                    ErrorLayer::DataLayer => StatusCode::NOT_FOUND,
+                    _ => StatusCode::INTERNAL_SERVER_ERROR,
+                };
+                (status, "sticky", Some(details))
+            }
+            ServerError::TransientProblem(details) => {
+                (StatusCode::SERVICE_UNAVAILABLE, "transient", Some(details))
+            }
+            ServerError::Unsupported => (StatusCode::NOT_IMPLEMENTED, "unsupported", None),
+        };
+        let body = json!({
+            "error": {
+                "kind": kind,
+                "layer": details.map(|d| format!("{:?}", d.layer)),
+                "message": details.map(|d| d.message.clone()),
+            }
+        });
+        (status, Json(body)).into_response()
     }
 }
 
@@ -205,7 +233,7 @@ pub struct FileMatches {
 }
 
 pub enum SearchfoxIndexRoot {
-    /// Already gzipped analysis files.  Note that `fetch_raw_analysis` exists
+    /// Already gzipped analysis files.  Note that `fetch_analysis` exists
     /// and should be used in preference to this for reading file contents.
     CompressedAnalysis,
     /// The root of the config repo.
@@ -290,6 +318,10 @@ pub trait AbstractServer {
     /// Return info about the latest commit, primarily for templating purposes.
     fn commit_info(&self) -> Result<Option<CommitInfo>>;
 
+    /// Create a server for another tree from the same configuration/server so
+    /// that commands can compare the trees.
+    fn other_tree(&self, tree_name: &str) -> Result<Box<dyn AbstractServer + Send + Sync>>;
+
     /// Convert a searchfox tree-local path into an absolute path on disk using
     /// the requested root.  This fundamentally only works for local indices.
     /// Note that many paths also have uncompressed (pre compress-outputs.sh)
@@ -314,9 +346,24 @@ pub trait AbstractServer {
         sf_path: &str,
     ) -> Result<Option<code_coverage_report::NodeMetadata>>;
 
-    /// Fetch the contents of the analysis file for the given searchfox
-    /// tree-local path, decompressing if it's compressed.
-    async fn fetch_raw_analysis<'a>(&self, sf_path: &str) -> Result<BoxStream<'a, Value>>;
+    /// Fetch the typed records of the analysis file for the given searchfox
+    /// tree-local path that match `predicate`, in file order.  See
+    /// `analysis_reader.rs` for how bad records are handled.
+    async fn fetch_analysis<'a>(
+        &self,
+        sf_path: &str,
+        predicate: &AnalysisPredicate,
+    ) -> Result<BoxStream<'a, WithLocation<AnalysisUnion>>>;
+
+    /// Fetch the records of the analysis file for the given searchfox
+    /// tree-local path that match `predicate` as the JSON objects they're
+    /// stored as, in file order.  Unlike `fetch_analysis`, records that don't
+    /// match the typed definitions are kept.
+    async fn fetch_raw_analysis<'a>(
+        &self,
+        sf_path: &str,
+        predicate: &AnalysisPredicate,
+    ) -> Result<BoxStream<'a, Value>>;
 
     /// Fetch the contents of a raw (not HTML rendered) source file
     /// corresponding to the indexed revision like you would get out of revision
@@ -339,6 +386,16 @@ pub trait AbstractServer {
     /// INDEX/dir sub-tree.
     async fn fetch_html(&self, root: HtmlFileRoot, sf_path: &str) -> Result<String>;
 
+    /// Fetch per-line blame information for the given range of lines of a
+    /// file as of `rev`, or the indexed revision if `rev` is `None`.  See
+    /// `blame::get_blame_json` for the format.
+    async fn fetch_blame(
+        &self,
+        sf_path: &str,
+        rev: Option<&str>,
+        lines: RangeInclusive<u32>,
+    ) -> Result<Value>;
+
     /// Retrieve the JSON contents of the crossref database for the given
     /// symbol.
     async fn crossref_lookup(&self, symbol: &str) -> Result<Option<CrossrefData>>;
@@ -394,6 +451,17 @@ pub trait AbstractServer {
         match_limit: usize,
     ) -> Result<Vec<(Ustr, Ustr)>>;
 
+    /// Like `search_identifiers` but searching the names of the synthetic
+    /// `TOPIC_` symbols produced by ontology `[topics]` rules, which are kept
+    /// out of the identifiers table.
+    async fn search_topics(
+        &self,
+        needle: &str,
+        exact_match: bool,
+        ignore_case: bool,
+        match_limit: usize,
+    ) -> Result<Vec<(Ustr, Ustr)>>;
+
     /// Given an re2 search pattern and additional config info, run a
     /// livegrep codesearch against an already-running codesearch server.  In
     /// the future while our rust code may be responsible for starting the
@@ -28,7 +28,8 @@ extern crate clap;
 use clap::Parser;
 use git2::{DiffFindOptions, ObjectType, Oid, Patch, Repository, Sort};
 use tools::blame::LineData;
-use tools::file_format::config::index_blame;
+use tools::file_format::config::{index_blame, ingest_git_fast_import_marks_file};
+use tools::revision_mapper::{CinnabarMapper, RevisionMapper, open_revision_map};
 
 #[derive(Parser)]
 struct BuildBlameCli {
@@ -83,6 +84,12 @@ struct BuildBlameCli {
     #[clap(long, value_parser)]
     old_cinnabar_repo_path: Option<String>,
 
+    /// Path to a revision mapping file or sorted map directory (see
+    /// `tools::revision_mapper`) to map git revisions to hg revisions instead
+    /// of asking git-cinnabar.  Takes precedence over no-cinnabar.
+    #[clap(long, value_parser, env = "REVISION_MAP")]
+    revision_map: Option<String>,
+
     /// Path to a mapping file to be consulted before old-cinnabar-repo-path to
     /// look up what old revisions should be associated with a given new
     /// revision.  This is intended to support the change from gecko-dev to
@@ -106,54 +113,6 @@ struct BuildBlameCli {
     commit_limit: usize,
 }
 
-fn get_hg_rev(helper: &mut Child, git_oid: &Oid) -> Option<String> {
-    writeln!(helper.stdin.as_mut().unwrap(), "{}", git_oid).unwrap();
-    let mut reader = BufReader::new(helper.stdout.as_mut().unwrap());
-    let mut result = String::new();
-    reader.read_line(&mut result).unwrap();
-    let hgrev = result.trim();
-    if hgrev.chars().all(|c| c == '0') {
-        return None;
-    }
-    Some(hgrev.to_string())
-}
-
-fn get_git_rev(helper: &mut Child, hg_rev: &str) -> Option<String> {
-    writeln!(helper.stdin.as_mut().unwrap(), "{}", hg_rev).unwrap();
-    let mut reader = BufReader::new(helper.stdout.as_mut().unwrap());
-    let mut result = String::new();
-    reader.read_line(&mut result).unwrap();
-    let gitrev = result.trim();
-    if gitrev.chars().all(|c| c == '0') {
-        return None;
-    }
-    Some(gitrev.to_string())
-}
-
-fn start_cinnabar_helper(git_repo: &Repository) -> Child {
-    Command::new("git")
-        .arg("cinnabar")
-        .arg("git2hg")
-        .arg("--batch")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .current_dir(git_repo.path())
-        .spawn()
-        .unwrap()
-}
-
-fn start_old_cinnabar_hg2git_helper(git_repo: &Repository) -> Child {
-    Command::new("git")
-        .arg("cinnabar")
-        .arg("hg2git")
-        .arg("--batch")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .current_dir(git_repo.path())
-        .spawn()
-        .unwrap()
-}
-
 /// Starts the git-fast-import subcommand, to which data
 /// is fed for adding to the blame repo. Refer to
 /// https://git-scm.com/docs/git-fast-import for detailed
@@ -194,71 +153,6 @@ enum BlameRepoCommit {
     Mark(usize),
 }
 
-/// Helper to parse the contents of a marks file written by git-fast-import's
-/// `--export-marks=` argument.
-///
-/// This is used by our blame/history preprocessing logic in order to be able to
-///  handle restarting processing when a (large) merge wasn't fully processing
-/// in a single run.  The problem that can happen in those cases is that
-/// `index_blame`/similar methods only revwalk a single branch of our
-/// transformed blame and until the merge commit is ingested, we will lose track
-/// of the first parent of the merge if we've begun processing the second parent
-/// of the merge (modulo any commits they may share).
-///
-/// As the most dramatic example, our revwalk of the blink repository's history
-/// up until the point of the merge of the "chromium" and "link trees" looks
-/// like: [(289k commits of chromium), (182k commits of blink), (merge commit)].
-/// As long as our import process doesn't fail, we're fine, but if we crash or
-/// stop because of our "limit" mechanism once we've moved onto the blink
-/// commits, then they won't be referenced by the single branch tag we're using
-/// in our processing.
-///
-/// To this end, we load up the marks file to augment the information from
-/// index_blame and make sure to pass `--import-marks-if-exists=` when we kick
-/// off git-fast-import.  Note that we currently don't actually reference the
-/// marker identifiers we get from the file since we do have the hashes
-/// available and it makes sense to just use that, so the main reason we want
-/// to tell git-fast-import to import the marks file is so that the contents of
-/// the file are maintained through successive runs rather than only containing
-/// revisions processed in the last run.
-///
-/// Note that another alternative would have been some kind of complex branch
-/// management.  See bug 1782285 for more context.
-fn ingest_git_fast_import_marks_file(
-    blame_repo: &Repository,
-    blame_map: &mut HashMap<Oid, BlameRepoCommit>,
-    marks_file: &str,
-) -> (u32, u32) {
-    // The number of revisions the marker file told us about.
-    let mut marker_revs_exist: u32 = 0;
-    // The number of marker revisions we used to fill in the blame_map.  If this
-    // is zero it means that `index_blame`
-    let mut marker_revs_needed: u32 = 0;
-
-    for line in marks_file.lines() {
-        let line_pieces = line.split_whitespace().collect::<Vec<_>>();
-        let marker_oid = Oid::from_str(line_pieces[1]).unwrap();
-
-        marker_revs_exist += 1;
-
-        let commit = blame_repo.find_commit(marker_oid).unwrap();
-
-        let msg = commit.message().unwrap();
-        // (This must match what's in `index_blame`.)
-        let pieces = msg.split_whitespace().collect::<Vec<_>>();
-
-        let orig_oid = Oid::from_str(pieces[1]).unwrap();
-        blame_map.entry(orig_oid).or_insert_with(|| {
-            marker_revs_needed += 1;
-            BlameRepoCommit::Commit(marker_oid)
-        });
-
-        // (we don't care about pieces[3] which is the hg commit if it's there)
-    }
-
-    (marker_revs_exist, marker_revs_needed)
-}
-
 impl fmt::Display for BlameRepoCommit {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
@@ -911,17 +805,19 @@ fn main() {
 
     let git_repo = Repository::open(&cli.git_repo_path).unwrap();
     let blame_repo = Repository::open(&cli.blame_repo_path).unwrap();
-    let mut hg_helper = if !cli.no_cinnabar {
-        Some(start_cinnabar_helper(&git_repo))
-    } else {
-        None
+    let hg_mapper: Option<Box<dyn RevisionMapper>> = match &cli.revision_map {
+        Some(path) => Some(open_revision_map(path).unwrap()),
+        None if !cli.no_cinnabar => Some(Box::new(CinnabarMapper::new(git_repo.path()))),
+        None => None,
     };
 
     let old_git_repo = cli
         .old_cinnabar_repo_path
         .map(|old_cinnabar_repo_path| Repository::open(old_cinnabar_repo_path).unwrap());
 
-    let mut old_hg_helper = old_git_repo.as_ref().map(start_old_cinnabar_hg2git_helper);
+    let old_hg_mapper = old_git_repo
+        .as_ref()
+        .map(|old_git_repo| CinnabarMapper::new(old_git_repo.path()));
 
     let oldrevs_from_newrev_map = if let Some(old_rev_map_path) = cli.old_revision_map {
         let contents = read_to_string(old_rev_map_path).unwrap();
@@ -951,8 +847,12 @@ fn main() {
 
     let mut prev_revs_done: usize = 0;
     if let Ok(contents) = std::fs::read_to_string(cli.marks_file.clone()) {
-        let (revs_found, revs_needed) =
-            ingest_git_fast_import_marks_file(&blame_repo, &mut blame_map, &contents);
+        let (revs_found, revs_needed) = ingest_git_fast_import_marks_file(
+            &blame_repo,
+            &mut blame_map,
+            &contents,
+            BlameRepoCommit::Commit,
+        );
         prev_revs_done = revs_found as usize;
         info!(
             "Marks file at {} (MARKS_FILE env overrides) had {} revs, {} were needed to augment branch data.",
@@ -1039,16 +939,12 @@ fn main() {
 
         rev_done += 1;
 
-        let hg_rev = match hg_helper {
-            Some(ref mut helper) => get_hg_rev(helper, git_oid),
-            None => None, // we don't support mapfiles any more.
-        };
+        let hg_rev = hg_mapper
+            .as_ref()
+            .and_then(|mapper| mapper.git_to_hg(*git_oid));
 
-        let oldrev_via_hg = match (&hg_rev, &mut old_hg_helper) {
-            // Note that we're actually giving it an hg rev to get a git rev;
-            // a hash is a hash, the difference is the old helper was spawned
-            // using hg2git
-            (Some(hgrev), Some(helper)) => get_git_rev(helper, hgrev),
+        let oldrev_via_hg = match (&hg_rev, &old_hg_mapper) {
+            (Some(hgrev), Some(mapper)) => mapper.hg_to_git(hgrev).map(|oid| oid.to_string()),
             _ => None,
         };
 
@@ -1164,9 +1060,10 @@ fn main() {
         }
     }
 
-    if let Some(mut helper) = hg_helper {
-        helper.kill().unwrap();
-    }
+    // Shut down any cinnabar helpers now rather than leaving them around while
+    // fast-import finishes.
+    drop(hg_mapper);
+    drop(old_hg_mapper);
 
     info!("Shutting down fast-import...");
     let exitcode = import_helper.wait().unwrap();
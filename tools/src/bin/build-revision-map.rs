@@ -0,0 +1,35 @@
+//! Convert a git/hg revision mapping file, where each line looks like
+//! `<git rev> <hg rev>`, into the sorted map directory format that a tree's
+//! `revision_map` config can point at.  Unlike a mapping file, a sorted map is
+//! bisected in place instead of being loaded into memory, which matters for
+//! trees with millions of revisions.
+//!
+//! A mapping file can be produced for a git-cinnabar clone with:
+//! `git rev-list --all > revs && git cinnabar git2hg --batch < revs | paste -d' ' revs -`
+
+use std::env;
+
+extern crate env_logger;
+extern crate tools;
+use tools::revision_mapper::build_sorted_map;
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    if args.len() != 2 {
+        eprintln!("Usage: build-revision-map <mapping-file> <output-dir>");
+        eprintln!("  This tool will write a sorted revision map to the output directory,");
+        eprintln!("  creating it if needed, from the given `<git rev> <hg rev>` lines.");
+        std::process::exit(1);
+    }
+
+    match build_sorted_map(&args[0], &args[1]) {
+        Ok(count) => println!("Wrote {} revisions to {}", count, args[1]),
+        Err(err) => {
+            eprintln!("{}", err);
+            std::process::exit(1);
+        }
+    }
+}
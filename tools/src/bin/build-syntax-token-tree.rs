@@ -3,7 +3,6 @@
 // synthetic files using the same tree-sitter derived information.  It is
 // intended to be subsequently processed by build-syntax-blame-tree.rs.
 
-extern crate env_logger;
 extern crate git2;
 #[macro_use]
 extern crate log;
@@ -19,44 +18,79 @@ use std::process::{Child, Command, Stdio};
 use std::sync::mpsc::{Receiver, Sender, channel};
 use std::thread;
 
-use git2::{ObjectType, Oid, Repository, Sort};
-use tools::file_format::config::index_blame;
+extern crate clap;
+use clap::Parser;
+
+use git2::{ObjectType, Repository, Sort};
+use tools::file_format::config::{index_blame, ingest_git_fast_import_marks_file};
 use tools::file_format::history::io_helpers::{
     read_record_file_contents, record_file_contents_to_string,
 };
 use tools::file_format::history::syntax_files_struct::{FileStructureHeader, FileStructureRow};
 use tools::file_format::history::syntax_symdex::{SymdexHeader, SymdexRecord};
+use tools::logging::{BuildProgress, init_build_logging};
+use tools::revision_mapper::{CinnabarMapper, RevisionMapper, open_revision_map};
 use tools::tree_sitter_support::cst_tokenizer::{HyperTokenized, hypertokenize_source_file};
 
-fn get_hg_rev(helper: &mut Child, git_oid: &Oid) -> Option<String> {
-    writeln!(helper.stdin.as_mut().unwrap(), "{}", git_oid).unwrap();
-    let mut reader = BufReader::new(helper.stdout.as_mut().unwrap());
-    let mut result = String::new();
-    reader.read_line(&mut result).unwrap();
-    let hgrev = result.trim();
-    if hgrev.chars().all(|c| c == '0') {
-        return None;
-    }
-    Some(hgrev.to_string())
-}
+#[derive(Parser)]
+struct BuildSyntaxTreeCli {
+    /// Path to the source git tree to build the syntax tree for.
+    #[clap(value_parser)]
+    git_repo_path: String,
 
-fn start_cinnabar_helper(git_repo: &Repository) -> Child {
-    Command::new("git")
-        .arg("cinnabar")
-        .arg("git2hg")
-        .arg("--batch")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .current_dir(git_repo.path())
-        .spawn()
-        .unwrap()
+    /// Path to the syntax git tree we are populating.
+    #[clap(value_parser)]
+    syntax_repo_path: String,
+
+    /// The name of the branch/git ref to use in both the source tree and the
+    /// syntax tree we are populating.  The default of HEAD will use the
+    /// current branch in both trees (and only works for non-bare trees).
+    #[clap(long, value_parser, env = "BLAME_REF", default_value = "HEAD")]
+    blame_ref: String,
+
+    /// Path to use for the git-fast-import marks file which lets us resume
+    /// where a previous run stopped; see `build-blame.rs` for details.  The
+    /// file is deleted on successful exit if no commit-limit was used.
+    #[clap(
+        long,
+        value_parser,
+        env = "SYNTAX_MARKS_FILE",
+        default_value = "/tmp/mozsearch-syntax-fast-import.marks"
+    )]
+    marks_file: String,
+
+    /// Disable asking git-cinnabar to map revisions to hg revisions.  Setting
+    /// the "CINNABAR" environment variable to 0 also disables it.
+    #[clap(long, value_parser)]
+    no_cinnabar: bool,
+
+    /// Path to a revision mapping file or sorted map directory to map
+    /// revisions to hg revisions instead of asking git-cinnabar.
+    #[clap(long, value_parser, env = "REVISION_MAP")]
+    revision_map: Option<String>,
+
+    /// Number of commits to transform before stopping; default of 0 means no
+    /// limit.  Use this to build history in bounded batches; each subsequent
+    /// run will pick up where the last one stopped.
+    #[clap(long, value_parser, env = "COMMIT_LIMIT", default_value = "0")]
+    commit_limit: usize,
+
+    /// Number of commits between git-fast-import checkpoints.  Work since the
+    /// last checkpoint is lost if we crash.
+    #[clap(
+        long,
+        value_parser,
+        env = "CHECKPOINT_INTERVAL",
+        default_value = "100000"
+    )]
+    checkpoint_interval: usize,
 }
 
 /// Starts the git-fast-import subcommand, to which data
 /// is fed for adding to the blame repo. Refer to
 /// https://git-scm.com/docs/git-fast-import for detailed
 /// documentation on git-fast-import.
-fn start_fast_import(git_repo: &Repository) -> Child {
+fn start_fast_import(git_repo: &Repository, marks_file: &str) -> Child {
     // Note that we use the `--force` flag here, because there
     // are cases where the blame repo branch we're building was
     // initialized from some other branch (e.g. gecko-dev beta
@@ -70,6 +104,8 @@ fn start_fast_import(git_repo: &Repository) -> Child {
         .arg("fast-import")
         .arg("--force")
         .arg("--quiet")
+        .arg(format!("--import-marks-if-exists={}", marks_file))
+        .arg(format!("--export-marks={}", marks_file))
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .current_dir(git_repo.path())
@@ -695,26 +731,21 @@ fn compute_thread_main(
 }
 
 fn main() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-
-    let args: Vec<_> = env::args().collect();
-    let git_repo_path = args[1].to_string();
-    let git_repo = Repository::open(&git_repo_path).unwrap();
-    let blame_repo = Repository::open(&args[2]).unwrap();
-    let use_cinnabar = env::var("CINNABAR").map_or(true, |v| v != "0");
-    let mut hg_helper = if use_cinnabar {
-        Some(start_cinnabar_helper(&git_repo))
-    } else {
-        None
+    init_build_logging();
+
+    let cli = BuildSyntaxTreeCli::parse();
+    let use_cinnabar = !cli.no_cinnabar && env::var("CINNABAR").map_or(true, |v| v != "0");
+
+    let git_repo = Repository::open(&cli.git_repo_path).unwrap();
+    let blame_repo = Repository::open(&cli.syntax_repo_path).unwrap();
+    let hg_mapper: Option<Box<dyn RevisionMapper>> = match &cli.revision_map {
+        Some(path) => Some(open_revision_map(path).unwrap()),
+        None if use_cinnabar => Some(Box::new(CinnabarMapper::new(git_repo.path()))),
+        None => None,
     };
-    let blame_ref = env::var("BLAME_REF").ok().unwrap_or("HEAD".to_string());
-    let commit_limit = env::var("COMMIT_LIMIT")
-        .ok()
-        .and_then(|x| x.parse::<usize>().ok())
-        .unwrap_or(0);
-
-    info!("Reading existing blame map of ref {}...", blame_ref);
-    let mut blame_map = if let Ok(oid) = blame_repo.refname_to_id(&blame_ref) {
+
+    info!("Reading existing blame map of ref {}...", cli.blame_ref);
+    let mut blame_map = if let Ok(oid) = blame_repo.refname_to_id(&cli.blame_ref) {
         let (blame_map, _, _) = index_blame(&blame_repo, Some(oid));
         blame_map
             .into_iter()
@@ -725,21 +756,41 @@ fn main() {
     };
     info!("  Blame map has {} existing entries.", blame_map.len());
 
+    let mut prev_revs_done: usize = 0;
+    if let Ok(contents) = std::fs::read_to_string(&cli.marks_file) {
+        let (revs_found, revs_needed) = ingest_git_fast_import_marks_file(
+            &blame_repo,
+            &mut blame_map,
+            &contents,
+            SyntaxRepoCommit::Commit,
+        );
+        prev_revs_done = revs_found as usize;
+        info!(
+            "Marks file at {} (SYNTAX_MARKS_FILE env overrides) had {} revs, {} were needed to augment branch data.",
+            cli.marks_file, revs_found, revs_needed
+        );
+    } else {
+        info!(
+            "No pre-existing marks file at {} (SYNTAX_MARKS_FILE env overrides).",
+            cli.marks_file
+        );
+    };
+
     let mut walk = git_repo.revwalk().unwrap();
     walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE).unwrap();
-    walk.push(git_repo.refname_to_id(&blame_ref).unwrap())
+    walk.push(git_repo.refname_to_id(&cli.blame_ref).unwrap())
         .unwrap();
     let mut revs_to_process = walk
         .map(|r| r.unwrap()) // walk produces Result<git2::Oid> so we unwrap to just the Oid
         .filter(|git_oid| !blame_map.contains_key(git_oid))
         .collect::<Vec<_>>();
-    if commit_limit > 0 && commit_limit < revs_to_process.len() {
+    if cli.commit_limit > 0 && cli.commit_limit < revs_to_process.len() {
         info!(
             "Truncating list of commits from {} to specified limit {}",
             revs_to_process.len(),
-            commit_limit
+            cli.commit_limit
         );
-        revs_to_process.truncate(commit_limit);
+        revs_to_process.truncate(cli.commit_limit);
     }
     let rev_count = revs_to_process.len();
 
@@ -749,7 +800,7 @@ fn main() {
     info!("Starting {} compute threads...", num_threads);
     let mut compute_threads = Vec::with_capacity(num_threads);
     for _ in 0..num_threads {
-        compute_threads.push(ComputeThread::new(&git_repo_path));
+        compute_threads.push(ComputeThread::new(&cli.git_repo_path));
     }
 
     // This tracks the index of the next revision in revs_to_process for which
@@ -769,7 +820,8 @@ fn main() {
     // if we ran out of requests because there were so few.
     assert!((compute_index % num_threads == 0) || compute_index == rev_count);
 
-    let mut import_helper = start_fast_import(&blame_repo);
+    let mut import_helper = start_fast_import(&blame_repo, &cli.marks_file);
+    let mut progress = BuildProgress::new("syntax", rev_count);
 
     // Tracks completion count and serves as the basis for the mark <idnum>
     // assigned to each commit.
@@ -793,10 +845,9 @@ fn main() {
 
         rev_done += 1;
 
-        let hg_rev = match hg_helper {
-            Some(ref mut helper) => get_hg_rev(helper, git_oid),
-            None => None, // we don't support mapfiles any more.
-        };
+        let hg_rev = hg_mapper
+            .as_ref()
+            .and_then(|mapper| mapper.git_to_hg(*git_oid));
 
         info!(
             "Transforming {} (hg {:?}) progress {}/{}",
@@ -819,9 +870,13 @@ fn main() {
             // https://git-scm.com/docs/git-fast-import#_commit
             // https://git-scm.com/docs/git-fast-import#_mark
             let mut import_stream = BufWriter::new(import_helper.stdin.as_mut().unwrap());
-            writeln!(import_stream, "commit {}", blame_ref).unwrap();
-            writeln!(import_stream, "mark :{}", rev_done).unwrap();
-            blame_map.insert(*git_oid, SyntaxRepoCommit::Mark(rev_done));
+            writeln!(import_stream, "commit {}", cli.blame_ref).unwrap();
+            // Because we maintain the markers through multiple consecutive runs,
+            // we need to add the count of imported marks so we don't clobber any
+            // existing ones.
+            let mark_id = rev_done + prev_revs_done;
+            writeln!(import_stream, "mark :{}", mark_id).unwrap();
+            blame_map.insert(*git_oid, SyntaxRepoCommit::Mark(mark_id));
 
             let mut write_role = |role: &str, sig: &git2::Signature| {
                 write!(import_stream, "{} ", role).unwrap();
@@ -896,15 +951,15 @@ fn main() {
 
         process_symdex_tree(symdex, &mut import_helper, &blame_parents).unwrap();
 
-        if rev_done % 100000 == 0 {
-            info!("Completed 100,000 commits, issuing checkpoint...");
+        progress.advance(&git_oid.to_string());
+
+        if cli.checkpoint_interval > 0 && rev_done % cli.checkpoint_interval == 0 {
+            info!("Completed {} commits, issuing checkpoint...", rev_done);
             writeln!(import_helper.stdin.as_mut().unwrap(), "checkpoint").unwrap();
         }
     }
 
-    if let Some(mut helper) = hg_helper {
-        helper.kill().unwrap();
-    }
+    drop(hg_mapper);
 
     info!("Shutting down fast-import...");
     let exitcode = import_helper.wait().unwrap();
@@ -913,4 +968,14 @@ fn main() {
     } else {
         info!("Fast-import exited with {:?}", exitcode.code());
     }
+
+    // As in build-blame.rs, the marks file is only retained if we need it for
+    // a subsequent run or if something went wrong.
+    if exitcode.success() && cli.commit_limit == 0 {
+        info!(
+            "Removing marks file {} after successful run because no limit was specified.",
+            cli.marks_file
+        );
+        let _ = std::fs::remove_file(&cli.marks_file);
+    }
 }
@@ -1,7 +1,6 @@
 // This binary consumes the "syntax" repo built by `build-syntax-token-tree.rs`
 // to produce the "timeline" repo.
 
-extern crate env_logger;
 extern crate git2;
 #[macro_use]
 extern crate log;
@@ -9,7 +8,7 @@ extern crate num_cpus;
 extern crate tools;
 
 use std::borrow::{Borrow, Cow};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fmt;
 use std::io::{BufRead, BufReader, BufWriter, Read, Write};
@@ -18,39 +17,93 @@ use std::process::{Child, Command, Stdio};
 use std::sync::mpsc::{Receiver, Sender, channel};
 use std::thread;
 
-use git2::{DiffFindOptions, ObjectType, Oid, Patch, Repository, Sort};
+extern crate clap;
+use clap::Parser;
+
+use git2::{DiffFindOptions, DiffOptions, ObjectType, Oid, Patch, Repository, Sort};
 use tools::blame::LineData;
-use tools::file_format::config::index_blame;
+use tools::file_format::config::{index_blame, ingest_git_fast_import_marks_file};
+use tools::file_format::history::io_helpers::read_record_file_contents;
+use tools::file_format::history::rev_summaries::{RevFileSummaryRecord, RevSummaryRecord};
+use tools::file_format::history::syntax_files_struct::{FileStructureHeader, FileStructureRow};
+use tools::logging::{BuildProgress, init_build_logging};
+use tools::revision_mapper::{CinnabarMapper, RevisionMapper, open_revision_map};
+
+#[derive(Parser)]
+struct BuildTimelineTreeCli {
+    /// Path to the "syntax" git tree to build the timeline for.
+    #[clap(value_parser)]
+    git_repo_path: String,
 
-fn get_hg_rev(helper: &mut Child, git_oid: &Oid) -> Option<String> {
-    writeln!(helper.stdin.as_mut().unwrap(), "{}", git_oid).unwrap();
-    let mut reader = BufReader::new(helper.stdout.as_mut().unwrap());
-    let mut result = String::new();
-    reader.read_line(&mut result).unwrap();
-    let hgrev = result.trim();
-    if hgrev.chars().all(|c| c == '0') {
-        return None;
-    }
-    Some(hgrev.to_string())
-}
-
-fn start_cinnabar_helper(git_repo: &Repository) -> Child {
-    Command::new("git")
-        .arg("cinnabar")
-        .arg("git2hg")
-        .arg("--batch")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .current_dir(git_repo.path())
-        .spawn()
-        .unwrap()
+    /// Path to the timeline git tree we are populating.
+    #[clap(value_parser)]
+    timeline_repo_path: String,
+
+    /// The name of the branch/git ref to use in both the source tree and the
+    /// timeline tree we are populating.  The default of HEAD will use the
+    /// current branch in both trees (and only works for non-bare trees).
+    #[clap(long, value_parser, env = "BLAME_REF", default_value = "HEAD")]
+    blame_ref: String,
+
+    /// Path to use for the git-fast-import marks file which lets us resume
+    /// where a previous run stopped; see `build-blame.rs` for details.  The
+    /// file is deleted on successful exit if no commit-limit was used.
+    #[clap(
+        long,
+        value_parser,
+        env = "TIMELINE_MARKS_FILE",
+        default_value = "/tmp/mozsearch-timeline-fast-import.marks"
+    )]
+    marks_file: String,
+
+    /// Disable asking git-cinnabar to map revisions to hg revisions.  Setting
+    /// the "CINNABAR" environment variable to 0 also disables it.
+    #[clap(long, value_parser)]
+    no_cinnabar: bool,
+
+    /// Path to a revision mapping file or sorted map directory to map
+    /// revisions to hg revisions instead of asking git-cinnabar.
+    #[clap(long, value_parser, env = "REVISION_MAP")]
+    revision_map: Option<String>,
+
+    /// Number of commits to transform before stopping; default of 0 means no
+    /// limit.  Use this to build history in bounded batches; each subsequent
+    /// run will pick up where the last one stopped.
+    #[clap(long, value_parser, env = "COMMIT_LIMIT", default_value = "0")]
+    commit_limit: usize,
+
+    /// Number of commits between git-fast-import checkpoints.  Work since the
+    /// last checkpoint is lost if we crash.
+    #[clap(
+        long,
+        value_parser,
+        env = "CHECKPOINT_INTERVAL",
+        default_value = "100000"
+    )]
+    checkpoint_interval: usize,
+
+    /// Directory to write per-revision summaries into, using the
+    /// `by-source-rev/aa/bb/<rev>` layout described in `rev_summaries.rs`.
+    /// No summaries are written if this is not specified.
+    #[clap(
+        long,
+        value_parser,
+        env = "REV_SUMMARIES_DIR",
+        requires = "source_repo_path"
+    )]
+    rev_summaries_dir: Option<String>,
+
+    /// Path to the source git tree the syntax tree was built from, used to
+    /// look up the commit messages for the revision summaries.
+    #[clap(long, value_parser)]
+    source_repo_path: Option<String>,
 }
 
 /// Starts the git-fast-import subcommand, to which data
 /// is fed for adding to the blame repo. Refer to
 /// https://git-scm.com/docs/git-fast-import for detailed
 /// documentation on git-fast-import.
-fn start_fast_import(git_repo: &Repository) -> Child {
+fn start_fast_import(git_repo: &Repository, marks_file: &str) -> Child {
     // Note that we use the `--force` flag here, because there
     // are cases where the blame repo branch we're building was
     // initialized from some other branch (e.g. gecko-dev beta
@@ -64,6 +117,8 @@ fn start_fast_import(git_repo: &Repository) -> Child {
         .arg("fast-import")
         .arg("--force")
         .arg("--quiet")
+        .arg(format!("--import-marks-if-exists={}", marks_file))
+        .arg(format!("--export-marks={}", marks_file))
         .stdin(Stdio::piped())
         .stdout(Stdio::piped())
         .current_dir(git_repo.path())
@@ -162,6 +217,22 @@ fn read_path_blob(
     Some(blob)
 }
 
+/// Return the oid of the commit we wrote with the given mark.  Documentation
+/// for the fast-import command used is at
+/// https://git-scm.com/docs/git-fast-import#_get_mark
+fn read_mark_oid(import_helper: &mut Child, mark_id: usize) -> String {
+    writeln!(
+        import_helper.stdin.as_mut().unwrap(),
+        "get-mark :{}",
+        mark_id
+    )
+    .unwrap();
+    let mut reader = BufReader::new(import_helper.stdout.as_mut().unwrap());
+    let mut result = String::new();
+    reader.read_line(&mut result).unwrap();
+    result.trim().to_string()
+}
+
 /// Sanitizes a path into a format that git-fast-import wants.
 fn sanitize(path: &Path) -> std::borrow::Cow<'_, str> {
     // Technically, I'm not sure what git-fast-import expects to happen with
@@ -541,6 +612,142 @@ struct DiffData {
     // The value in the map is a vec of line mappings as produced by the
     // `unmodified_lines` function.
     unmodified_lines: HashMap<(git2::Oid, PathBuf), Vec<(usize, usize)>>,
+    // The revision summary for this revision relative to its first parent if
+    // we were asked to write summaries.  The timeline revision and commit
+    // message still need to be filled in.
+    rev_summary: Option<RevSummaryRecord>,
+}
+
+/// Read the `FileStructureRow`s for the source file at `path` from the
+/// "files-struct" subtree of the given syntax repo tree.
+fn read_structure_rows(
+    git_repo: &git2::Repository,
+    tree: Option<&git2::Tree>,
+    path: &Path,
+) -> Vec<FileStructureRow> {
+    let Some(entry) = tree.and_then(|t| t.get_path(&Path::new("files-struct").join(path)).ok())
+    else {
+        return vec![];
+    };
+    let Ok(blob) = entry.to_object(git_repo).and_then(|o| o.peel_to_blob()) else {
+        return vec![];
+    };
+    let parsed: Option<(FileStructureHeader, Vec<FileStructureRow>)> =
+        read_record_file_contents(blob.content());
+    parsed.map(|(_, rows)| rows).unwrap_or_default()
+}
+
+/// Summarize the symbol-level changes of a syntax repo revision relative to its
+/// first parent by diffing the tokenized "files" subtree, including detecting
+/// symbols that were renamed and/or moved between files.
+fn compute_rev_summary(
+    git_repo: &git2::Repository,
+    commit: &git2::Commit,
+) -> Result<RevSummaryRecord, git2::Error> {
+    let tree = commit.tree()?;
+    let parent_tree = match commit.parent_count() {
+        0 => None,
+        _ => Some(commit.parent(0)?.tree()?),
+    };
+    let mut diff_opts = DiffOptions::new();
+    diff_opts.pathspec("files/");
+    let diff =
+        git_repo.diff_tree_to_tree(parent_tree.as_ref(), Some(&tree), Some(&mut diff_opts))?;
+
+    let read_blob = |oid: Oid| -> Result<Vec<u8>, git2::Error> {
+        if oid.is_zero() {
+            return Ok(vec![]);
+        }
+        Ok(git_repo.find_blob(oid)?.content().to_vec())
+    };
+
+    let mut file_deltas = BTreeMap::new();
+    let mut structure_rows = HashMap::new();
+    for delta in diff.deltas() {
+        let Some(files_path) = delta.new_file().path().or(delta.old_file().path()) else {
+            continue;
+        };
+        let Ok(path) = files_path.strip_prefix("files") else {
+            continue;
+        };
+        let old_contents = read_blob(delta.old_file().id())?;
+        let new_contents = read_blob(delta.new_file().id())?;
+        let patch = Patch::from_buffers(&old_contents, None, &new_contents, None, None)?;
+        let mut removed_lines = vec![];
+        let mut added_lines = vec![];
+        for i_hunk in 0..patch.num_hunks() {
+            for i_line in 0..patch.num_lines_in_hunk(i_hunk)? {
+                let line = patch.line_in_hunk(i_hunk, i_line)?;
+                let lines = match line.origin() {
+                    '-' => &mut removed_lines,
+                    '+' => &mut added_lines,
+                    _ => continue,
+                };
+                lines.push(
+                    String::from_utf8_lossy(line.content())
+                        .trim_end()
+                        .to_string(),
+                );
+            }
+        }
+
+        let old_rows = read_structure_rows(git_repo, parent_tree.as_ref(), path);
+        let new_rows = read_structure_rows(git_repo, Some(&tree), path);
+        let path_str = path.to_string_lossy().to_string();
+        file_deltas.insert(
+            path_str.clone(),
+            RevFileSummaryRecord::from_token_lines(
+                removed_lines.iter().map(String::as_str),
+                added_lines.iter().map(String::as_str),
+                &old_rows,
+                &new_rows,
+            ),
+        );
+        // Removed symbols only have a row in the parent revision.
+        for row in old_rows.into_iter().chain(new_rows) {
+            structure_rows.insert((path_str.clone(), row.pretty.clone()), row);
+        }
+    }
+
+    // The syntax repo commit message starts with "git <source rev>".
+    let source_rev = commit
+        .message()
+        .ok()
+        .and_then(|msg| msg.split_whitespace().nth(1))
+        .unwrap_or_default()
+        .to_string();
+    let when = commit.committer().when();
+    let iso_date = chrono::DateTime::from_timestamp(when.seconds(), 0)
+        .map(|dt| {
+            dt.with_timezone(&chrono::FixedOffset::east_opt(when.offset_minutes() * 60).unwrap())
+                .to_rfc3339()
+        })
+        .unwrap_or_default();
+
+    let mut summary = RevSummaryRecord {
+        source_rev,
+        syntax_rev: commit.id().to_string(),
+        timeline_rev: String::new(),
+        message: String::new(),
+        iso_date,
+        unmapped_author: commit.author().name().unwrap_or_default().to_string(),
+        file_deltas,
+        renames: vec![],
+    };
+    summary.detect_renames(&structure_rows);
+    Ok(summary)
+}
+
+/// Write a revision summary into the `by-source-rev/aa/bb/<rev>` hierarchy
+/// under `summaries_dir`.
+fn write_rev_summary(summaries_dir: &str, summary: &RevSummaryRecord) {
+    let rev = summary.source_rev.to_lowercase();
+    let mut dir = PathBuf::from(summaries_dir);
+    dir.push("by-source-rev");
+    dir.push(rev.get(0..2).unwrap_or("__"));
+    dir.push(rev.get(2..4).unwrap_or("__"));
+    std::fs::create_dir_all(&dir).unwrap();
+    std::fs::write(dir.join(&rev), serde_json::to_string(summary).unwrap()).unwrap();
 }
 
 // Does the CPU-intensive work required for blame computation of a given revision.
@@ -549,6 +756,7 @@ struct DiffData {
 fn compute_diff_data(
     git_repo: &git2::Repository,
     git_oid: &git2::Oid,
+    want_rev_summary: bool,
 ) -> Result<DiffData, git2::Error> {
     let commit = git_repo.find_commit(*git_oid).unwrap();
     let file_movement = if commit.parent_count() == 1 {
@@ -596,10 +804,16 @@ fn compute_diff_data(
         &mut unmodified_lines,
     )?;
 
+    let rev_summary = match want_rev_summary {
+        true => Some(compute_rev_summary(git_repo, &commit)?),
+        false => None,
+    };
+
     Ok(DiffData {
         revision: *git_oid,
         file_movement,
         unmodified_lines,
+        rev_summary,
     })
 }
 
@@ -609,12 +823,12 @@ struct ComputeThread {
 }
 
 impl ComputeThread {
-    fn new(git_repo_path: &str) -> Self {
+    fn new(git_repo_path: &str, want_rev_summary: bool) -> Self {
         let (query_tx, query_rx) = channel();
         let (response_tx, response_rx) = channel();
         let git_repo_path = git_repo_path.to_string();
         thread::spawn(move || {
-            compute_thread_main(query_rx, response_tx, git_repo_path);
+            compute_thread_main(query_rx, response_tx, git_repo_path, want_rev_summary);
         });
 
         ComputeThread {
@@ -642,35 +856,35 @@ fn compute_thread_main(
     query_rx: Receiver<git2::Oid>,
     response_tx: Sender<DiffData>,
     git_repo_path: String,
+    want_rev_summary: bool,
 ) {
     let git_repo = Repository::open(git_repo_path).unwrap();
     while let Ok(rev) = query_rx.recv() {
-        let result = compute_diff_data(&git_repo, &rev).unwrap();
+        let result = compute_diff_data(&git_repo, &rev, want_rev_summary).unwrap();
         response_tx.send(result).unwrap();
     }
 }
 
 fn main() {
-    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
-
-    let args: Vec<_> = env::args().collect();
-    let git_repo_path = args[1].to_string();
-    let git_repo = Repository::open(&git_repo_path).unwrap();
-    let blame_repo = Repository::open(&args[2]).unwrap();
-    let use_cinnabar = env::var("CINNABAR").map_or(true, |v| v != "0");
-    let mut hg_helper = if use_cinnabar {
-        Some(start_cinnabar_helper(&git_repo))
-    } else {
-        None
+    init_build_logging();
+
+    let cli = BuildTimelineTreeCli::parse();
+    let use_cinnabar = !cli.no_cinnabar && env::var("CINNABAR").map_or(true, |v| v != "0");
+
+    let git_repo = Repository::open(&cli.git_repo_path).unwrap();
+    let blame_repo = Repository::open(&cli.timeline_repo_path).unwrap();
+    let source_repo = cli
+        .source_repo_path
+        .as_ref()
+        .map(|path| Repository::open(path).unwrap());
+    let hg_mapper: Option<Box<dyn RevisionMapper>> = match &cli.revision_map {
+        Some(path) => Some(open_revision_map(path).unwrap()),
+        None if use_cinnabar => Some(Box::new(CinnabarMapper::new(git_repo.path()))),
+        None => None,
     };
-    let blame_ref = env::var("BLAME_REF").ok().unwrap_or("HEAD".to_string());
-    let commit_limit = env::var("COMMIT_LIMIT")
-        .ok()
-        .and_then(|x| x.parse::<usize>().ok())
-        .unwrap_or(0);
 
-    info!("Reading existing blame map of ref {}...", blame_ref);
-    let mut blame_map = if let Ok(oid) = blame_repo.refname_to_id(&blame_ref) {
+    info!("Reading existing blame map of ref {}...", cli.blame_ref);
+    let mut blame_map = if let Ok(oid) = blame_repo.refname_to_id(&cli.blame_ref) {
         let (blame_map, _, _) = index_blame(&blame_repo, Some(oid));
         blame_map
             .into_iter()
@@ -679,22 +893,43 @@ fn main() {
     } else {
         HashMap::new()
     };
+    info!("  Blame map has {} existing entries.", blame_map.len());
+
+    let mut prev_revs_done: usize = 0;
+    if let Ok(contents) = std::fs::read_to_string(&cli.marks_file) {
+        let (revs_found, revs_needed) = ingest_git_fast_import_marks_file(
+            &blame_repo,
+            &mut blame_map,
+            &contents,
+            BlameRepoCommit::Commit,
+        );
+        prev_revs_done = revs_found as usize;
+        info!(
+            "Marks file at {} (TIMELINE_MARKS_FILE env overrides) had {} revs, {} were needed to augment branch data.",
+            cli.marks_file, revs_found, revs_needed
+        );
+    } else {
+        info!(
+            "No pre-existing marks file at {} (TIMELINE_MARKS_FILE env overrides).",
+            cli.marks_file
+        );
+    };
 
     let mut walk = git_repo.revwalk().unwrap();
     walk.set_sorting(Sort::TOPOLOGICAL | Sort::REVERSE).unwrap();
-    walk.push(git_repo.refname_to_id(&blame_ref).unwrap())
+    walk.push(git_repo.refname_to_id(&cli.blame_ref).unwrap())
         .unwrap();
     let mut revs_to_process = walk
         .map(|r| r.unwrap()) // walk produces Result<git2::Oid> so we unwrap to just the Oid
         .filter(|git_oid| !blame_map.contains_key(git_oid))
         .collect::<Vec<_>>();
-    if commit_limit > 0 && commit_limit < revs_to_process.len() {
+    if cli.commit_limit > 0 && cli.commit_limit < revs_to_process.len() {
         info!(
             "Truncating list of commits from {} to specified limit {}",
             revs_to_process.len(),
-            commit_limit
+            cli.commit_limit
         );
-        revs_to_process.truncate(commit_limit);
+        revs_to_process.truncate(cli.commit_limit);
     }
     let rev_count = revs_to_process.len();
 
@@ -704,7 +939,10 @@ fn main() {
     info!("Starting {} compute threads...", num_threads);
     let mut compute_threads = Vec::with_capacity(num_threads);
     for _ in 0..num_threads {
-        compute_threads.push(ComputeThread::new(&git_repo_path));
+        compute_threads.push(ComputeThread::new(
+            &cli.git_repo_path,
+            cli.rev_summaries_dir.is_some(),
+        ));
     }
 
     // This tracks the index of the next revision in revs_to_process for which
@@ -724,7 +962,8 @@ fn main() {
     // if we ran out of requests because there were so few.
     assert!((compute_index % num_threads == 0) || compute_index == rev_count);
 
-    let mut import_helper = start_fast_import(&blame_repo);
+    let mut import_helper = start_fast_import(&blame_repo, &cli.marks_file);
+    let mut progress = BuildProgress::new("timeline", rev_count);
 
     // Tracks completion count and serves as the basis for the mark <idnum>
     // assigned to each commit.
@@ -736,7 +975,7 @@ fn main() {
         // thread is going to give us our result.
         // We assert to make sure it's the right one.
         let thread = &compute_threads[rev_done % num_threads];
-        let diff_data = thread.read_result();
+        let mut diff_data = thread.read_result();
         assert!(diff_data.revision == *git_oid);
 
         // If there are more revisions that we haven't requested yet, request
@@ -748,10 +987,9 @@ fn main() {
 
         rev_done += 1;
 
-        let hg_rev = match hg_helper {
-            Some(ref mut helper) => get_hg_rev(helper, git_oid),
-            None => None, // we don't support mapfiles any more.
-        };
+        let hg_rev = hg_mapper
+            .as_ref()
+            .and_then(|mapper| mapper.git_to_hg(*git_oid));
 
         info!(
             "Transforming {} (hg {:?}) progress {}/{}",
@@ -767,6 +1005,11 @@ fn main() {
             .map(|pid| blame_map[&pid])
             .collect::<Vec<_>>();
 
+        // Because we maintain the markers through multiple consecutive runs,
+        // we need to add the count of imported marks so we don't clobber any
+        // existing ones.
+        let mark_id = rev_done + prev_revs_done;
+
         // Scope the import_helper borrow
         {
             // Here we write out the metadata for a new commit to the blame repo.
@@ -774,9 +1017,9 @@ fn main() {
             // https://git-scm.com/docs/git-fast-import#_commit
             // https://git-scm.com/docs/git-fast-import#_mark
             let mut import_stream = BufWriter::new(import_helper.stdin.as_mut().unwrap());
-            writeln!(import_stream, "commit {}", blame_ref).unwrap();
-            writeln!(import_stream, "mark :{}", rev_done).unwrap();
-            blame_map.insert(*git_oid, BlameRepoCommit::Mark(rev_done));
+            writeln!(import_stream, "commit {}", cli.blame_ref).unwrap();
+            writeln!(import_stream, "mark :{}", mark_id).unwrap();
+            blame_map.insert(*git_oid, BlameRepoCommit::Mark(mark_id));
 
             let mut write_role = |role: &str, sig: &git2::Signature| {
                 write!(import_stream, "{} ", role).unwrap();
@@ -846,15 +1089,32 @@ fn main() {
         )
         .unwrap();
 
-        if rev_done % 100000 == 0 {
-            info!("Completed 100,000 commits, issuing checkpoint...");
+        if let (Some(summaries_dir), Some(mut summary)) =
+            (&cli.rev_summaries_dir, diff_data.rev_summary.take())
+        {
+            summary.timeline_rev = read_mark_oid(&mut import_helper, mark_id);
+            summary.message = source_repo
+                .as_ref()
+                .and_then(|repo| {
+                    Oid::from_str(&summary.source_rev)
+                        .ok()
+                        .map(|oid| (repo, oid))
+                })
+                .and_then(|(repo, oid)| repo.find_commit(oid).ok())
+                .and_then(|c| c.message().ok().map(str::to_string))
+                .unwrap_or_default();
+            write_rev_summary(summaries_dir, &summary);
+        }
+
+        progress.advance(&git_oid.to_string());
+
+        if cli.checkpoint_interval > 0 && rev_done % cli.checkpoint_interval == 0 {
+            info!("Completed {} commits, issuing checkpoint...", rev_done);
             writeln!(import_helper.stdin.as_mut().unwrap(), "checkpoint").unwrap();
         }
     }
 
-    if let Some(mut helper) = hg_helper {
-        helper.kill().unwrap();
-    }
+    drop(hg_mapper);
 
     info!("Shutting down fast-import...");
     let exitcode = import_helper.wait().unwrap();
@@ -863,4 +1123,14 @@ fn main() {
     } else {
         info!("Fast-import exited with {:?}", exitcode.code());
     }
+
+    // As in build-blame.rs, the marks file is only retained if we need it for
+    // a subsequent run or if something went wrong.
+    if exitcode.success() && cli.commit_limit == 0 {
+        info!(
+            "Removing marks file {} after successful run because no limit was specified.",
+            cli.marks_file
+        );
+        let _ = std::fs::remove_file(&cli.marks_file);
+    }
 }
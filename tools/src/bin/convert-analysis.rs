@@ -0,0 +1,80 @@
+//! Convert an analysis file between the NDJSON format the indexers produce and
+//! the compact binary encoding from `analysis_binary.rs`.  Both formats are
+//! accepted as input regardless of the direction, so this can also be used to
+//! normalize a JSON file.
+//!
+//! Input and output files ending in .gz are decompressed/compressed.  Records
+//! that don't match the typed analysis definitions can't be represented in
+//! the binary encoding and are dropped, as `read_analyses` would drop them;
+//! use `validate-analysis` to find out why.
+
+use std::env;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+
+extern crate env_logger;
+extern crate tools;
+use flate2::Compression;
+use flate2::write::GzEncoder;
+use tools::file_format::analysis::{WithLocation, read_analysis_records};
+use tools::file_format::analysis_binary::BinaryAnalysisWriter;
+
+enum OutputWriter<W: Write> {
+    Json(W),
+    Binary(BinaryAnalysisWriter<W>),
+}
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    if args.len() != 3 || (args[0] != "to-binary" && args[0] != "to-json") {
+        eprintln!("Usage: convert-analysis <to-binary|to-json> <input> <output>");
+        eprintln!("  This tool will convert the given analysis file (in either format)");
+        eprintln!("  into the binary or JSON analysis format.");
+        std::process::exit(1);
+    }
+    let to_binary = args[0] == "to-binary";
+    let input = &args[1];
+    let output = &args[2];
+
+    let file = File::create(output).unwrap();
+    let writer: Box<dyn Write> = if output.ends_with(".gz") {
+        Box::new(GzEncoder::new(file, Compression::default()))
+    } else {
+        Box::new(file)
+    };
+    let writer = BufWriter::new(writer);
+    let mut output_writer = if to_binary {
+        OutputWriter::Binary(BinaryAnalysisWriter::new(writer).unwrap())
+    } else {
+        OutputWriter::Json(writer)
+    };
+
+    let (mut converted, mut dropped) = (0, 0);
+    read_analysis_records(input, &mut |record, loc| {
+        let Some(data) = record.into_union() else {
+            dropped += 1;
+            return;
+        };
+        converted += 1;
+        match &mut output_writer {
+            OutputWriter::Binary(writer) => writer.write_record(&loc, &data).unwrap(),
+            OutputWriter::Json(writer) => {
+                let line = serde_json::to_string(&WithLocation { loc, data }).unwrap();
+                writeln!(writer, "{}", line).unwrap();
+            }
+        }
+    })
+    .unwrap();
+    match output_writer {
+        OutputWriter::Binary(writer) => writer.into_inner().flush().unwrap(),
+        OutputWriter::Json(mut writer) => writer.flush().unwrap(),
+    }
+
+    eprintln!(
+        "Converted {} records from [{}] to [{}], dropped {} records",
+        converted, input, output, dropped
+    );
+}
@@ -20,6 +20,8 @@ use serde::Serialize;
 use serde_json::Map;
 extern crate tools;
 use tools::file_format::analysis::AnalysisStructured;
+use tools::file_format::analysis::BindingImplKind;
+use tools::file_format::analysis::BindingSlotKind;
 use tools::file_format::analysis::BindingSlotLang;
 use tools::file_format::analysis::OntologySlotInfo;
 use tools::file_format::analysis::OntologySlotKind;
@@ -28,11 +30,14 @@ use tools::file_format::analysis::StructuredPointerInfo;
 use tools::file_format::analysis::StructuredTag;
 use tools::file_format::analysis::{
     AnalysisKind, AnalysisTarget, BindingSlotProps, Location, SearchResult,
-    StructuredBindingSlotInfo, collect_file_syms_from_target, read_analysis, read_structured,
-    read_target,
+    StructuredBindingSlotInfo, collect_file_syms_from_target, group_by_location, read_analysis,
+    read_source, read_target,
 };
 use tools::file_format::analysis_manglings::make_file_sym_from_path;
 use tools::file_format::analysis_manglings::split_pretty;
+use tools::file_format::analysis_reader::{
+    AnalysisPredicate, RecordType, read_matching_analysis, split_targets_and_structured,
+};
 use tools::file_format::config;
 use tools::file_format::config::{Config, FindSourceFile, TreeConfig};
 use tools::file_format::crossref::Callee;
@@ -42,7 +47,9 @@ use tools::file_format::crossref::FieldMemberUse;
 use tools::file_format::jumpref::convert_crossref_value_to_sym_info_rep;
 use tools::file_format::ontology_mapping::OntologyRunnableMode;
 use tools::file_format::ontology_mapping::{
-    OntologyLabelOwningClass, OntologyMappingIngestion, OntologyPointerKind,
+    OntologyLabelOwningClass, OntologyMappingIngestion, OntologyPointerKind, OntologyRule,
+    OntologySelector, find_string_literals, make_topic_pretty, make_topic_sym,
+    parse_string_literal,
 };
 use tools::file_format::repo_data_ingestion::RepoIngestion;
 use tools::logging::LoggedSpan;
@@ -350,6 +357,7 @@ fn make_subsystem(
 
             can_gc: None,
             gc_path: None,
+            ipdl_protocol: None,
         };
         structured_items.push(file_structured);
         pretty_items.push((file_sym, *path));
@@ -503,7 +511,22 @@ fn read_analysis_files_thread(
         let analysis_fname = format!("{}/analysis/{}", index_path, path);
         let fallback_file_sym: Ustr = ustr(&make_file_sym_from_path(path));
 
-        let analysis = read_analysis(&analysis_fname, &mut read_target);
+        // Read the file once for both the targets and the structured records.
+        // A missing analysis file is treated like an empty one, as
+        // `read_analyses` does.
+        let records = read_matching_analysis(
+            &analysis_fname,
+            &AnalysisPredicate::for_record_types(&[RecordType::Target, RecordType::Structured]),
+        )
+        .unwrap_or_else(|e| {
+            info!(
+                "Error trying to read analysis file [{}]: {}",
+                analysis_fname, e
+            );
+            vec![]
+        });
+        let (targets, structured) = split_targets_and_structured(records);
+        let analysis = group_by_location(targets);
 
         let file_syms = collect_file_syms_from_target(path, &analysis);
 
@@ -520,8 +543,7 @@ fn read_analysis_files_thread(
         // to allow us to ingest the structured records from SCIP indexing that
         // do not actually correspond to a source file.  This is the case for
         // Java imports from the JDK/Kotlin/Android runtimes.
-        let structured_analysis = read_analysis(&analysis_fname, &mut read_structured);
-        for datum in structured_analysis {
+        for datum in group_by_location(structured) {
             for piece in datum.data {
                 // If we don't have a location for the structured record then
                 // this is the SCIP external structured record case mentioned
@@ -817,7 +839,11 @@ fn read_analysis_files(
 
 type FieldOwningClassRules = UstrMap<OntologyLabelOwningClass>;
 
-fn read_field_owning_class_rules(ontology: &OntologyMappingIngestion) -> FieldOwningClassRules {
+fn read_field_owning_class_rules(
+    ontology: &OntologyMappingIngestion,
+    id_table: &IdTable,
+    meta_table: &MetaTable,
+) -> FieldOwningClassRules {
     // ### Extract field-processing rules to run over every class.
     let mut field_owning_class_rules: UstrMap<OntologyLabelOwningClass> = UstrMap::default();
 
@@ -833,6 +859,21 @@ fn read_field_owning_class_rules(ontology: &OntologyMappingIngestion) -> FieldOw
         field_owning_class_rules.insert(ustr(&type_prettied), label_owning_class.clone());
     }
 
+    // Selector rules get resolved to the classes they match and then use the
+    // same "class "-prefixed keying.
+    for selector_rule in &ontology.config.rules {
+        let Some(label_owning_class) = &selector_rule.rule.label_owning_class else {
+            continue;
+        };
+        for sym in resolve_ontology_selector(&selector_rule.selector, id_table, meta_table) {
+            let Some(meta) = meta_table.get(&sym) else {
+                continue;
+            };
+            let type_prettied = format!("class {}", meta.pretty);
+            field_owning_class_rules.insert(ustr(&type_prettied), label_owning_class.clone());
+        }
+    }
+
     field_owning_class_rules
 }
 
@@ -849,11 +890,20 @@ fn process_class_fields(
         if meta.kind.as_str() != "class" && meta.kind.as_str() != "struct" {
             continue;
         }
+        // SCIP symbols look like "S_rs_..." and we only want to map pointees to
+        // types from the same language.
+        let scip_prefix = meta
+            .sym
+            .strip_prefix("S_")
+            .and_then(|rest| rest.find('_'))
+            .map(|lang_len| &meta.sym[..lang_len + 3]);
 
         for field in &mut meta.fields {
             // In order to avoid getting confused by native types, require that we have some
-            // typesym.  We won't have a typesym for native types.
-            if field.type_sym.is_empty() {
+            // typesym.  We won't have a typesym for native types.  SCIP fields can lack a
+            // typesym for types from outside the index like `Rc`, but natives get filtered
+            // out below because they won't map to a type symbol.
+            if field.type_sym.is_empty() && scip_prefix.is_none() {
                 continue;
             }
 
@@ -865,9 +915,15 @@ fn process_class_fields(
                 }
             }
 
-            let (ptr_infos, type_labels) = ontology
-                .config
-                .maybe_parse_type_as_pointer(&field.type_pretty);
+            let (ptr_infos, type_labels) = if scip_prefix.is_some() {
+                ontology
+                    .config
+                    .maybe_parse_untagged_type_as_pointer(&field.type_pretty)
+            } else {
+                ontology
+                    .config
+                    .maybe_parse_type_as_pointer(&field.type_pretty)
+            };
             for label in type_labels {
                 meta.labels.insert(label);
             }
@@ -882,11 +938,17 @@ fn process_class_fields(
                 // We need to find the first symbol that's referring to a type.
                 // Conveniently, for C++, these will always start with `T_`,
                 // which is nice because we can't do a lookup in meta right now.
+                // For SCIP, type symbols end with the "#" type descriptor.
                 // TODO: Generalize to better understand what's a type, especially
                 // in JS.  It might be easiest to sidestep this problem by having
                 // the analyzer be emitting structured information for the field
                 // so that we're just working in symbol space in the first place.
-                let best_sym = pointee_syms.iter().find(|s| s.starts_with("T_"));
+                let best_sym = match scip_prefix {
+                    Some(prefix) => pointee_syms
+                        .iter()
+                        .find(|s| s.starts_with(prefix) && s.ends_with('#')),
+                    None => pointee_syms.iter().find(|s| s.starts_with("T_")),
+                };
                 let Some(sym) = best_sym else {
                     continue;
                 };
@@ -914,207 +976,283 @@ fn process_ontology_rules(
 ) {
     // ### Process Ontology Rules
     for (pretty_id, rule) in ontology.config.pretty.iter() {
-        // #### Labels we just slap on
-        if !rule.labels.is_empty()
-            && let Some(root_syms) = id_table.get(pretty_id)
-        {
-            for sym in root_syms {
-                let Some(sym_meta) = meta_table.get_mut(sym) else {
-                    continue;
-                };
-                for label in &rule.labels {
-                    sym_meta.labels.insert(*label);
+        let Some(root_syms) = id_table.get(pretty_id) else {
+            continue;
+        };
+        let root_syms: Vec<Ustr> = root_syms.iter().copied().collect();
+        apply_ontology_rule(
+            pretty_id,
+            rule,
+            &root_syms,
+            search_result_table,
+            id_table,
+            meta_table,
+        );
+    }
+
+    // ### Process Selector Rules
+    for selector_rule in &ontology.config.rules {
+        let root_syms = resolve_ontology_selector(&selector_rule.selector, id_table, meta_table);
+        info!(
+            " Selector rule {} matched {} symbols",
+            selector_rule.name,
+            root_syms.len()
+        );
+        apply_ontology_rule(
+            &selector_rule.name,
+            &selector_rule.rule,
+            &root_syms,
+            search_result_table,
+            id_table,
+            meta_table,
+        );
+    }
+}
+
+/// Resolve an `OntologySelector` to the (sorted) list of symbols it matches.
+fn resolve_ontology_selector(
+    selector: &OntologySelector,
+    id_table: &IdTable,
+    meta_table: &MetaTable,
+) -> Vec<Ustr> {
+    // The hierarchy-based criteria give us a (hopefully) small candidate set
+    // that we then filter; otherwise we need to consider every symbol.
+    let mut candidates: Option<UstrSet> = None;
+    for (root_pretty, via_overrides) in [
+        (selector.overrides_of, true),
+        (selector.subclasses_of, false),
+    ] {
+        let Some(root_pretty) = root_pretty else {
+            continue;
+        };
+        let mut descendants = UstrSet::default();
+        let mut pending: Vec<Ustr> = id_table
+            .get(&root_pretty)
+            .map(|syms| syms.iter().copied().collect())
+            .unwrap_or_default();
+        while let Some(sym) = pending.pop() {
+            let Some(meta) = meta_table.get(&sym) else {
+                continue;
+            };
+            let children = if via_overrides {
+                &meta.overridden_by_syms
+            } else {
+                &meta.subclass_syms
+            };
+            for child in children {
+                if descendants.insert(*child) {
+                    pending.push(*child);
                 }
             }
         }
+        candidates = Some(match candidates {
+            Some(existing) => existing.intersection(&descendants).copied().collect(),
+            None => descendants,
+        });
+    }
 
-        // #### Runnables
-        if let Some(runnable_mode) = &rule.runnable {
-            info!(" Processing pretty runnable rule for: {}", pretty_id);
-            if let Some(root_method_syms) = id_table.get(pretty_id) {
-                // The list of symbols to process for the runnable relationship.
-                // We process the root syms to find their descendants, but we
-                // don't actually process the root symbols.  These pending syms
-                // will both be directly processed and have their children
-                // appended as well.
-                let mut pending_method_syms = vec![];
-                let mut is_jvm = false;
-                for sym in root_method_syms {
-                    // XXX We should really have an easy way to figure out the
-                    // implementation language from the structured record.  Right
-                    // now we only really have that for binding slots.
-                    if sym.starts_with("S_jvm_") {
-                        is_jvm = true;
-                    }
-                    let Some(sym_meta) = meta_table.get(sym) else {
-                        continue;
-                    };
-                    for over in &sym_meta.overridden_by_syms {
-                        pending_method_syms.push(*over);
-                    }
-                }
-
-                info!("  found {} initial method syms", pending_method_syms.len());
-
-                // (this is LIFO traversal, which is fine for us)
-                while let Some(method_sym) = pending_method_syms.pop() {
-                    info!("  processing method sym: {}", method_sym);
+    match candidates {
+        Some(syms) => syms
+            .into_iter()
+            .filter(|sym| {
+                meta_table
+                    .get(sym)
+                    .is_some_and(|meta| selector.matches_meta(meta))
+            })
+            .sorted()
+            .collect(),
+        None => meta_table
+            .iter()
+            .filter(|(_, meta)| selector.matches_meta(meta))
+            .map(|(sym, _)| *sym)
+            .collect(),
+    }
+}
 
-                    // use the method to find its owning class
-                    let class_sym = if let Some(method_meta) = meta_table.get(&method_sym) {
-                        for over in &method_meta.overridden_by_syms {
-                            pending_method_syms.push(*over);
-                        }
+/// Apply a single ontology rule to the given root symbols which were either
+/// looked up from an exact pretty identifier or resolved from a selector.
+fn apply_ontology_rule(
+    rule_name: &str,
+    rule: &OntologyRule,
+    root_syms: &[Ustr],
+    search_result_table: &SearchResultTable,
+    id_table: &IdTable,
+    // read-write
+    meta_table: &mut MetaTable,
+) {
+    // #### Labels we just slap on
+    if !rule.labels.is_empty() {
+        for sym in root_syms {
+            let Some(sym_meta) = meta_table.get_mut(sym) else {
+                continue;
+            };
+            for label in &rule.labels {
+                sym_meta.labels.insert(*label);
+            }
+        }
+    }
 
-                        match method_meta.parent_sym {
-                            Some(p) => p,
-                            _ => continue,
-                        }
-                    } else {
-                        continue;
-                    };
+    // #### Runnables
+    if let Some(runnable_mode) = &rule.runnable {
+        info!(" Processing runnable rule for: {}", rule_name);
+        // The list of symbols to process for the runnable relationship.
+        // We process the root syms to find their descendants, but we
+        // don't actually process the root symbols.  These pending syms
+        // will both be directly processed and have their children
+        // appended as well.
+        let mut pending_method_syms = vec![];
+        let mut is_jvm = false;
+        for sym in root_syms {
+            // XXX We should really have an easy way to figure out the
+            // implementation language from the structured record.  Right
+            // now we only really have that for binding slots.
+            if sym.starts_with("S_jvm_") {
+                is_jvm = true;
+            }
+            let Some(sym_meta) = meta_table.get(sym) else {
+                continue;
+            };
+            for over in &sym_meta.overridden_by_syms {
+                pending_method_syms.push(*over);
+            }
+        }
 
-                    info!("  found class sym: {}", class_sym);
-
-                    // ### use the class to find its constructors
-                    let linkage_syms = match runnable_mode {
-                        OntologyRunnableMode::Constructor => {
-                            if let Some(class_meta) = meta_table.get(&class_sym) {
-                                let mut syms = vec![];
-                                // For C++ we expect the constructors to have the same name as the class;
-                                // currently for C++ we don't actually emit a special "props" "constructor"
-                                // value.
-                                //
-                                // For the JVM we expect constructors to have a pretty name of "<init>".
-                                let constructor_name: &str = if is_jvm {
-                                    "<init>"
-                                } else {
-                                    class_meta.pretty.rsplit("::").next().unwrap()
-                                };
-
-                                let constructor_pretty =
-                                    ustr(&format!("{}::{}", class_meta.pretty, constructor_name));
-                                for method in &class_meta.methods {
-                                    // Skip constructors that aren't known; this can happen for the copy
-                                    // constructor/etc.
-                                    if method.pretty == constructor_pretty
-                                        && search_result_table.contains_key(&method.sym)
-                                    {
-                                        syms.push(method.sym);
-                                    }
-                                }
-                                syms
-                            } else {
-                                continue;
-                            }
-                        }
-                        OntologyRunnableMode::Class => {
-                            vec![class_sym]
-                        }
-                    };
+        info!("  found {} initial method syms", pending_method_syms.len());
 
-                    info!("  found linkage syms: {:?}", linkage_syms);
+        // A selector can match both a method and its overrides, so we need to
+        // make sure we only process each method once or we would emit
+        // duplicate ontology slots.
+        let mut processed_method_syms = UstrSet::default();
 
-                    // ### mutate each of the constructors to have the ontology slot
-                    for con_sym in &linkage_syms {
-                        let Some(con_meta) = meta_table.get_mut(con_sym) else {
-                            continue;
-                        };
-                        // XXX we could track precedence for runnable rules so that
-                        // we could remove lower precedence relationships here.  This
-                        // would be relevant for WorkerRunnable.
+        // (this is LIFO traversal, which is fine for us)
+        while let Some(method_sym) = pending_method_syms.pop() {
+            if !processed_method_syms.insert(method_sym) {
+                continue;
+            }
+            info!("  processing method sym: {}", method_sym);
 
-                        con_meta.ontology_slots.push(OntologySlotInfo {
-                            slot_kind: OntologySlotKind::RunnableMethod,
-                            syms: vec![method_sym],
-                        });
-                    }
+            // use the method to find its owning class
+            let class_sym = if let Some(method_meta) = meta_table.get(&method_sym) {
+                for over in &method_meta.overridden_by_syms {
+                    pending_method_syms.push(*over);
+                }
 
-                    // ### mutate our method_sym to have the ontology slot to the constructors
-                    let Some(method_meta) = meta_table.get_mut(&method_sym) else {
-                        continue;
-                    };
-                    method_meta.ontology_slots.push(OntologySlotInfo {
-                        slot_kind: OntologySlotKind::RunnableConstructor,
-                        syms: linkage_syms,
-                    })
+                match method_meta.parent_sym {
+                    Some(p) => p,
+                    _ => continue,
                 }
-            }
-        }
+            } else {
+                continue;
+            };
 
-        // #### Class Labeling (Some)
-        //
-        // Some rules are processed as we process structured fields above.
+            info!("  found class sym: {}", class_sym);
+
+            // ### use the class to find its constructors
+            let linkage_syms = match runnable_mode {
+                OntologyRunnableMode::Constructor => {
+                    if let Some(class_meta) = meta_table.get(&class_sym) {
+                        let mut syms = vec![];
+                        // For C++ we expect the constructors to have the same name as the class;
+                        // currently for C++ we don't actually emit a special "props" "constructor"
+                        // value.
+                        //
+                        // For the JVM we expect constructors to have a pretty name of "<init>".
+                        let constructor_name: &str = if is_jvm {
+                            "<init>"
+                        } else {
+                            class_meta.pretty.rsplit("::").next().unwrap()
+                        };
 
-        if let Some(label_rule) = &rule.label_containing_class {
-            info!(
-                " Processing pretty label_containing_class for: {}",
-                pretty_id
-            );
-            if let Some(root_class_syms) = id_table.get(pretty_id) {
-                let mut investigate_class_syms = vec![];
-                // We don't care about the root itself, just its subclasses.
-                for sym in root_class_syms {
-                    let Some(sym_meta) = meta_table.get(sym) else {
+                        let constructor_pretty =
+                            ustr(&format!("{}::{}", class_meta.pretty, constructor_name));
+                        for method in &class_meta.methods {
+                            // Skip constructors that aren't known; this can happen for the copy
+                            // constructor/etc.
+                            if method.pretty == constructor_pretty
+                                && search_result_table.contains_key(&method.sym)
+                            {
+                                syms.push(method.sym);
+                            }
+                        }
+                        syms
+                    } else {
                         continue;
-                    };
-                    for sub in &sym_meta.subclass_syms {
-                        investigate_class_syms.push(*sub);
                     }
                 }
+                OntologyRunnableMode::Class => {
+                    vec![class_sym]
+                }
+            };
 
-                while let Some(class_sym) = investigate_class_syms.pop() {
-                    let Some(sym_meta) = meta_table.get(&class_sym) else {
-                        continue;
-                    };
+            info!("  found linkage syms: {:?}", linkage_syms);
 
-                    for sub in &sym_meta.subclass_syms {
-                        investigate_class_syms.push(*sub);
-                    }
+            // ### mutate each of the constructors to have the ontology slot
+            for con_sym in &linkage_syms {
+                let Some(con_meta) = meta_table.get_mut(con_sym) else {
+                    continue;
+                };
+                // XXX we could track precedence for runnable rules so that
+                // we could remove lower precedence relationships here.  This
+                // would be relevant for WorkerRunnable.
+
+                // A method can also be reached by more than one rule.
+                if con_meta.ontology_slots.iter().any(|slot| {
+                    slot.slot_kind == OntologySlotKind::RunnableMethod
+                        && slot.syms.contains(&method_sym)
+                }) {
+                    continue;
+                }
+                con_meta.ontology_slots.push(OntologySlotInfo {
+                    slot_kind: OntologySlotKind::RunnableMethod,
+                    syms: vec![method_sym],
+                    rule: Some(ustr(rule_name)),
+                });
+            }
 
-                    // The structured record currently doesn't have a reference
-                    // to its containing symbol; we need to pop the last pretty
-                    // segment and perform a lookup.
-                    let (pieces, delim) = split_pretty(&sym_meta.pretty, &sym_meta.sym);
-                    let Some((_, containing_pieces)) = pieces.split_last() else {
-                        continue;
-                    };
-                    let containing_pretty = containing_pieces.join(delim);
-                    let containing_pretty_ustr = ustr(&containing_pretty);
-                    let Some(containing_syms) = id_table.get(&containing_pretty_ustr) else {
-                        continue;
-                    };
-                    for sym in containing_syms {
-                        let Some(containing_meta) = meta_table.get_mut(sym) else {
-                            continue;
-                        };
-                        for rule in &label_rule.labels {
-                            containing_meta.labels.insert(rule.label);
-                        }
+            // ### mutate our method_sym to have the ontology slot to the constructors
+            let Some(method_meta) = meta_table.get_mut(&method_sym) else {
+                continue;
+            };
+            // If another rule already linked this method, merge our linkage
+            // syms into its slot (sorted so the result doesn't depend on rule
+            // order) rather than dropping them, since the rules may use
+            // different modes.
+            if let Some(slot) = method_meta
+                .ontology_slots
+                .iter_mut()
+                .find(|slot| slot.slot_kind == OntologySlotKind::RunnableConstructor)
+            {
+                if slot.rule != Some(ustr(rule_name)) {
+                    warn!(
+                        "  method sym {} matched runnable rules {:?} and {}; merging their linkage syms",
+                        method_sym, slot.rule, rule_name
+                    );
+                }
+                for sym in linkage_syms {
+                    if !slot.syms.contains(&sym) {
+                        slot.syms.push(sym);
                     }
                 }
+                slot.syms.sort_unstable();
+                continue;
             }
+            method_meta.ontology_slots.push(OntologySlotInfo {
+                slot_kind: OntologySlotKind::RunnableConstructor,
+                syms: linkage_syms,
+                rule: Some(ustr(rule_name)),
+            })
         }
+    }
 
-        // #### Field Labeling
-        //
-        // We start from an ancestral class and find all of its subclasses and all of their fields.
-        // For each field, we check its uses and see if they match the rules.  If so, we will plan
-        // to add a label to the field on its class.  (Currently we do not do anythign to the
-        // structured info for field symbol itself.)
-        let Some(label_rule) = &rule.label_containing_class_field_uses else {
-            continue;
-        };
-        info!(
-            " Processing pretty label_containing_class_field_uses rule for: {}",
-            pretty_id
-        );
-        let Some(root_class_syms) = id_table.get(pretty_id) else {
-            continue;
-        };
+    // #### Class Labeling (Some)
+    //
+    // Some rules are processed as we process structured fields above.
+
+    if let Some(label_rule) = &rule.label_containing_class {
+        info!(" Processing label_containing_class rule for: {}", rule_name);
         let mut investigate_class_syms = vec![];
         // We don't care about the root itself, just its subclasses.
-        for sym in root_class_syms {
+        for sym in root_syms {
             let Some(sym_meta) = meta_table.get(sym) else {
                 continue;
             };
@@ -1148,25 +1286,419 @@ fn process_ontology_rules(
                 let Some(containing_meta) = meta_table.get_mut(sym) else {
                     continue;
                 };
-                for field in &mut containing_meta.fields {
-                    let Some(kind_map) = search_result_table.get(&field.sym) else {
-                        continue;
-                    };
-                    let Some(path_hits) = kind_map.get(&AnalysisKind::Use) else {
+                for rule in &label_rule.labels {
+                    containing_meta.labels.insert(rule.label);
+                }
+            }
+        }
+    }
+
+    // #### Field Labeling
+    //
+    // We start from an ancestral class and find all of its subclasses and all of their fields.
+    // For each field, we check its uses and see if they match the rules.  If so, we will plan
+    // to add a label to the field on its class.  (Currently we do not do anythign to the
+    // structured info for field symbol itself.)
+    let Some(label_rule) = &rule.label_containing_class_field_uses else {
+        return;
+    };
+    info!(
+        " Processing label_containing_class_field_uses rule for: {}",
+        rule_name
+    );
+    let mut investigate_class_syms = vec![];
+    // We don't care about the root itself, just its subclasses.
+    for sym in root_syms {
+        let Some(sym_meta) = meta_table.get(sym) else {
+            continue;
+        };
+        for sub in &sym_meta.subclass_syms {
+            investigate_class_syms.push(*sub);
+        }
+    }
+
+    while let Some(class_sym) = investigate_class_syms.pop() {
+        let Some(sym_meta) = meta_table.get(&class_sym) else {
+            continue;
+        };
+
+        for sub in &sym_meta.subclass_syms {
+            investigate_class_syms.push(*sub);
+        }
+
+        // The structured record currently doesn't have a reference
+        // to its containing symbol; we need to pop the last pretty
+        // segment and perform a lookup.
+        let (pieces, delim) = split_pretty(&sym_meta.pretty, &sym_meta.sym);
+        let Some((_, containing_pieces)) = pieces.split_last() else {
+            continue;
+        };
+        let containing_pretty = containing_pieces.join(delim);
+        let containing_pretty_ustr = ustr(&containing_pretty);
+        let Some(containing_syms) = id_table.get(&containing_pretty_ustr) else {
+            continue;
+        };
+        for sym in containing_syms {
+            let Some(containing_meta) = meta_table.get_mut(sym) else {
+                continue;
+            };
+            for field in &mut containing_meta.fields {
+                let Some(kind_map) = search_result_table.get(&field.sym) else {
+                    continue;
+                };
+                let Some(path_hits) = kind_map.get(&AnalysisKind::Use) else {
+                    continue;
+                };
+                for hits in path_hits.values() {
+                    for hit in hits {
+                        for rule in &label_rule.labels {
+                            if hit.context.ends_with(rule.context_sym_suffix.as_str()) {
+                                field.labels.insert(rule.label);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Find the symbols for the given exact pretty identifier plus all of the
+/// symbols that transitively override them.
+fn collect_syms_and_overrides(
+    pretty: &Ustr,
+    id_table: &IdTable,
+    meta_table: &MetaTable,
+) -> UstrSet {
+    let mut syms = UstrSet::default();
+    let mut pending: Vec<Ustr> = id_table
+        .get(pretty)
+        .map(|roots| roots.iter().copied().collect())
+        .unwrap_or_default();
+    while let Some(sym) = pending.pop() {
+        if !syms.insert(sym) {
+            continue;
+        }
+        if let Some(meta) = meta_table.get(&sym) {
+            pending.extend(meta.overridden_by_syms.iter().copied());
+        }
+    }
+    syms
+}
+
+/// Read the given source file as (untrimmed) lines.
+fn read_source_lines(find_source_file: &FindSourceFile, path: &Ustr) -> Option<Vec<String>> {
+    let source_file = File::open(find_source_file.find(path)).ok()?;
+    Some(
+        BufReader::new(source_file)
+            .lines()
+            .map(|l| l.unwrap_or_default())
+            .collect(),
+    )
+}
+
+fn make_topic_structured(
+    namespace: Ustr,
+    sym: Ustr,
+    pretty: Ustr,
+    handler_syms: Vec<Ustr>,
+) -> AnalysisStructured {
+    let mut ontology_slots = vec![];
+    if !handler_syms.is_empty() {
+        ontology_slots.push(OntologySlotInfo {
+            slot_kind: OntologySlotKind::TopicHandler,
+            syms: handler_syms,
+            rule: Some(namespace),
+        });
+    }
+    AnalysisStructured {
+        structured: StructuredTag::Structured,
+        pretty,
+        sym,
+        type_pretty: None,
+        kind: ustr("topic"),
+        subsystem: None,
+        parent_sym: None,
+        slot_owner: None,
+        impl_kind: ustr("impl"),
+        size_bytes: None,
+        alignment_bytes: None,
+        own_vf_ptr_bytes: None,
+        binding_slots: vec![],
+        ontology_slots,
+        supers: vec![],
+        methods: vec![],
+        fields: vec![],
+        overrides: vec![],
+        props: vec![],
+        labels: BTreeSet::default(),
+
+        idl_sym: None,
+        subclass_syms: vec![],
+        overridden_by_syms: vec![],
+        variants: vec![],
+        extra: Map::default(),
+
+        can_gc: None,
+        gc_path: None,
+        ipdl_protocol: None,
+    }
+}
+
+/// Process the `[topics]` ontology rules, binding the string literal arguments
+/// of emitter and subscriber calls into synthetic `TOPIC_` symbols that are
+/// linked to their handlers via ontology slots.
+///
+/// This runs after `process_ontology_rules` because we need the override
+/// hierarchy to find the handlers and the search results to know which files
+/// contain relevant calls.  Only those files' analysis records are re-read.
+///
+/// Only the namespaces a tree opts into via its `ontology_topics` config are
+/// processed.  Topic names go in `topic_table` rather than the identifiers
+/// table so they don't show up in identifier search and completion.
+#[allow(clippy::too_many_arguments)]
+fn process_ontology_topics(
+    ontology: &OntologyMappingIngestion,
+    tree_config: &TreeConfig,
+    id_table: &IdTable,
+    // read-write
+    search_result_table: &mut SearchResultTable,
+    pretty_table: &mut PrettyTable,
+    topic_table: &mut IdTable,
+    meta_table: &mut MetaTable,
+    callees_table: &mut CalleesTable,
+) {
+    let index_path = &tree_config.paths.index_path;
+    let find_source_file = tree_config.get_find_source_file();
+
+    for (namespace, topic_rule) in ontology.config.topics.iter() {
+        if !tree_config
+            .paths
+            .ontology_topics
+            .iter()
+            .any(|enabled| enabled == namespace.as_str())
+        {
+            info!(
+                " Skipping topic rule not enabled for this tree: {}",
+                namespace
+            );
+            continue;
+        }
+        info!(" Processing topic rule for: {}", namespace);
+
+        // ### Find calls that pass a string literal topic.
+        //
+        // Emitter calls are keyed by topic and retain the search result for
+        // the call so we can make it a use of the topic symbol.
+        let mut topic_emits: BTreeMap<String, Vec<(Ustr, SearchResult)>> = BTreeMap::new();
+        let mut topic_handlers: BTreeMap<String, BTreeSet<Ustr>> = BTreeMap::new();
+        for (is_emitter, arg_rules) in [
+            (true, &topic_rule.emitters),
+            (false, &topic_rule.subscribers),
+        ] {
+            for arg_rule in arg_rules {
+                let method_syms =
+                    collect_syms_and_overrides(&arg_rule.pretty, id_table, meta_table);
+                let mut paths = BTreeSet::new();
+                for sym in &method_syms {
+                    if let Some(path_hits) = search_result_table
+                        .get(sym)
+                        .and_then(|kind_map| kind_map.get(&AnalysisKind::Use))
+                    {
+                        paths.extend(path_hits.keys().copied());
+                    }
+                }
+                info!(
+                    "  found {} syms used in {} paths for {}",
+                    method_syms.len(),
+                    paths.len(),
+                    arg_rule.pretty
+                );
+
+                for path in paths {
+                    let Some(lines) = read_source_lines(&find_source_file, &path) else {
                         continue;
                     };
-                    for hits in path_hits.values() {
-                        for hit in hits {
-                            for rule in &label_rule.labels {
-                                if hit.context.ends_with(rule.context_sym_suffix.as_str()) {
-                                    field.labels.insert(rule.label);
-                                }
+                    let analysis_fname = format!("{}/analysis/{}", index_path, path);
+                    for datum in read_analysis(&analysis_fname, &mut read_target) {
+                        for piece in datum.data {
+                            if piece.kind != AnalysisKind::Use
+                                || piece.contextsym.is_empty()
+                                || !method_syms.contains(&piece.sym)
+                            {
+                                continue;
                             }
+                            let Some(arg_range) = piece.arg_ranges.get(arg_rule.arg_index as usize)
+                            else {
+                                continue;
+                            };
+                            let Some(line) = (arg_range.start_lineno as usize)
+                                .checked_sub(1)
+                                .and_then(|i| lines.get(i))
+                            else {
+                                continue;
+                            };
+                            let Some((topic, literal_len)) = line
+                                .get(arg_range.start_col as usize..)
+                                .and_then(parse_string_literal)
+                            else {
+                                continue;
+                            };
+                            if topic.is_empty() {
+                                continue;
+                            }
+
+                            if !is_emitter {
+                                topic_handlers
+                                    .entry(topic)
+                                    .or_default()
+                                    .insert(piece.contextsym);
+                                continue;
+                            }
+
+                            let (line_buf, offset) = line_to_buf_and_offset(line.clone());
+                            let col_start = arg_range.start_col.saturating_sub(offset);
+                            topic_emits.entry(topic).or_default().push((
+                                path,
+                                SearchResult {
+                                    lineno: arg_range.start_lineno,
+                                    bounds: (col_start, col_start + literal_len as u32),
+                                    line: line_buf,
+                                    context: piece.context,
+                                    contextsym: piece.contextsym,
+                                    peek_range: piece.peek_range,
+                                },
+                            ));
                         }
                     }
                 }
             }
         }
+
+        // ### Find handler methods mentioning an emitted topic.
+        for handler_rule in &topic_rule.handlers {
+            let mut handler_syms =
+                collect_syms_and_overrides(&handler_rule.overrides_of, id_table, meta_table);
+            // The base method itself is not a handler.
+            if let Some(root_syms) = id_table.get(&handler_rule.overrides_of) {
+                for sym in root_syms {
+                    handler_syms.remove(sym);
+                }
+            }
+            let mut paths = BTreeSet::new();
+            for sym in &handler_syms {
+                if let Some(path_hits) = search_result_table
+                    .get(sym)
+                    .and_then(|kind_map| kind_map.get(&AnalysisKind::Def))
+                {
+                    paths.extend(path_hits.keys().copied());
+                }
+            }
+            info!(
+                "  found {} handler syms defined in {} paths for {}",
+                handler_syms.len(),
+                paths.len(),
+                handler_rule.overrides_of
+            );
+
+            for path in paths {
+                let Some(lines) = read_source_lines(&find_source_file, &path) else {
+                    continue;
+                };
+                let analysis_fname = format!("{}/analysis/{}", index_path, path);
+                for datum in read_analysis(&analysis_fname, &mut read_source) {
+                    for piece in datum.data {
+                        if piece.nesting_range.is_empty() {
+                            continue;
+                        }
+                        let Some(handler_sym) =
+                            piece.sym.iter().find(|sym| handler_syms.contains(*sym))
+                        else {
+                            continue;
+                        };
+                        let start = (piece.nesting_range.start_lineno as usize).saturating_sub(1);
+                        let end = (piece.nesting_range.end_lineno as usize).min(lines.len());
+                        if start >= end {
+                            continue;
+                        }
+                        for literal in find_string_literals(&lines[start..end].join("\n")) {
+                            if topic_emits.contains_key(&literal) {
+                                topic_handlers
+                                    .entry(literal)
+                                    .or_default()
+                                    .insert(*handler_sym);
+                            }
+                        }
+                    }
+                }
+            }
+        }
+
+        // ### Create the topic symbols and link everything up.
+        info!("  found {} emitted topics", topic_emits.len());
+        for (topic, emits) in topic_emits {
+            let topic_sym = ustr(&make_topic_sym(namespace, &topic));
+            let topic_pretty = ustr(&make_topic_pretty(namespace, &topic));
+
+            let handler_syms: Vec<Ustr> = topic_handlers
+                .remove(&topic)
+                .unwrap_or_default()
+                .into_iter()
+                .collect();
+            for handler_sym in &handler_syms {
+                let Some(handler_meta) = meta_table.get_mut(handler_sym) else {
+                    continue;
+                };
+                match handler_meta
+                    .ontology_slots
+                    .iter_mut()
+                    .find(|slot| slot.slot_kind == OntologySlotKind::HandledTopic)
+                {
+                    Some(slot) => slot.syms.push(topic_sym),
+                    None => handler_meta.ontology_slots.push(OntologySlotInfo {
+                        slot_kind: OntologySlotKind::HandledTopic,
+                        syms: vec![topic_sym],
+                        rule: Some(*namespace),
+                    }),
+                }
+            }
+
+            for (path, result) in emits {
+                let callee_syms = callees_table.entry(result.contextsym).or_default();
+                let (from_path, callee_jump_lines) = callee_syms
+                    .entry(topic_sym)
+                    .or_insert_with(|| (path, BTreeSet::new()));
+                if *from_path == path {
+                    callee_jump_lines.insert(result.lineno);
+                }
+
+                search_result_table
+                    .entry(topic_sym)
+                    .or_default()
+                    .entry(AnalysisKind::Use)
+                    .or_default()
+                    .entry(path)
+                    .or_default()
+                    .push(result);
+            }
+
+            meta_table.insert(
+                topic_sym,
+                make_topic_structured(*namespace, topic_sym, topic_pretty, handler_syms),
+            );
+            pretty_table.insert(topic_sym, topic_pretty);
+            // Whitespace would break the `topics` file format.
+            if !topic.contains(char::is_whitespace) {
+                topic_table
+                    .entry(topic_pretty)
+                    .or_default()
+                    .insert(topic_sym);
+                topic_table
+                    .entry(ustr(&topic))
+                    .or_default()
+                    .insert(topic_sym);
+            }
+        }
     }
 }
 
@@ -1206,6 +1738,23 @@ fn create_js_idl_table(meta_table: &MetaTable) -> JSIDLTable {
     js_idl_table
 }
 
+/// Whether a binding slot on an IDL symbol names the C++ code that a JS call
+/// through the binding ends up running.  We skip the auto-generated binding
+/// glue in favor of the actual implementation.
+fn is_cpp_impl_callee_slot(props: &BindingSlotProps) -> bool {
+    if props.slot_lang != BindingSlotLang::Cpp || props.impl_kind == Some(BindingImplKind::Binding)
+    {
+        return false;
+    }
+    matches!(
+        props.slot_kind,
+        BindingSlotKind::Method
+            | BindingSlotKind::Attribute
+            | BindingSlotKind::Getter
+            | BindingSlotKind::Setter
+    )
+}
+
 fn write_inline_and_ext(
     out: &mut File,
     ext_out: &mut File,
@@ -1323,17 +1872,51 @@ fn write_crossref_and_jumpref_thread(
         }
         if let Some(callee_syms) = callees_table.get(id) {
             let mut callees = Vec::new();
+            let mut seen_callees = UstrSet::default();
             for (callee_sym, (call_path, call_lines)) in callee_syms {
-                let Some(meta) = meta_table.get(callee_sym) else {
+                let jump = format!("{}#{}", call_path, call_lines.iter().join(","));
+                // A direct call may also have been reached through another
+                // callee's binding slots (or vice versa); only list it once.
+                if let Some(meta) = meta_table.get(callee_sym)
+                    && seen_callees.insert(*callee_sym)
+                {
+                    callees.push(Callee {
+                        sym: *callee_sym,
+                        pretty: pretty_table.get(callee_sym).copied(),
+                        kind: meta.kind,
+                        via: None,
+                        jump: jump.clone(),
+                    });
+                }
+                // Calls from JS into WebIDL/XPIDL-exposed methods would
+                // otherwise dead-end at the JS property, so jump through the
+                // IDL symbol's binding slots to the C++ implementation.
+                let Some(idl_syms) = js_idl_table.get(callee_sym) else {
                     continue;
                 };
-                let callee = Callee {
-                    sym: *callee_sym,
-                    pretty: pretty_table.get(callee_sym).copied(),
-                    kind: meta.kind,
-                    jump: format!("{}#{}", call_path, call_lines.iter().join(",")),
-                };
-                callees.push(callee);
+                if idl_syms.len() >= MAX_JS_IDL_SYMS {
+                    continue;
+                }
+                for idl_sym in idl_syms {
+                    let Some(idl_meta) = meta_table.get(idl_sym) else {
+                        continue;
+                    };
+                    for slot in &idl_meta.binding_slots {
+                        if !is_cpp_impl_callee_slot(&slot.props) {
+                            continue;
+                        }
+                        if !meta_table.contains_key(&slot.sym) || !seen_callees.insert(slot.sym) {
+                            continue;
+                        }
+                        callees.push(Callee {
+                            sym: slot.sym,
+                            pretty: pretty_table.get(&slot.sym).copied(),
+                            kind: ustr("binding"),
+                            via: Some(*idl_sym),
+                            jump: jump.clone(),
+                        });
+                    }
+                }
             }
             crossref_data.callees = Some(callees);
         }
@@ -1579,8 +2162,10 @@ fn write_crossref_and_jumpref(
     });
 }
 
-fn write_identifiers(tree_config: &TreeConfig, id_table: IdTable) {
-    let id_file = format!("{}/identifiers", tree_config.paths.index_path);
+/// Write an `IdTable` to the given index file in the sorted "id sym" line
+/// format that `IdentMap` expects.  Used for both `identifiers` and `topics`.
+fn write_identifiers(tree_config: &TreeConfig, filename: &str, id_table: IdTable) {
+    let id_file = format!("{}/{}", tree_config.paths.index_path, filename);
 
     let mut idf = File::create(id_file).unwrap();
 
@@ -1703,11 +2288,11 @@ async fn main() {
         Local::now().format("%Y-%m-%dT%H:%M:%S%z")
     );
     let AnalysisData {
-        search_result_table,
-        pretty_table,
+        mut search_result_table,
+        mut pretty_table,
         id_table,
         mut meta_table,
-        callees_table,
+        mut callees_table,
     } = read_analysis_files(
         analysis_relative_paths,
         tree_name,
@@ -1726,7 +2311,7 @@ async fn main() {
 
     info!("Processing ontology now that all analysis files have been read in.");
 
-    let field_owning_class_rules = read_field_owning_class_rules(&ontology);
+    let field_owning_class_rules = read_field_owning_class_rules(&ontology, &id_table, &meta_table);
     let field_member_use_table = process_class_fields(
         &ontology,
         &id_table,
@@ -1736,6 +2321,18 @@ async fn main() {
 
     process_ontology_rules(&ontology, &search_result_table, &id_table, &mut meta_table);
 
+    let mut topic_table = IdTable::default();
+    process_ontology_topics(
+        &ontology,
+        tree_config,
+        &id_table,
+        &mut search_result_table,
+        &mut pretty_table,
+        &mut topic_table,
+        &mut meta_table,
+        &mut callees_table,
+    );
+
     // Consume the ontology logged span, pass it through our ontology-ingestion
     // explainer template, and write it to disk.
     drop(ontology_entered);
@@ -1771,7 +2368,8 @@ async fn main() {
         tree_name,
         Local::now().format("%Y-%m-%dT%H:%M:%S%z")
     );
-    write_identifiers(tree_config, id_table);
+    write_identifiers(tree_config, "identifiers", id_table);
+    write_identifiers(tree_config, "topics", topic_table);
 
     println!(
         "Performing crossref::write-concise-file-info step for {} : {}",
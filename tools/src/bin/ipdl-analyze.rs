@@ -1,4 +1,4 @@
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::fs::File;
 use std::io::BufRead;
@@ -111,14 +111,63 @@ fn find_analysis<'a>(analysis: &'a TargetAnalysis, mangled: &str) -> Option<&'a
     best_piece
 }
 
+/// Find all the overloads matching `mangled`, like the promise-returning and
+/// callback-taking variants of a `Send` method with a reply, so that calls of
+/// any of them can be traversed across the process boundary.  The overload
+/// `find_analysis` picks comes first.  For constructors, the other overload is
+/// the single-arg `SendPFooConstructor` wrapper that `find_analysis` skips and
+/// which just calls the overload it picks, so only that one is returned.
+fn find_all_analyses<'a>(
+    analysis: &'a TargetAnalysis,
+    mangled: &str,
+    is_ctor: bool,
+) -> Vec<&'a AnalysisTarget> {
+    let Some(best_piece) = find_analysis(analysis, mangled) else {
+        return vec![];
+    };
+    let mut pieces = vec![best_piece];
+    if is_ctor {
+        return pieces;
+    }
+    for datum in analysis {
+        for piece in &datum.data {
+            if (piece.kind == AnalysisKind::Decl || piece.kind == AnalysisKind::Def)
+                && piece.sym.contains(mangled)
+                && !pieces.iter().any(|p| p.sym == piece.sym)
+            {
+                pieces.push(piece);
+            }
+        }
+    }
+    pieces
+}
+
 fn output_ipc_data(
     outputf: &mut File,
     locstr: &str,
     ipc_pretty: &str,
     ipc_sym: &str,
-    send_datum: &AnalysisTarget,
+    send_data: &[&AnalysisTarget],
     recv_datum: &AnalysisTarget,
 ) {
+    let mut binding_slots: Vec<_> = send_data
+        .iter()
+        .map(|send_datum| {
+            json!({
+                "slotKind": "send",
+                "slotLang": "cpp",
+                "ownerLang": "idl",
+                "sym": send_datum.sym,
+            })
+        })
+        .collect();
+    binding_slots.push(json!({
+        "slotKind": "recv",
+        "slotLang": "cpp",
+        "ownerLang": "idl",
+        "sym": recv_datum.sym,
+    }));
+
     write!(
         outputf,
         "{}",
@@ -156,26 +205,90 @@ fn output_ipc_data(
             // Note that this is different than the target record kind.
             "kind": "ipc",
             "implKind": "idl",
-            "bindingSlots": [
-                {
-                    "slotKind": "send",
-                    "slotLang": "cpp",
-                    "ownerLang": "idl",
-                    "sym": send_datum.sym,
-                },
-                {
-                    "slotKind": "recv",
-                    "slotLang": "cpp",
-                    "ownerLang": "idl",
-                    "sym": recv_datum.sym,
-                },
-            ]
+            "bindingSlots": binding_slots,
+        })
+    )
+    .unwrap();
+    writeln!(outputf).unwrap();
+}
+
+fn protocol_sym(namespaces: &[String], protocol: &str) -> String {
+    format!("IPDL_{}_{}", namespaces.join("_"), protocol)
+}
+
+fn message_sym(namespaces: &[String], protocol: &str, message: &str) -> String {
+    format!("IPC_{}_{}_{}", namespaces.join("_"), protocol, message)
+}
+
+/// Emit the records for the protocol itself, whose structured record lets
+/// `traverse --edge=protocol` find the managed protocols and the messages
+/// without having to know the IPDL.
+fn output_protocol_data(
+    outputf: &mut File,
+    protocol: &ast::Namespace,
+    managers: Vec<String>,
+    manages: Vec<String>,
+    messages: Vec<serde_json::Value>,
+) {
+    let loc = &protocol.name.loc;
+    let locstr = format!(
+        "{}:{}-{}",
+        loc.lineno,
+        loc.colno,
+        loc.colno + protocol.name.id.len()
+    );
+    let pretty = format!("{}::{}", protocol.namespaces.join("::"), protocol.name.id);
+    let sym = protocol_sym(&protocol.namespaces, &protocol.name.id);
+
+    write!(
+        outputf,
+        "{}",
+        json!({
+            "loc": locstr,
+            "target": 1,
+            "kind": "idl",
+            "pretty": pretty,
+            "sym": sym,
+        })
+    )
+    .unwrap();
+    writeln!(outputf).unwrap();
+    write!(
+        outputf,
+        "{}",
+        json!({
+            "loc": locstr,
+            "source": 1,
+            "syntax": "idl,ipc,def",
+            "pretty": format!("protocol {}", pretty),
+            "sym": sym,
+        })
+    )
+    .unwrap();
+    writeln!(outputf).unwrap();
+    write!(
+        outputf,
+        "{}",
+        json!({
+            "loc": locstr,
+            "structured": 1,
+            "pretty": pretty,
+            "sym": sym,
+            "kind": "ipc-protocol",
+            "implKind": "idl",
+            "ipdlProtocol": {
+                "managers": managers,
+                "manages": manages,
+                "messages": messages,
+            },
         })
     )
     .unwrap();
     writeln!(outputf).unwrap();
 }
 
+/// Returns the IPC symbol of the message if the send and recv methods were
+/// found and its records were emitted.
 #[allow(clippy::too_many_arguments)]
 fn output_send_recv(
     outputf: &mut File,
@@ -187,7 +300,7 @@ fn output_send_recv(
     send_analysis: &TargetAnalysis,
     recv_side: &str,
     recv_analysis: &TargetAnalysis,
-) {
+) -> Option<String> {
     let send_prefix = if message.send_semantics == ast::SendSemantics::Intr {
         "Call"
     } else {
@@ -206,8 +319,8 @@ fn output_send_recv(
         &format!("{}{}", protocol.name.id, send_side),
         &format!("{}{}{}", send_prefix, message.name.id, ctor_suffix),
     );
-    let maybe_send_datum = find_analysis(send_analysis, &mangled);
-    if maybe_send_datum.is_none() {
+    let send_data = find_all_analyses(send_analysis, &mangled, is_ctor);
+    if send_data.is_empty() {
         println!("No analysis target found for send: {}", mangled);
     }
 
@@ -235,28 +348,27 @@ fn output_send_recv(
         );
     }
 
-    if let (Some(send_datum), Some(recv_datum)) = (maybe_send_datum, maybe_recv_datum) {
+    if !send_data.is_empty()
+        && let Some(recv_datum) = maybe_recv_datum
+    {
         let ipc_pretty = format!(
             "{}::{}::{}",
             protocol.namespaces.join("_"),
             protocol.name.id,
             message.name.id
         );
-        let ipc_sym = format!(
-            "IPC_{}_{}_{}",
-            protocol.namespaces.join("_"),
-            protocol.name.id,
-            message.name.id
-        );
+        let ipc_sym = message_sym(&protocol.namespaces, &protocol.name.id, &message.name.id);
         output_ipc_data(
             outputf,
             locstr,
             &ipc_pretty,
             &ipc_sym,
-            send_datum,
+            &send_data,
             recv_datum,
         );
+        return Some(ipc_sym);
     }
+    None
 }
 
 fn main() {
@@ -300,6 +412,16 @@ fn main() {
 
     let tus = maybe_tus.unwrap();
 
+    // Managers and managed protocols are referenced by their unqualified name,
+    // so we need the namespaces of all the protocols to produce their symbols.
+    // Protocols we didn't parse are assumed to be in the same namespace as the
+    // referencing protocol.
+    let protocol_namespaces: HashMap<String, Vec<String>> = tus
+        .values()
+        .filter_map(|tu| tu.protocol.as_ref())
+        .map(|(ns, _)| (ns.name.id.clone(), ns.namespaces.clone()))
+        .collect();
+
     for (_, tu) in tus {
         println!("Analyzing {:?}", tu.file_name);
 
@@ -427,6 +549,14 @@ fn main() {
 
             let is_toplevel = protocol.managers.is_empty();
 
+            let related_protocol_sym = |id: &ast::Identifier| {
+                let namespaces = protocol_namespaces.get(&id.id).unwrap_or(&ns.namespaces);
+                protocol_sym(namespaces, &id.id)
+            };
+            let managers = protocol.managers.iter().map(related_protocol_sym).collect();
+            let manages = protocol.manages.iter().map(related_protocol_sym).collect();
+            let mut message_infos = vec![];
+
             for message in protocol.messages {
                 let loc = &message.name.loc;
                 let locstr = format!(
@@ -442,10 +572,11 @@ fn main() {
 
                 let is_ctor = protocol.manages.iter().any(|e| e.id == message.name.id);
 
+                let mut ipc_sym = None;
                 if message.direction == ast::Direction::To(ProtocolSide::Child)
                     || message.direction == ast::Direction::Both
                 {
-                    output_send_recv(
+                    ipc_sym = output_send_recv(
                         &mut outputf,
                         &locstr,
                         &ns,
@@ -461,7 +592,7 @@ fn main() {
                 if message.direction == ast::Direction::To(ProtocolSide::Parent)
                     || message.direction == ast::Direction::Both
                 {
-                    output_send_recv(
+                    ipc_sym = output_send_recv(
                         &mut outputf,
                         &locstr,
                         &ns,
@@ -471,9 +602,24 @@ fn main() {
                         &child_analysis,
                         "Parent",
                         &parent_analysis,
-                    );
+                    )
+                    .or(ipc_sym);
+                }
+
+                if let Some(ipc_sym) = ipc_sym {
+                    let direction = match message.direction {
+                        ast::Direction::To(ProtocolSide::Child) => "to-child",
+                        ast::Direction::To(ProtocolSide::Parent) => "to-parent",
+                        ast::Direction::Both => "both",
+                    };
+                    message_infos.push(json!({
+                        "sym": ipc_sym,
+                        "direction": direction,
+                    }));
                 }
             }
+
+            output_protocol_data(&mut outputf, &ns, managers, manages, message_infos);
         }
     }
 }
@@ -7,11 +7,13 @@ extern crate scip;
 extern crate tools;
 
 use clap::Parser;
+use include_dir::{Dir, include_dir};
 use lazy_static::lazy_static;
 use regex::Regex;
 use scip::types::descriptor::Suffix;
+use serde::Deserialize;
 use serde_json::Map;
-use std::collections::BTreeSet;
+use std::collections::{BTreeSet, HashMap};
 use std::fs::{self, File};
 use std::io;
 use std::io::BufReader;
@@ -19,8 +21,8 @@ use std::path::{Path, PathBuf};
 use tools::file_format::analysis::{
     AnalysisKind, AnalysisSource, AnalysisStructured, AnalysisTarget, BindingOwnerLang,
     BindingSlotLang, BindingSlotProps, LineRange, Location, SourceRange, SourceTag,
-    StructuredBindingSlotInfo, StructuredFieldInfo, StructuredMethodInfo, StructuredOverrideInfo,
-    StructuredSuperInfo, StructuredTag, TargetTag, WithLocation,
+    StructuredArgInfo, StructuredBindingSlotInfo, StructuredFieldInfo, StructuredMethodInfo,
+    StructuredOverrideInfo, StructuredSuperInfo, StructuredTag, TargetTag, WithLocation,
 };
 use tools::file_format::analysis_manglings::mangle_file;
 use tools::file_format::config;
@@ -158,8 +160,15 @@ enum ScipLang {
     Rust,
     Typescript,
     Jvm,
+    Go,
+    CSharp,
+    Ruby,
 }
 
+/// What a SCIP descriptor contributes to the pretty name of its symbol.  The
+/// rules that pick one are in `languages/nesting_queries/pretty_rules.toml`.
+#[derive(Clone, Copy, Deserialize)]
+#[serde(rename_all = "snake_case")]
 enum PrettyAction {
     /// Don't include this in the pretty, but keep using what we've built.
     Omit,
@@ -167,6 +176,10 @@ enum PrettyAction {
     Append,
     /// Reset the list and use this as the first entry of the new list.
     ResetAndUse,
+    /// Append only the last "/"-delimited segment of this.  Go packages are
+    /// named by their full import path but code refers to them by the last
+    /// segment.
+    AppendLastPathSegment,
     /// The symbol name is known to be useless and some other means of inferring
     /// a pretty name (Ex: from the documentation) must be used.  Reset the list
     /// and use the alternate name source.  Note that it's expected this will be
@@ -175,217 +188,193 @@ enum PrettyAction {
     UseAlternateSource,
 }
 
-/// Helper structure that we use to populate our tree-sitter queries.  See the
-/// block comment in `analyze_using_scip` for context.
-///
-/// This structure captures the relevant data for queries that help us know
-/// which SCIP occurrence should be the definition that starts a nesting range
-/// both for position:sticky purposes and for context/contextsym purposes.
-/// In general this just means knowing the node name for a given construct plus
-/// the field names for the name and body.  Frequently the field name is "name",
-/// but sometimes it can be something like "type".  Usually the body is "body".
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrettyRule {
+    suffix: String,
+    name_prefix: Option<String>,
+    action: PrettyAction,
+}
+
+/// The name `pretty_rules.toml` uses for each descriptor suffix.
+fn suffix_rule_name(suffix: Suffix) -> &'static str {
+    match suffix {
+        Suffix::Package => "package",
+        Suffix::Namespace => "namespace",
+        Suffix::Type => "type",
+        Suffix::Term => "term",
+        Suffix::Method => "method",
+        Suffix::TypeParameter => "type_parameter",
+        Suffix::Parameter => "parameter",
+        Suffix::Macro => "macro",
+        Suffix::Meta => "meta",
+        Suffix::Local => "local",
+        Suffix::UnspecifiedSuffix => "unspecified",
+    }
+}
+
+const PRETTY_RULES_FILENAME: &str = "pretty_rules.toml";
+
+/// The pretty-name rules for one language: its own rules followed by the
+/// `default` ones.  Like the nesting queries, a tree can override the built-in
+/// `pretty_rules.toml` by putting one in its `scip_nesting_queries_path`.
+struct PrettyRules {
+    rules: Vec<PrettyRule>,
+}
+
+impl PrettyRules {
+    fn new(nesting_queries: &NestingQueries, lang_name: &str) -> Self {
+        let source = nesting_queries.read_file(PRETTY_RULES_FILENAME);
+        let mut by_lang: HashMap<String, Vec<PrettyRule>> = toml::from_str(&source)
+            .unwrap_or_else(|e| panic!("Bad {}: {}", PRETTY_RULES_FILENAME, e));
+        let mut rules = by_lang.remove(lang_name).unwrap_or_default();
+        rules.extend(by_lang.remove("default").unwrap_or_default());
+        PrettyRules { rules }
+    }
+
+    /// The action for the first matching rule, defaulting to `Append`.
+    fn action(&self, suffix: Suffix, name: &str) -> PrettyAction {
+        let suffix = suffix_rule_name(suffix);
+        self.rules
+            .iter()
+            .find(|rule| {
+                rule.suffix == suffix
+                    && rule
+                        .name_prefix
+                        .as_deref()
+                        .is_none_or(|prefix| name.starts_with(prefix))
+            })
+            .map_or(PrettyAction::Append, |rule| rule.action)
+    }
+}
+
+static NESTING_QUERIES_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/languages/nesting_queries");
+
+/// The tree-sitter queries that tell us which SCIP definition occurrences start
+/// a nesting range, both for position:sticky purposes and for context/contextsym
+/// purposes.  See the block comment in `analyze_using_scip` for context.
 ///
-/// Note that the query syntax is quite powerful and the names of captures could
-/// potentially be used to encode metadata, so if making any changes to the type
-/// here or adding a whole bunch of additional instances below, it's probably
-/// worth considering moving to using externally stored ".scm" files which
-/// have semantic capture groups instead of this current approach.  In
-/// particular, alternations could be invaluable.  Also, we should avoid doing
-/// anything that resembles reinventing `tree-sitter-highlight`.
+/// There's a `<name>.scm` file per grammar in `languages/nesting_queries`, and
+/// a tree can override any of them by putting a file with the same name in the
+/// directory its `scip_nesting_queries_path` config setting points at, so
+/// adding or tuning a language doesn't require rebuilding.  Every pattern needs
+/// the captures:
+/// - `@name`: The node that should line up with the SCIP definition occurrence
+///   of the symbol starting the nesting range.
+/// - `@body`: The node whose extent is the nesting range.
 ///
-/// The current approach has been chosen for prototyping expediency and for
-/// ease of adding sidecar data, but as noted above and after having done
-/// additional research, especially around conventions (and limited rust binding
-/// support) for `#`-prefixed predicates, it's clear this is not the path
-/// forward without a very good reason.  (Readability could be a good reason;
-/// the s-expr syntax is powerful but probably unfamiliar to most people.  That
-/// said, its use around tree-sitter is so common that it seems like a
-/// reasonable thing to understand if touching tree-sitter related code.)
+/// Patterns can use the standard predicates like `#match?` and `#eq?` and can
+/// set these properties with `#set!`:
+/// - `(#set! nesting.contextsym "false")`: The range still gets a sticky line,
+///   but its symbol isn't used as the contextsym of the symbols inside it.
+/// - `(#set! nesting.pretty "source")`: Use the source text of `@name` as the
+///   context of the symbols inside it instead of the symbol's pretty, for when
+///   SCIP doesn't give us a useful pretty, like for locals.
 ///
-/// A reasonable hybrid approach, depending on how easy it is to add custom
-/// predicates to the rust bindings, is to embed full s-exprs here but leave
-/// our sidecar structure so that we can just use rust logic for what would
-/// otherwise be potentially complex predicates.
-struct SitterNesting {
-    root_node_type: Vec<&'static str>,
-    name_field: &'static str,
-    body_field: &'static str,
-    body_node: &'static str,
+/// The same directory holds `pretty_rules.toml`; see `PrettyRules`.
+struct NestingQueries {
+    override_dir: Option<PathBuf>,
+    sources: HashMap<&'static str, String>,
 }
 
-lazy_static! {
-    // our list is manually derived from the tags.scm file:
-    // https://github.com/tree-sitter/tree-sitter-python/blob/master/queries/tags.scm
-    static ref PYTHON_NESTING: Vec<SitterNesting> = vec![
-        SitterNesting {
-            root_node_type: vec!["class_definition"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["function_definition"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-    ];
-    // our list is manually derived from the tags.scm file:
-    // https://github.com/tree-sitter/tree-sitter-rust/blob/master/queries/tags.scm
-    static ref RUST_NESTING: Vec<SitterNesting> = vec![
-        SitterNesting {
-            root_node_type: vec!["struct_item"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["enum_item"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["union_item"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["function_item"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["trait_item"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["mod_item"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        // TODO macro_definition lacks a body so the body needs to be the parent
-        // node maybe?
-        //
-        // impl can also have a "trait" field, but symbol-wise I think the
-        // important symbol for context is the struct type not the trait type.
-        SitterNesting {
-            root_node_type: vec!["impl_item"],
-            name_field: "type",
-            body_field: "body",
-            body_node: "",
-        },
-    ];
-    // tree-sitter support for typescript is a little weird because the
-    // typescript languages (typescript and tsx) extend the javascript
-    // language.
-    //
-    // for now we manually derive these from both base queries tags.scm files:
-    // https://github.com/tree-sitter/tree-sitter-javascript/blob/master/queries/tags.scm
-    // https://github.com/tree-sitter/tree-sitter-typescript/blob/master/queries/tags.scm
-    static ref JS_NESTING: Vec<SitterNesting> = vec![
-        // ### from the JS tags
-        SitterNesting {
-            root_node_type: vec!["method_definition"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        // There's an alt over class and class_declaration; class_declaration
-        // becomes "class" if we add a "let blah = " ahead of it (and it stops
-        // being a declaration).
-        SitterNesting {
-            root_node_type: vec!["class", "class_declaration"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        // There's also an alt over function/generators
-        SitterNesting {
-            root_node_type: vec![
-                "function_expression",
-                "function_declaration",
-                "generator_function",
-                "generator_function_declaration"
-            ],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        // TODO: tags.scm has logic for lexical binds on arrow functions and
-        // this is worth considering, although arguably this might also resemble
-        // the lambda case.  But this is also beyond our current approach with
-        // generated syntax.  Also, conceptually, the arrow functions should
-        // already exist within a nesting scope, which raises the question of
-        // whether it's actually desirable to treat them like C++ lambdas which
-        // we also currently fold in.
-        // TODO: There's also property-defined arrow functions.
-        //
-        // ### from the TS tags
-        // XXX skipping function_signature because it lacks a directly available
-        // body.
-        // XXX skipping (abstract_)method_signature because it lacks a directly
-        // available body.
-        SitterNesting {
-            root_node_type: vec!["abstract_class_declaration"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["module"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["interface_declaration"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-    ];
-    // https://github.com/tree-sitter/tree-sitter-java/blob/master/queries/tags.scm
-    static ref JAVA_NESTING: Vec<SitterNesting> = vec![
-        SitterNesting {
-            root_node_type: vec!["class_declaration"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["method_declaration"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-        SitterNesting {
-            root_node_type: vec!["interface_declaration"],
-            name_field: "name",
-            body_field: "body",
-            body_node: "",
-        },
-    ];
-    // no tags.scm in tree-sitter-kotlin
-    static ref KOTLIN_NESTING: Vec<SitterNesting> = vec![
-        SitterNesting {
-            root_node_type: vec!["class_declaration"],
-            name_field: "name",
-            body_field: "",
-            body_node: "class_body",
-        },
-        SitterNesting {
-            root_node_type: vec!["function_declaration"],
-            name_field: "name",
-            body_field: "",
-            body_node: "function_body",
-        },
-    ];
+/// The per-pattern properties described on `NestingQueries`.
+#[derive(Clone, Copy)]
+struct NestingProps {
+    contextsym: bool,
+    pretty_from_source: bool,
+}
+
+impl Default for NestingProps {
+    fn default() -> Self {
+        NestingProps {
+            contextsym: true,
+            pretty_from_source: false,
+        }
+    }
+}
+
+impl NestingQueries {
+    fn new(paths: &config::TreeConfigPaths) -> Self {
+        NestingQueries {
+            override_dir: paths.scip_nesting_queries_path.as_ref().map(PathBuf::from),
+            sources: HashMap::new(),
+        }
+    }
+
+    /// Read the tree's override of the file if it has one, or else the
+    /// built-in one.
+    fn read_file(&self, filename: &str) -> String {
+        if let Some(dir) = &self.override_dir {
+            match fs::read_to_string(dir.join(filename)) {
+                Ok(source) => {
+                    info!(
+                        "Using nesting query override {}",
+                        dir.join(filename).display()
+                    );
+                    return source;
+                }
+                Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+                Err(e) => panic!("Failed to read nesting query override {}: {}", filename, e),
+            }
+        }
+        NESTING_QUERIES_DIR
+            .get_file(filename)
+            .and_then(|f| f.contents_utf8())
+            .unwrap_or_else(|| panic!("Missing built-in nesting query {}", filename))
+            .to_string()
+    }
+
+    fn source(&mut self, name: &'static str) -> &str {
+        if !self.sources.contains_key(name) {
+            let source = self.read_file(&format!("{}.scm", name));
+            self.sources.insert(name, source);
+        }
+        &self.sources[name]
+    }
+
+    /// Compile the named query, returning it along with the properties for each
+    /// of its patterns.
+    fn compile(
+        &mut self,
+        lang: &tree_sitter::Language,
+        name: &'static str,
+    ) -> (tree_sitter::Query, Vec<NestingProps>) {
+        let query = tree_sitter::Query::new(lang, self.source(name))
+            .unwrap_or_else(|e| panic!("Bad nesting query {}.scm: {}", name, e));
+
+        let mut pattern_props = vec![];
+        for pattern_ix in 0..query.pattern_count() {
+            let quantifiers = query.capture_quantifiers(pattern_ix);
+            for capture_name in ["name", "body"] {
+                let has_capture = query
+                    .capture_index_for_name(capture_name)
+                    .is_some_and(|ix| {
+                        quantifiers[ix as usize] == tree_sitter::CaptureQuantifier::One
+                    });
+                if !has_capture {
+                    panic!(
+                        "Nesting query {}.scm pattern {} needs exactly one @{} capture",
+                        name, pattern_ix, capture_name
+                    );
+                }
+            }
+
+            let mut props = NestingProps::default();
+            for prop in query.property_settings(pattern_ix) {
+                match (&*prop.key, prop.value.as_deref()) {
+                    ("nesting.contextsym", Some("false")) => props.contextsym = false,
+                    ("nesting.pretty", Some("source")) => props.pretty_from_source = true,
+                    (key, value) => warn!(
+                        "Unknown nesting property {} {:?} in {}.scm",
+                        key, value, name
+                    ),
+                }
+            }
+            pattern_props.push(props);
+        }
+
+        (query, pattern_props)
+    }
 }
 
 struct NestedSymbol {
@@ -394,51 +383,14 @@ struct NestedSymbol {
     sym: Ustr,
     /// The pretty identifier of that symbol.
     pretty: Ustr,
+    /// Whether this should be the contextsym for symbols inside it; see
+    /// `NestingProps`.
+    is_contextsym: bool,
     /// The range it covers; we really only need the last line, but include this
     /// for debugging.
     nesting_range: SourceRange,
 }
 
-fn compile_nesting_queries(
-    lang: &tree_sitter::Language,
-    nesting: &[SitterNesting],
-) -> tree_sitter::Query {
-    let query_pats: Vec<String> = nesting
-        .iter()
-        .map(|ndef| {
-            let mut parts = vec![];
-            let mut indent = "";
-            if ndef.root_node_type.len() > 1 {
-                parts.push("[".to_string());
-                indent = "  ";
-            }
-            for node_type in &ndef.root_node_type {
-                parts.push(format!(
-                    "{}({} {}: (_) @name {}({}) @body)",
-                    indent,
-                    node_type,
-                    ndef.name_field,
-                    if !ndef.body_field.is_empty() {
-                        format!("{}: ", ndef.body_field)
-                    } else {
-                        "".to_string()
-                    },
-                    if ndef.body_node.is_empty() {
-                        "_"
-                    } else {
-                        ndef.body_node
-                    }
-                ));
-            }
-            if ndef.root_node_type.len() > 1 {
-                parts.push("]".to_string());
-            }
-            parts.join("\n")
-        })
-        .collect();
-    tree_sitter::Query::new(lang, &query_pats.join("\n")).unwrap()
-}
-
 // ### Process SCIP Descriptors
 //
 // Map the descriptor individually to build up canonical
@@ -476,7 +428,7 @@ fn symbol_name(lang_name: &str, subtree_name: Option<&str>, scip_symbol: &str) -
 fn analyse_symbol(
     symbol: &scip::types::Symbol,
     enclosing: Option<Ustr>,
-    lang: &ScipLang,
+    pretty_rules: &PrettyRules,
     lang_name: &str,
     subtree_name: Option<&str>,
     relative_path: &str,
@@ -509,15 +461,11 @@ fn analyse_symbol(
         };
         let escaped = sanitize_symbol(&descriptor.name);
 
-        let (sym_piece, pretty_action, maybe_kind, contributes_to_parent) = match suffix {
+        let (sym_piece, maybe_kind, contributes_to_parent) = match suffix {
             // Confusingly, package is deprecated in favor of
             // namespace, but right now the SCIP crate parses '/'
             // as Package, not Namespace.
             Suffix::Package | Suffix::Namespace => {
-                // Pretty: For JS/TS the namespace includes the file path which
-                // ends up way too verbose and now how humans would describe
-                // things.
-                //
                 // TODO: Handle scip-typescript emitting symbols for file names
                 // since our explicit heuristic above ends up leaving them
                 // entirely with an empty pretty, and in that case we do want
@@ -527,71 +475,32 @@ fn analyse_symbol(
                 // TODO: it would also be good to emit a "namespace" kind and/or
                 // symbol for rust modules as we go? (For C++ we don't
                 // emit a structured record but do emit an "NS_"-prefixed sym.)
-                (
-                    format!("{}/", escaped),
-                    match lang {
-                        ScipLang::Typescript => PrettyAction::Omit,
-                        _ => PrettyAction::Append,
-                    },
-                    None,
-                    false,
-                )
+                (format!("{}/", escaped), None, false)
             }
-            Suffix::Type => (
-                format!("{}#", escaped),
-                PrettyAction::Append,
-                Some("class"),
-                false,
-            ),
-            Suffix::Term => (
-                format!("{}.", escaped),
-                PrettyAction::Append,
-                Some("field"),
-                true,
-            ),
+            Suffix::Type => (format!("{}#", escaped), Some("class"), false),
+            Suffix::Term => (format!("{}.", escaped), Some("field"), true),
             Suffix::Method => (
                 format!(
                     "{}({}).",
                     escaped,
                     sanitize_symbol(&descriptor.disambiguator)
                 ),
-                PrettyAction::Append,
                 Some("method"),
                 true,
             ),
             Suffix::TypeParameter => {
                 // Not sure what cases this is used in...
-                (
-                    format!("[{}]", escaped),
-                    PrettyAction::ResetAndUse,
-                    None,
-                    false,
-                )
+                (format!("[{}]", escaped), None, false)
             }
             Suffix::Parameter => {
                 // For now, at least, arguments don't get tracked by the parent.
-                (
-                    format!("({})", escaped),
-                    PrettyAction::ResetAndUse,
-                    Some("arg"),
-                    false,
-                )
+                (format!("({})", escaped), Some("arg"), false)
             }
-            Suffix::Macro => (
-                format!("{}!", escaped),
-                PrettyAction::Append,
-                Some("macro"),
-                false,
-            ),
+            Suffix::Macro => (format!("{}!", escaped), Some("macro"), false),
             Suffix::Meta => {
                 // We see this used for fields in JS, at least when
                 // preceded by a `Type#`.
-                (
-                    format!("{}:", escaped),
-                    PrettyAction::Append,
-                    Some("field"),
-                    true,
-                )
+                (format!("{}:", escaped), Some("field"), true)
             }
             // Local is special because the symbol's "scheme" is
             // "local", so the suffix is interesting as a marker,
@@ -608,15 +517,15 @@ fn analyse_symbol(
                 // extra name-spacing.
                 (
                     format!("{}/#{}", sanitize_symbol(relative_path), escaped),
-                    PrettyAction::UseAlternateSource,
                     None,
                     false,
                 )
             }
             // Suffix::UnspecifiedSuffix is not possible because we
             // excluded it above, but rust doesn't know that.
-            Suffix::UnspecifiedSuffix => ("".to_owned(), PrettyAction::Omit, None, false),
+            Suffix::UnspecifiedSuffix => ("".to_owned(), None, false),
         };
+        let pretty_action = pretty_rules.action(suffix, &descriptor.name);
         prev_kind = last_kind;
         last_kind = maybe_kind;
         last_contributes_to_parent = contributes_to_parent;
@@ -632,6 +541,10 @@ fn analyse_symbol(
                 pretty_pieces.clear();
                 pretty_pieces.push(descriptor.name.clone());
             }
+            PrettyAction::AppendLastPathSegment => {
+                let name = descriptor.name.trim_end_matches('/');
+                pretty_pieces.push(name.rsplit('/').next().unwrap_or(name).to_string());
+            }
             PrettyAction::UseAlternateSource => {
                 pretty_pieces.clear();
                 if let Some(name) = doc_name {
@@ -675,6 +588,139 @@ fn analyse_symbol(
     }
 }
 
+/// Split `s` on `sep` where it isn't nested inside brackets, parens or
+/// generics.  The `>` of a `->` doesn't count as a closing bracket.
+fn split_top_level(s: &str, sep: char) -> Vec<&str> {
+    let mut pieces = vec![];
+    let mut depth = 0i32;
+    let mut start = 0;
+    let mut prev = ' ';
+    for (i, c) in s.char_indices() {
+        match c {
+            c if c == sep && depth == 0 => {
+                pieces.push(&s[start..i]);
+                start = i + c.len_utf8();
+            }
+            '(' | '[' | '{' | '<' => depth += 1,
+            '>' if prev == '-' || prev == '=' => {}
+            ')' | ']' | '}' | '>' => depth -= 1,
+            _ => {}
+        }
+        prev = c;
+    }
+    pieces.push(&s[start..]);
+    pieces
+}
+
+/// Given `s` starting with an opening paren, return the contents up to the
+/// matching closing paren.
+fn paren_contents(s: &str) -> Option<&str> {
+    if !s.starts_with('(') {
+        return None;
+    }
+    let mut depth = 0;
+    for (i, c) in s.char_indices() {
+        match c {
+            '(' => depth += 1,
+            ')' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(&s[1..i]);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Parse a comma-separated `name: type` parameter list, skipping any rust
+/// `self` receivers.
+fn parse_arg_list(args: &str) -> Vec<StructuredArgInfo> {
+    split_top_level(args, ',')
+        .into_iter()
+        .filter_map(|arg| {
+            let arg = arg.trim();
+            let (name, type_pretty) = arg.split_once(": ").unwrap_or((arg, ""));
+            let name = name
+                .trim_start_matches("mut ")
+                .trim_start_matches("...")
+                .trim_end_matches('?');
+            if name.is_empty() || matches!(name, "self" | "&self" | "&mut self") {
+                return None;
+            }
+            Some(StructuredArgInfo {
+                name: ustr(name),
+                type_pretty: ustr(type_pretty.trim()),
+                type_sym: ustr(""),
+            })
+        })
+        .collect()
+}
+
+/// Extract the rust declaration from rust-analyzer's hover markdown, which is
+/// the last fenced code block, minus the layout comments it may include.
+fn rust_hover_declaration(hover: &str) -> Option<String> {
+    let (_, block) = hover.rsplit_once("```rust\n")?;
+    let (block, _) = block.split_once("```")?;
+    let decl: Vec<&str> = block
+        .lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect();
+    Some(decl.join("\n").trim().to_string())
+}
+
+/// Parse the type out of a rust field declaration like `pub(crate) foo: Foo`.
+fn parse_rust_field_type(decl: &str) -> Option<Ustr> {
+    lazy_static! {
+        static ref RE_RUST_FIELD: Regex =
+            Regex::new(r"^(?:pub(?:\([^)]*\))?\s+)?(?:[A-Za-z_][A-Za-z0-9_]*|\d+): (?s)(.+)$")
+                .unwrap();
+    }
+    let caps = RE_RUST_FIELD.captures(decl)?;
+    Some(ustr(caps.get(1)?.as_str().trim()))
+}
+
+/// Parse the parameters out of a rust function declaration, skipping over any
+/// generics between the name and the parameter list.
+fn parse_rust_fn_args(decl: &str) -> Option<Vec<StructuredArgInfo>> {
+    lazy_static! {
+        static ref RE_RUST_FN: Regex = Regex::new(r"\bfn\s+[A-Za-z_][A-Za-z0-9_]*\s*").unwrap();
+    }
+    let name_match = RE_RUST_FN.find(decl)?;
+    let mut rest = &decl[name_match.end()..];
+    if rest.starts_with('<') {
+        let generics = split_top_level(rest, '(').into_iter().next()?;
+        rest = rest[generics.len()..].trim_start();
+    }
+    Some(parse_arg_list(paren_contents(rest)?))
+}
+
+/// Find the name of the outermost type in a type string like `&'a mut Foo`,
+/// `crate::foo::Bar<T>` or `Foo[] | null` so it can be mapped to a symbol.
+fn primary_type_name(type_pretty: &str) -> Option<&str> {
+    let mut s = type_pretty.trim();
+    loop {
+        let before = s;
+        s = s.trim_start_matches('&').trim_start();
+        if s.starts_with('\'') {
+            s = s.split_once(' ').map_or("", |(_, rest)| rest);
+        }
+        for prefix in ["mut ", "dyn ", "impl ", "*const ", "*mut ", "readonly "] {
+            s = s.strip_prefix(prefix).unwrap_or(s);
+        }
+        if s == before {
+            break;
+        }
+    }
+    let end = s
+        .find(['<', '[', '(', ' ', '|', ',', ';'])
+        .unwrap_or(s.len());
+    let path = &s[..end];
+    let name = path.rsplit("::").next()?.rsplit('.').next()?;
+    if name.is_empty() { None } else { Some(name) }
+}
+
 fn analyze_using_scip(
     tree_config: &config::TreeConfig,
     subtree_name: Option<&str>,
@@ -699,16 +745,22 @@ fn analyze_using_scip(
     // we can generate best-effort structured representations for these cases.
     let mut possible_unknown_scip_symbols: UstrMap<SymbolAnalysis> = UstrMap::default();
 
+    let mut nesting_queries = NestingQueries::new(&tree_config.paths);
+
     let (lang_name, lang) = match index.metadata.tool_info.name.as_str() {
         "rust-analyzer" => ("rs", ScipLang::Rust),
         "scip-python" => ("py", ScipLang::Python),
         "scip-typescript" => ("js", ScipLang::Typescript),
         "scip-java" => ("jvm", ScipLang::Jvm),
+        "scip-go" => ("go", ScipLang::Go),
+        "scip-dotnet" => ("cs", ScipLang::CSharp),
+        "scip-ruby" => ("rb", ScipLang::Ruby),
         _ => {
             warn!("Unsupported language; we need tree-sitter support.");
             return;
         }
     };
+    let pretty_rules = PrettyRules::new(&nesting_queries, lang_name);
 
     // ## First Pass: Process Symbol Definitions
     //
@@ -764,6 +816,10 @@ fn analyze_using_scip(
                 let mut fallback_kind = None;
                 let mut doc_name = None;
                 let mut type_pretty = None;
+                // The type of a field and the arguments of a method for the
+                // parent's structured record.
+                let mut field_type = None;
+                let mut method_args = None;
 
                 lazy_static! {
                     // used for modules, classes
@@ -782,6 +838,7 @@ fn analyze_using_scip(
                             }
                             ScipLang::Rust => {}
                             ScipLang::Typescript => {}
+                            ScipLang::Go | ScipLang::CSharp | ScipLang::Ruby => {}
                             ScipLang::Jvm => {
                                 if let Some(caps) = RE_KT_FUNCTION.captures(doc) {
                                     fallback_kind = Some("method");
@@ -797,7 +854,24 @@ fn analyze_using_scip(
                 }
 
                 // Signature documentation is new and a more reliable source of
-                // type information.
+                // type information.  Older rust-analyzer versions don't provide
+                // it, but the hover markdown contains the declaration.
+                let rust_decl = if lang == ScipLang::Rust {
+                    match scip_sym_info.signature_documentation.as_ref() {
+                        Some(doc) if !doc.text.is_empty() => Some(doc.text.clone()),
+                        _ => scip_sym_info
+                            .documentation
+                            .first()
+                            .and_then(|hover| rust_hover_declaration(hover)),
+                    }
+                } else {
+                    None
+                };
+                if let Some(decl) = &rust_decl {
+                    field_type = parse_rust_field_type(decl);
+                    method_args = parse_rust_fn_args(decl);
+                }
+
                 if let Some(doc) = scip_sym_info.signature_documentation.as_ref()
                     && !doc.text.is_empty()
                 {
@@ -818,9 +892,16 @@ fn analyze_using_scip(
                         }
                         if let Some(s) = caps.get(2) {
                             doc_name = Some(s.as_str().to_string());
+                            // Methods look like `(method) foo(a: number): boolean`.
+                            method_args = s
+                                .as_str()
+                                .find('(')
+                                .and_then(|paren| paren_contents(&s.as_str()[paren..]))
+                                .map(parse_arg_list);
                         }
                         if let Some(s) = caps.get(3) {
                             type_pretty = Some(ustr(s.as_str()));
+                            field_type = type_pretty;
                         }
                     } else if lang == ScipLang::Typescript
                         && let Some(caps) = RE_TS_UNTYPED.captures(&doc.text)
@@ -845,7 +926,7 @@ fn analyze_using_scip(
                         let enclosing_symbol_info = analyse_symbol(
                             &enclosing_symbol,
                             None,
-                            &lang,
+                            &pretty_rules,
                             lang_name,
                             subtree_name,
                             &doc.relative_path,
@@ -863,7 +944,7 @@ fn analyze_using_scip(
                 let mut symbol_info = analyse_symbol(
                     &scip_sym,
                     enclosing_symbol,
-                    &lang,
+                    &pretty_rules,
                     lang_name,
                     subtree_name,
                     &doc.relative_path,
@@ -884,7 +965,7 @@ fn analyze_using_scip(
                     let parent_symbol_info = analyse_symbol(
                         &rel_scip_sym,
                         None,
-                        &lang,
+                        &pretty_rules,
                         lang_name,
                         subtree_name,
                         &doc.relative_path,
@@ -961,6 +1042,7 @@ fn analyze_using_scip(
 
                     can_gc: None,
                     gc_path: None,
+                    ipdl_protocol: None,
                 };
                 // for local symbols we use our own symbol because the SCIP symbol is not
                 // actually unique; we also need to update our helper mapping.
@@ -988,8 +1070,7 @@ fn analyze_using_scip(
                                 sym: symbol_info.norm_sym,
                                 props: vec![],
                                 labels: BTreeSet::default(),
-                                // TODO: see about trying to extract args from markdown?
-                                args: vec![],
+                                args: method_args.unwrap_or_default(),
                             });
                         }
                         Some("field") => {
@@ -997,7 +1078,7 @@ fn analyze_using_scip(
                                 line_range: ustr(""),
                                 pretty: symbol_info.pretty,
                                 sym: symbol_info.norm_sym,
-                                type_pretty: type_pretty.unwrap_or_else(|| ustr("")),
+                                type_pretty: field_type.unwrap_or_else(|| ustr("")),
                                 type_sym: ustr(""),
                                 offset_bytes: Some(offset_bytes),
                                 bit_positions: None,
@@ -1023,6 +1104,57 @@ fn analyze_using_scip(
         }
     }
 
+    // ## Resolve Type Symbols
+    //
+    // Signatures only give us the names of types, so now that we know about
+    // all the types in the index we can map the names back to symbols.  We
+    // leave ambiguous names unresolved rather than guessing.
+    let mut type_syms_by_name: UstrMap<Option<Ustr>> = UstrMap::default();
+    let known_types = scip_symbol_to_structured
+        .values()
+        .filter(|s| s.kind == "class")
+        .map(|s| (s.pretty, s.sym))
+        .chain(
+            possible_unknown_scip_symbols
+                .values()
+                .filter(|s| s.kind == Some("class"))
+                .map(|s| (s.pretty, s.norm_sym)),
+        );
+    for (pretty, sym) in known_types {
+        let Some(name) = pretty.rsplit("::").next().filter(|n| !n.is_empty()) else {
+            continue;
+        };
+        type_syms_by_name
+            .entry(ustr(name))
+            .and_modify(|existing| {
+                if *existing != Some(sym) {
+                    *existing = None;
+                }
+            })
+            .or_insert(Some(sym));
+    }
+    let resolve_type_sym = |type_pretty: &str| {
+        primary_type_name(type_pretty)
+            .and_then(|name| type_syms_by_name.get(&ustr(name)).copied().flatten())
+    };
+    // Fields also get their typesym on their source records.
+    let mut field_type_syms: UstrMap<Ustr> = UstrMap::default();
+    for structured in scip_symbol_to_structured.values_mut() {
+        for field in &mut structured.fields {
+            if let Some(type_sym) = resolve_type_sym(&field.type_pretty) {
+                field.type_sym = type_sym;
+                field_type_syms.insert(field.sym, type_sym);
+            }
+        }
+        for method in &mut structured.methods {
+            for arg in &mut method.args {
+                if let Some(type_sym) = resolve_type_sym(&arg.type_pretty) {
+                    arg.type_sym = type_sym;
+                }
+            }
+        }
+    }
+
     let analysis_root = Path::new(&tree_config.paths.index_path).join(match platform {
         None => "analysis".to_string(),
         Some(platform) => format!("analysis-{}", platform),
@@ -1096,31 +1228,34 @@ fn analyze_using_scip(
         // basis.  We should revisit this if profiling shows this is a big problem.
         // Same thing with Java and Kotlin
         let mut parser = tree_sitter::Parser::new();
-        let (ts_lang, ts_nesting): (tree_sitter::Language, &Vec<SitterNesting>) = match &lang {
-            ScipLang::Python => (tree_sitter_python::LANGUAGE.into(), &PYTHON_NESTING),
-            ScipLang::Rust => (tree_sitter_rust::LANGUAGE.into(), &RUST_NESTING),
+        let (ts_lang, query_name): (tree_sitter::Language, &'static str) = match &lang {
+            ScipLang::Python => (tree_sitter_python::LANGUAGE.into(), "python"),
+            ScipLang::Rust => (tree_sitter_rust::LANGUAGE.into(), "rust"),
             ScipLang::Typescript => {
                 if doc.relative_path.ends_with(".tsx") || doc.relative_path.ends_with(".jsx") {
-                    (tree_sitter_typescript::LANGUAGE_TSX.into(), &JS_NESTING)
+                    (tree_sitter_typescript::LANGUAGE_TSX.into(), "typescript")
                 } else {
                     (
                         tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
-                        &JS_NESTING,
+                        "typescript",
                     )
                 }
             }
             ScipLang::Jvm => {
                 if doc.relative_path.ends_with(".kt") {
-                    (tree_sitter_kotlin_ng::LANGUAGE.into(), &KOTLIN_NESTING)
+                    (tree_sitter_kotlin_ng::LANGUAGE.into(), "kotlin")
                 } else {
-                    (tree_sitter_java::LANGUAGE.into(), &JAVA_NESTING)
+                    (tree_sitter_java::LANGUAGE.into(), "java")
                 }
             }
+            ScipLang::Go => (tree_sitter_go::LANGUAGE.into(), "go"),
+            ScipLang::CSharp => (tree_sitter_c_sharp::LANGUAGE.into(), "c_sharp"),
+            ScipLang::Ruby => (tree_sitter_ruby::LANGUAGE.into(), "ruby"),
         };
         parser
             .set_language(&ts_lang)
             .expect("Error loading grammar");
-        let ts_query = compile_nesting_queries(&ts_lang, ts_nesting);
+        let (ts_query, pattern_props) = nesting_queries.compile(&ts_lang, query_name);
         let name_capture_ix = ts_query.capture_index_for_name("name").unwrap();
         let body_capture_ix = ts_query.capture_index_for_name("body").unwrap();
 
@@ -1138,6 +1273,8 @@ fn analyze_using_scip(
         let mut next_parse_match;
         let mut next_parse_loc = Location::default();
         let mut next_parse_nesting = SourceRange::default();
+        let mut next_parse_props = NestingProps::default();
+        let mut next_parse_name = ustr("");
 
         let mut nesting_stack: Vec<NestedSymbol> = vec![];
 
@@ -1188,7 +1325,7 @@ fn analyze_using_scip(
                     let symbol_info = analyse_symbol(
                         &symbol,
                         None,
-                        &lang,
+                        &pretty_rules,
                         lang_name,
                         subtree_name,
                         &doc.relative_path,
@@ -1229,6 +1366,7 @@ fn analyze_using_scip(
 
                         can_gc: None,
                         gc_path: None,
+                        ipdl_protocol: None,
                     };
                     scip_symbol_to_structured.insert(norm_scip_sym, fake);
                     possible_unknown_scip_symbols.remove(&norm_scip_sym);
@@ -1261,11 +1399,9 @@ fn analyze_using_scip(
             // mechanism.
             //
             // A very nice thing about the query mechanism is it can tell you
-            // which pattern index matched, which means we can define a little
-            // data structure that we use to derive the query and also have
-            // structured rust data that we can consult without any hacks.
-            // (NB: As documented on the SitterNesting type, we probably should
-            // be using a different approach based on `.scm` files.)
+            // which pattern index matched, which lets the `.scm` files attach
+            // per-pattern properties that we consult here; see
+            // `NestingQueries`.
 
             // Pop off any nested symbols which don't include the current line.
             while let Some(nested) = nesting_stack.last() {
@@ -1284,19 +1420,17 @@ fn analyze_using_scip(
             while next_parse_loc < loc {
                 next_parse_match = query_matches.next();
                 (next_parse_loc, next_parse_nesting) = if let Some(pm) = &next_parse_match {
+                    // `NestingQueries::compile` checked every pattern has
+                    // exactly one of each.
+                    let name_node = pm.nodes_for_capture_index(name_capture_ix).next().unwrap();
+                    let body_node = pm.nodes_for_capture_index(body_capture_ix).next().unwrap();
+                    next_parse_props = pattern_props[pm.pattern_index];
+                    next_parse_name = ustr(&String::from_utf8_lossy(
+                        &source_contents[name_node.byte_range()],
+                    ));
                     (
-                        node_range_to_searchfox_location(
-                            pm.nodes_for_capture_index(name_capture_ix)
-                                .next()
-                                .unwrap()
-                                .range(),
-                        ),
-                        node_range_to_searchfox_range(
-                            pm.nodes_for_capture_index(body_capture_ix)
-                                .next()
-                                .unwrap()
-                                .range(),
-                        ),
+                        node_range_to_searchfox_location(name_node.range()),
+                        node_range_to_searchfox_range(body_node.range()),
                     )
                 } else {
                     (
@@ -1323,7 +1457,12 @@ fn analyze_using_scip(
                 // other consumers to handle this.
                 Some(NestedSymbol {
                     sym: sinfo.sym,
-                    pretty: sinfo.pretty,
+                    pretty: if next_parse_props.pretty_from_source {
+                        next_parse_name
+                    } else {
+                        sinfo.pretty
+                    },
+                    is_contextsym: next_parse_props.contextsym,
                     nesting_range: next_parse_nesting.clone(),
                 })
             } else {
@@ -1349,9 +1488,8 @@ fn analyze_using_scip(
                         } else {
                             SourceRange::default()
                         },
-                        // TODO: Expose type information for fields/etc.
                         type_pretty: sinfo.type_pretty,
-                        type_sym: None,
+                        type_sym: field_type_syms.get(&sinfo.sym).copied(),
                         arg_ranges: vec![],
                         expansion_info: None,
                         confidence: None,
@@ -1370,7 +1508,11 @@ fn analyze_using_scip(
             // TODO: Contextual info.
 
             if !no_crossref {
-                let (contextsym, context) = if let Some(nested) = nesting_stack.last() {
+                let (contextsym, context) = if let Some(nested) = nesting_stack
+                    .iter()
+                    .rev()
+                    .find(|nested| nested.is_contextsym)
+                {
                     (nested.sym, nested.pretty)
                 } else {
                     (ustr(""), ustr(""))
@@ -1436,6 +1578,7 @@ fn analyze_using_scip(
 
             can_gc: None,
             gc_path: None,
+            ipdl_protocol: None,
         };
         scip_symbol_to_structured.insert(symbol_info.norm_sym, fake);
     }
@@ -1514,7 +1657,7 @@ fn binding_links(
         .map(|captures| captures.extract())
         .flat_map(|(_, [target_language, kind, target_symbol])| {
             let self_language = match language {
-                ScipLang::Python => return None,
+                ScipLang::Python | ScipLang::Go | ScipLang::CSharp | ScipLang::Ruby => return None,
                 ScipLang::Rust => BindingSlotLang::Rust,
                 ScipLang::Typescript => BindingSlotLang::JS,
                 ScipLang::Jvm => BindingSlotLang::Jvm,
@@ -1550,7 +1693,7 @@ fn binding_links(
         .map(|captures| captures.extract())
         .flat_map(|(_, [target_language, kind, target_symbol])| {
             let self_language = match language {
-                ScipLang::Python => return None,
+                ScipLang::Python | ScipLang::Go | ScipLang::CSharp | ScipLang::Ruby => return None,
                 ScipLang::Rust => BindingOwnerLang::Rust,
                 ScipLang::Typescript => BindingOwnerLang::JS,
                 ScipLang::Jvm => BindingOwnerLang::Jvm,
@@ -1600,3 +1743,162 @@ fn main() {
         );
     }
 }
+
+#[test]
+fn test_builtin_nesting_queries_compile() {
+    let mut nesting_queries = NestingQueries {
+        override_dir: None,
+        sources: HashMap::new(),
+    };
+    let mut compiled = 0;
+    for file in NESTING_QUERIES_DIR.files() {
+        let path = file.path();
+        if path.extension().and_then(|ext| ext.to_str()) != Some("scm") {
+            continue;
+        }
+        let name: &'static str = path.file_stem().unwrap().to_str().unwrap();
+        let ts_langs: Vec<tree_sitter::Language> = match name {
+            "python" => vec![tree_sitter_python::LANGUAGE.into()],
+            "rust" => vec![tree_sitter_rust::LANGUAGE.into()],
+            "typescript" => vec![
+                tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+                tree_sitter_typescript::LANGUAGE_TSX.into(),
+            ],
+            "java" => vec![tree_sitter_java::LANGUAGE.into()],
+            "kotlin" => vec![tree_sitter_kotlin_ng::LANGUAGE.into()],
+            "go" => vec![tree_sitter_go::LANGUAGE.into()],
+            "c_sharp" => vec![tree_sitter_c_sharp::LANGUAGE.into()],
+            "ruby" => vec![tree_sitter_ruby::LANGUAGE.into()],
+            _ => panic!("No grammar for nesting query {}.scm", name),
+        };
+        for ts_lang in ts_langs {
+            // This panics if the query doesn't compile or lacks a capture.
+            let (query, pattern_props) = nesting_queries.compile(&ts_lang, name);
+            assert_eq!(query.pattern_count(), pattern_props.len());
+            compiled += 1;
+        }
+    }
+    assert_eq!(compiled, 9);
+}
+
+#[test]
+fn test_builtin_pretty_rules() {
+    let nesting_queries = NestingQueries {
+        override_dir: None,
+        sources: HashMap::new(),
+    };
+    let action_name = |lang_name: &str, suffix: Suffix, name: &str| {
+        let rules = PrettyRules::new(&nesting_queries, lang_name);
+        match rules.action(suffix, name) {
+            PrettyAction::Omit => "omit",
+            PrettyAction::Append => "append",
+            PrettyAction::ResetAndUse => "reset_and_use",
+            PrettyAction::AppendLastPathSegment => "append_last_path_segment",
+            PrettyAction::UseAlternateSource => "use_alternate_source",
+        }
+    };
+    assert_eq!(action_name("js", Suffix::Namespace, "src/a.ts"), "omit");
+    assert_eq!(action_name("rs", Suffix::Namespace, "mod"), "append");
+    assert_eq!(
+        action_name("go", Suffix::Package, "example.com/pkg/"),
+        "append_last_path_segment"
+    );
+    assert_eq!(action_name("rb", Suffix::Type, "<Class:Foo>"), "omit");
+    assert_eq!(action_name("rb", Suffix::Type, "Foo"), "append");
+    assert_eq!(action_name("py", Suffix::Parameter, "x"), "reset_and_use");
+    assert_eq!(
+        action_name("jvm", Suffix::Local, "3"),
+        "use_alternate_source"
+    );
+}
+
+#[test]
+fn test_split_top_level() {
+    assert_eq!(
+        split_top_level("a: Foo<A, B>, b: (u8, u8), f: impl Fn(u8) -> u8", ','),
+        vec!["a: Foo<A, B>", " b: (u8, u8)", " f: impl Fn(u8) -> u8"]
+    );
+    assert_eq!(split_top_level("", ','), vec![""]);
+}
+
+#[test]
+fn test_paren_contents() {
+    assert_eq!(paren_contents("(a, (b, c)) -> d"), Some("a, (b, c)"));
+    assert_eq!(paren_contents("()"), Some(""));
+    assert_eq!(paren_contents("(unbalanced"), None);
+    assert_eq!(paren_contents("a(b)"), None);
+}
+
+#[test]
+fn test_parse_arg_list() {
+    let described = |args: &str| -> Vec<(String, String)> {
+        parse_arg_list(args)
+            .into_iter()
+            .map(|arg| (arg.name.to_string(), arg.type_pretty.to_string()))
+            .collect()
+    };
+    assert_eq!(
+        described("&mut self, mut myself: Foo, itself: &Bar<A, B>"),
+        vec![
+            ("myself".to_string(), "Foo".to_string()),
+            ("itself".to_string(), "&Bar<A, B>".to_string()),
+        ]
+    );
+    assert_eq!(described("self"), vec![]);
+    assert_eq!(
+        described("&self, x: u8"),
+        vec![("x".to_string(), "u8".to_string())]
+    );
+    // TypeScript optional and rest parameters.
+    assert_eq!(
+        described("a?: number, ...rest: string[]"),
+        vec![
+            ("a".to_string(), "number".to_string()),
+            ("rest".to_string(), "string[]".to_string()),
+        ]
+    );
+}
+
+#[test]
+fn test_rust_hover_declaration() {
+    let hover = "```rust\ncrate::foo\n```\n\n```rust\n// size = 8, align = 0x8\npub fn bar(&self) -> u8\n```\n\n---\n\nDocs.";
+    assert_eq!(
+        rust_hover_declaration(hover).as_deref(),
+        Some("pub fn bar(&self) -> u8")
+    );
+    assert_eq!(rust_hover_declaration("no code here"), None);
+}
+
+#[test]
+fn test_parse_rust_field_type() {
+    assert_eq!(
+        parse_rust_field_type("pub(crate) foo: HashMap<String, Foo>"),
+        Some(ustr("HashMap<String, Foo>"))
+    );
+    assert_eq!(parse_rust_field_type("0: u32"), Some(ustr("u32")));
+    assert_eq!(parse_rust_field_type("pub fn foo(x: u8)"), None);
+}
+
+#[test]
+fn test_parse_rust_fn_args() {
+    let names = |decl: &str| -> Option<Vec<String>> {
+        parse_rust_fn_args(decl)
+            .map(|args| args.into_iter().map(|arg| arg.name.to_string()).collect())
+    };
+    assert_eq!(
+        names("pub fn foo<T: Into<(u8, u8)>>(&mut self, a: T, b: u8) -> bool"),
+        Some(vec!["a".to_string(), "b".to_string()])
+    );
+    assert_eq!(names("fn new() -> Self"), Some(vec![]));
+    assert_eq!(names("pub struct Foo"), None);
+}
+
+#[test]
+fn test_primary_type_name() {
+    assert_eq!(primary_type_name("&'a mut Foo"), Some("Foo"));
+    assert_eq!(primary_type_name("crate::foo::Bar<T>"), Some("Bar"));
+    assert_eq!(primary_type_name("Box<dyn Baz>"), Some("Box"));
+    assert_eq!(primary_type_name("Foo[] | null"), Some("Foo"));
+    assert_eq!(primary_type_name("ns.Thing"), Some("Thing"));
+    assert_eq!(primary_type_name("(u8, u8)"), None);
+}
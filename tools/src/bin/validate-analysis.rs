@@ -0,0 +1,82 @@
+//! Strictly validate analysis files, printing a JSON diagnostics report for
+//! each file with problems to stdout, one per line.  This is intended for use
+//! when developing an indexer; the indexing pipeline itself is forgiving of
+//! bad records, which means they can silently disappear.
+//!
+//! The analysis files are located by mirroring each source path under the
+//! analysis root, so this can be run directly against an indexer's output.  If
+//! no source paths are given, every file under the analysis root is checked.
+//!
+//! The exit code is 1 if any errors (as opposed to warnings) were found.
+
+use std::env;
+use std::path::Path;
+
+extern crate env_logger;
+extern crate tools;
+use tools::file_format::analysis_validator::validate_analysis_file;
+use walkdir::WalkDir;
+
+fn main() {
+    env_logger::init();
+
+    let args: Vec<_> = env::args().skip(1).collect();
+
+    if args.len() < 2 {
+        eprintln!("Usage: validate-analysis <files-root> <analysis-root> [<path> [...]]");
+        eprintln!("  This tool will check the analysis files for the given source paths");
+        eprintln!("  (or all analysis files if none are given) against the analysis");
+        eprintln!("  schema and the source files, and print JSON reports to stdout.");
+        std::process::exit(1);
+    }
+    let files_root = Path::new(&args[0]);
+    let analysis_root = Path::new(&args[1]);
+
+    let paths: Vec<String> = if args.len() > 2 {
+        args[2..].to_vec()
+    } else {
+        WalkDir::new(analysis_root)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| {
+                let rel = entry.path().strip_prefix(analysis_root).ok()?;
+                let rel = rel.to_str()?;
+                Some(rel.strip_suffix(".gz").unwrap_or(rel).to_string())
+            })
+            .collect()
+    };
+
+    let (mut files_with_errors, mut total_errors, mut total_warnings) = (0, 0, 0);
+    for path in &paths {
+        let mut analysis_path = analysis_root.join(path).to_string_lossy().to_string();
+        if !Path::new(&analysis_path).exists() {
+            analysis_path.push_str(".gz");
+        }
+        let source = std::fs::read(files_root.join(path))
+            .ok()
+            .map(|bytes| String::from_utf8_lossy(&bytes).to_string());
+        let report = validate_analysis_file(path, &analysis_path, source.as_deref());
+
+        if report.errors > 0 {
+            files_with_errors += 1;
+        }
+        total_errors += report.errors;
+        total_warnings += report.warnings;
+        if !report.diagnostics.is_empty() {
+            println!("{}", serde_json::to_string(&report).unwrap());
+        }
+    }
+
+    eprintln!(
+        "Checked {} files: {} errors ({} files), {} warnings",
+        paths.len(),
+        total_errors,
+        files_with_errors,
+        total_warnings
+    );
+    if total_errors > 0 {
+        std::process::exit(1);
+    }
+}
@@ -1,5 +1,4 @@
 extern crate env_logger;
-extern crate hyper;
 extern crate tools;
 
 use std::collections::HashMap;
@@ -9,29 +8,28 @@ use std::io::BufReader;
 use std::io::{Read, Write};
 use std::net::SocketAddr;
 use std::path::Path;
-use std::process::Command;
 use std::sync::Arc;
 
+use axum::http::{StatusCode, Uri, header};
+use axum::response::{IntoResponse, Response};
+use axum::routing::get;
+use axum::{Extension, Router, middleware};
 use git2::Oid;
-use hyper::http;
-use hyper::server::conn::http1;
-use hyper::service::service_fn;
-use hyper::{Method, StatusCode, header};
-
-use hyper_util::rt::TokioIo;
 use tokio::net::TcpListener;
-use tokio::sync::Semaphore;
+use tools::abstract_server::make_all_local_servers;
 use tools::blame;
 use tools::diagnostics::diagnostics_from_config;
 use tools::file_format::config;
 use tools::file_format::identifiers::IdentMap;
 use tools::format;
-use tools::git_ops;
+use tools::web_routes::{conditional_get, pipeline_router};
+use tower::limit::GlobalConcurrencyLimitLayer;
 
 use tools::url_encode_path::url_decode_path;
 
 struct WebRequest<'a> {
     path: &'a str,
+    query: Option<&'a str>,
 }
 
 struct WebResponse {
@@ -77,6 +75,14 @@ impl WebResponse {
         }
     }
 
+    fn bad_request(body: String) -> WebResponse {
+        WebResponse {
+            status: StatusCode::BAD_REQUEST,
+            output: body,
+            ..WebResponse::default()
+        }
+    }
+
     fn not_found() -> WebResponse {
         WebResponse {
             status: StatusCode::NOT_FOUND,
@@ -94,6 +100,23 @@ impl WebResponse {
     }
 }
 
+impl IntoResponse for WebResponse {
+    fn into_response(self) -> Response {
+        let mut response = (
+            self.status,
+            [(header::CONTENT_TYPE, self.content_type)],
+            self.output,
+        )
+            .into_response();
+        if let Some(loc) = self.redirect_location
+            && let Ok(value) = loc.parse()
+        {
+            response.headers_mut().insert(header::LOCATION, value);
+        }
+        response
+    }
+}
+
 fn handle_static(path: String, content_type: Option<&str>) -> WebResponse {
     let source_file = match File::open(&path) {
         Ok(f) => f,
@@ -180,27 +203,20 @@ fn handle(
             }
 
             let tree_config = &cfg.trees[*tree_name];
-            let git_path = match tree_config.get_git_path() {
-                Ok(git_path) => git_path,
+            let git = match tree_config.get_git() {
+                Ok(git) => git,
                 Err(_) => return WebResponse::not_found(),
             };
 
             let hg_rev = path[2];
-            let output_result = Command::new("git")
-                .arg("cinnabar")
-                .arg("hg2git")
-                .arg(hg_rev)
-                .current_dir(git_path)
-                .output();
-            match output_result {
-                Ok(output) if output.status.success() => WebResponse::redirect(format!(
+            match git.hg_to_git(hg_rev) {
+                Some(git_rev) => WebResponse::redirect(format!(
                     "/{}/rev/{}/{}",
                     tree_name,
-                    git_ops::decode_bytes(output.stdout).trim(),
+                    git_rev,
                     path[3..].join("/")
                 )),
-                Ok(_) => WebResponse::not_found(),
-                Err(err) => WebResponse::internal_error(format!("{:?}", err)),
+                None => WebResponse::not_found(),
             }
         }
 
@@ -320,6 +336,46 @@ fn handle(
             }
         }
 
+        // Per-line blame for `/{tree}/blame/{rev}/{path}?lines=N-M`, omitting
+        // "lines" for the whole file.
+        "blame" => {
+            if path.len() < 4 {
+                return WebResponse::not_found();
+            }
+
+            let rev = &path[2];
+            let path = path.clone().split_off(3);
+            let path = path.join("/");
+
+            let lines = match req
+                .query
+                .into_iter()
+                .flat_map(|q| q.split('&'))
+                .find_map(|kv| kv.strip_prefix("lines="))
+            {
+                Some(range) => match blame::parse_line_range(range) {
+                    Some(lines) => lines,
+                    None => {
+                        return WebResponse::bad_request(format!("Bad line range: {}", range));
+                    }
+                },
+                None => 1..=u32::MAX,
+            };
+
+            let git = match cfg.trees.get(*tree_name).map(|t| t.get_git()) {
+                Some(Ok(git)) => git,
+                _ => return WebResponse::not_found(),
+            };
+            match blame::get_blame_json(git, rev, &path, lines) {
+                Ok(json) => WebResponse::json(json.to_string()),
+                Err(err @ blame::BlameError::BadRevision) => {
+                    WebResponse::bad_request(err.to_string())
+                }
+                Err(blame::BlameError::NoSuchPath) => WebResponse::not_found(),
+                Err(err) => WebResponse::internal_error(err.to_string()),
+            }
+        }
+
         "complete" => {
             if let Some(ids) = ident_map.get(&tree_name.to_string()) {
                 let json = ids.lookup_json(path[2], false, false, 6);
@@ -333,16 +389,90 @@ fn handle(
     }
 }
 
+/// The path kinds handled by `handle`.  Each is mounted both with and without a
+/// trailing wildcard path.
+const WEB_KINDS: &[&str] = &[
+    "diagnostics",
+    "rev",
+    "hgrev",
+    "oldrev",
+    "source",
+    "diff",
+    "olddiff",
+    "commit",
+    "oldcommit",
+    "commit-info",
+    "blame",
+    "complete",
+];
+
+async fn handle_web(
+    Extension(cfg): Extension<Arc<config::Config>>,
+    Extension(ident_map): Extension<Arc<HashMap<String, IdentMap>>>,
+    uri: Uri,
+) -> WebResponse {
+    match tokio::task::spawn_blocking(move || {
+        handle(
+            &cfg,
+            &ident_map,
+            WebRequest {
+                path: uri.path(),
+                query: uri.query(),
+            },
+        )
+    })
+    .await
+    {
+        Ok(response) => response,
+
+        Err(join_err) if join_err.is_panic() => {
+            eprintln!("Request handler panicked: {:?}", join_err);
+            WebResponse::internal_error("Internal server error".to_owned())
+        }
+
+        Err(join_err) => {
+            eprintln!(
+                "Request handler task failed or was cancelled: {:?}",
+                join_err
+            );
+            WebResponse::internal_error("Internal server error".to_owned())
+        }
+    }
+}
+
+fn web_router() -> Router {
+    let mut router = Router::new().route("/static/{*path}", get(handle_web));
+    for kind in WEB_KINDS {
+        router = router
+            .route(&format!("/{{tree}}/{}", kind), get(handle_web))
+            .route(&format!("/{{tree}}/{}/{{*rest}}", kind), get(handle_web));
+    }
+    // Limit ourselves to processing 4 requests at the same time.
+    router.layer(GlobalConcurrencyLimitLayer::new(4))
+}
+
 #[tokio::main]
 async fn main() {
     env_logger::init();
 
-    let cfg = config::load(&env::args().nth(1).unwrap(), true, None, None, None);
+    let config_path = env::args().nth(1).unwrap();
+    let cfg = config::load(&config_path, true, None, None, None);
     let ident_map = IdentMap::load(&cfg);
+    let local_servers = make_all_local_servers(&config_path).unwrap();
 
     let cfg = Arc::new(cfg);
     let ident_map = Arc::new(ident_map);
 
+    // The query routes are the same ones served by pipeline-server.rs so that
+    // a local searchfox only needs this one server.  Each set of routes has its
+    // own concurrency limit.
+    let app = web_router()
+        .merge(pipeline_router())
+        .layer(middleware::from_fn(conditional_get))
+        .layer(Extension(cfg))
+        .layer(Extension(ident_map))
+        .layer(Extension(local_servers));
+
     {
         // We *append* to the status file because other server components
         // also write to this file when they are done starting up, and we
@@ -355,70 +485,8 @@ async fn main() {
         writeln!(status_out, "web-server.rs loaded").unwrap();
     }
 
-    // Limit ourselves to processing 4 requests at the same time.
-    static SEMAPHORE: Semaphore = Semaphore::const_new(4);
-
     let addr: SocketAddr = "0.0.0.0:8001".parse().unwrap();
-    let server = TcpListener::bind(addr).await.unwrap();
+    let listener = TcpListener::bind(addr).await.unwrap();
     println!("Listening on http://{addr}");
-    loop {
-        let (stream, _) = server.accept().await.unwrap();
-        let io = TokioIo::new(stream);
-
-        let cfg = cfg.clone();
-        let ident_map = ident_map.clone();
-        tokio::spawn(async move {
-            let handler = async |req: http::Request<_>| {
-                if req.method() != Method::GET {
-                    return http::Response::builder()
-                        .status(StatusCode::METHOD_NOT_ALLOWED)
-                        .body("Invalid method".to_string());
-                }
-
-                let response = {
-                    let _permit = SEMAPHORE.acquire().await.unwrap();
-                    let cfg = cfg.clone();
-                    let ident_map = ident_map.clone();
-                    match tokio::task::spawn_blocking(move || {
-                        let path = req.uri().path();
-                        handle(&cfg, &ident_map, WebRequest { path })
-                    })
-                    .await
-                    {
-                        Ok(response) => response,
-
-                        Err(join_err) if join_err.is_panic() => {
-                            eprintln!("Request handler panicked: {:?}", join_err);
-                            WebResponse::internal_error("Internal server error".to_owned())
-                        }
-
-                        Err(join_err) => {
-                            eprintln!(
-                                "Request handler task failed or was cancelled: {:?}",
-                                join_err
-                            );
-                            WebResponse::internal_error("Internal server error".to_owned())
-                        }
-                    }
-                };
-
-                let mut builder = http::Response::builder()
-                    .status(response.status)
-                    .header(header::CONTENT_TYPE, response.content_type);
-
-                if let Some(loc) = response.redirect_location {
-                    builder = builder.header(header::LOCATION, loc);
-                }
-
-                builder.body(response.output)
-            };
-
-            if let Err(err) = http1::Builder::new()
-                .serve_connection(io, service_fn(handler))
-                .await
-            {
-                println!("Error serving connection: {:?}", err);
-            }
-        });
-    }
+    axum::serve(listener, app).await.unwrap();
 }
@@ -1,8 +1,11 @@
-use crate::file_format::config::Config;
+use crate::file_format::config::{Config, GitData};
 use crate::{git_ops, links};
 
-use serde_json::{Map, json, to_string};
+use git2::Oid;
+use serde_json::{Map, Value, json, to_string};
 use std::borrow::Cow;
+use std::collections::HashMap;
+use std::ops::RangeInclusive;
 use std::str::Split;
 
 fn find_phab_rev(iter: Split<char>) -> Option<String> {
@@ -109,8 +112,8 @@ pub fn get_commit_info(cfg: &Config, tree_name: &str, revs: &str) -> Result<Stri
 
         obj.insert("date".to_owned(), json!(t));
 
-        if let (Some(hg_path), Some(hg_id)) =
-            (&tree_config.paths.hg_root, git.hg_map.get(&commit_obj.id()))
+        if let Some(hg_path) = &tree_config.paths.hg_root
+            && let Some(hg_id) = git.git_to_hg(&commit_obj.id())
         {
             obj.insert(
                 "fulldiff".to_owned(),
@@ -165,3 +168,294 @@ impl<'a> LineData<'a> {
         format!("{}:{}:{}:", self.rev, self.path, self.lineno)
     }
 }
+
+/// Parse a line range of the form "N" or "N-M" (both inclusive and 1-based).
+pub fn parse_line_range(s: &str) -> Option<RangeInclusive<u32>> {
+    let (start, end) = match s.split_once('-') {
+        Some((start, end)) => (start.trim().parse().ok()?, end.trim().parse().ok()?),
+        None => {
+            let line = s.trim().parse().ok()?;
+            (line, line)
+        }
+    };
+    if start == 0 || end < start {
+        return None;
+    }
+    Some(start..=end)
+}
+
+/// The most ignored revisions we will look through for a single line.  This
+/// mainly exists to protect us from cycles in broken blame data.
+const MAX_IGNORED_REVS: usize = 16;
+
+fn find_blame_commit(git: &GitData, rev: Oid) -> Option<git2::Commit<'_>> {
+    let blame_oid = git.blame_map.get(&rev)?;
+    git.blame_repo.as_ref()?.find_commit(*blame_oid).ok()
+}
+
+/// A `LineData` resolved against the path it was looked up for.
+struct BlameOrigin {
+    rev: Oid,
+    path: String,
+    lineno: u32,
+}
+
+impl BlameOrigin {
+    fn from_line_data(data: &LineData, looked_up_path: &str) -> Option<Self> {
+        Some(BlameOrigin {
+            rev: Oid::from_str(&data.rev).ok()?,
+            path: if data.is_path_unchanged() {
+                looked_up_path.to_owned()
+            } else {
+                data.path.to_string()
+            },
+            lineno: data.lineno.parse().ok()?,
+        })
+    }
+}
+
+/// Map a 1-based line number in the new side of a diff to the corresponding line
+/// on the old side, given the diff's `(old_start, old_lines, new_start,
+/// new_lines)` hunks as produced with zero context lines.  Lines in a modified
+/// hunk map to the same offset in the old side of the hunk (clamped to its
+/// length), which is what we want for reformatting changes.  Lines that were
+/// purely inserted have no counterpart and map to None.
+fn map_line_through_hunks(hunks: &[(u32, u32, u32, u32)], lineno: u32) -> Option<u32> {
+    let mut delta: i64 = 0;
+    for &(old_start, old_lines, new_start, new_lines) in hunks {
+        // For a zero-length side, the start is the line before the change.
+        let (new_first, new_end) = match new_lines {
+            0 => (new_start + 1, new_start + 1),
+            _ => (new_start, new_start + new_lines),
+        };
+        let old_end = match old_lines {
+            0 => old_start + 1,
+            _ => old_start + old_lines,
+        };
+        if lineno < new_first {
+            break;
+        }
+        if lineno < new_end {
+            if old_lines == 0 {
+                return None;
+            }
+            return Some(old_start + (lineno - new_start).min(old_lines - 1));
+        }
+        delta = old_end as i64 - new_end as i64;
+    }
+    u32::try_from(lineno as i64 + delta).ok()
+}
+
+/// Map a line of `path` as of `rev` to the same line in `parent` by diffing
+/// the file between the two revisions.
+fn map_line_to_parent(
+    git: &GitData,
+    rev: Oid,
+    parent: Oid,
+    path: &str,
+    lineno: u32,
+) -> Option<u32> {
+    let read_blob = |oid: Oid| {
+        let commit = git.repo.find_commit(oid).ok()?;
+        let entry = commit
+            .tree()
+            .ok()?
+            .get_path(std::path::Path::new(path))
+            .ok()?;
+        git.repo.find_blob(entry.id()).ok()
+    };
+    let new_blob = read_blob(rev)?;
+    let old_blob = read_blob(parent)?;
+    let mut opts = git2::DiffOptions::new();
+    opts.context_lines(0);
+    let patch = git2::Patch::from_blobs(&old_blob, None, &new_blob, None, Some(&mut opts)).ok()?;
+    let hunks = (0..patch.num_hunks())
+        .filter_map(|i| patch.hunk(i).ok())
+        .map(|(hunk, _)| {
+            (
+                hunk.old_start(),
+                hunk.old_lines(),
+                hunk.new_start(),
+                hunk.new_lines(),
+            )
+        })
+        .collect::<Vec<_>>();
+    map_line_through_hunks(&hunks, lineno)
+}
+
+/// Look up where the given line came from as of the given revision.
+fn find_line_origin(git: &GitData, rev: Oid, path: &str, lineno: u32) -> Option<BlameOrigin> {
+    let blame_commit = find_blame_commit(git, rev);
+    let blame_lines = git_ops::get_blame_lines(Some(git), &blame_commit, path)?;
+    let line = blame_lines.get(lineno.checked_sub(1)? as usize)?;
+    BlameOrigin::from_line_data(&LineData::deserialize(line), path)
+}
+
+fn blame_rev_info(git: &GitData, rev: Oid) -> Map<String, Value> {
+    let mut obj = Map::new();
+    obj.insert("rev".to_owned(), json!(rev.to_string()));
+    if let Ok(commit) = git.repo.find_commit(rev) {
+        let sig = commit.author();
+        let (name, email) = git
+            .mailmap
+            .lookup(sig.name().unwrap_or(""), sig.email().unwrap_or(""));
+        obj.insert("author".to_owned(), json!(name));
+        obj.insert("email".to_owned(), json!(email));
+        let t = git_ops::git_time_to_chrono(commit.time());
+        obj.insert("date".to_owned(), json!(t.to_rfc3339()));
+    }
+    if let Some(hg_id) = git.git_to_hg(&rev) {
+        obj.insert("hg".to_owned(), json!(hg_id));
+    }
+    obj
+}
+
+/// Why `get_blame_json` couldn't produce blame, so that callers can tell bad
+/// requests apart from problems with our data.
+#[derive(Debug, PartialEq)]
+pub enum BlameError {
+    /// The revision doesn't resolve to a commit.
+    BadRevision,
+    /// There's no blame for the path as of the revision, usually because the
+    /// path doesn't exist there.
+    NoSuchPath,
+    /// The blame repository is missing the revision or has unparseable data.
+    BrokenBlame(&'static str),
+}
+
+impl std::fmt::Display for BlameError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            BlameError::BadRevision => write!(f, "Bad revision"),
+            BlameError::NoSuchPath => write!(f, "No blame for path"),
+            BlameError::BrokenBlame(msg) => write!(f, "{}", msg),
+        }
+    }
+}
+
+/// Produce per-line blame information for `lines` of `path` as of `rev`.  Lines
+/// past the end of the file are ignored.
+///
+/// Each line reports the revision the line originated in (with its mail-mapped
+/// author, date, and hg revision when known), the path and line number it had
+/// in that revision, and the chain of paths the line lived at if it was moved
+/// between files.
+///
+/// Revisions in the `BlameIgnoreList` are looked through by continuing the
+/// lookup in the ignored revision's first parent.  The blame data doesn't tell
+/// us where a line was before it was (re)introduced, so we map the line through
+/// the ignored revision's diff of the file, stopping if the line was newly
+/// inserted rather than modified.  The skipped revisions are reported as
+/// "ignored".
+///
+/// Requires `GitData` loaded with its blame indexes.
+pub fn get_blame_json(
+    git: &GitData,
+    rev: &str,
+    path: &str,
+    lines: RangeInclusive<u32>,
+) -> Result<Value, BlameError> {
+    let commit = git
+        .repo
+        .revparse_single(rev)
+        .and_then(|obj| obj.peel_to_commit())
+        .map_err(|_| BlameError::BadRevision)?;
+    let blame_commit = find_blame_commit(git, commit.id())
+        .ok_or(BlameError::BrokenBlame("Unable to find blame"))?;
+    let blame_lines = git_ops::get_blame_lines(Some(git), &Some(blame_commit), path)
+        .ok_or(BlameError::NoSuchPath)?;
+
+    let mut rev_infos: HashMap<Oid, Map<String, Value>> = HashMap::new();
+    let mut results = vec![];
+    for lineno in lines {
+        let Some(line) = (lineno as usize)
+            .checked_sub(1)
+            .and_then(|idx| blame_lines.get(idx))
+        else {
+            break;
+        };
+        let Some(mut origin) = BlameOrigin::from_line_data(&LineData::deserialize(line), path)
+        else {
+            return Err(BlameError::BrokenBlame("Bad blame data"));
+        };
+
+        let mut path_chain = vec![path.to_owned()];
+        let mut ignored = vec![];
+        while ignored.len() < MAX_IGNORED_REVS
+            && git.should_ignore_for_blame(&origin.rev.to_string())
+        {
+            let Some(parent) = git
+                .repo
+                .find_commit(origin.rev)
+                .ok()
+                .and_then(|c| c.parent_id(0).ok())
+            else {
+                break;
+            };
+            let Some(parent_lineno) =
+                map_line_to_parent(git, origin.rev, parent, &origin.path, origin.lineno)
+            else {
+                break;
+            };
+            let Some(prev) = find_line_origin(git, parent, &origin.path, parent_lineno) else {
+                break;
+            };
+            if path_chain.last() != Some(&origin.path) {
+                path_chain.push(origin.path.clone());
+            }
+            ignored.push(origin.rev.to_string());
+            origin = prev;
+        }
+        if path_chain.last() != Some(&origin.path) {
+            path_chain.push(origin.path.clone());
+        }
+
+        let mut obj = rev_infos
+            .entry(origin.rev)
+            .or_insert_with(|| blame_rev_info(git, origin.rev))
+            .clone();
+        obj.insert("line".to_owned(), json!(lineno));
+        obj.insert("path".to_owned(), json!(origin.path));
+        obj.insert("lineno".to_owned(), json!(origin.lineno));
+        obj.insert("pathChain".to_owned(), json!(path_chain));
+        if !ignored.is_empty() {
+            obj.insert("ignored".to_owned(), json!(ignored));
+        }
+        results.push(Value::Object(obj));
+    }
+
+    Ok(json!({
+        "rev": commit.id().to_string(),
+        "path": path,
+        "lines": results,
+    }))
+}
+
+#[test]
+fn test_parse_line_range() {
+    assert_eq!(parse_line_range("7"), Some(7..=7));
+    assert_eq!(parse_line_range("3-10"), Some(3..=10));
+    assert_eq!(parse_line_range("0-10"), None);
+    assert_eq!(parse_line_range("10-3"), None);
+    assert_eq!(parse_line_range("a-b"), None);
+}
+
+#[test]
+fn test_map_line_through_hunks() {
+    // Line 3 was reformatted into lines 3-4, and 2 lines were inserted after
+    // old line 7 (new line 8).
+    let hunks = [(3, 1, 3, 2), (7, 0, 9, 2)];
+    assert_eq!(map_line_through_hunks(&hunks, 1), Some(1));
+    assert_eq!(map_line_through_hunks(&hunks, 3), Some(3));
+    assert_eq!(map_line_through_hunks(&hunks, 4), Some(3));
+    assert_eq!(map_line_through_hunks(&hunks, 5), Some(4));
+    assert_eq!(map_line_through_hunks(&hunks, 8), Some(7));
+    assert_eq!(map_line_through_hunks(&hunks, 9), None);
+    assert_eq!(map_line_through_hunks(&hunks, 10), None);
+    assert_eq!(map_line_through_hunks(&hunks, 11), Some(8));
+
+    // Old lines 2-3 were deleted.
+    let hunks = [(2, 2, 1, 0)];
+    assert_eq!(map_line_through_hunks(&hunks, 1), Some(1));
+    assert_eq!(map_line_through_hunks(&hunks, 2), Some(4));
+}
@@ -20,10 +20,12 @@ use crate::{
 
 use super::{
     cmd_augment_results::AugmentResultsCommand, cmd_batch_render::BatchRenderCommand,
-    cmd_format_symbols::FormatSymbolsCommand, cmd_fuse_crossrefs::FuseCrossrefsCommand,
-    cmd_jq::JQCommand, cmd_jumpref_lookup::JumprefLookupCommand, cmd_render::RenderCommand,
+    cmd_blame::BlameCommand, cmd_format_symbols::FormatSymbolsCommand,
+    cmd_fuse_crossrefs::FuseCrossrefsCommand, cmd_jq::JQCommand,
+    cmd_jumpref_lookup::JumprefLookupCommand, cmd_layout_audit::LayoutAuditCommand,
+    cmd_platform_divergence::PlatformDivergenceCommand, cmd_render::RenderCommand,
     cmd_tokenize_source::TokenizeSourceCommand, cmd_traverse::TraverseCommand,
-    cmd_webtest::WebtestCommand,
+    cmd_validate_analysis::ValidateAnalysisCommand, cmd_webtest::WebtestCommand,
 };
 use super::{
     cmd_cat_html::CatHtmlCommand,
@@ -57,6 +59,8 @@ pub fn fab_command_from_opts(
 
         (Command::BatchRender(br), _) => Ok(Box::new(BatchRenderCommand { args: br })),
 
+        (Command::Blame(b), _) => Ok(Box::new(BlameCommand { args: b })),
+
         (Command::CatHtml(ch), _) => Ok(Box::new(CatHtmlCommand { args: ch })),
 
         (Command::CrossrefExpand(ce), _) => Ok(Box::new(CrossrefExpandCommand { args: ce })),
@@ -73,12 +77,18 @@ pub fn fab_command_from_opts(
 
         (Command::JQ(cl), _) => Ok(Box::new(JQCommand { args: cl })),
 
+        (Command::LayoutAudit(la), _) => Ok(Box::new(LayoutAuditCommand { args: la })),
+
         (Command::MergeAnalyses(ma), _) => Ok(Box::new(MergeAnalysesCommand { args: ma })),
 
         (Command::NormalizeUnstableData(pf), _) => {
             Ok(Box::new(NormalizeUnstableDataCommand { args: pf }))
         }
 
+        (Command::PlatformDivergence(pd), _) => {
+            Ok(Box::new(PlatformDivergenceCommand { args: pd }))
+        }
+
         (Command::Query(q), _) => Ok(Box::new(QueryCommand { args: q })),
 
         (Command::Render(r), _) => Ok(Box::new(RenderCommand { args: r })),
@@ -97,6 +107,8 @@ pub fn fab_command_from_opts(
 
         (Command::Traverse(t), _) => Ok(Box::new(TraverseCommand { args: t })),
 
+        (Command::ValidateAnalysis(va), _) => Ok(Box::new(ValidateAnalysisCommand { args: va })),
+
         (Command::Webtest(t), CommandSafetyLevel::DangerousToolUseAllowed) => {
             Ok(Box::new(WebtestCommand { args: t }))
         }
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use clap::Args;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
+    blame::parse_line_range,
+};
+
+/// Output per-line blame information for a source file: the revision each line
+/// originated in along with its (mail-mapped) author, date, hg revision, and
+/// the paths the line lived at if it moved between files.  Revisions in the
+/// tree's blame ignore list are looked through.
+#[derive(Debug, Args)]
+pub struct Blame {
+    /// Tree-relative source file path.
+    #[clap(value_parser)]
+    file: String,
+
+    /// Lines to report on, either "N" or "N-M".  Defaults to the whole file.
+    #[clap(short, long, value_parser)]
+    lines: Option<String>,
+
+    /// Revision to blame the file as of.  Defaults to the indexed revision.
+    #[clap(short, long, value_parser)]
+    rev: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct BlameCommand {
+    pub args: Blame,
+}
+
+#[async_trait]
+impl PipelineCommand for BlameCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let lines = match &self.args.lines {
+            Some(range) => parse_line_range(range).ok_or_else(|| {
+                ServerError::StickyProblem(ErrorDetails {
+                    layer: ErrorLayer::BadInput,
+                    message: format!("bad line range: {}", range),
+                })
+            })?,
+            None => 1..=u32::MAX,
+        };
+
+        let value = server
+            .fetch_blame(&self.args.file, self.args.rev.as_deref(), lines)
+            .await?;
+        Ok(PipelineValues::JsonValue(JsonValue { value }))
+    }
+}
@@ -2,12 +2,12 @@ use async_trait::async_trait;
 use clap::Args;
 use tokio_stream::StreamExt;
 
-use super::interface::{
-    JsonRecords, PipelineCommand, PipelineValues, RecordType, SymbolicQueryOpts,
-};
+use super::interface::{JsonRecords, PipelineCommand, PipelineValues, SymbolicQueryOpts};
 use crate::{
-    abstract_server::{AbstractServer, Result},
+    abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
+    blame::parse_line_range,
     cmd_pipeline::interface::JsonRecordsByFile,
+    file_format::analysis_reader::{AnalysisPredicate, RecordType},
 };
 
 /// Filter the contents of a single analysis file.
@@ -23,6 +23,10 @@ pub struct FilterAnalysis {
     #[clap(long, short, value_parser)]
     kind: Option<String>,
 
+    /// Only include records on these lines, either "N" or "N-M".
+    #[clap(long, short, value_parser)]
+    lines: Option<String>,
+
     #[clap(flatten)]
     query_opts: SymbolicQueryOpts,
 }
@@ -68,10 +72,11 @@ fn remove_source_prefix(name: &str) -> &str {
     }
 }
 
-/// ### Implementation Note
-/// Filtering is currently performed via generic JSON rather than the strongly
-/// typed `analysis.rs` types, but this pre-dates the change to using serde-json
-/// and it probably makes sense to switch to using the raw types.
+/// Record type, kind, symbol, and line filtering are pushed down into the
+/// analysis reader; identifier filtering happens here because it needs to
+/// strip the source record prefix from the pretty name.  The records are
+/// emitted exactly as they're stored, so this uses the raw JSON records rather
+/// than the typed ones.
 #[async_trait]
 impl PipelineCommand for FilterAnalysisCommand {
     async fn execute(
@@ -79,62 +84,25 @@ impl PipelineCommand for FilterAnalysisCommand {
         server: &(dyn AbstractServer + Send + Sync),
         _input: PipelineValues,
     ) -> Result<PipelineValues> {
-        let mut filtered = server.fetch_raw_analysis(&self.args.file).await?;
-
-        // ## Filter by record type
-        if let Some(record_types) = &self.args.record_type {
-            filtered = Box::pin(filtered.filter(move |val| {
-                // Record type is currently indicated via boolean presence of
-                // "source", "target", or "structured" so check for the
-                // stringified version of the enum value.
-                for rt in record_types {
-                    if val.get(rt.name()).is_some() {
-                        return true;
-                    }
-                }
-                false
-            }));
-        }
-
-        // ## Filter by kind
-        if let Some(kind) = &self.args.kind {
-            // kind varies by record type:
-            // - target: "kind" is a single valued attribute
-            // - source: kind is baked into the comma-delimited "syntax"
-            filtered = Box::pin(filtered.filter(move |val| {
-                match (val["source"].is_number(), val["target"].is_number()) {
-                    // source: consult "syntax"
-                    (true, _) => match val["syntax"].as_str() {
-                        None => false,
-                        Some(actual) => actual.split(",").any(|k| k == kind),
-                    },
-                    // target: consult "kind"
-                    (false, true) => match val["kind"].as_str() {
-                        None => false,
-                        Some(actual) => actual == kind,
-                    },
-                    _ => false,
-                }
-            }));
-        }
-
-        // ## Filter by symbol
-        if let Some(symbol) = &self.args.query_opts.symbol {
-            // "sym" is optionally
-            filtered = Box::pin(filtered.filter(move |val| match val["sym"].as_str() {
-                None => false,
-                Some(actual) => actual.split(",").any(|s| s == symbol),
-            }));
-        }
-
-        // ## Filter by symbol prefix
-        if let Some(symbol_prefix) = &self.args.query_opts.symbol_prefix {
-            // "sym" is optionally
-            filtered = Box::pin(filtered.filter(move |val| match val["sym"].as_str() {
-                None => false,
-                Some(actual) => actual.split(",").any(|s| s.starts_with(symbol_prefix)),
-            }));
-        }
+        let lines = match &self.args.lines {
+            Some(range) => Some(parse_line_range(range).ok_or_else(|| {
+                ServerError::StickyProblem(ErrorDetails {
+                    layer: ErrorLayer::BadInput,
+                    message: format!("bad line range: {}", range),
+                })
+            })?),
+            None => None,
+        };
+        let predicate = AnalysisPredicate {
+            record_types: self.args.record_type.clone(),
+            kind: self.args.kind.clone(),
+            symbol: self.args.query_opts.symbol.clone(),
+            symbol_prefix: self.args.query_opts.symbol_prefix.clone(),
+            lines,
+        };
+        let mut filtered = server
+            .fetch_raw_analysis(&self.args.file, &predicate)
+            .await?;
 
         // ## Filter by identifier
         if let Some(identifier) = &self.args.query_opts.identifier {
@@ -1,19 +1,21 @@
 use std::cmp::Ordering;
 use std::collections::{HashMap, HashSet, VecDeque};
 use std::hash::{DefaultHasher, Hash, Hasher};
+use std::sync::Arc;
 
 use async_trait::async_trait;
 use clap::{Args, ValueEnum};
 use itertools::Itertools;
-use serde_json::from_str;
+use serde_json::{from_str, json};
 
 use ustr::Ustr;
 
 use super::{
     interface::{
-        PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolTreeTable,
-        SymbolTreeTableAlignmentAndSize, SymbolTreeTableField, SymbolTreeTableFieldOffsetAndSize,
-        SymbolTreeTableFieldType, SymbolTreeTableItem, SymbolTreeTableList, SymbolTreeTableNode,
+        PipelineCommand, PipelineValues, SymbolCrossrefInfo, SymbolCrossrefInfoList,
+        SymbolTreeTable, SymbolTreeTableAlignmentAndSize, SymbolTreeTableField,
+        SymbolTreeTableFieldOffsetAndSize, SymbolTreeTableFieldType, SymbolTreeTableItem,
+        SymbolTreeTableList, SymbolTreeTableNode,
     },
     symbol_graph::{DerivedSymbolInfo, SymbolGraphNodeId},
 };
@@ -28,6 +30,10 @@ use crate::file_format::jumpref::JumprefData;
 #[derive(Clone, Debug, PartialEq, ValueEnum)]
 pub enum SymbolFormatMode {
     FieldLayout,
+    // Field layout with two columns, comparing the layout of a class on two
+    // platforms or in two trees, plus notes on size changes, new holes, and
+    // reordered fields.
+    FieldLayoutDiff,
     // - class-field-use-matrix: table for each class, look up all its methods and all its
     //   fields, then filter the method "calls" to the fields.
     // - caller-matrix: look up a class, get all its methods.  look up all of
@@ -55,6 +61,20 @@ pub struct FormatSymbols {
 
     #[clap(long, value_parser)]
     pub hide_cols: Option<String>,
+
+    /// For field-layout-diff, the platform whose layout the comparison starts
+    /// from.  When comparing against another tree, this picks the platform
+    /// variant used for both trees, defaulting to the canonical variant.
+    #[clap(long, value_parser)]
+    pub base_platform: Option<String>,
+
+    /// For field-layout-diff, the platform to compare with the base platform.
+    #[clap(long, value_parser)]
+    pub compare_platform: Option<String>,
+
+    /// For field-layout-diff, the tree to compare with the current tree.
+    #[clap(long, value_parser)]
+    pub compare_tree: Option<String>,
 }
 
 #[derive(Debug)]
@@ -449,6 +469,10 @@ struct PlatformMap {
     has_opt: bool,
 
     is_all_platform_same: bool,
+
+    // In field-layout-diff mode, the base and compare labels, whose columns
+    // come first and in this order instead of the usual platform order.
+    column_order: Vec<String>,
 }
 
 impl PlatformMap {
@@ -458,6 +482,7 @@ impl PlatformMap {
             platform_name_to_id: HashMap::new(),
             has_opt: false,
             is_all_platform_same: false,
+            column_order: vec![],
         }
     }
 
@@ -501,6 +526,14 @@ impl PlatformMap {
             .collect()
     }
 
+    fn column_index(&self, platform_id: &PlatformId) -> usize {
+        let name = &self.platform_id_to_name[platform_id.0 as usize];
+        self.column_order
+            .iter()
+            .position(|label| label == name)
+            .unwrap_or(self.column_order.len())
+    }
+
     fn get_name(&self, platform_id: &PlatformId) -> String {
         let name = self.platform_id_to_name[platform_id.0 as usize].clone();
         if self.has_opt && !self.is_all_platform_same && !name.ends_with("-opt") {
@@ -615,6 +648,13 @@ impl FieldsPerPlatform {
 
         // Make the order consistent as much as possible across classes.
         platform_ids.sort_by(|a, b| {
+            let result = platform_map
+                .column_index(a)
+                .cmp(&platform_map.column_index(b));
+            if result != Ordering::Equal {
+                return result;
+            }
+
             let a_name = platform_map.get_name(a);
             let b_name = platform_map.get_name(b);
 
@@ -940,6 +980,14 @@ struct ClassMap {
     // Formatted lines of each file referred from fields.
     file_lines: HashMap<String, Vec<String>>,
 
+    // In field-layout-diff mode against another tree, the label of the
+    // platform that holds the other tree's layout and the server for that
+    // tree, so that the symbols and lines of its fields come from that tree.
+    compare_tree: Option<(String, Arc<dyn AbstractServer + Send + Sync>)>,
+
+    // Formatted lines of each file referred from the other tree's fields.
+    compare_file_lines: HashMap<String, Vec<String>>,
+
     has_multiple_inheritance: bool,
     has_non_zero_super_offset: bool,
 
@@ -947,6 +995,10 @@ struct ClassMap {
 
     root_class_id: Option<ClassId>,
     stt: SymbolTreeTable,
+
+    // Notes summarizing the differences between the columns in
+    // field-layout-diff mode, emitted as warnings on the root class.
+    diff_notes: Vec<String>,
 }
 
 impl ClassMap {
@@ -957,11 +1009,14 @@ impl ClassMap {
             platform_map: PlatformMap::new(),
             groups: vec![],
             file_lines: HashMap::new(),
+            compare_tree: None,
+            compare_file_lines: HashMap::new(),
             has_multiple_inheritance: false,
             has_non_zero_super_offset: false,
             has_layout_field: false,
             root_class_id: None,
             stt: SymbolTreeTable::new(),
+            diff_notes: vec![],
         }
     }
 
@@ -969,6 +1024,31 @@ impl ClassMap {
         self.has_multiple_inheritance && !self.has_non_zero_super_offset
     }
 
+    // The other tree's server if the given platform holds its layout.
+    fn compare_server_for(
+        &self,
+        platform_id: &PlatformId,
+    ) -> Option<Arc<dyn AbstractServer + Send + Sync>> {
+        let (label, compare_server) = self.compare_tree.as_ref()?;
+        (self.platform_map.platform_name_to_id.get(label) == Some(platform_id))
+            .then(|| compare_server.clone())
+    }
+
+    // Whether the fields of the given group only come from the other tree.
+    fn is_compare_group(&self, group_index: usize) -> bool {
+        let Some(compare_id) = self
+            .compare_tree
+            .as_ref()
+            .and_then(|(label, _)| self.platform_map.platform_name_to_id.get(label))
+        else {
+            return false;
+        };
+        self.groups[group_index]
+            .1
+            .iter()
+            .all(|platform_id| platform_id == compare_id)
+    }
+
     async fn populate(
         &mut self,
         nom_sym_info: SymbolCrossrefInfo,
@@ -1130,6 +1210,9 @@ impl ClassMap {
             self.class_list.push(traversal_id);
 
             for (platform_id, s) in class_per_platform_list {
+                let compare_server = self.compare_server_for(&platform_id);
+                let platform_server = compare_server.as_deref().unwrap_or(server);
+
                 let class_alignment = s.alignment_bytes;
 
                 if let Some(class_size) = s.size_bytes {
@@ -1159,7 +1242,7 @@ impl ClassMap {
                         let (field_id, field_info) = self
                             .stt
                             .node_set
-                            .ensure_symbol(&field.sym, server, depth + 1)
+                            .ensure_symbol(&field.sym, platform_server, depth + 1)
                             .await?;
 
                         (field_id, field_info.get_def_lno())
@@ -1174,7 +1257,7 @@ impl ClassMap {
                         let _ = self
                             .stt
                             .node_set
-                            .ensure_symbol(&field.type_sym, server, depth + 1)
+                            .ensure_symbol(&field.type_sym, platform_server, depth + 1)
                             .await?;
 
                         field_type_syms_vec.push(field.type_sym.to_string());
@@ -1188,7 +1271,7 @@ impl ClassMap {
                         let _ = self
                             .stt
                             .node_set
-                            .ensure_symbol(&info.sym, server, depth + 1)
+                            .ensure_symbol(&info.sym, platform_server, depth + 1)
                             .await?;
 
                         field_type_syms_vec.push(info.sym.to_string());
@@ -1208,7 +1291,12 @@ impl ClassMap {
                         field_lineno,
                         &field,
                     );
-                    self.populate_file_lines(&field.def_path, server).await?;
+                    self.populate_file_lines(
+                        &field.def_path,
+                        platform_server,
+                        compare_server.is_some(),
+                    )
+                    .await?;
                     fields_per_platform.add_field(&platform_id, field.clone());
                 }
             }
@@ -1288,6 +1376,10 @@ impl ClassMap {
                     }
                     maybe_platform_id = Some(platform_id);
                 }
+                let compare_server = maybe_platform_id
+                    .as_ref()
+                    .and_then(|platform_id| self.compare_server_for(platform_id));
+                let platform_server = compare_server.as_deref().unwrap_or(server);
 
                 let class_alignment = s.alignment_bytes;
 
@@ -1339,7 +1431,7 @@ impl ClassMap {
                     let (super_id, _) = self
                         .stt
                         .node_set
-                        .ensure_symbol(&super_info.sym, server, depth + 1)
+                        .ensure_symbol(&super_info.sym, platform_server, depth + 1)
                         .await?;
 
                     if super_info.offset_bytes.unwrap_or(0) > 0 {
@@ -1370,7 +1462,7 @@ impl ClassMap {
                         let (field_id, field_info) = self
                             .stt
                             .node_set
-                            .ensure_symbol(&field.sym, server, depth + 1)
+                            .ensure_symbol(&field.sym, platform_server, depth + 1)
                             .await?;
 
                         (field_id, field_info.get_def_lno())
@@ -1385,7 +1477,7 @@ impl ClassMap {
                         let _ = self
                             .stt
                             .node_set
-                            .ensure_symbol(&field.type_sym, server, depth + 1)
+                            .ensure_symbol(&field.type_sym, platform_server, depth + 1)
                             .await?;
 
                         field_type_syms_vec.push(field.type_sym.to_string());
@@ -1399,7 +1491,7 @@ impl ClassMap {
                         let _ = self
                             .stt
                             .node_set
-                            .ensure_symbol(&info.sym, server, depth + 1)
+                            .ensure_symbol(&info.sym, platform_server, depth + 1)
                             .await?;
 
                         field_type_syms_vec.push(info.sym.to_string());
@@ -1421,7 +1513,12 @@ impl ClassMap {
                             field_lineno,
                             &field,
                         );
-                        self.populate_file_lines(&field.def_path, server).await?;
+                        self.populate_file_lines(
+                            &field.def_path,
+                            platform_server,
+                            compare_server.is_some(),
+                        )
+                        .await?;
                         fields_per_platform.add_field(platform_id, field.clone());
                     } else {
                         for platform_id in item.platforms() {
@@ -1437,7 +1534,8 @@ impl ClassMap {
                                 field_lineno,
                                 &field,
                             );
-                            self.populate_file_lines(&field.def_path, server).await?;
+                            self.populate_file_lines(&field.def_path, server, false)
+                                .await?;
                             fields_per_platform.add_field(&platform_id, field.clone());
                         }
                     }
@@ -1475,12 +1573,18 @@ impl ClassMap {
         &mut self,
         path: &String,
         server: &(dyn AbstractServer + Send + Sync),
+        from_compare_tree: bool,
     ) -> Result<()> {
         if path.is_empty() {
             return Ok(());
         }
 
-        if self.file_lines.contains_key(path) {
+        let file_lines = if from_compare_tree {
+            &self.compare_file_lines
+        } else {
+            &self.file_lines
+        };
+        if file_lines.contains_key(path) {
             return Ok(());
         }
 
@@ -1498,7 +1602,11 @@ impl ClassMap {
             }
         }
 
-        self.file_lines.insert(path.clone(), lines);
+        if from_compare_tree {
+            self.compare_file_lines.insert(path.clone(), lines);
+        } else {
+            self.file_lines.insert(path.clone(), lines);
+        }
 
         Ok(())
     }
@@ -1569,6 +1677,181 @@ impl ClassMap {
         Ok(())
     }
 
+    // Populate `diff_notes` by comparing the columns of the two labels that
+    // `make_diff_structured` gave the root class's variants.
+    fn compute_diff_notes(&mut self, base_label: &str, compare_label: &str) {
+        let platform_names = &self.platform_map.platform_name_to_id;
+        let (Some(base_id), Some(compare_id)) = (
+            platform_names.get(base_label).copied(),
+            platform_names.get(compare_label).copied(),
+        ) else {
+            return;
+        };
+        let group_index = |platform_id: PlatformId| {
+            self.groups
+                .iter()
+                .position(|(_, platforms)| platforms.contains(&platform_id))
+        };
+        let (Some(base_index), Some(compare_index)) =
+            (group_index(base_id), group_index(compare_id))
+        else {
+            return;
+        };
+
+        let base_name = self.platform_map.get_name(&base_id);
+        let compare_name = self.platform_map.get_name(&compare_id);
+        if base_index == compare_index {
+            self.diff_notes.push(format!(
+                "The layouts for {} and {} are the same",
+                base_name, compare_name
+            ));
+            return;
+        }
+
+        let mut notes = vec![];
+        let root_class_id = self.root_class_id.as_ref().unwrap();
+        for traversal_id in &self.class_list {
+            let cls = self.class_map.get(traversal_id).unwrap();
+
+            if cls.id == *root_class_id
+                && let (Some(base), Some(compare)) = (
+                    cls.alignment_and_size.get(&base_id),
+                    cls.alignment_and_size.get(&compare_id),
+                )
+            {
+                match compare.size.cmp(&base.size) {
+                    Ordering::Greater => notes.push(format!(
+                        "Size grew from {} to {} bytes (+{})",
+                        base.size,
+                        compare.size,
+                        compare.size - base.size
+                    )),
+                    Ordering::Less => notes.push(format!(
+                        "Size shrank from {} to {} bytes (-{})",
+                        base.size,
+                        compare.size,
+                        base.size - compare.size
+                    )),
+                    Ordering::Equal => {}
+                }
+                if base.alignment != compare.alignment
+                    && let (Some(base_alignment), Some(compare_alignment)) =
+                        (base.alignment, compare.alignment)
+                {
+                    notes.push(format!(
+                        "Alignment changed from {} to {} bytes",
+                        base_alignment, compare_alignment
+                    ));
+                }
+            }
+
+            let field_label = |field: &Field| {
+                if field.field_id.is_some() {
+                    field.pretty.clone()
+                } else {
+                    format!("{} {}", cls.name, field.pretty)
+                }
+            };
+            let bit_offset = |field: &Field| {
+                field.offset_bytes as u64 * 8
+                    + field
+                        .bit_positions
+                        .as_ref()
+                        .map_or(0, |pos| pos.begin as u64)
+            };
+
+            // Fields present in both columns, for detecting reordering.
+            let mut common = vec![];
+            for field_variants in &cls.merged_fields {
+                let base = &field_variants[base_index];
+                let compare = &field_variants[compare_index];
+                match (base, compare) {
+                    (None, Some(field)) => notes.push(format!(
+                        "{} was added @ {:#x}",
+                        field_label(field),
+                        field.offset_bytes
+                    )),
+                    (Some(field), None) => {
+                        notes.push(format!("{} was removed", field_label(field)))
+                    }
+                    (Some(base), Some(compare)) => {
+                        if base.size_bytes != compare.size_bytes {
+                            notes.push(format!(
+                                "{} changed size from {} to {}",
+                                field_label(base),
+                                base.size_bytes.unwrap_or(0),
+                                compare.size_bytes.unwrap_or(0)
+                            ));
+                        }
+                        common.push((field_label(base), bit_offset(base), bit_offset(compare)));
+                    }
+                    (None, None) => {}
+                }
+
+                let Some(compare) = compare else {
+                    continue;
+                };
+                let base_hole = base.as_ref().and_then(|f| f.hole_bytes).unwrap_or(0);
+                let compare_hole = compare.hole_bytes.unwrap_or(0);
+                if compare_hole > base_hole {
+                    notes.push(if base_hole == 0 {
+                        format!(
+                            "New {} byte hole before {}",
+                            compare_hole,
+                            field_label(compare)
+                        )
+                    } else {
+                        format!(
+                            "Hole before {} grew from {} to {} bytes",
+                            field_label(compare),
+                            base_hole,
+                            compare_hole
+                        )
+                    });
+                }
+                let base_padding = base.as_ref().and_then(|f| f.end_padding_bytes).unwrap_or(0);
+                let compare_padding = compare.end_padding_bytes.unwrap_or(0);
+                if compare_padding > base_padding {
+                    notes.push(format!(
+                        "Padding after {} grew from {} to {} bytes",
+                        field_label(compare),
+                        base_padding,
+                        compare_padding
+                    ));
+                }
+            }
+
+            let base_order: Vec<_> = common
+                .iter()
+                .sorted_by_key(|(_, base, _)| *base)
+                .map(|(label, _, _)| label)
+                .collect();
+            let compare_order: Vec<_> = common
+                .iter()
+                .sorted_by_key(|(_, _, compare)| *compare)
+                .map(|(label, _, _)| label)
+                .collect();
+            if base_order != compare_order {
+                notes.push(format!(
+                    "Fields of {} were reordered from {} to {}",
+                    cls.name,
+                    base_order.iter().join(", "),
+                    compare_order.iter().join(", ")
+                ));
+            }
+        }
+
+        if notes.is_empty() {
+            notes.push(
+                "No size, hole, or field order differences; see the table for offsets and types"
+                    .to_string(),
+            );
+        }
+        self.diff_notes
+            .push(format!("Comparing {} to {}:", base_name, compare_name));
+        self.diff_notes.extend(notes);
+    }
+
     fn generate_tables(mut self, tables: &mut Vec<SymbolTreeTable>) {
         for (_, platforms) in &self.groups {
             let label = platforms
@@ -1622,6 +1905,14 @@ impl ClassMap {
                 );
             }
 
+            if is_root {
+                for note in &self.diff_notes {
+                    class_node
+                        .items
+                        .push(SymbolTreeTableItem::Warning(note.clone()));
+                }
+            }
+
             let field_prefix = format!("{}::", cls.name);
 
             for field_variants in &cls.merged_fields {
@@ -1686,7 +1977,7 @@ impl ClassMap {
                 let mut type_label_set = HashSet::new();
                 let mut path_and_range_set = HashSet::new();
 
-                for maybe_field in field_variants {
+                for (group_index, maybe_field) in field_variants.iter().enumerate() {
                     match maybe_field {
                         Some(field) => {
                             if !type_label_set.contains(&field.type_pretty) {
@@ -1706,7 +1997,12 @@ impl ClassMap {
                             if !path_and_range_set.contains(&key) {
                                 path_and_range_set.insert(key);
 
-                                if let Some(lines) = self.file_lines.get(&field.def_path) {
+                                let file_lines = if self.is_compare_group(group_index) {
+                                    &self.compare_file_lines
+                                } else {
+                                    &self.file_lines
+                                };
+                                if let Some(lines) = file_lines.get(&field.def_path) {
                                     for lineno in field.start_lineno..=field.end_lineno {
                                         if lineno == 0 {
                                             continue;
@@ -1803,6 +2099,146 @@ impl ClassMap {
     }
 }
 
+// Pick the variant of a structured record for the given platform, or the
+// canonical variant if no platform is given, without the attributes added by
+// merging.
+fn structured_for_platform(
+    structured: &AnalysisStructured,
+    platform: Option<&str>,
+) -> Option<AnalysisStructured> {
+    let variant = match platform {
+        Some(platform) => {
+            structured
+                .per_platform()
+                .into_iter()
+                .find(|(maybe_platform, _)| maybe_platform.as_ref().is_none_or(|p| p == platform))?
+                .1
+        }
+        None => structured,
+    };
+    let mut variant = variant.clone();
+    variant.variants.clear();
+    variant.extra.remove("platforms");
+    Some(variant)
+}
+
+// Combine two structured records into a single record whose variants are
+// labeled with the given names as their platforms, so that the field layout
+// table renders them as two columns.
+fn make_diff_structured(
+    mut base: AnalysisStructured,
+    base_label: &str,
+    mut compare: AnalysisStructured,
+    compare_label: &str,
+) -> AnalysisStructured {
+    base.extra
+        .insert("platforms".to_string(), json!([base_label]));
+    compare
+        .extra
+        .insert("platforms".to_string(), json!([compare_label]));
+    base.variants = vec![compare];
+    base
+}
+
+impl FormatSymbolsCommand {
+    async fn field_layout_diff(
+        &self,
+        cil: SymbolCrossrefInfoList,
+        server: &(dyn AbstractServer + Send + Sync),
+        tables: &mut Vec<SymbolTreeTable>,
+    ) -> Result<()> {
+        let base_tree = server.tree_info()?.name;
+        let (base_label, compare_label) = match (
+            &self.args.compare_tree,
+            &self.args.base_platform,
+            &self.args.compare_platform,
+        ) {
+            (Some(compare_tree), base_platform, None) if *compare_tree != base_tree => {
+                let suffix = match base_platform {
+                    Some(platform) => format!(" ({})", platform),
+                    None => "".to_string(),
+                };
+                (
+                    format!("{}{}", base_tree, suffix),
+                    format!("{}{}", compare_tree, suffix),
+                )
+            }
+            (None, Some(base_platform), Some(compare_platform))
+                if base_platform != compare_platform =>
+            {
+                (base_platform.clone(), compare_platform.clone())
+            }
+            _ => {
+                return Err(ServerError::StickyProblem(ErrorDetails {
+                    layer: ErrorLayer::BadInput,
+                    message: "field-layout-diff needs either a different tree to compare with \
+                              or two different platforms to compare"
+                        .to_string(),
+                }));
+            }
+        };
+        let compare_server: Option<Arc<dyn AbstractServer + Send + Sync>> =
+            match &self.args.compare_tree {
+                Some(compare_tree) => Some(server.other_tree(compare_tree)?.into()),
+                None => None,
+            };
+
+        for mut nom_sym_info in cil.symbol_crossref_infos {
+            let pretty = nom_sym_info.get_pretty();
+            let Some(structured) = nom_sym_info.crossref_info.meta.take() else {
+                continue;
+            };
+
+            let base = structured_for_platform(&structured, self.args.base_platform.as_deref());
+            let compare = match &compare_server {
+                Some(compare_server) => compare_server
+                    .crossref_lookup(&nom_sym_info.symbol)
+                    .await?
+                    .and_then(|info| info.meta)
+                    .and_then(|s| structured_for_platform(&s, self.args.base_platform.as_deref())),
+                None => structured_for_platform(&structured, self.args.compare_platform.as_deref()),
+            };
+
+            // If one side is missing, just show the layout we have.
+            let missing_label = match (base, compare) {
+                (Some(base), Some(compare)) => {
+                    nom_sym_info.crossref_info.meta = Some(make_diff_structured(
+                        base,
+                        &base_label,
+                        compare,
+                        &compare_label,
+                    ));
+                    None
+                }
+                (base, _) => {
+                    nom_sym_info.crossref_info.meta = Some(structured);
+                    Some(if base.is_none() {
+                        &base_label
+                    } else {
+                        &compare_label
+                    })
+                }
+            };
+
+            let mut map = ClassMap::new();
+            map.platform_map.column_order = vec![base_label.clone(), compare_label.clone()];
+            if let Some(compare_server) = &compare_server {
+                map.compare_tree = Some((compare_label.clone(), compare_server.clone()));
+            }
+            map.populate(nom_sym_info, server).await?;
+            match missing_label {
+                Some(label) => map
+                    .diff_notes
+                    .push(format!("{} has no layout for {}", pretty, label)),
+                None => map.compute_diff_notes(&base_label, &compare_label),
+            }
+            map.generate_tables(tables);
+        }
+
+        Ok(())
+    }
+}
+
 #[async_trait]
 impl PipelineCommand for FormatSymbolsCommand {
     async fn execute(
@@ -1820,43 +2256,45 @@ impl PipelineCommand for FormatSymbolsCommand {
             }
         };
 
+        let mut tables = vec![];
         match self.args.mode {
             SymbolFormatMode::FieldLayout => {
-                let mut tables = vec![];
-
                 for nom_sym_info in cil.symbol_crossref_infos {
                     let mut map = ClassMap::new();
                     map.populate(nom_sym_info, server).await?;
                     map.generate_tables(&mut tables);
                 }
+            }
+            SymbolFormatMode::FieldLayoutDiff => {
+                self.field_layout_diff(cil, server, &mut tables).await?;
+            }
+        }
 
-                let mut class_names = vec![];
-                if let Some(cols) = &self.args.show_cols {
-                    for col in cols.split(",") {
-                        if col == "type" {
-                            class_names.push(format!("show-{}", col));
-                        }
-                    }
+        let mut class_names = vec![];
+        if let Some(cols) = &self.args.show_cols {
+            for col in cols.split(",") {
+                if col == "type" {
+                    class_names.push(format!("show-{}", col));
                 }
-                if let Some(cols) = &self.args.hide_cols {
-                    for col in cols.split(",") {
-                        if col == "line" || col == "name" {
-                            class_names.push(format!("hide-{}", col));
-                        }
-                    }
+            }
+        }
+        if let Some(cols) = &self.args.hide_cols {
+            for col in cols.split(",") {
+                if col == "line" || col == "name" {
+                    class_names.push(format!("hide-{}", col));
                 }
-
-                let class_name = if class_names.is_empty() {
-                    None
-                } else {
-                    Some(class_names.join(" "))
-                };
-
-                Ok(PipelineValues::SymbolTreeTableList(SymbolTreeTableList {
-                    tables,
-                    class_name,
-                }))
             }
         }
+
+        let class_name = if class_names.is_empty() {
+            None
+        } else {
+            Some(class_names.join(" "))
+        };
+
+        Ok(PipelineValues::SymbolTreeTableList(SymbolTreeTableList {
+            tables,
+            class_name,
+        }))
     }
 }
@@ -0,0 +1,406 @@
+use std::collections::HashSet;
+
+use async_trait::async_trait;
+use clap::{Args, ValueEnum};
+use serde::Serialize;
+use serde_json::to_value;
+use tokio_stream::StreamExt;
+use tracing::info;
+
+use super::{
+    interface::{JsonValue, PipelineCommand, PipelineValues, TextFile},
+    transforms::path_glob_transform,
+};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    file_format::{
+        analysis::{AnalysisStructured, AnalysisUnion, StructuredFieldInfo, StructuredSuperInfo},
+        analysis_reader::{AnalysisPredicate, RecordType},
+    },
+};
+
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum LayoutAuditSort {
+    /// Total bytes of holes and end padding.
+    Wasted,
+    /// Bytes that would be saved per allocation if packing the class moved it
+    /// into a smaller allocator size class.
+    SizeClassSavings,
+    Size,
+}
+
+#[derive(Clone, Debug, PartialEq, ValueEnum)]
+pub enum LayoutAuditFormat {
+    Json,
+    /// Tab-separated columns with a header row, for `sort` and spreadsheets.
+    Table,
+}
+
+/// Rank the classes defined under a path by wasted padding bytes and by how
+/// much packing them would shrink their allocator size class.
+#[derive(Debug, Args)]
+pub struct LayoutAudit {
+    /// Path to audit; this will be searchfox glob-transformed.
+    #[clap(value_parser)]
+    path: String,
+
+    /// The platform whose layouts are audited.  Defaults to the canonical
+    /// variant of each class.
+    #[clap(long, short, value_parser)]
+    platform: Option<String>,
+
+    #[clap(long, value_parser, value_enum, default_value = "wasted")]
+    sort: LayoutAuditSort,
+
+    #[clap(long, short, value_parser, value_enum, default_value = "json")]
+    format: LayoutAuditFormat,
+
+    /// Only report classes wasting at least this many bytes.
+    #[clap(long, value_parser, default_value = "1")]
+    min_wasted: u32,
+
+    /// Maximum number of classes to report, 0 for no limit.
+    #[clap(long, value_parser, default_value = "0")]
+    top: usize,
+
+    /// Maximum number of files to examine.
+    #[clap(long, value_parser, default_value = "10000")]
+    file_limit: usize,
+}
+
+/// Uses the same inputs as the `format-symbols` field layout table: the
+/// `StructuredFieldInfo` offsets and sizes (or `StructuredBitPositionInfo` for
+/// bit-fields) of the class and, via their `layout`, of its base classes.
+/// Classes whose base classes have no layout or whose offsets are unknown are
+/// skipped since their holes can't be computed.
+#[derive(Debug)]
+pub struct LayoutAuditCommand {
+    pub args: LayoutAudit,
+}
+
+#[derive(Debug, Default, PartialEq, Serialize)]
+pub struct LayoutWaste {
+    pub size: u32,
+    pub holes: u32,
+    pub hole_bytes: u32,
+    pub largest_hole: u32,
+    pub end_padding: u32,
+}
+
+impl LayoutWaste {
+    pub fn wasted(&self) -> u32 {
+        self.hole_bytes + self.end_padding
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayoutAuditEntry {
+    pub pretty: String,
+    pub sym: String,
+    pub path: String,
+    pub lineno: u32,
+    pub alignment: Option<u32>,
+    #[serde(flatten)]
+    pub waste: LayoutWaste,
+    pub wasted: u32,
+    /// The size if all the holes and padding were removed, rounded up to the
+    /// alignment.
+    pub packed_size: u32,
+    pub size_class: u32,
+    pub packed_size_class: u32,
+    pub size_class_savings: u32,
+}
+
+/// Approximates mozjemalloc's size classes: tiny classes, then 16 byte quantum
+/// spacing up to 128, 256 byte spacing up to 512, powers of two up to half a
+/// page, and then whole pages.
+pub fn malloc_size_class(size: u32) -> u32 {
+    match size {
+        0..=4 => 4,
+        5..=8 => 8,
+        9..=128 => size.div_ceil(16) * 16,
+        129..=512 => size.div_ceil(256) * 256,
+        513..=2048 => size.next_power_of_two(),
+        _ => size.div_ceil(4096) * 4096,
+    }
+}
+
+// Add the bit ranges occupied by the vtable pointer, fields, and base classes
+// of a class at `offset`, returning false if some offset or size is unknown.
+fn collect_occupied_bits(
+    offset: u32,
+    own_vf_ptr_bytes: Option<u32>,
+    supers: &[StructuredSuperInfo],
+    fields: &[StructuredFieldInfo],
+    occupied: &mut Vec<(u64, u64)>,
+) -> bool {
+    let bits = |bytes: u32| bytes as u64 * 8;
+    if let Some(vf_ptr_bytes) = own_vf_ptr_bytes {
+        occupied.push((bits(offset), bits(offset + vf_ptr_bytes)));
+    }
+    for field in fields {
+        let Some(field_offset) = field.offset_bytes else {
+            return false;
+        };
+        let start = bits(offset + field_offset);
+        match (&field.bit_positions, field.size_bytes) {
+            (Some(pos), _) => {
+                let start = start + pos.begin as u64;
+                occupied.push((start, start + pos.width as u64));
+            }
+            (None, Some(size)) => occupied.push((start, start + bits(size))),
+            (None, None) => return false,
+        }
+    }
+    for super_info in supers {
+        let Some(layout) = &super_info.layout else {
+            return false;
+        };
+        if super_info.offset_bytes.is_none() && supers.len() > 1 {
+            return false;
+        }
+        if !collect_occupied_bits(
+            offset + super_info.offset_bytes.unwrap_or(0),
+            layout.own_vf_ptr_bytes,
+            &layout.supers,
+            &layout.fields,
+            occupied,
+        ) {
+            return false;
+        }
+    }
+    true
+}
+
+/// Compute the holes and end padding of a class, or None if its layout isn't
+/// fully known.  Bytes only partially used by bit-fields count as used.
+pub fn compute_layout_waste(structured: &AnalysisStructured) -> Option<LayoutWaste> {
+    let size = structured.size_bytes?;
+    let mut occupied = vec![];
+    if !collect_occupied_bits(
+        0,
+        structured.own_vf_ptr_bytes,
+        &structured.supers,
+        &structured.fields,
+        &mut occupied,
+    ) {
+        return None;
+    }
+    // Empty classes are all padding but there's nothing to be done about it.
+    if occupied.is_empty() {
+        return None;
+    }
+    occupied.sort();
+
+    let mut waste = LayoutWaste {
+        size,
+        ..LayoutWaste::default()
+    };
+    let mut end = 0;
+    for (start, stop) in occupied {
+        let start_byte = (start / 8) as u32;
+        if start_byte > end {
+            let hole = start_byte - end;
+            waste.holes += 1;
+            waste.hole_bytes += hole;
+            waste.largest_hole = waste.largest_hole.max(hole);
+        }
+        end = end.max(stop.div_ceil(8) as u32);
+    }
+    waste.end_padding = size.saturating_sub(end);
+    Some(waste)
+}
+
+fn audit_entry(
+    path: &str,
+    lineno: u32,
+    structured: &AnalysisStructured,
+) -> Option<LayoutAuditEntry> {
+    let waste = compute_layout_waste(structured)?;
+    let alignment = structured.alignment_bytes;
+    let wasted = waste.wasted();
+    let packed_size = waste
+        .size
+        .saturating_sub(wasted)
+        .next_multiple_of(alignment.unwrap_or(1).max(1));
+    let size_class = malloc_size_class(waste.size);
+    let packed_size_class = malloc_size_class(packed_size);
+    Some(LayoutAuditEntry {
+        pretty: structured.pretty.to_string(),
+        sym: structured.sym.to_string(),
+        path: path.to_string(),
+        lineno,
+        alignment,
+        waste,
+        wasted,
+        packed_size,
+        size_class,
+        packed_size_class,
+        size_class_savings: size_class.saturating_sub(packed_size_class),
+    })
+}
+
+fn format_table(entries: &[LayoutAuditEntry]) -> String {
+    let mut lines = vec![
+        [
+            "wasted",
+            "holes",
+            "largest_hole",
+            "end_padding",
+            "size",
+            "packed_size",
+            "size_class",
+            "packed_size_class",
+            "size_class_savings",
+            "class",
+            "location",
+        ]
+        .join("\t"),
+    ];
+    for entry in entries {
+        lines.push(format!(
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}#{}",
+            entry.wasted,
+            entry.waste.holes,
+            entry.waste.largest_hole,
+            entry.waste.end_padding,
+            entry.waste.size,
+            entry.packed_size,
+            entry.size_class,
+            entry.packed_size_class,
+            entry.size_class_savings,
+            entry.pretty,
+            entry.path,
+            entry.lineno
+        ));
+    }
+    lines.join("\n")
+}
+
+#[async_trait]
+impl PipelineCommand for LayoutAuditCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let matches = server
+            .search_files(
+                &path_glob_transform(&self.args.path),
+                false,
+                self.args.file_limit,
+            )
+            .await?;
+
+        let predicate = AnalysisPredicate::for_record_types(&[RecordType::Structured]);
+        let mut seen_syms = HashSet::new();
+        let mut entries = vec![];
+        for file_match in matches.file_matches {
+            let records = match server.fetch_analysis(&file_match.path, &predicate).await {
+                Ok(records) => records.collect::<Vec<_>>().await,
+                Err(e) => {
+                    info!("no analysis for {}: {:?}", file_match.path, e);
+                    continue;
+                }
+            };
+            for record in records {
+                let AnalysisUnion::Structured(structured) = record.data else {
+                    continue;
+                };
+                if !matches!(
+                    structured.kind.as_str(),
+                    "class" | "struct" | "union" | "__interface"
+                ) || !seen_syms.insert(structured.sym)
+                {
+                    continue;
+                }
+                let variant = match &self.args.platform {
+                    Some(platform) => structured
+                        .per_platform()
+                        .into_iter()
+                        .find(|(p, _)| p.as_ref().is_none_or(|p| p == platform))
+                        .map(|(_, variant)| variant),
+                    None => Some(&structured),
+                };
+                if let Some(entry) = variant
+                    .and_then(|variant| audit_entry(&file_match.path, record.loc.lineno, variant))
+                    && entry.wasted >= self.args.min_wasted
+                {
+                    entries.push(entry);
+                }
+            }
+        }
+
+        entries.sort_by(|a, b| {
+            let key = |e: &LayoutAuditEntry| match self.args.sort {
+                LayoutAuditSort::Wasted => (e.wasted, e.size_class_savings),
+                LayoutAuditSort::SizeClassSavings => (e.size_class_savings, e.wasted),
+                LayoutAuditSort::Size => (e.waste.size, e.wasted),
+            };
+            key(b).cmp(&key(a)).then_with(|| a.pretty.cmp(&b.pretty))
+        });
+        if self.args.top > 0 {
+            entries.truncate(self.args.top);
+        }
+
+        match self.args.format {
+            LayoutAuditFormat::Json => Ok(PipelineValues::JsonValue(JsonValue {
+                value: to_value(entries)?,
+            })),
+            LayoutAuditFormat::Table => Ok(PipelineValues::TextFile(TextFile {
+                mime_type: "text/tab-separated-values".to_string(),
+                contents: format_table(&entries),
+            })),
+        }
+    }
+}
+
+#[test]
+fn test_layout_waste() {
+    use serde_json::from_str;
+
+    // struct Base { void* vptr; uint8_t b; };  (size 16)
+    // struct S : Base { uint8_t a; uint64_t c; uint8_t d : 3; };  (size 40)
+    let structured: AnalysisStructured = from_str(
+        r#"{
+        "structured": 1, "pretty": "S", "sym": "T_S", "kind": "struct",
+        "sizeBytes": 40, "alignmentBytes": 8,
+        "supers": [{
+            "sym": "T_Base", "offsetBytes": 0,
+            "layout": {
+                "pretty": "Base", "sizeBytes": 16, "alignmentBytes": 8, "ownVFPtrBytes": 8,
+                "fields": [{"pretty": "Base::b", "sym": "F_b", "offsetBytes": 8, "sizeBytes": 1}]
+            }
+        }],
+        "fields": [
+            {"pretty": "S::a", "sym": "F_a", "offsetBytes": 16, "sizeBytes": 1},
+            {"pretty": "S::c", "sym": "F_c", "offsetBytes": 24, "sizeBytes": 8},
+            {"pretty": "S::d", "sym": "F_d", "offsetBytes": 32, "bitPositions": {"begin": 0, "width": 3}}
+        ]
+    }"#,
+    )
+    .unwrap();
+
+    assert_eq!(
+        compute_layout_waste(&structured),
+        Some(LayoutWaste {
+            size: 40,
+            holes: 2,
+            hole_bytes: 7 + 7,
+            largest_hole: 7,
+            end_padding: 7,
+        })
+    );
+
+    let entry = audit_entry("s.h", 3, &structured).unwrap();
+    assert_eq!(entry.wasted, 21);
+    assert_eq!(entry.packed_size, 24);
+    assert_eq!(entry.size_class, 48);
+    assert_eq!(entry.packed_size_class, 32);
+    assert_eq!(entry.size_class_savings, 16);
+
+    // A base class without layout information makes the holes unknowable.
+    let mut no_layout = structured.clone();
+    no_layout.supers[0].layout = None;
+    assert_eq!(compute_layout_waste(&no_layout), None);
+}
@@ -0,0 +1,317 @@
+use std::collections::BTreeSet;
+
+use async_trait::async_trait;
+use clap::Args;
+use serde::Serialize;
+use serde_json::to_value;
+use tokio_stream::StreamExt;
+use tracing::info;
+
+use super::interface::{JsonValue, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, Result},
+    file_format::{
+        analysis::{AnalysisUnion, ExpansionInfo, StructuredLayoutOnlyInfo, WithLocation},
+        analysis_reader::{AnalysisPredicate, RecordType},
+    },
+};
+
+/// Report where the per-platform analyses merged by `merge-analyses` diverge
+/// for a file or for all the files under a directory.
+#[derive(Debug, Args)]
+pub struct PlatformDivergence {
+    /// Tree-relative file or directory path.
+    #[clap(value_parser)]
+    path: String,
+
+    /// The platforms the tree is indexed for.  Symbols and expansions not
+    /// present on all of these are reported.  If not specified, every platform
+    /// mentioned by the analysis of the files being examined is used, which
+    /// won't notice divergence if all of those files' platform-specific
+    /// symbols are specific to the same platforms.
+    #[clap(long, short, value_parser)]
+    platform: Vec<String>,
+
+    /// Maximum number of files to examine.
+    #[clap(long, value_parser, default_value = "2000")]
+    limit: usize,
+}
+
+/// Looks at the structured records and the macro expansions of the source
+/// records, which are the only parts of the merged analysis that say which
+/// platforms they came from.  Structured records of symbols defined only on
+/// some platforms carry a `platforms` list and records whose contents differ
+/// across platforms carry `variants` (see `merger.rs`), and `ExpandsTo`
+/// expansion info is keyed by platform.
+#[derive(Debug)]
+pub struct PlatformDivergenceCommand {
+    pub args: PlatformDivergence,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformOnlySymbol {
+    pub path: String,
+    pub lineno: u32,
+    pub pretty: String,
+    pub sym: String,
+    pub kind: String,
+    pub platforms: Vec<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformLayout {
+    pub platforms: Vec<String>,
+    pub layout: StructuredLayoutOnlyInfo,
+}
+
+#[derive(Debug, Serialize)]
+pub struct LayoutDifference {
+    pub path: String,
+    pub lineno: u32,
+    pub pretty: String,
+    pub sym: String,
+    pub layouts: Vec<PlatformLayout>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct PlatformOnlyExpansion {
+    pub path: String,
+    pub lineno: u32,
+    pub pretty: String,
+    /// The `ExpandsTo` key; the macro symbol followed by its dependencies.
+    pub expansion: String,
+    pub platforms: Vec<String>,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct PlatformDivergenceReport {
+    pub platforms: Vec<String>,
+    pub platform_only_symbols: Vec<PlatformOnlySymbol>,
+    pub layout_differences: Vec<LayoutDifference>,
+    pub platform_only_expansions: Vec<PlatformOnlyExpansion>,
+}
+
+/// The layout properties that matter when comparing variants.  Field and
+/// super types are left out so that platform-specific type names with the same
+/// layout (ex: `long` vs `long long`) aren't reported.
+type LayoutKey = (
+    Option<u32>,
+    Option<u32>,
+    Option<u32>,
+    Vec<(String, Option<u32>)>,
+    Vec<(String, Option<u32>, Option<u32>, Option<(u32, u32)>)>,
+);
+
+fn layout_key(layout: &StructuredLayoutOnlyInfo) -> LayoutKey {
+    (
+        layout.size_bytes,
+        layout.alignment_bytes,
+        layout.own_vf_ptr_bytes,
+        layout
+            .supers
+            .iter()
+            .map(|s| (s.sym.to_string(), s.offset_bytes))
+            .collect(),
+        layout
+            .fields
+            .iter()
+            .map(|f| {
+                (
+                    f.pretty.to_string(),
+                    f.offset_bytes,
+                    f.size_bytes,
+                    f.bit_positions.as_ref().map(|b| (b.begin, b.width)),
+                )
+            })
+            .collect(),
+    )
+}
+
+/// Accumulates the per-platform information of a set of files.  Whether a
+/// symbol or expansion is platform-specific can only be decided once the full
+/// set of platforms is known, so that's deferred to `finish`.
+#[derive(Default)]
+pub struct PlatformDivergenceCollector {
+    seen_platforms: BTreeSet<String>,
+    symbols: Vec<PlatformOnlySymbol>,
+    layout_differences: Vec<LayoutDifference>,
+    expansions: Vec<PlatformOnlyExpansion>,
+}
+
+impl PlatformDivergenceCollector {
+    pub fn add_file(&mut self, path: &str, records: Vec<WithLocation<AnalysisUnion>>) {
+        for WithLocation { loc, data } in records {
+            match data {
+                AnalysisUnion::Structured(structured) => {
+                    let per_platform = structured.per_platform();
+                    let platforms: Vec<String> =
+                        per_platform.iter().filter_map(|(p, _)| p.clone()).collect();
+                    if platforms.is_empty() {
+                        continue;
+                    }
+                    self.seen_platforms.extend(platforms.iter().cloned());
+
+                    let mut layouts: Vec<(LayoutKey, PlatformLayout)> = vec![];
+                    for (platform, variant) in per_platform {
+                        let layout = variant.layout_only();
+                        let key = layout_key(&layout);
+                        let platform = platform.unwrap_or_default();
+                        match layouts.iter_mut().find(|(k, _)| *k == key) {
+                            Some((_, existing)) => existing.platforms.push(platform),
+                            None => layouts.push((
+                                key,
+                                PlatformLayout {
+                                    platforms: vec![platform],
+                                    layout,
+                                },
+                            )),
+                        }
+                    }
+                    if layouts.len() > 1 {
+                        self.layout_differences.push(LayoutDifference {
+                            path: path.to_string(),
+                            lineno: loc.lineno,
+                            pretty: structured.pretty.to_string(),
+                            sym: structured.sym.to_string(),
+                            layouts: layouts.into_iter().map(|(_, l)| l).collect(),
+                        });
+                    }
+
+                    self.symbols.push(PlatformOnlySymbol {
+                        path: path.to_string(),
+                        lineno: loc.lineno,
+                        pretty: structured.pretty.to_string(),
+                        sym: structured.sym.to_string(),
+                        kind: structured.kind.to_string(),
+                        platforms,
+                    });
+                }
+                AnalysisUnion::Source(src) => {
+                    let Some(ExpansionInfo::ExpandsTo(expansions)) = src.expansion_info else {
+                        continue;
+                    };
+                    for (expansion, per_platform) in expansions {
+                        let platforms: Vec<String> = per_platform.into_keys().collect();
+                        self.seen_platforms.extend(platforms.iter().cloned());
+                        self.expansions.push(PlatformOnlyExpansion {
+                            path: path.to_string(),
+                            lineno: loc.lineno,
+                            pretty: src.pretty.to_string(),
+                            expansion,
+                            platforms,
+                        });
+                    }
+                }
+                AnalysisUnion::Target(_) => {}
+            }
+        }
+    }
+
+    /// Produce the report, treating `platforms` as the full set of platforms
+    /// or, if it's empty, every platform that was seen.
+    pub fn finish(self, platforms: &[String]) -> PlatformDivergenceReport {
+        let all_platforms: BTreeSet<String> = if platforms.is_empty() {
+            self.seen_platforms
+        } else {
+            platforms.iter().cloned().collect()
+        };
+        let is_partial = |present: &[String]| !all_platforms.iter().all(|p| present.contains(p));
+
+        PlatformDivergenceReport {
+            platform_only_symbols: self
+                .symbols
+                .into_iter()
+                .filter(|s| is_partial(&s.platforms))
+                .collect(),
+            layout_differences: self.layout_differences,
+            platform_only_expansions: self
+                .expansions
+                .into_iter()
+                .filter(|e| is_partial(&e.platforms))
+                .collect(),
+            platforms: all_platforms.into_iter().collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl PipelineCommand for PlatformDivergenceCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        // Match the path itself if it's a file, or everything under it if it's
+        // a directory.
+        let path = self.args.path.trim_end_matches('/');
+        let pathre = format!("^{}(/|$)", regex::escape(path));
+        let matches = server.search_files(&pathre, false, self.args.limit).await?;
+
+        let predicate =
+            AnalysisPredicate::for_record_types(&[RecordType::Source, RecordType::Structured]);
+        let mut collector = PlatformDivergenceCollector::default();
+        for file_match in matches.file_matches {
+            // Files without analysis (ex: non-source files) are expected.
+            let records = match server.fetch_analysis(&file_match.path, &predicate).await {
+                Ok(records) => records.collect::<Vec<_>>().await,
+                Err(e) => {
+                    info!("no analysis for {}: {:?}", file_match.path, e);
+                    continue;
+                }
+            };
+            collector.add_file(&file_match.path, records);
+        }
+
+        Ok(PipelineValues::JsonValue(JsonValue {
+            value: to_value(collector.finish(&self.args.platform))?,
+        }))
+    }
+}
+
+#[test]
+fn test_platform_divergence() {
+    let lines = [
+        r#"{"loc":"00001:7","structured":1,"pretty":"Common","sym":"T_Common","kind":"struct","sizeBytes":4,"fields":[{"pretty":"Common::a","sym":"F_a","offsetBytes":0,"sizeBytes":4}]}"#,
+        r#"{"loc":"00005:7","structured":1,"pretty":"WinOnly","sym":"T_WinOnly","kind":"struct","sizeBytes":1,"platforms":["win64"]}"#,
+        r#"{"loc":"00009:7","structured":1,"pretty":"S","sym":"T_S","kind":"struct","sizeBytes":16,"fields":[{"pretty":"S::p","sym":"F_p","type":"long","offsetBytes":0,"sizeBytes":8},{"pretty":"S::x","sym":"F_x","offsetBytes":8,"sizeBytes":4}],"platforms":["linux64","macosx64"],"variants":[{"structured":1,"pretty":"S","sym":"T_S","kind":"struct","sizeBytes":12,"fields":[{"pretty":"S::p","sym":"F_p","type":"long long","offsetBytes":0,"sizeBytes":8},{"pretty":"S::x","sym":"F_x","offsetBytes":8,"sizeBytes":4}],"platforms":["win64"]}]}"#,
+        r#"{"loc":"00015:0-6","source":1,"syntax":"use,macro","pretty":"macro FOO","sym":"M_FOO","expandsTo":{"M_FOO":{"linux64":"1","macosx64":"1","win64":"1"}}}"#,
+        r#"{"loc":"00016:0-6","source":1,"syntax":"use,macro","pretty":"macro BAR","sym":"M_BAR","expandsTo":{"M_BAR":{"linux64":"2"}}}"#,
+    ];
+    let records = lines
+        .iter()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    let mut collector = PlatformDivergenceCollector::default();
+    collector.add_file("a.cpp", records);
+    let report = collector.finish(&[]);
+
+    assert_eq!(report.platforms, vec!["linux64", "macosx64", "win64"]);
+    let syms: Vec<_> = report
+        .platform_only_symbols
+        .iter()
+        .map(|s| (s.pretty.as_str(), s.platforms.clone()))
+        .collect();
+    assert_eq!(syms, vec![("WinOnly", vec!["win64".to_string()])]);
+    assert_eq!(report.layout_differences.len(), 1);
+    let layouts: Vec<_> = report.layout_differences[0]
+        .layouts
+        .iter()
+        .map(|l| (l.platforms.clone(), l.layout.size_bytes))
+        .collect();
+    assert_eq!(
+        layouts,
+        vec![
+            (
+                vec!["linux64".to_string(), "macosx64".to_string()],
+                Some(16)
+            ),
+            (vec!["win64".to_string()], Some(12)),
+        ]
+    );
+    let expansions: Vec<_> = report
+        .platform_only_expansions
+        .iter()
+        .map(|e| (e.lineno, e.expansion.as_str()))
+        .collect();
+    assert_eq!(expansions, vec![(16, "M_BAR")]);
+}
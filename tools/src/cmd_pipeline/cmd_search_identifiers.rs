@@ -29,6 +29,11 @@ pub struct SearchIdentifiers {
     #[clap(long, value_parser)]
     types_only: bool,
 
+    /// Search the names of ontology `[topics]` like observer topics instead of
+    /// identifiers.
+    #[clap(long, value_parser)]
+    topics: bool,
+
     /// Minimum identifier length to search for.  The default of 3 is derived
     /// from router.py's `is_trivial_search` heuristic requiring a length of 3,
     /// although it was only required along one axis.
@@ -75,15 +80,26 @@ impl PipelineCommand for SearchIdentifiersCommand {
                 continue;
             }
 
-            for (sym, from_ident) in server
-                .search_identifiers(
-                    &id,
-                    self.args.exact_match,
-                    !self.args.case_sensitive,
-                    self.args.limit,
-                )
-                .await?
-            {
+            let matches = if self.args.topics {
+                server
+                    .search_topics(
+                        &id,
+                        self.args.exact_match,
+                        !self.args.case_sensitive,
+                        self.args.limit,
+                    )
+                    .await?
+            } else {
+                server
+                    .search_identifiers(
+                        &id,
+                        self.args.exact_match,
+                        !self.args.case_sensitive,
+                        self.args.limit,
+                    )
+                    .await?
+            };
+            for (sym, from_ident) in matches {
                 if self.args.types_only && !sym.starts_with("T_") {
                     continue;
                 }
@@ -1,21 +1,32 @@
+use std::collections::BTreeMap;
+
 use async_trait::async_trait;
 use clap::{Args, ValueEnum};
 
 use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues, TextFile};
 use crate::{
     abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
-    tree_sitter_support::cst_tokenizer::hypertokenize_source_file,
+    format::tokenize_with_format,
+    languages::{FormatAs, select_formatting},
+    tokenize::{Token, TokenKind},
+    tree_sitter_support::{
+        cst_tokenizer::hypertokenize_source_file, highlighter::highlight_source,
+    },
 };
 
 #[derive(Clone, Debug, PartialEq, ValueEnum)]
 pub enum TokenizeMode {
     Tokenize,
     Outline,
+    /// The tree-sitter HTML highlighting tokens, one per line.
+    Highlight,
+    /// Only the HTML highlighting tokens where the hand-rolled tokenizer and
+    /// tree-sitter disagree, prefixed with "-" and "+" respectively.
+    CompareHighlight,
 }
 
-/// Tokenize the given source file using the syntax-token-tree tokenizer.  We
-/// do also have the HTML formatting hand-rolled tokenizers, but they aren't
-/// hooked up right now.
+/// Tokenize the given source file using the syntax-token-tree tokenizer, or
+/// for the highlight modes, the HTML formatting tokenizers.
 #[derive(Debug, Args)]
 pub struct TokenizeSource {
     /// Tree-relative source file path or directory.
@@ -40,6 +51,13 @@ impl PipelineCommand for TokenizeSourceCommand {
     ) -> Result<PipelineValues> {
         let source_str = server.fetch_raw_source(&self.args.file).await?;
 
+        if matches!(
+            self.args.mode,
+            TokenizeMode::Highlight | TokenizeMode::CompareHighlight
+        ) {
+            return self.highlight(&source_str);
+        }
+
         let token_lines = match hypertokenize_source_file(&self.args.file, &source_str) {
             Ok(content) => content,
             Err(e) => {
@@ -55,6 +73,7 @@ impl PipelineCommand for TokenizeSourceCommand {
                 mime_type: "text/plain".to_string(),
                 contents: token_lines.tokenized.join("\n"),
             })),
+            TokenizeMode::Highlight | TokenizeMode::CompareHighlight => unreachable!(),
             TokenizeMode::Outline => Ok(PipelineValues::JsonValueList(JsonValueList {
                 values: token_lines
                     .structure
@@ -67,3 +86,100 @@ impl PipelineCommand for TokenizeSourceCommand {
         }
     }
 }
+
+fn token_kind_name(kind: &TokenKind) -> &'static str {
+    match kind {
+        TokenKind::PlainText => "plain",
+        TokenKind::Punctuation => "punct",
+        TokenKind::Identifier(None) => "ident",
+        TokenKind::Identifier(Some(_)) => "keyword",
+        TokenKind::StringLiteral => "string",
+        TokenKind::Comment => "comment",
+        TokenKind::RegularExpressionLiteral => "regex",
+        TokenKind::TagName => "tag",
+        TokenKind::TagAttrName => "tagattr",
+        TokenKind::EndTagName => "endtag",
+        TokenKind::Newline => "newline",
+    }
+}
+
+/// Describe the tokens that have any content as `line:col kind text`, keyed by
+/// their byte range.
+fn describe_tokens(source: &str, tokens: &[Token]) -> BTreeMap<(usize, usize), String> {
+    let mut described = BTreeMap::new();
+    let mut line = 1;
+    let mut line_start = 0;
+    for token in tokens {
+        if token.kind == TokenKind::Newline {
+            line += 1;
+            line_start = token.end;
+            continue;
+        }
+        let text = &source[token.start..token.end];
+        if text.trim().is_empty() {
+            continue;
+        }
+        described.insert(
+            (token.start, token.end),
+            format!(
+                "{}:{} {} {}",
+                line,
+                token.start - line_start + 1,
+                token_kind_name(&token.kind),
+                text
+            ),
+        );
+    }
+    described
+}
+
+impl TokenizeSourceCommand {
+    fn highlight(&self, source_str: &str) -> Result<PipelineValues> {
+        let Some(ts_tokens) = highlight_source(&self.args.file, source_str) else {
+            return Err(ServerError::StickyProblem(ErrorDetails {
+                layer: ErrorLayer::DataLayer,
+                message: format!("No clean tree-sitter parse for {}", self.args.file),
+            }));
+        };
+        let ts_described = describe_tokens(source_str, &ts_tokens);
+
+        let lines: Vec<String> = if self.args.mode == TokenizeMode::Highlight {
+            ts_described.into_values().collect()
+        } else {
+            let format = select_formatting(&self.args.file);
+            if let FormatAs::Binary = format {
+                return Err(ServerError::StickyProblem(ErrorDetails {
+                    layer: ErrorLayer::DataLayer,
+                    message: "Binary files can't be tokenized".to_string(),
+                }));
+            }
+            let hand_described =
+                describe_tokens(source_str, &tokenize_with_format(&format, source_str));
+
+            let mut ranges: Vec<&(usize, usize)> =
+                hand_described.keys().chain(ts_described.keys()).collect();
+            ranges.sort();
+            ranges.dedup();
+            let mut lines = vec![];
+            for range in ranges {
+                let hand = hand_described.get(range);
+                let ts = ts_described.get(range);
+                if hand == ts {
+                    continue;
+                }
+                if let Some(hand) = hand {
+                    lines.push(format!("- {}", hand));
+                }
+                if let Some(ts) = ts {
+                    lines.push(format!("+ {}", ts));
+                }
+            }
+            lines
+        };
+
+        Ok(PipelineValues::TextFile(TextFile {
+            mime_type: "text/plain".to_string(),
+            contents: lines.join("\n"),
+        }))
+    }
+}
@@ -18,7 +18,7 @@ use super::{
 
 use crate::{
     abstract_server::{AbstractServer, ErrorDetails, ErrorLayer, Result, ServerError},
-    cmd_pipeline::symbol_graph::{EdgeDetail, EdgeKind},
+    cmd_pipeline::symbol_graph::{EdgeDetail, EdgeKind, EdgeProvenance, EdgeSource},
     file_format::{
         analysis::{BindingOwnerLang, BindingSlotKind, OntologySlotKind},
         ontology_mapping::{label_to_badge_info, pointer_kind_to_badge_info},
@@ -31,10 +31,26 @@ use crate::{
 #[derive(Debug, Args)]
 pub struct Traverse {
     /// The edge to traverse, currently one of: "uses", "callees", "class",
-    /// "inheritance".
+    /// "inheritance", "pref-users", "metric-recorders", "protocol".
+    ///
+    /// "pref-users" and "metric-recorders" are "uses" traversals that also go
+    /// from a StaticPrefs pref or Glean metric symbol through its binding slots
+    /// (the generated C++/JS accessors) so that the code reading the pref or
+    /// recording the metric and its callers show up.
+    ///
+    /// "protocol" starts from IPDL protocols and walks down the tree of
+    /// protocols they manage, their messages, and the C++ `Recv*` methods that
+    /// implement them.  The chain of protocols managing the starting protocols
+    /// is also included.
     #[clap(long, short, value_parser, default_value = "callees")]
     edge: String,
 
+    /// For "protocol" traversals, also include the `Send*` methods of the
+    /// messages and traverse their uses like "uses" does, within the depth
+    /// limit.
+    #[clap(long, value_parser)]
+    protocol_senders: bool,
+
     /// Maximum traversal depth.  This used to have no limit because traversal
     /// will also be constrained by the applicable node-limit and our breadth
     /// first processing, but depth is now also used to limit the paths-between
@@ -110,10 +126,13 @@ pub struct TraverseCommand {
 }
 
 bitflags! {
-    #[derive(Clone, Copy)]
+    #[derive(Clone, Copy, PartialEq, Eq)]
     pub struct Traversals: u32 {
         const Super    = 0b00000001;
         const Subclass = 0b00000010;
+        /// Set on the `Send*` methods of "protocol" traversals and their
+        /// callers so that only they traverse their uses.
+        const Uses     = 0b00000100;
     }
 }
 
@@ -143,6 +162,7 @@ bitflags! {
 /// We potentially traverse all of the following crossref paths:
 /// - "calls"
 /// - "meta/fields":
+/// - "meta/ipdlProtocol":
 /// - "meta/ontologySlots":
 /// - "meta/overrides":
 /// - "meta/overriddenBy":
@@ -256,7 +276,7 @@ impl PipelineCommand for TraverseCommand {
         let stop_at_class_label = match self.args.edge.as_str() {
             "callees" => Some("calls-diagram:stop"),
             "class" => Some("class-diagram:stop"),
-            "uses" => Some("uses-diagram:stop"),
+            "uses" | "pref-users" | "metric-recorders" | "protocol" => Some("uses-diagram:stop"),
             _ => None,
         };
 
@@ -290,7 +310,7 @@ impl PipelineCommand for TraverseCommand {
         };
         let traverse_overrides = match self.args.edge.as_str() {
             "inheritance" => true,
-            "uses" => true,
+            "uses" | "pref-users" | "metric-recorders" => true,
             // We intentionally do not traverse upwards here for the callees
             // case; only downwards in the "overridden_by" case above.
             _ => false,
@@ -306,9 +326,16 @@ impl PipelineCommand for TraverseCommand {
             _ => false,
         };
         let traverse_uses = match self.args.edge.as_str() {
-            "uses" => true,
+            "uses" | "pref-users" | "metric-recorders" => true,
             _ => false,
         };
+        // The binding slots of a pref or metric are its accessors, which are
+        // how code reads the pref or records the metric, so they're "uses" of
+        // the pref or metric for these traversals.
+        let traverse_pref_slots = self.args.edge == "pref-users";
+        let traverse_metric_slots = self.args.edge == "metric-recorders";
+        let traverse_protocol = self.args.edge == "protocol";
+        let traverse_protocol_senders = traverse_protocol && self.args.protocol_senders;
 
         let ignore_node_set = match &self.args.ignore_nodes {
             Some(s) => HashSet::from_iter(s.split(",")),
@@ -422,13 +449,24 @@ impl PipelineCommand for TraverseCommand {
                                 pri,
                                 edge_kind,
                                 use_badge,
-                                vec![EdgeDetail::HoverClass(use_class.to_string())],
+                                vec![
+                                    EdgeDetail::HoverClass(use_class.to_string()),
+                                    EdgeDetail::Provenance(
+                                        EdgeProvenance::new(EdgeSource::FieldPointer)
+                                            .with_detail(field.pretty.to_string()),
+                                    ),
+                                ],
                             ));
 
                             if !considered.insert(ptr_info.sym) {
                                 continue;
                             }
                             if next_depth >= max_depth {
+                                if let Some((_, _, _, _, edge_details)) = targets.last_mut() {
+                                    edge_details.push(EdgeDetail::Limit(
+                                        OverloadKind::DepthLimitOnFieldPointer,
+                                    ));
+                                }
                                 overloads_hit.push(OverloadInfo {
                                     kind: OverloadKind::DepthLimitOnFieldPointer,
                                     sym: Some(ptr_info.sym.to_string()),
@@ -552,6 +590,100 @@ impl PipelineCommand for TraverseCommand {
                 }
             }
 
+            // Walk down from an IPDL protocol to the protocols it manages and to
+            // its messages.  The messages' binding slots then lead to the
+            // `Recv*` implementations (and `Send*` methods) below.  We also walk
+            // up to the protocols that manage it, but like for superclasses we
+            // only keep going up from there so that the manager's other managees
+            // and messages don't swamp the graph.
+            if traverse_protocol
+                && let Some(protocol_info) = sym_node_set
+                    .get(&sym_id)
+                    .get_structured()
+                    .and_then(|meta| meta.ipdl_protocol.as_ref())
+                    .cloned()
+            {
+                let mut related = vec![];
+                if cur_traversals.contains(Traversals::Super) {
+                    related.extend(protocol_info.managers.iter().map(|manager_sym| {
+                        (
+                            *manager_sym,
+                            EdgeKind::Composition,
+                            "manages".to_string(),
+                            Traversals::Super,
+                        )
+                    }));
+                }
+                if cur_traversals.contains(Traversals::Subclass) {
+                    related.extend(protocol_info.manages.iter().map(|managed_sym| {
+                        (
+                            *managed_sym,
+                            EdgeKind::Composition,
+                            "manages".to_string(),
+                            Traversals::Subclass,
+                        )
+                    }));
+                    related.extend(protocol_info.messages.iter().map(|message| {
+                        (
+                            message.sym,
+                            EdgeKind::Default,
+                            message.direction.name().to_string(),
+                            all_traversals_valid,
+                        )
+                    }));
+                }
+                for (rel_sym, edge_kind, detail, rel_traversals) in related {
+                    let (rel_id, rel_info) = sym_node_set
+                        .ensure_symbol(&rel_sym, server, next_depth)
+                        .await?;
+
+                    let rel_pretty = match rel_info.get_structured().map(|meta| meta.pretty) {
+                        Some(pretty) => pretty,
+                        _ => ustr(""),
+                    };
+                    if ignore_node_set.contains(&*rel_pretty) {
+                        continue;
+                    }
+
+                    let edge_details = vec![EdgeDetail::Provenance(
+                        EdgeProvenance::new(EdgeSource::IpdlProtocol).with_detail(detail),
+                    )];
+                    // Managers point at the protocols they manage regardless of
+                    // which direction we're walking.
+                    let (from_id, to_id) = if rel_traversals == Traversals::Super {
+                        (rel_id, sym_id.clone())
+                    } else {
+                        (sym_id.clone(), rel_id)
+                    };
+                    let edge_id = sym_edge_set.ensure_edge_in_graph(
+                        from_id,
+                        to_id,
+                        edge_kind,
+                        edge_details,
+                        &mut graph,
+                    );
+                    if !considered.insert(rel_sym) {
+                        continue;
+                    }
+                    if next_depth >= max_depth {
+                        sym_edge_set
+                            .add_edge_limit(&edge_id, OverloadKind::DepthLimitOnIpdlProtocol);
+                        overloads_hit.push(OverloadInfo {
+                            kind: OverloadKind::DepthLimitOnIpdlProtocol,
+                            sym: Some(rel_sym.to_string()),
+                            pretty: Some(rel_pretty.to_string()),
+                            exist: next_depth,
+                            included: depth + 1,
+                            local_limit: 0,
+                            global_limit: max_depth,
+                        });
+                        continue;
+                    }
+                    trace!(sym = rel_sym.as_str(), "scheduling ipdl protocol sym");
+                    to_traverse.push_back((rel_sym, rel_pretty, next_depth, rel_traversals));
+                }
+            }
+
             // Check whether to traverse a parent binding slot relationship.
             if let Some(slot_owner) = slot_owner {
                 // There are a few possibilities with a binding slot.  It can be
@@ -612,8 +744,17 @@ impl PipelineCommand for TraverseCommand {
                         (BindingOwnerLang::Idl, _) => {
                             (true, false, None, false, EdgeKind::Implementation)
                         }
-                        (BindingOwnerLang::Glean, _) => {
-                            (true, false, None, false, EdgeKind::Implementation)
+                        // When we're walking out from the metric, the owner is
+                        // where we came from and has already drawn the edge.
+                        (BindingOwnerLang::Glean, _) => (
+                            !traverse_metric_slots,
+                            false,
+                            None,
+                            false,
+                            EdgeKind::Implementation,
+                        ),
+                        (BindingOwnerLang::Prefs, _) if traverse_pref_slots => {
+                            (false, false, None, false, EdgeKind::Default)
                         }
                         // Cross-language binding class relationships are weird because
                         // the bindings inside are bidirectional, so let's ignore them.
@@ -643,89 +784,108 @@ impl PipelineCommand for TraverseCommand {
 
                     // Handle the case where we need to traverse a slot
                     if let Some(other_slot) = traverse_slot {
-                        let Some(other_sym) = owner_info.get_binding_slot_sym(other_slot) else {
-                            continue;
-                        };
-                        let (other_id, other_info) = sym_node_set
-                            .ensure_symbol(&other_sym, server, next_depth)
-                            .await?;
+                        // IPC messages can have multiple Send overloads (ex: the
+                        // promise and callback variants), all of which are slots.
+                        for other_sym in owner_info.get_binding_slot_syms(other_slot) {
+                            let (other_id, other_info) = sym_node_set
+                                .ensure_symbol(&other_sym, server, next_depth)
+                                .await?;
 
-                        let other_pretty = match other_info.get_structured().map(|meta| meta.pretty)
-                        {
-                            Some(pretty) => pretty,
-                            _ => ustr(""),
-                        };
-                        if ignore_node_set.contains(&*other_pretty) {
-                            continue;
-                        }
+                            let other_pretty =
+                                match other_info.get_structured().map(|meta| meta.pretty) {
+                                    Some(pretty) => pretty,
+                                    _ => ustr(""),
+                                };
+                            if ignore_node_set.contains(&*other_pretty) {
+                                continue;
+                            }
 
-                        if outbound_edge {
-                            sym_edge_set.ensure_edge_in_graph(
-                                sym_id.clone(),
-                                other_id,
-                                edge_kind,
-                                vec![],
-                                &mut graph,
-                            );
-                        } else {
-                            sym_edge_set.ensure_edge_in_graph(
-                                other_id,
-                                sym_id.clone(),
-                                edge_kind,
-                                vec![],
-                                &mut graph,
+                            let edge_details = vec![EdgeDetail::Provenance(
+                                EdgeProvenance::new(EdgeSource::SlotOwner).with_detail(format!(
+                                    "{:?} via {} to {:?}",
+                                    slot_owner.props.slot_kind, owner_pretty, other_slot
+                                )),
+                            )];
+                            let edge_id = if outbound_edge {
+                                sym_edge_set.ensure_edge_in_graph(
+                                    sym_id.clone(),
+                                    other_id,
+                                    edge_kind.clone(),
+                                    edge_details,
+                                    &mut graph,
+                                )
+                            } else {
+                                sym_edge_set.ensure_edge_in_graph(
+                                    other_id,
+                                    sym_id.clone(),
+                                    edge_kind.clone(),
+                                    edge_details,
+                                    &mut graph,
+                                )
+                            };
+                            if !considered.insert(other_info.symbol) {
+                                continue;
+                            }
+                            if next_depth >= max_depth {
+                                sym_edge_set.add_edge_limit(
+                                    &edge_id,
+                                    OverloadKind::DepthLimitOnBindingSlot,
+                                );
+                                overloads_hit.push(OverloadInfo {
+                                    kind: OverloadKind::DepthLimitOnBindingSlot,
+                                    sym: Some(other_info.symbol.to_string()),
+                                    pretty: Some(other_pretty.to_string()),
+                                    exist: next_depth,
+                                    included: depth + 1,
+                                    local_limit: 0,
+                                    global_limit: max_depth,
+                                });
+                                continue;
+                            }
+                            trace!(
+                                sym = other_info.symbol.as_str(),
+                                "scheduling traversed binding slot sym"
                             );
+                            to_traverse.push_back((
+                                other_info.symbol,
+                                other_pretty,
+                                next_depth,
+                                all_traversals_valid,
+                            ));
                         }
-                        if !considered.insert(other_info.symbol) {
-                            continue;
-                        }
-                        if next_depth >= max_depth {
-                            overloads_hit.push(OverloadInfo {
-                                kind: OverloadKind::DepthLimitOnBindingSlot,
-                                sym: Some(other_info.symbol.to_string()),
-                                pretty: Some(other_pretty.to_string()),
-                                exist: next_depth,
-                                included: depth + 1,
-                                local_limit: 0,
-                                global_limit: max_depth,
-                            });
-                            continue;
-                        }
-                        trace!(
-                            sym = other_info.symbol.as_str(),
-                            "scheduling traversed binding slot sym"
-                        );
-                        to_traverse.push_back((
-                            other_info.symbol,
-                            other_pretty,
-                            next_depth,
-                            all_traversals_valid,
-                        ));
                         continue;
                     }
                     if !ignore_node_set.contains(&*owner_pretty) {
                         'block: {
-                            if outbound_edge {
+                            let edge_details = vec![EdgeDetail::Provenance(
+                                EdgeProvenance::new(EdgeSource::SlotOwner)
+                                    .with_detail(format!("{:?}", slot_owner.props.slot_kind)),
+                            )];
+                            let edge_id = if outbound_edge {
                                 sym_edge_set.ensure_edge_in_graph(
                                     sym_id.clone(),
                                     owner_id,
                                     edge_kind,
-                                    vec![],
+                                    edge_details,
                                     &mut graph,
-                                );
+                                )
                             } else {
                                 sym_edge_set.ensure_edge_in_graph(
                                     owner_id,
                                     sym_id.clone(),
                                     edge_kind,
-                                    vec![],
+                                    edge_details,
                                     &mut graph,
-                                );
-                            }
+                                )
+                            };
                             if !considered.insert(owner_info.symbol) {
                                 break 'block;
                             }
                             if next_depth >= max_depth {
+                                sym_edge_set.add_edge_limit(
+                                    &edge_id,
+                                    OverloadKind::DepthLimitOnBindingSlot,
+                                );
                                 overloads_hit.push(OverloadInfo {
                                     kind: OverloadKind::DepthLimitOnBindingSlot,
                                     sym: Some(owner_info.symbol.to_string()),
@@ -772,11 +932,28 @@ impl PipelineCommand for TraverseCommand {
                 for slot in &slots {
                     let (should_traverse, skip_other_edges, outbound_edge, edge_kind) =
                         match (slot.props.owner_lang, slot.props.slot_kind) {
+                            // Protocol diagrams show the implementations of IPDL messages and
+                            // optionally their senders.
+                            (BindingOwnerLang::Idl, BindingSlotKind::Recv) if traverse_protocol => {
+                                (true, false, true, EdgeKind::IPC)
+                            }
+                            (BindingOwnerLang::Idl, BindingSlotKind::Send)
+                                if traverse_protocol_senders =>
+                            {
+                                (true, false, false, EdgeKind::IPC)
+                            }
                             // Don't bother with IDL bindings; all the relevant traversals involve a
                             // slotOwner at this time.
                             (BindingOwnerLang::Idl, _) => (false, false, false, EdgeKind::Default),
-                            (BindingOwnerLang::Glean, _) => {
-                                (false, false, false, EdgeKind::Default)
+                            // Accessors are drawn as using the pref or metric.
+                            (BindingOwnerLang::Glean, _) => (
+                                traverse_metric_slots,
+                                false,
+                                false,
+                                EdgeKind::Implementation,
+                            ),
+                            (BindingOwnerLang::Prefs, _) if traverse_pref_slots => {
+                                (true, false, false, EdgeKind::Implementation)
                             }
                             // Cross-language binding class relationships are weird because
                             // the bindings inside are bidirectional, so let's ignore them.
@@ -810,27 +987,33 @@ impl PipelineCommand for TraverseCommand {
                         continue;
                     }
 
-                    if outbound_edge {
+                    let edge_details = vec![EdgeDetail::Provenance(
+                        EdgeProvenance::new(EdgeSource::BindingSlot)
+                            .with_detail(format!("{:?}", slot.props.slot_kind)),
+                    )];
+                    let edge_id = if outbound_edge {
                         sym_edge_set.ensure_edge_in_graph(
                             sym_id.clone(),
                             rel_id,
                             edge_kind,
-                            vec![],
+                            edge_details,
                             &mut graph,
-                        );
+                        )
                     } else {
                         sym_edge_set.ensure_edge_in_graph(
                             rel_id,
                             sym_id.clone(),
                             edge_kind,
-                            vec![],
+                            edge_details,
                             &mut graph,
-                        );
-                    }
+                        )
+                    };
                     if !considered.insert(slot.sym) {
                         continue;
                     }
                     if next_depth >= max_depth {
+                        sym_edge_set
+                            .add_edge_limit(&edge_id, OverloadKind::DepthLimitOnBindingSlot);
                         overloads_hit.push(OverloadInfo {
                             kind: OverloadKind::DepthLimitOnBindingSlot,
                             sym: Some(slot.sym.to_string()),
@@ -843,7 +1026,13 @@ impl PipelineCommand for TraverseCommand {
                         continue;
                     }
                     trace!(sym = slot.sym.as_str(), "scheduling bind slot sym");
-                    to_traverse.push_back((slot.sym, rel_pretty, next_depth, all_traversals_valid));
+                    let slot_traversals = match slot.props.slot_kind {
+                        BindingSlotKind::Send if traverse_protocol_senders => {
+                            all_traversals_valid | Traversals::Uses
+                        }
+                        _ => all_traversals_valid,
+                    };
+                    to_traverse.push_back((slot.sym, rel_pretty, next_depth, slot_traversals));
                 }
                 if skip_after_slots {
                     // XXX we should potentially be using reduce_memory_usage_by_dropping_non_jumpref_info
@@ -863,6 +1052,8 @@ impl PipelineCommand for TraverseCommand {
                     let (should_traverse, upwards) = match slot.slot_kind {
                         OntologySlotKind::RunnableConstructor => (self.args.edge == "uses", true),
                         OntologySlotKind::RunnableMethod => (self.args.edge == "callees", false),
+                        OntologySlotKind::TopicHandler => (self.args.edge == "callees", false),
+                        OntologySlotKind::HandledTopic => (self.args.edge == "uses", true),
                     };
                     if !should_traverse {
                         continue;
@@ -880,27 +1071,33 @@ impl PipelineCommand for TraverseCommand {
                             continue;
                         }
 
-                        if upwards {
+                        let mut provenance = EdgeProvenance::new(EdgeSource::OntologySlot)
+                            .with_detail(format!("{:?}", slot.slot_kind));
+                        provenance.rule = slot.rule;
+                        let edge_details = vec![EdgeDetail::Provenance(provenance)];
+                        let edge_id = if upwards {
                             sym_edge_set.ensure_edge_in_graph(
                                 rel_id,
                                 sym_id.clone(),
                                 EdgeKind::Default,
-                                vec![],
+                                edge_details,
                                 &mut graph,
-                            );
+                            )
                         } else {
                             sym_edge_set.ensure_edge_in_graph(
                                 sym_id.clone(),
                                 rel_id,
                                 EdgeKind::Default,
-                                vec![],
+                                edge_details,
                                 &mut graph,
-                            );
-                        }
+                            )
+                        };
                         if !considered.insert(*rel_sym) {
                             continue;
                         }
                         if next_depth >= max_depth {
+                            sym_edge_set
+                                .add_edge_limit(&edge_id, OverloadKind::DepthLimitOnOntologySlot);
                             overloads_hit.push(OverloadInfo {
                                 kind: OverloadKind::DepthLimitOnOntologySlot,
                                 sym: Some(rel_sym.to_string()),
@@ -952,17 +1149,20 @@ impl PipelineCommand for TraverseCommand {
                         continue;
                     }
 
-                    sym_edge_set.ensure_edge_in_graph(
+                    let edge_id = sym_edge_set.ensure_edge_in_graph(
                         sym_id.clone(),
                         target_id,
                         EdgeKind::Inheritance,
-                        vec![],
+                        vec![EdgeDetail::Provenance(EdgeProvenance::new(
+                            EdgeSource::Subclass,
+                        ))],
                         &mut graph,
                     );
                     if !considered.insert(target_info.symbol) {
                         continue;
                     }
                     if next_depth >= max_depth {
+                        sym_edge_set.add_edge_limit(&edge_id, OverloadKind::DepthLimitOnSubclass);
                         overloads_hit.push(OverloadInfo {
                             kind: OverloadKind::DepthLimitOnSubclass,
                             sym: Some(target_info.symbol.to_string()),
@@ -1024,11 +1224,13 @@ impl PipelineCommand for TraverseCommand {
                         }
                     }
 
-                    sym_edge_set.ensure_edge_in_graph(
+                    let edge_id = sym_edge_set.ensure_edge_in_graph(
                         target_id,
                         sym_id.clone(),
                         EdgeKind::Inheritance,
-                        vec![],
+                        vec![EdgeDetail::Provenance(EdgeProvenance::new(
+                            EdgeSource::Super,
+                        ))],
                         &mut graph,
                     );
 
@@ -1036,6 +1238,7 @@ impl PipelineCommand for TraverseCommand {
                         continue;
                     }
                     if next_depth >= max_depth {
+                        sym_edge_set.add_edge_limit(&edge_id, OverloadKind::DepthLimitOnSuper);
                         overloads_hit.push(OverloadInfo {
                             kind: OverloadKind::DepthLimitOnSuper,
                             sym: Some(target_info.symbol.to_string()),
@@ -1093,14 +1296,17 @@ impl PipelineCommand for TraverseCommand {
                     // because overrides are an equivalence class from
                     // our perspective (right now, before actually
                     // checking the definition of equivalence class. ;)
-                    sym_edge_set.ensure_edge_in_graph(
+                    let edge_id = sym_edge_set.ensure_edge_in_graph(
                         target_id,
                         sym_id.clone(),
                         EdgeKind::Inheritance,
-                        vec![],
+                        vec![EdgeDetail::Provenance(EdgeProvenance::new(
+                            EdgeSource::Overrides,
+                        ))],
                         &mut graph,
                     );
                     if next_depth >= max_depth {
+                        sym_edge_set.add_edge_limit(&edge_id, OverloadKind::DepthLimitOnOverrides);
                         overloads_hit.push(OverloadInfo {
                             kind: OverloadKind::DepthLimitOnOverrides,
                             sym: Some(target_info.symbol.to_string()),
@@ -1146,14 +1352,18 @@ impl PipelineCommand for TraverseCommand {
                         continue;
                     }
                     // Same rationale on avoiding a duplicate edge.
-                    sym_edge_set.ensure_edge_in_graph(
+                    let edge_id = sym_edge_set.ensure_edge_in_graph(
                         sym_id.clone(),
                         target_id,
                         EdgeKind::Inheritance,
-                        vec![],
+                        vec![EdgeDetail::Provenance(EdgeProvenance::new(
+                            EdgeSource::OverriddenBy,
+                        ))],
                         &mut graph,
                     );
                     if next_depth >= max_depth {
+                        sym_edge_set
+                            .add_edge_limit(&edge_id, OverloadKind::DepthLimitOnOverriddenBy);
                         overloads_hit.push(OverloadInfo {
                             kind: OverloadKind::DepthLimitOnOverriddenBy,
                             sym: Some(target_info.symbol.to_string()),
@@ -1190,13 +1400,17 @@ impl PipelineCommand for TraverseCommand {
                 };
 
                 // Callees are synthetically derived from crossref and is a
-                // flat list of { kind, pretty, sym }.  This differs from
+                // flat list of { kind, pretty, sym, via }.  This differs from
                 // most other edges which are path hit-lists.
                 for target in callees {
                     let mut edge_info = vec![];
                     // The jump is precomputed by the crossref process when
                     // deriving the "callees" kindmap entry.
                     edge_info.push(EdgeDetail::Jump(target.jump.clone()));
+                    edge_info.push(EdgeDetail::Provenance(EdgeProvenance::from_jump(
+                        EdgeSource::Callees,
+                        &target.jump,
+                    )));
 
                     let (target_id, target_info) = sym_node_set
                         .ensure_symbol(&target.sym, server, next_depth)
@@ -1213,10 +1427,16 @@ impl PipelineCommand for TraverseCommand {
                     if !target_info.is_callable() {
                         continue;
                     }
-                    sym_edge_set.ensure_edge_in_graph(
+                    // Callees synthesized through IDL binding slots cross
+                    // from JS into the C++ implementation.
+                    let edge_kind = match target.via {
+                        Some(_) => EdgeKind::CrossLanguage,
+                        None => EdgeKind::Default,
+                    };
+                    let edge_id = sym_edge_set.ensure_edge_in_graph(
                         sym_id.clone(),
                         target_id,
-                        EdgeKind::Default,
+                        edge_kind,
                         edge_info,
                         &mut graph,
                     );
@@ -1224,6 +1444,7 @@ impl PipelineCommand for TraverseCommand {
                         continue;
                     }
                     if next_depth >= max_depth {
+                        sym_edge_set.add_edge_limit(&edge_id, OverloadKind::DepthLimitOnCallees);
                         overloads_hit.push(OverloadInfo {
                             kind: OverloadKind::DepthLimitOnCallees,
                             sym: Some(target_info.symbol.to_string()),
@@ -1245,7 +1466,7 @@ impl PipelineCommand for TraverseCommand {
                 }
             }
 
-            if traverse_uses {
+            if traverse_uses || cur_traversals.contains(Traversals::Uses) {
                 let sym_info = sym_node_set.get_mut(&sym_id);
                 let uses = match (
                     self.args.retain_all_symbol_data,
@@ -1351,11 +1572,14 @@ impl PipelineCommand for TraverseCommand {
                         // and traversed this edge because we want to merge in edge detail
                         // information.
                         let jump = format!("{}#{}", path, source.lineno);
-                        sym_edge_set.ensure_edge_in_graph(
+                        let mut provenance = EdgeProvenance::new(EdgeSource::Uses);
+                        provenance.path = Some(path);
+                        provenance.lineno = Some(source.lineno);
+                        let edge_id = sym_edge_set.ensure_edge_in_graph(
                             source_id,
                             sym_id.clone(),
                             EdgeKind::Default,
-                            vec![EdgeDetail::Jump(jump)],
+                            vec![EdgeDetail::Jump(jump), EdgeDetail::Provenance(provenance)],
                             &mut graph,
                         );
                         if !use_considered.insert(source_info.symbol) {
@@ -1366,6 +1590,7 @@ impl PipelineCommand for TraverseCommand {
                         }
                         // Only traverse the edge once.
                         if next_depth >= max_depth {
+                            sym_edge_set.add_edge_limit(&edge_id, OverloadKind::DepthLimitOnUses);
                             overloads_hit.push(OverloadInfo {
                                 kind: OverloadKind::DepthLimitOnUses,
                                 sym: Some(source_info.symbol.to_string()),
@@ -1382,7 +1607,7 @@ impl PipelineCommand for TraverseCommand {
                             source_info.symbol,
                             source_pretty,
                             next_depth,
-                            all_traversals_valid,
+                            all_traversals_valid | (cur_traversals & Traversals::Uses),
                         ));
                     }
                 }
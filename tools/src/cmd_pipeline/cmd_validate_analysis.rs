@@ -0,0 +1,58 @@
+use async_trait::async_trait;
+use clap::Args;
+use serde_json::to_value;
+
+use super::interface::{JsonValue, JsonValueList, PipelineCommand, PipelineValues};
+use crate::{
+    abstract_server::{AbstractServer, Result, SearchfoxIndexRoot},
+    file_format::analysis_validator::validate_analysis_file,
+};
+
+/// Strictly validate analysis files, producing a diagnostics report per file.
+#[derive(Debug, Args)]
+pub struct ValidateAnalysis {
+    /// Tree-relative source file paths whose analysis files should be checked.
+    #[clap(value_parser)]
+    files: Vec<String>,
+
+    /// Only emit reports for files that have diagnostics.
+    #[clap(long, value_parser)]
+    only_problems: bool,
+}
+
+/// Checks every record against the typed analysis definitions and checks
+/// locations against the source file.  See `analysis_validator.rs` and the
+/// standalone `validate-analysis.rs` binary.
+#[derive(Debug)]
+pub struct ValidateAnalysisCommand {
+    pub args: ValidateAnalysis,
+}
+
+#[async_trait]
+impl PipelineCommand for ValidateAnalysisCommand {
+    async fn execute(
+        &self,
+        server: &(dyn AbstractServer + Send + Sync),
+        _input: PipelineValues,
+    ) -> Result<PipelineValues> {
+        let mut values = vec![];
+        for file in &self.args.files {
+            let mut analysis_path =
+                server.translate_path(SearchfoxIndexRoot::CompressedAnalysis, file)?;
+            // Like `read_analyses`, fall back to the uncompressed file.
+            if analysis_path.ends_with(".gz") && !std::fs::exists(&analysis_path).unwrap_or(false) {
+                analysis_path.truncate(analysis_path.len() - 3);
+            }
+            let source = server.fetch_raw_source(file).await.ok();
+            let report = validate_analysis_file(file, &analysis_path, source.as_deref());
+            if self.args.only_problems && report.diagnostics.is_empty() {
+                continue;
+            }
+            values.push(JsonValue {
+                value: to_value(report)?,
+            });
+        }
+
+        Ok(PipelineValues::JsonValueList(JsonValueList { values }))
+    }
+}
@@ -1,6 +1,6 @@
 use async_trait::async_trait;
 use bitflags::bitflags;
-use clap::{Args, ValueEnum};
+use clap::Args;
 use serde::{Serialize, Serializer, ser::SerializeStruct};
 use serde_json::{Value, to_string_pretty};
 use std::{
@@ -22,23 +22,6 @@ use crate::{
 
 use super::symbol_graph::{SymbolGraphCollection, SymbolGraphNodeSet};
 
-#[derive(Clone, Debug, PartialEq, ValueEnum)]
-pub enum RecordType {
-    Source,
-    Target,
-    Structured,
-}
-
-impl RecordType {
-    pub fn name(&self) -> &'static str {
-        match self {
-            RecordType::Source => "source",
-            RecordType::Target => "target",
-            RecordType::Structured => "structured",
-        }
-    }
-}
-
 #[derive(Debug, Args)]
 pub struct SymbolicQueryOpts {
     /// Exact symbol match
@@ -422,7 +405,7 @@ impl Ord for SymbolQuality {
     }
 }
 
-#[derive(Clone, Debug, Serialize)]
+#[derive(Clone, Debug, PartialEq, Serialize)]
 pub enum OverloadKind {
     /// There's just too many overrides!  This would happen for
     /// nsISupports::AddRef for example.
@@ -446,6 +429,7 @@ pub enum OverloadKind {
     DepthLimitOnFieldPointer,
     DepthLimitOnBindingSlot,
     DepthLimitOnOntologySlot,
+    DepthLimitOnIpdlProtocol,
     DepthLimitOnSubclass,
     DepthLimitOnSuper,
     DepthLimitOnOverrides,
@@ -455,6 +439,23 @@ pub enum OverloadKind {
     DepthLimitOnFieldMemberUses,
 }
 
+impl OverloadKind {
+    /// Returns true if this limit is reported against the symbol whose
+    /// neighbors went unconsidered rather than against an already-created edge
+    /// to the symbol that wasn't traversed (the `DepthLimitOn*` variants).
+    pub fn prunes_neighbors(&self) -> bool {
+        matches!(
+            self,
+            OverloadKind::Overrides
+                | OverloadKind::Subclasses
+                | OverloadKind::UsesPaths
+                | OverloadKind::UsesLines
+                | OverloadKind::FieldMemberUses
+                | OverloadKind::NodeLimit
+        )
+    }
+}
+
 /// Information about overloads encountered when processing some aspect of a
 /// symbol.  We've had a history of being unclear when limits are hit, so the
 /// goal here is to be able to explicitly convey when we're hitting limits and
@@ -8,6 +8,7 @@ pub mod transforms;
 
 mod cmd_augment_results;
 mod cmd_batch_render;
+mod cmd_blame;
 mod cmd_cat_html;
 mod cmd_compile_results;
 mod cmd_crossref_expand;
@@ -18,8 +19,10 @@ mod cmd_fuse_crossrefs;
 mod cmd_graph;
 mod cmd_jq;
 mod cmd_jumpref_lookup;
+mod cmd_layout_audit;
 mod cmd_merge_analyses;
 mod cmd_normalize_unstable_data;
+mod cmd_platform_divergence;
 mod cmd_query;
 mod cmd_render;
 mod cmd_search;
@@ -29,6 +32,7 @@ mod cmd_search_text;
 mod cmd_show_html;
 mod cmd_tokenize_source;
 mod cmd_traverse;
+mod cmd_validate_analysis;
 mod cmd_webtest;
 
 pub use builder::build_pipeline;
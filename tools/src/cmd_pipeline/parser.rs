@@ -2,6 +2,7 @@ use clap::{Parser, Subcommand, ValueEnum};
 
 use super::cmd_augment_results::AugmentResults;
 use super::cmd_batch_render::BatchRender;
+use super::cmd_blame::Blame;
 use super::cmd_cat_html::CatHtml;
 use super::cmd_compile_results::CompileResults;
 use super::cmd_crossref_expand::CrossrefExpand;
@@ -12,8 +13,10 @@ use super::cmd_fuse_crossrefs::FuseCrossrefs;
 use super::cmd_graph::Graph;
 use super::cmd_jq::JQ;
 use super::cmd_jumpref_lookup::JumprefLookup;
+use super::cmd_layout_audit::LayoutAudit;
 use super::cmd_merge_analyses::MergeAnalyses;
 use super::cmd_normalize_unstable_data::NormalizeUnstableData;
+use super::cmd_platform_divergence::PlatformDivergence;
 use super::cmd_query::Query;
 use super::cmd_render::Render;
 use super::cmd_search::Search;
@@ -23,6 +26,7 @@ use super::cmd_search_text::SearchText;
 use super::cmd_show_html::ShowHtml;
 use super::cmd_tokenize_source::TokenizeSource;
 use super::cmd_traverse::Traverse;
+use super::cmd_validate_analysis::ValidateAnalysis;
 use super::cmd_webtest::Webtest;
 
 #[derive(Clone, Debug, PartialEq, ValueEnum)]
@@ -65,6 +69,7 @@ pub struct ToolOpts {
 pub enum Command {
     AugmentResults(AugmentResults),
     BatchRender(BatchRender),
+    Blame(Blame),
     CatHtml(CatHtml),
     CrossrefExpand(CrossrefExpand),
     CrossrefLookup(CrossrefLookup),
@@ -73,8 +78,10 @@ pub enum Command {
     Graph(Graph),
     JumprefLookup(JumprefLookup),
     JQ(JQ),
+    LayoutAudit(LayoutAudit),
     MergeAnalyses(MergeAnalyses),
     NormalizeUnstableData(NormalizeUnstableData),
+    PlatformDivergence(PlatformDivergence),
     Query(Query),
     Render(Render),
     Search(Search),
@@ -84,6 +91,7 @@ pub enum Command {
     ShowHtml(ShowHtml),
     TokenizeSource(TokenizeSource),
     Traverse(Traverse),
+    ValidateAnalysis(ValidateAnalysis),
     Webtest(Webtest),
 }
 
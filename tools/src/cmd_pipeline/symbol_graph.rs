@@ -34,7 +34,7 @@ pub use crate::symbol_graph_edge_kind::EdgeKind;
 
 use super::{
     cmd_graph::{GraphHierarchy, GraphLayout},
-    interface::OverloadInfo,
+    interface::{OverloadInfo, OverloadKind},
 };
 
 /**
@@ -135,6 +135,10 @@ pub fn semantic_kind_is_callable(semantic_kind: &str) -> bool {
         // fields being referenced when we're talking about control-flow.  That
         // might suggest an orthogonal diagram setting or something.
         "file" => true,
+        // Synthetic ontology topics (ex: observer topics) sit between the
+        // emitting call sites and the handlers, so they need to be traversable
+        // in both directions.
+        "topic" => true,
         _ => false,
     }
 }
@@ -294,6 +298,20 @@ impl DerivedSymbolInfo {
         None
     }
 
+    /// Like `get_binding_slot_sym` but for slot kinds that can have multiple
+    /// symbols, like the overloads of an IPC `Send` method.
+    pub fn get_binding_slot_syms(&self, kind: BindingSlotKind) -> Vec<Ustr> {
+        self.get_structured()
+            .map(|meta| {
+                meta.binding_slots
+                    .iter()
+                    .filter(|slot| slot.props.slot_kind == kind)
+                    .map(|slot| slot.sym)
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
     pub fn get_subsystem(&self) -> Option<Ustr> {
         self.get_structured().and_then(|meta| meta.subsystem)
     }
@@ -411,7 +429,10 @@ impl SymbolGraphCollection {
     ///
     /// - nodes is a sorted array of symbol strings.
     /// - edges is a sorted array of { from, to } where from/to are symbol
-    ///   strings and the sort is over [from, to]
+    ///   strings and the sort is over [from, to].  Each edge also carries its
+    ///   `EdgeDetail`s as jumps, hovers, provenance, and (if any) limits.
+    /// - nodeLimits, if present, maps symbols to the `OverloadInfo`s for limits
+    ///   that kept us from considering the symbol's neighbors.
     pub fn graph_to_json(&self, graph_idx: usize) -> Value {
         let graph = match self.graphs.get(graph_idx) {
             Some(g) => g,
@@ -421,9 +442,6 @@ impl SymbolGraphCollection {
         // I am biasing for code readability over performance.  In particular,
         // note that we need not infer the nodes from the edges, but it's less
         // code this way.
-        //
-        // XXX currently we're not serializing the edge information here either,
-        // but probably should.
         let mut nodes = BTreeSet::new();
         let mut edges = BTreeMap::new();
         for (source_id, target_id, edge_id) in graph.list_edges() {
@@ -439,29 +457,52 @@ impl SymbolGraphCollection {
 
             let mut jumps = vec![];
             let mut hovers = vec![];
+            let mut provenance = vec![];
+            let mut limits = vec![];
             for detail in &edge_info.data {
                 match detail {
                     EdgeDetail::Jump(s) => jumps.push(s.clone()),
                     EdgeDetail::HoverClass(s) => hovers.push(s.clone()),
+                    EdgeDetail::Provenance(p) => provenance.push(p.clone()),
+                    EdgeDetail::Limit(kind) => limits.push(kind.clone()),
                 }
             }
 
-            edges.insert(
-                format!("{}-{}", source_sym, target_sym),
-                json!({
-                    "from": source_sym,
-                    "to": target_sym,
-                    "kind": edge_info.kind,
-                    "jumps": json!(jumps),
-                    "hovers": json!(hovers),
-                }),
-            );
+            let mut edge_json = json!({
+                "from": source_sym,
+                "to": target_sym,
+                "kind": edge_info.kind,
+                "jumps": json!(jumps),
+                "hovers": json!(hovers),
+                "provenance": json!(provenance),
+            });
+            // Limits are rare, so only include them when present.
+            if !limits.is_empty() {
+                edge_json["limits"] = json!(limits);
+            }
+            edges.insert(format!("{}-{}", source_sym, target_sym), edge_json);
         }
 
-        json!({
+        // Report the limits that kept us from considering some of a node's
+        // neighbors at all, which means there is no edge to hang them off of.
+        let mut node_limits: BTreeMap<String, Vec<&OverloadInfo>> = BTreeMap::new();
+        for overload in &self.overloads_hit {
+            if let Some(sym) = &overload.sym
+                && overload.kind.prunes_neighbors()
+                && nodes.contains(&ustr(sym))
+            {
+                node_limits.entry(sym.clone()).or_default().push(overload);
+            }
+        }
+
+        let mut graph_json = json!({
             "nodes": nodes.into_iter().collect::<Vec<Ustr>>(),
             "edges": edges.into_values().collect::<Value>(),
-        })
+        });
+        if !node_limits.is_empty() {
+            graph_json["nodeLimits"] = json!(node_limits);
+        }
+        graph_json
     }
 
     /// Convert the graph with the given index to a { nodes, edges } rep where:
@@ -597,6 +638,11 @@ impl SymbolGraphCollection {
         // visually at 10 right now.
         const SYNTHETIC_DEPTH: u32 = 13;
 
+        let process_sides = match &policies.grouping {
+            GraphHierarchy::Flat | GraphHierarchy::Flatbadges => HashMap::default(),
+            _ => derive_process_sides(graph, &self.node_set, &self.edge_set),
+        };
+
         for sym_id in graph.list_nodes() {
             let (sym, sym_pretty) = {
                 let node_info = self.node_set.get(&sym_id);
@@ -803,6 +849,14 @@ impl SymbolGraphCollection {
                             .collect_vec()
                     }
                 };
+            // Symbols on one side of an IPC boundary go in a cluster for that
+            // side.
+            let segments_and_syms = match process_sides.get(&sym_id) {
+                Some(side) => std::iter::once((HierarchySegment::Process(side.clone()), None))
+                    .chain(segments_and_syms)
+                    .collect_vec(),
+                None => segments_and_syms,
+            };
 
             let mut segments_so_far = vec![];
             for (segment, maybe_sym) in segments_and_syms {
@@ -1243,8 +1297,9 @@ pub enum HierarchicalLayoutAction {
 }
 
 /// A typed hierarchy segment.  While initially we expect all segments to be
-/// part of a pretty identifier, in the future this may include:
-/// - Process Type (Parent, Content, Network, etc.)
+/// part of a pretty identifier, this now also includes the process side of an
+/// IPC boundary and in the future this may include:
+/// - Process Type (Parent, Content, Network, etc.) rather than just the side
 /// - Subsystem / subcomponent / submodule
 ///
 /// XXX for now we're just going to use `PrettySegment` for everything because
@@ -1259,12 +1314,17 @@ pub enum HierarchySegment {
     /// include the delimeter as part of the segment string and the delimiter
     /// may accordingly be an empty string.
     PrettySegment(String, &'static str),
+    /// The side of IPC edges the symbols under this segment are on; see
+    /// `derive_process_sides`.  This is always the outermost segment and isn't
+    /// part of the pretty identifier.
+    Process(String),
 }
 
 impl HierarchySegment {
     pub fn to_human_readable(&self) -> String {
         match self {
             Self::PrettySegment(p, _) => p.clone(),
+            Self::Process(side) => side.clone(),
         }
     }
 
@@ -1276,10 +1336,105 @@ impl HierarchySegment {
             Self::PrettySegment(piece, delim) => {
                 format!("{}{}{}", existing_str, delim, piece)
             }
+            Self::Process(_) => existing_str.to_string(),
         }
     }
 }
 
+/// Determine which actor of which protocol a Send/Recv method belongs to from
+/// the IPDL convention that actors are `*Parent` and `*Child` classes.  Note
+/// that this is the actor's role in the protocol, not which process it lives
+/// in; a child process can host the parent actor of another protocol.
+fn ipc_actor_side(pretty: &str) -> Option<(String, &'static str)> {
+    let (class_pretty, _method) = pretty.rsplit_once("::")?;
+    let class_name = class_pretty.rsplit("::").next()?;
+    let (actor, side) = if let Some(actor) = class_name.strip_suffix("Parent") {
+        (actor, "parent")
+    } else if let Some(actor) = class_name.strip_suffix("Child") {
+        (actor, "child")
+    } else {
+        return None;
+    };
+    // Implementation classes drop the "P" prefix of the protocol name.
+    let protocol = match actor.strip_prefix('P') {
+        Some(rest) if rest.starts_with(|c: char| c.is_ascii_uppercase()) => actor.to_string(),
+        _ => format!("P{}", actor),
+    };
+    Some((protocol, side))
+}
+
+/// Group nodes by the side of the IPC edges they are on when the graph crosses
+/// IPC edges.  Nodes connected by non-IPC edges, ignoring direction, are on
+/// the same side, and the side is named after the actors whose Send/Recv
+/// methods (per `ipc_actor_side`) are in the group, like "PFoo child side".  A
+/// group can be on the sides of multiple protocols when the graph makes
+/// multiple IPC hops.  Groups containing both actors of the same protocol (ex:
+/// shared utility code) aren't assigned a side for that protocol.
+fn derive_process_sides(
+    graph: &NamedSymbolGraph,
+    node_set: &SymbolGraphNodeSet,
+    edge_set: &SymbolGraphEdgeSet,
+) -> HashMap<SymbolGraphNodeId, String> {
+    let mut neighbors: HashMap<SymbolGraphNodeId, Vec<SymbolGraphNodeId>> = HashMap::default();
+    let mut seeds = vec![];
+    for (from_id, to_id, edge_id) in graph.list_edges() {
+        if let EdgeKind::IPC = edge_set.get(&edge_id).kind {
+            for node_id in [from_id, to_id] {
+                if let Some(actor_side) = ipc_actor_side(&node_set.get(&node_id).get_pretty()) {
+                    seeds.push((node_id, actor_side));
+                }
+            }
+        } else {
+            neighbors
+                .entry(from_id.clone())
+                .or_default()
+                .push(to_id.clone());
+            neighbors.entry(to_id).or_default().push(from_id);
+        }
+    }
+
+    // Assign each group the index of the first seed that reached it.
+    let mut groups: HashMap<SymbolGraphNodeId, usize> = HashMap::default();
+    let mut group_sides: Vec<BTreeMap<String, BTreeSet<&'static str>>> = vec![];
+    for (seed_id, (protocol, side)) in seeds {
+        let group = match groups.get(&seed_id) {
+            Some(group) => *group,
+            None => {
+                let group = group_sides.len();
+                group_sides.push(BTreeMap::new());
+                let mut to_visit = vec![seed_id];
+                while let Some(node_id) = to_visit.pop() {
+                    if groups.contains_key(&node_id) {
+                        continue;
+                    }
+                    groups.insert(node_id.clone(), group);
+                    if let Some(kids) = neighbors.get(&node_id) {
+                        to_visit.extend(kids.iter().cloned());
+                    }
+                }
+                group
+            }
+        };
+        group_sides[group].entry(protocol).or_default().insert(side);
+    }
+
+    let group_names = group_sides
+        .into_iter()
+        .map(|protocol_sides| {
+            protocol_sides
+                .into_iter()
+                .filter(|(_, sides)| sides.len() == 1)
+                .map(|(protocol, sides)| format!("{} {} side", protocol, sides.first().unwrap()))
+                .join(", ")
+        })
+        .collect_vec();
+    groups
+        .into_iter()
+        .filter(|(_, group)| !group_names[*group].is_empty())
+        .map(|(node_id, group)| (node_id, group_names[group].clone()))
+        .collect()
+}
+
 /// A hierarchial graph derived from a NamedSymbolGraph.
 #[derive(Debug)]
 pub struct HierarchicalSymbolGraph {
@@ -1469,8 +1624,11 @@ impl HierarchicalNode {
 
         // If the node has only one child and no edges, we can collapse it UNLESS
         // the child is a class, in which case we really don't want to.
+        // Process clusters are never collapsed because showing the boundary is
+        // their purpose.
         if !is_root
             && !be_class
+            && !matches!(self.segment, HierarchySegment::Process(_))
             && self.children.len() == 1
             && self.edges.is_empty()
             && !has_edge_from_or_to_self
@@ -1496,6 +1654,7 @@ impl HierarchicalNode {
                     // its best delimiter.
                     let delim = match &sole_kid.segment {
                         HierarchySegment::PrettySegment(_, delim) => delim,
+                        HierarchySegment::Process(_) => "",
                     };
                     sole_kid.display_name =
                         format!("{}{}{}", self.display_name, delim, sole_kid.display_name);
@@ -1700,6 +1859,9 @@ impl HierarchicalNode {
             }
             HierarchicalLayoutAction::Cluster(cluster_id, placeholder_id) => {
                 let mut sg = subgraph!(esc cluster_id; attr!("cluster", "true"), attr!("label", esc escape_quotes(&self.display_name)));
+                if let HierarchySegment::Process(_) = self.segment {
+                    sg.stmts.push(stmt!(attr!("style", "dashed")));
+                }
                 sg.stmts.push(stmt!(
                     node!(esc placeholder_id; attr!("shape", "point"), attr!("style", "invis"))
                 ));
@@ -2009,6 +2171,12 @@ pub struct SvgNodeExtra {
 #[derive(Debug, Serialize)]
 pub struct SvgEdgeExtra {
     pub jump: String,
+    /// See `EdgeDetail::Provenance`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub provenance: Vec<EdgeProvenance>,
+    /// See `EdgeDetail::Limit`.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub limits: Vec<OverloadKind>,
 }
 
 impl Default for HierarchicalRenderState {
@@ -2098,6 +2266,8 @@ impl HierarchicalRenderState {
 
         let mut hover_classes = vec![];
         let mut jump = "".to_string();
+        let mut provenance = vec![];
+        let mut limits = vec![];
         for detail in edge_data {
             match detail {
                 EdgeDetail::Jump(jump_detail) => {
@@ -2118,6 +2288,12 @@ impl HierarchicalRenderState {
                 EdgeDetail::HoverClass(class_detail) => {
                     hover_classes.push(class_detail.clone());
                 }
+                EdgeDetail::Provenance(edge_provenance) => {
+                    provenance.push(edge_provenance.clone());
+                }
+                EdgeDetail::Limit(kind) => {
+                    limits.push(kind.clone());
+                }
             }
         }
 
@@ -2127,9 +2303,17 @@ impl HierarchicalRenderState {
 
         if let Some(extra) = self.svg_edge_extra.get_mut(&edge_eid) {
             extra.jump = jump;
+            extra.provenance = provenance;
+            extra.limits = limits;
         } else {
-            self.svg_edge_extra
-                .insert(edge_eid.clone(), SvgEdgeExtra { jump });
+            self.svg_edge_extra.insert(
+                edge_eid.clone(),
+                SvgEdgeExtra {
+                    jump,
+                    provenance,
+                    limits,
+                },
+            );
         }
 
         let to_extra = self.svg_node_extra.entry(to_eid).or_default();
@@ -2169,6 +2353,85 @@ pub enum EdgeDetail {
     /// Hover class for the target node when the source node is hovered.  This
     /// could also be applied to the edge if desired.
     HoverClass(String),
+    /// Explains which crossref record or ontology rule caused the edge to be
+    /// created.  An edge can have multiple provenances, like a function that
+    /// calls another function on multiple lines.
+    Provenance(EdgeProvenance),
+    /// The far side of this edge was not traversed further because of this
+    /// limit, so its other neighbors were pruned.
+    Limit(OverloadKind),
+}
+
+/// The crossref data that an edge was derived from.
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum EdgeSource {
+    /// A "uses" hit whose contextsym is the source of the edge.
+    Uses,
+    /// A "callees" entry computed by crossref.
+    Callees,
+    /// A field's `pointerInfo`.
+    FieldPointer,
+    /// The symbol's `slotOwner`.
+    SlotOwner,
+    /// One of the symbol's `bindingSlots`.
+    BindingSlot,
+    /// One of the symbol's `ontologySlots` from `ontology-mapping.toml`.
+    OntologySlot,
+    /// An IPDL protocol's `ipdlProtocol` managed protocols or messages.
+    IpdlProtocol,
+    Subclass,
+    Super,
+    Overrides,
+    OverriddenBy,
+}
+
+/// "Explain why" information for an edge so that surprising graphs can be
+/// debugged without having to re-derive what the traversal was looking at.
+#[derive(Clone, Debug, PartialEq, Serialize)]
+pub struct EdgeProvenance {
+    pub source: EdgeSource,
+    /// The source file of the record, when the edge came from a hit.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path: Option<Ustr>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lineno: Option<u32>,
+    /// For ontology slots, the `ontology-mapping.toml` rule that created the
+    /// slot.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub rule: Option<Ustr>,
+    /// Additional specifics like the binding/ontology slot kind or the field
+    /// that holds a pointer.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub detail: Option<String>,
+}
+
+impl EdgeProvenance {
+    pub fn new(source: EdgeSource) -> Self {
+        Self {
+            source,
+            path: None,
+            lineno: None,
+            rule: None,
+            detail: None,
+        }
+    }
+
+    /// Create a provenance from a "path#lines" jump where only the first line
+    /// is retained.
+    pub fn from_jump(source: EdgeSource, jump: &str) -> Self {
+        let mut prov = Self::new(source);
+        if let Some((path, lines)) = jump.rsplit_once('#') {
+            prov.path = Some(ustr(path));
+            prov.lineno = lines.split(',').next().and_then(|l| l.parse().ok());
+        }
+        prov
+    }
+
+    pub fn with_detail(mut self, detail: String) -> Self {
+        self.detail = Some(detail);
+        self
+    }
 }
 
 /// Information about the edge between two nodes that is something we either
@@ -2517,8 +2780,8 @@ impl SymbolGraphEdgeSet {
     /// exist.  Then calls ensure_edge on the underlying graph using the
     /// underlying SymbolGraphEdgeId.
     ///
-    /// Currently we don't return the SymbolGraphEdgeId but we could if callers
-    /// needed it.
+    /// The SymbolGraphEdgeId is returned so that callers can add details like
+    /// `EdgeDetail::Limit` that they only know about after the fact.
     pub fn ensure_edge_in_graph(
         &mut self,
         source: SymbolGraphNodeId,
@@ -2526,7 +2789,7 @@ impl SymbolGraphEdgeSet {
         kind: EdgeKind,
         data: Vec<EdgeDetail>,
         graph: &mut NamedSymbolGraph,
-    ) {
+    ) -> SymbolGraphEdgeId {
         let edge_id = if let Some(idx) = self.edge_lookup.get(&(source.0, target.0)) {
             let info = self.edge_infos.get_mut(*idx as usize).unwrap();
             for detail in data {
@@ -2546,6 +2809,17 @@ impl SymbolGraphEdgeSet {
             self.edge_lookup.insert((source.0, target.0), index as u32);
             SymbolGraphEdgeId(index as u32)
         };
-        graph.ensure_edge(source, target, edge_id);
+        graph.ensure_edge(source, target, edge_id.clone());
+        edge_id
+    }
+
+    /// Note that we stopped traversing at the far side of the given edge
+    /// because of a limit.
+    pub fn add_edge_limit(&mut self, edge_id: &SymbolGraphEdgeId, kind: OverloadKind) {
+        let detail = EdgeDetail::Limit(kind);
+        let info = self.get_mut(edge_id);
+        if !info.data.contains(&detail) {
+            info.data.push(detail);
+        }
     }
 }
@@ -25,9 +25,15 @@ pub fn describe_file(contents: &str, path: &Path, format: &FormatAs) -> Option<S
             if spec.rust_tweaks {
                 describe_from_rust_comment(substr).or_else(|| describe_from_c_comment(substr))
             } else if spec.c_style_comments {
-                describe_from_c_comment(substr)
+                describe_from_c_comment(substr).or_else(|| {
+                    spec.describe_line_comments
+                        .then(|| describe_from_line_comments(substr, "//"))
+                        .flatten()
+                })
             } else if spec.triple_quote_literals {
                 describe_py(substr)
+            } else if spec.hash_comment {
+                describe_from_line_comments(substr, "#")
             } else {
                 None
             }
@@ -35,6 +41,8 @@ pub fn describe_file(contents: &str, path: &Path, format: &FormatAs) -> Option<S
         FormatAs::FormatXPIDL(_, _) => describe_from_c_comment(substr),
         FormatAs::Binary => None,
         FormatAs::YAML => None,
+        // README.md files used to be Plain, so keep the first line as a fallback.
+        FormatAs::Markdown => describe_markdown(substr).or_else(|| describe_readme(substr)),
         FormatAs::Plain => {
             let stem = path.file_stem()?.to_str()?;
             if stem.eq_ignore_ascii_case("README") {
@@ -78,6 +86,41 @@ fn describe_readme(contents: &str) -> Option<String> {
         .map(str::to_string)
 }
 
+/// Returns the text of the first heading, either ATX (`# Heading`) or setext
+/// (a line underlined with `=` or `-`).
+fn describe_markdown(contents: &str) -> Option<String> {
+    let mut prev_line: Option<&str> = None;
+    let mut in_fence = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            prev_line = None;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        if let Some(heading) = trimmed.strip_prefix('#') {
+            let heading = heading.trim_start_matches('#');
+            if heading.is_empty() || heading.starts_with(' ') {
+                let heading = heading.trim().trim_end_matches('#').trim();
+                if !heading.is_empty() {
+                    return Some(heading.to_string());
+                }
+            }
+        }
+        if let Some(prev) = prev_line
+            && !trimmed.is_empty()
+            && (trimmed.chars().all(|c| c == '=') || trimmed.chars().all(|c| c == '-'))
+        {
+            return Some(prev.to_string());
+        }
+        prev_line = Some(trimmed).filter(|l| !l.is_empty());
+    }
+    None
+}
+
 /// Returns the first Rust-style module doc-comment (`//!`).
 fn describe_from_rust_comment(contents: &str) -> Option<String> {
     let mut description = String::new();
@@ -98,6 +141,43 @@ fn describe_from_rust_comment(contents: &str) -> Option<String> {
     None
 }
 
+fn is_boilerplate_comment(comment_text: &str) -> bool {
+    comment_text.contains("tab-width") ||
+        comment_text.contains("vim:") ||
+        // Checking common case-variants is probably cheaper than lowercasing
+        // comment_text (which allocates a new string) and doing a lowercase search
+        comment_text.contains("license") ||
+        comment_text.contains("LICENSE") ||
+        comment_text.contains("License")
+}
+
+/// Returns the first block of line comments with the given prefix that comes
+/// before any code and isn't a shebang or vim/modeline/license boilerplate.
+fn describe_from_line_comments(contents: &str, prefix: &str) -> Option<String> {
+    let mut block: Vec<&str> = vec![];
+    for line in contents.lines().map(str::trim) {
+        if let Some(text) = line.strip_prefix(prefix)
+            && !line.starts_with("#!")
+        {
+            block.push(text.trim());
+            continue;
+        }
+        let description = block.join("\n");
+        if !description.trim().is_empty() && !is_boilerplate_comment(&description) {
+            return Some(description);
+        }
+        block.clear();
+        if !line.is_empty() && !line.starts_with("#!") {
+            return None;
+        }
+    }
+    let description = block.join("\n");
+    if !description.trim().is_empty() && !is_boilerplate_comment(&description) {
+        return Some(description);
+    }
+    None
+}
+
 /// Returns the first C-style comment that's not vim/modeline/license boilerplate
 fn describe_from_c_comment(contents: &str) -> Option<String> {
     lazy_static! {
@@ -113,14 +193,7 @@ fn describe_from_c_comment(contents: &str) -> Option<String> {
     for captures in COMMENT_REGEX.captures_iter(contents) {
         if let Some(comment_match) = captures.get(1) {
             let comment_text = comment_match.as_str();
-            if comment_text.contains("tab-width") ||
-                comment_text.contains("vim:") ||
-                // Checking common case-variants is probably cheaper than lowercasing
-                // comment_text (which allocates a new string) and doing a lowercase search
-                comment_text.contains("license") ||
-                comment_text.contains("LICENSE") ||
-                comment_text.contains("License")
-            {
+            if is_boilerplate_comment(comment_text) {
                 continue;
             }
             return Some(LEADING_STARS.replace_all(comment_text, "").into_owned());
@@ -128,3 +201,36 @@ fn describe_from_c_comment(contents: &str) -> Option<String> {
     }
     None
 }
+
+#[test]
+fn test_describe_new_languages() {
+    use crate::languages::select_formatting;
+
+    let describe = |path: &str, contents: &str| {
+        describe_file(contents, Path::new(path), &select_formatting(path))
+    };
+
+    assert_eq!(
+        describe(
+            "docs/index.md",
+            "<!-- x -->\n```\n# not a heading\n```\n## Getting Started ##\n"
+        ),
+        Some("Getting Started".to_string())
+    );
+    assert_eq!(
+        describe("README.markdown", "Searchfox\n=========\n"),
+        Some("Searchfox".to_string())
+    );
+    assert_eq!(
+        describe(
+            "build.sh",
+            "#!/bin/bash\n# This Source Code Form is subject to the terms of the Mozilla Public\n# License.\n\n# Builds the thing.\n# Quickly.\nset -e\n"
+        ),
+        Some("Builds the thing.\nQuickly.".to_string())
+    );
+    assert_eq!(describe("run.sh", "set -e\n# Too late.\n"), None);
+    assert_eq!(
+        describe("main.go", "// Package main does things.\npackage main\n"),
+        Some("Package main does things.".to_string())
+    );
+}
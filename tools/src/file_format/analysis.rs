@@ -22,6 +22,7 @@ use strum::EnumString;
 
 #[cfg(not(target_arch = "wasm32"))]
 use flate2::read::GzDecoder;
+use serde::de::Error as _;
 use serde::{Deserialize, Deserializer, Serialize, Serializer};
 #[cfg(not(target_arch = "wasm32"))]
 use serde_json::from_str;
@@ -35,6 +36,8 @@ type Ustr = String;
 
 use super::ontology_pointer_kind::OntologyPointerKind;
 
+#[cfg(not(target_arch = "wasm32"))]
+use super::analysis_binary::{BinaryAnalysisReader, sniff_binary_analysis};
 #[cfg(not(target_arch = "wasm32"))]
 use super::analysis_manglings::make_file_sym_from_path;
 
@@ -465,6 +468,54 @@ where
     pub sym: StrT,
 }
 
+/// The direction of an IPDL message from the perspective of the sending side.
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum IpdlDirection {
+    ToChild,
+    ToParent,
+    Both,
+}
+
+impl IpdlDirection {
+    /// The serialized (kebab-case) name of the direction.
+    pub fn name(&self) -> &'static str {
+        match self {
+            IpdlDirection::ToChild => "to-child",
+            IpdlDirection::ToParent => "to-parent",
+            IpdlDirection::Both => "both",
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StructuredIpdlMessageInfo<StrT = Ustr>
+where
+    StrT: Clone + Debug + Default + Deref<Target = str> + FromStr + Hash + Ord + PartialEq,
+{
+    /// The `IPC_` symbol of the message whose `bindingSlots` hold its `Send`
+    /// and `Recv` methods.
+    pub sym: StrT,
+    pub direction: IpdlDirection,
+}
+
+/// Emitted by `ipdl-analyze` on the structured record of an IPDL protocol so
+/// that the protocol's position in the manager tree and its messages can be
+/// found without re-parsing the IPDL.  Managers and managees are protocol
+/// symbols.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StructuredIpdlProtocolInfo<StrT = Ustr>
+where
+    StrT: Clone + Debug + Default + Deref<Target = str> + FromStr + Hash + Ord + PartialEq,
+{
+    #[serde(default)]
+    pub managers: Vec<StrT>,
+    #[serde(default)]
+    pub manages: Vec<StrT>,
+    #[serde(default)]
+    pub messages: Vec<StructuredIpdlMessageInfo<StrT>>,
+}
+
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(rename_all = "snake_case")]
 pub enum OntologySlotKind {
@@ -483,6 +534,16 @@ pub enum OntologySlotKind {
     /// the run methods which will reference this constructor and its siblings
     /// via `RunnableConstructor`.
     RunnableMethod,
+    /// Synthetic topic symbols created from `[topics]` ontology rules (ex:
+    /// observer service topics) have this slot pointing at the handler methods
+    /// that were found to handle the topic, for traversing callees.
+    ///
+    /// Handlers will have the reciprocal `HandledTopic` slot.
+    TopicHandler,
+    /// Handler methods for ontology topics have this slot pointing at the
+    /// topic symbols they handle so that traversing uses can move from the
+    /// handler to the topic and from there to the emitting call sites.
+    HandledTopic,
 }
 
 /// Evolving mechanism that allows trees to define high-level semantics that
@@ -540,6 +601,11 @@ where
     pub slot_kind: OntologySlotKind,
     /// The symbols
     pub syms: Vec<StrT>,
+    /// The name of the `ontology-mapping.toml` rule that created this slot (the
+    /// pretty key, `[[rules]]` name, or `[topics]` namespace) so that traversal
+    /// can explain where the resulting graph edges came from.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub rule: Option<StrT>,
 }
 
 /// The structured record type extracts out the necessary information to uniquely identify the
@@ -635,6 +701,9 @@ where
     pub can_gc: Option<bool>,
     #[serde(rename = "gcPath", skip_serializing_if = "Option::is_none")]
     pub gc_path: Option<StrT>,
+
+    #[serde(rename = "ipdlProtocol", skip_serializing_if = "Option::is_none")]
+    pub ipdl_protocol: Option<StructuredIpdlProtocolInfo<StrT>>,
 }
 
 impl<StrT> AnalysisStructured<StrT>
@@ -688,6 +757,19 @@ where
         }
         results
     }
+
+    /// Extract just the layout-related parts of the record, for comparing the
+    /// layouts of different platforms' variants.
+    pub fn layout_only(&self) -> StructuredLayoutOnlyInfo<StrT> {
+        StructuredLayoutOnlyInfo {
+            pretty: self.pretty.clone(),
+            size_bytes: self.size_bytes,
+            alignment_bytes: self.alignment_bytes,
+            own_vf_ptr_bytes: self.own_vf_ptr_bytes,
+            supers: self.supers.clone(),
+            fields: self.fields.clone(),
+        }
+    }
 }
 
 mod bool_as_int {
@@ -970,21 +1052,19 @@ where
 }
 
 pub fn parse_location(loc: &str) -> Location {
-    let v: Vec<&str> = loc.split(":").collect();
-    let lineno = v[0].parse::<u32>().unwrap();
-    let (col_start, col_end) = if v[1].contains("-") {
-        let v: Vec<&str> = v[1].split("-").collect();
-        (v[0], v[1])
-    } else {
-        (v[1], v[1])
-    };
-    let col_start = col_start.parse::<u32>().unwrap();
-    let col_end = col_end.parse::<u32>().unwrap();
-    Location {
-        lineno,
-        col_start,
-        col_end,
-    }
+    try_parse_location(loc).unwrap_or_else(|| panic!("Malformed location [{}]", loc))
+}
+
+/// Parse a "LINE:COL" or "LINE:COL-COL" location, returning None instead of
+/// panicking if it's malformed.
+pub fn try_parse_location(loc: &str) -> Option<Location> {
+    let (lineno, cols) = loc.split_once(':')?;
+    let (col_start, col_end) = cols.split_once('-').unwrap_or((cols, cols));
+    Some(Location {
+        lineno: lineno.parse().ok()?,
+        col_start: col_start.parse().ok()?,
+        col_end: col_end.parse().ok()?,
+    })
 }
 
 impl Serialize for Location {
@@ -1007,21 +1087,24 @@ impl<'de> Deserialize<'de> for Location {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Ok(parse_location(&s))
+        try_parse_location(&s)
+            .ok_or_else(|| D::Error::custom(format!("malformed location [{}]", s)))
     }
 }
 
-fn parse_line_range(range: &str) -> LineRange {
+/// Parse a "START-END" line range.  Ranges without exactly 2 pieces are treated
+/// as empty, but non-numeric pieces are an error.
+fn parse_line_range(range: &str) -> Option<LineRange> {
     let v: Vec<&str> = range.split("-").collect();
     if v.len() != 2 {
-        return LineRange::default();
+        return Some(LineRange::default());
     }
-    let start_lineno = v[0].parse::<u32>().unwrap();
-    let end_lineno = v[1].parse::<u32>().unwrap();
-    LineRange {
+    let start_lineno = v[0].parse::<u32>().ok()?;
+    let end_lineno = v[1].parse::<u32>().ok()?;
+    Some(LineRange {
         start_lineno,
         end_lineno,
-    }
+    })
 }
 
 impl Serialize for LineRange {
@@ -1039,25 +1122,28 @@ impl<'de> Deserialize<'de> for LineRange {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Ok(parse_line_range(&s))
+        parse_line_range(&s)
+            .ok_or_else(|| D::Error::custom(format!("malformed line range [{}]", s)))
     }
 }
 
-fn parse_source_range(range: &str) -> SourceRange {
+/// Parse a "LINE:COL-LINE:COL" source range.  Ranges without exactly 4 pieces
+/// are treated as empty, but non-numeric pieces are an error.
+fn parse_source_range(range: &str) -> Option<SourceRange> {
     let v: Vec<&str> = range.split(&['-', ':'][..]).collect();
     if v.len() != 4 {
-        return SourceRange::default();
+        return Some(SourceRange::default());
     }
-    let start_lineno = v[0].parse::<u32>().unwrap();
-    let start_col = v[1].parse::<u32>().unwrap();
-    let end_lineno = v[2].parse::<u32>().unwrap();
-    let end_col = v[3].parse::<u32>().unwrap();
-    SourceRange {
+    let start_lineno = v[0].parse::<u32>().ok()?;
+    let start_col = v[1].parse::<u32>().ok()?;
+    let end_lineno = v[2].parse::<u32>().ok()?;
+    let end_col = v[3].parse::<u32>().ok()?;
+    Some(SourceRange {
         start_lineno,
         start_col,
         end_lineno,
         end_col,
-    }
+    })
 }
 
 impl Serialize for SourceRange {
@@ -1078,25 +1164,176 @@ impl<'de> Deserialize<'de> for SourceRange {
         D: Deserializer<'de>,
     {
         let s = String::deserialize(deserializer)?;
-        Ok(parse_source_range(&s))
+        parse_source_range(&s)
+            .ok_or_else(|| D::Error::custom(format!("malformed source range [{}]", s)))
+    }
+}
+
+/// A single analysis record as handed to the `read_analyses` filter.  Records
+/// from JSON analysis files are only deserialized into the typed structs when
+/// the filter asks for them, so a filter that only wants targets doesn't pay
+/// for deserializing sources.  Records from binary analysis files (see
+/// `analysis_binary.rs`) are already typed, and boxed because structured
+/// records are much bigger than a `Value`.
+pub enum AnalysisRecord {
+    Json(Value),
+    Typed(Box<AnalysisUnion<Ustr>>),
+}
+
+impl AnalysisRecord {
+    pub fn into_target(self) -> Option<AnalysisTarget<Ustr>> {
+        match self {
+            // XXX this shouldn't be necessary thanks to our tag, so this should be removable
+            AnalysisRecord::Json(obj) => {
+                obj.get("target")?;
+                from_value(obj).ok()
+            }
+            AnalysisRecord::Typed(union) => match *union {
+                AnalysisUnion::Target(tgt) => Some(tgt),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn into_source(self) -> Option<AnalysisSource<Ustr>> {
+        match self {
+            AnalysisRecord::Json(obj) => {
+                obj.get("source")?;
+                from_value(obj).ok()
+            }
+            AnalysisRecord::Typed(union) => match *union {
+                AnalysisUnion::Source(src) => Some(src),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn into_structured(self) -> Option<AnalysisStructured<Ustr>> {
+        match self {
+            AnalysisRecord::Json(obj) => {
+                obj.get("structured")?;
+                from_value(obj).ok()
+            }
+            AnalysisRecord::Typed(union) => match *union {
+                AnalysisUnion::Structured(structured) => Some(structured),
+                _ => None,
+            },
+        }
+    }
+
+    pub fn into_union(self) -> Option<AnalysisUnion<Ustr>> {
+        match self {
+            AnalysisRecord::Json(obj) => from_value(obj).ok(),
+            AnalysisRecord::Typed(union) => Some(*union),
+        }
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
 pub fn read_analysis<T>(
     filename: &str,
-    filter: &mut dyn FnMut(Value, &Location, usize) -> Option<T>,
+    filter: &mut dyn FnMut(AnalysisRecord, &Location, usize) -> Option<T>,
 ) -> Vec<WithLocation<Vec<T>>> {
     read_analyses(vec![filename.to_string()].as_slice(), filter)
 }
 
+/// Invoke `callback` for every record in the analysis file `filename`, which
+/// may be either NDJSON or the binary encoding from `analysis_binary.rs`; the
+/// format is detected from the file contents.  Files ending in .gz are
+/// decompressed as they are read.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_analysis_records(
+    filename: &str,
+    callback: &mut dyn FnMut(AnalysisRecord, Location),
+) -> std::io::Result<()> {
+    let file = File::open(filename)?;
+    // An analysis file that ends in .gz is compressed and should be
+    // dynamically decompressed.
+    let reader: Box<dyn Read> = if filename.ends_with(".gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    read_analysis_records_from(reader, filename, callback)
+}
+
+/// Invoke `callback` for every record in the already-decompressed analysis
+/// contents of `reader`, detecting the format like `read_analysis_records`.
+/// `filename` is only used for warnings.
+///
+/// Malformed JSON lines and records without a valid "loc" are skipped with a
+/// warning.  A malformed binary record ends the file with a warning because
+/// there's no way to resynchronize.
+#[cfg(not(target_arch = "wasm32"))]
+pub fn read_analysis_records_from(
+    reader: Box<dyn Read>,
+    filename: &str,
+    callback: &mut dyn FnMut(AnalysisRecord, Location),
+) -> std::io::Result<()> {
+    let (is_binary, reader) = sniff_binary_analysis(reader)?;
+
+    if is_binary {
+        let mut reader = BinaryAnalysisReader::new(BufReader::new(reader));
+        loop {
+            match reader.read_record() {
+                Ok(Some((loc, record))) => callback(AnalysisRecord::Typed(Box::new(record)), loc),
+                Ok(None) => break,
+                Err(e) => {
+                    warn!(
+                        "Error [{}] trying to read binary analysis from file [{}] record [{}]",
+                        e,
+                        filename,
+                        reader.records_read() + 1
+                    );
+                    break;
+                }
+            }
+        }
+        return Ok(());
+    }
+
+    let reader = BufReader::new(reader);
+    let mut lineno = 0;
+    for line in reader.lines() {
+        let line = line?;
+        lineno += 1;
+        let data: serde_json::Result<Value> = from_str(&line);
+        let mut data = match data {
+            Ok(data) => data,
+            Err(e) => {
+                warn!(
+                    "Error [{}] trying to read analysis from file [{}] line [{}]: [{}]",
+                    e, filename, lineno, &line
+                );
+                continue;
+            }
+        };
+        // Destructively pull the "loc" out before passing it into the filter.  This is for
+        // read_structured which stores everything it doesn't directly process in `payload`.
+        let loc = match data.as_object_mut().and_then(|obj| obj.remove("loc")) {
+            Some(Value::String(loc)) => try_parse_location(&loc),
+            _ => None,
+        };
+        let Some(loc) = loc else {
+            warn!(
+                "Missing or malformed loc trying to read analysis from file [{}] line [{}]: [{}]",
+                filename, lineno, &line
+            );
+            continue;
+        };
+        callback(AnalysisRecord::Json(data), loc);
+    }
+    Ok(())
+}
+
 /// Load analysis data for one or more files, sorting and grouping by location, with data payloads
 /// transformed via the provided `filter`, resulting in either AnalysisSource records being
 /// returned (if `read_source` is provided) or AnalysisTarget (if `read_target`) and other record
 /// types being ignored.
 ///
 /// Analysis files ending in .gz will be automatically decompressed as they are
-/// read.
+/// read, and binary analysis files are detected and decoded directly into the
+/// typed records.
 ///
 /// Note that the filter function is invoked as records are read in, which means
 /// that the sort order seen by the filter function is the order the file
@@ -1104,7 +1341,7 @@ pub fn read_analysis<T>(
 #[cfg(not(target_arch = "wasm32"))]
 pub fn read_analyses<T>(
     filenames: &[String],
-    filter: &mut dyn FnMut(Value, &Location, usize) -> Option<T>,
+    filter: &mut dyn FnMut(AnalysisRecord, &Location, usize) -> Option<T>,
 ) -> Vec<WithLocation<Vec<T>>> {
     let mut result = Vec::new();
     for (i_file, filename) in filenames.iter().enumerate() {
@@ -1114,9 +1351,14 @@ pub fn read_analyses<T>(
             filename.as_str()
         };
 
-        let file = match File::open(used_filename) {
-            Ok(f) => f,
-            Err(_) => {
+        let read_result = read_analysis_records(used_filename, &mut |record, loc| {
+            if let Some(v) = filter(record, &loc, i_file) {
+                result.push(WithLocation { data: v, loc })
+            }
+        });
+        match read_result {
+            Ok(()) => {}
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
                 // TODO: This should be a warning again or have more explicit
                 // propagation of this case to callers.  This was reduced from
                 // a warning because we have a bunch of cases from
@@ -1128,54 +1370,30 @@ pub fn read_analyses<T>(
                 // these things should be addressed if we want to turn this back
                 // into a warning.
                 info!("Error trying to open analysis file [{}]", used_filename);
-                continue;
-            }
-        };
-        // An analysis file that ends in .gz is compressed and should be
-        // dynamically decompressed.
-        let reader: Box<dyn Read> = if used_filename.ends_with(".gz") {
-            Box::new(GzDecoder::new(file))
-        } else {
-            Box::new(file)
-        };
-        let reader = BufReader::new(reader);
-        let mut lineno = 0;
-        for line in reader.lines() {
-            let line = line.unwrap();
-            lineno += 1;
-            let data: serde_json::Result<Value> = from_str(&line);
-            let mut data = match data {
-                Ok(data) => data,
-                Err(e) => {
-                    warn!(
-                        "Error [{}] trying to read analysis from file [{}] line [{}]: [{}]",
-                        e, used_filename, lineno, &line
-                    );
-                    continue;
-                }
-            };
-            let obj = data.as_object_mut().unwrap();
-            // Destructively pull the "loc" out before passing it into the filter.  This is for
-            // read_structured which stores everything it doesn't directly process in `payload`.
-            let loc = parse_location(obj.remove("loc").unwrap().as_str().unwrap());
-            if let Some(v) = filter(data, &loc, i_file) {
-                result.push(WithLocation { data: v, loc })
             }
+            Err(e) => panic!("Error reading analysis file [{}]: {}", used_filename, e),
         }
     }
 
-    result.sort_by(|x1, x2| x1.loc.cmp(&x2.loc));
+    group_by_location(result)
+}
 
-    let mut result2 = Vec::new();
+/// Sort records by location and group the records sharing a location, as
+/// returned by `read_analyses`.  The sort is stable, so records at the same
+/// location stay in file order.
+pub fn group_by_location<T>(mut records: Vec<WithLocation<T>>) -> Vec<WithLocation<Vec<T>>> {
+    records.sort_by_key(|x1| x1.loc);
+
+    let mut result = Vec::new();
     let mut last_loc = None;
     let mut last_vec = Vec::new();
-    for r in result {
+    for r in records {
         match last_loc {
             Some(ll) => {
                 if ll == r.loc {
                     last_loc = Some(ll);
                 } else {
-                    result2.push(WithLocation {
+                    result.push(WithLocation {
                         loc: ll,
                         data: last_vec,
                     });
@@ -1191,38 +1409,37 @@ pub fn read_analyses<T>(
     }
 
     if let Some(ll) = last_loc {
-        result2.push(WithLocation {
+        result.push(WithLocation {
             loc: ll,
             data: last_vec,
         })
     }
 
-    result2
+    result
 }
 
-pub fn read_target(obj: Value, _loc: &Location, _i_size: usize) -> Option<AnalysisTarget<Ustr>> {
-    // XXX this shouldn't be necessary thanks to our tag, so this should be removable
-    obj.get("target")?;
-
-    from_value(obj).ok()
+pub fn read_target(
+    record: AnalysisRecord,
+    _loc: &Location,
+    _i_size: usize,
+) -> Option<AnalysisTarget<Ustr>> {
+    record.into_target()
 }
 
 pub fn read_structured(
-    obj: Value,
+    record: AnalysisRecord,
     _loc: &Location,
     _i_size: usize,
 ) -> Option<AnalysisStructured<Ustr>> {
-    // XXX this shouldn't be necessary thanks to our tag, so this should be removable
-    obj.get("structured")?;
-
-    from_value(obj).ok()
+    record.into_structured()
 }
 
-pub fn read_source(obj: Value, _loc: &Location, _i_size: usize) -> Option<AnalysisSource<Ustr>> {
-    // XXX this shouldn't be necessary thanks to our tag, so this should be removable
-    obj.get("source")?;
-
-    from_value(obj).ok()
+pub fn read_source(
+    record: AnalysisRecord,
+    _loc: &Location,
+    _i_size: usize,
+) -> Option<AnalysisSource<Ustr>> {
+    record.into_source()
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -0,0 +1,508 @@
+//! Compact binary encoding for analysis files.
+//!
+//! NDJSON analysis files are parsed into `serde_json::Value` and only then
+//! converted into the typed records, with the "loc" string re-parsed along the
+//! way.  For large trees that's a dominant cost of `output-file`,
+//! `merge-analyses` and `crossref`, so `read_analyses` also accepts this
+//! encoding, which decodes straight into `AnalysisUnion`.  The indexers keep
+//! producing JSON; `convert-analysis` converts files in either direction.
+//!
+//! The file starts with `BINARY_ANALYSIS_MAGIC` (which can't begin a JSON
+//! line) followed by a sequence of records.  Integers are unsigned LEB128
+//! varints.  Each record is a record type byte (1 = target, 2 = source,
+//! 3 = structured), the location as 3 varints, and then the record's fields
+//! in declaration order.
+//!
+//! Strings are interned in a table that's built up as the file is read: a
+//! string is written as its varint index in the table, and an index equal to
+//! the current table size means the string is new and its varint byte length
+//! and UTF-8 bytes follow inline, appending it to the table.  This means the
+//! file can only be read front to back.
+//!
+//! Structured records are written as an embedded length-prefixed JSON object
+//! because their free-form `extra` map doesn't have a fixed shape, and they're
+//! a small fraction of the records in a file.  The same goes for the rare
+//! expansion info on source records.
+
+use std::collections::HashMap;
+use std::io::{self, Cursor, ErrorKind, Read, Write};
+
+use ustr::Ustr;
+
+use super::analysis::{
+    AnalysisKind, AnalysisSource, AnalysisStructured, AnalysisTarget, AnalysisUnion,
+    ConfidenceLevel, ExpansionInfo, LineRange, Location, SourceRange, SourceTag, TargetTag,
+};
+
+/// The version is the last byte; bump it on any incompatible change.
+pub const BINARY_ANALYSIS_MAGIC: &[u8; 8] = b"\x00SFANA\x00\x01";
+
+const RECORD_TARGET: u8 = 1;
+const RECORD_SOURCE: u8 = 2;
+const RECORD_STRUCTURED: u8 = 3;
+
+fn invalid_data(msg: String) -> io::Error {
+    io::Error::new(ErrorKind::InvalidData, msg)
+}
+
+/// Check whether `reader` holds a binary analysis file by consuming its first
+/// bytes, returning a reader that still yields the full contents.
+pub fn sniff_binary_analysis(mut reader: Box<dyn Read>) -> io::Result<(bool, Box<dyn Read>)> {
+    let mut prefix = Vec::with_capacity(BINARY_ANALYSIS_MAGIC.len());
+    (&mut reader)
+        .take(BINARY_ANALYSIS_MAGIC.len() as u64)
+        .read_to_end(&mut prefix)?;
+    if prefix == BINARY_ANALYSIS_MAGIC {
+        return Ok((true, reader));
+    }
+    Ok((false, Box::new(Cursor::new(prefix).chain(reader))))
+}
+
+fn analysis_kind_to_u8(kind: AnalysisKind) -> u8 {
+    match kind {
+        AnalysisKind::Use => 0,
+        AnalysisKind::Def => 1,
+        AnalysisKind::Assign => 2,
+        AnalysisKind::Decl => 3,
+        AnalysisKind::Forward => 4,
+        AnalysisKind::Idl => 5,
+        AnalysisKind::Idlp => 6,
+        AnalysisKind::Glean => 7,
+        AnalysisKind::Alias => 8,
+    }
+}
+
+fn analysis_kind_from_u8(b: u8) -> io::Result<AnalysisKind> {
+    Ok(match b {
+        0 => AnalysisKind::Use,
+        1 => AnalysisKind::Def,
+        2 => AnalysisKind::Assign,
+        3 => AnalysisKind::Decl,
+        4 => AnalysisKind::Forward,
+        5 => AnalysisKind::Idl,
+        6 => AnalysisKind::Idlp,
+        7 => AnalysisKind::Glean,
+        8 => AnalysisKind::Alias,
+        _ => return Err(invalid_data(format!("bad analysis kind {}", b))),
+    })
+}
+
+fn confidence_to_u8(level: ConfidenceLevel) -> u8 {
+    match level {
+        ConfidenceLevel::CppTemplateHeuristic => 0,
+        ConfidenceLevel::Concrete => 1,
+    }
+}
+
+fn confidence_from_u8(b: u8) -> io::Result<ConfidenceLevel> {
+    Ok(match b {
+        0 => ConfidenceLevel::CppTemplateHeuristic,
+        1 => ConfidenceLevel::Concrete,
+        _ => return Err(invalid_data(format!("bad confidence level {}", b))),
+    })
+}
+
+pub struct BinaryAnalysisWriter<W: Write> {
+    writer: W,
+    strings: HashMap<Ustr, u64>,
+}
+
+impl<W: Write> BinaryAnalysisWriter<W> {
+    /// Creates the writer, immediately writing the file header.
+    pub fn new(mut writer: W) -> io::Result<Self> {
+        writer.write_all(BINARY_ANALYSIS_MAGIC)?;
+        Ok(BinaryAnalysisWriter {
+            writer,
+            strings: HashMap::new(),
+        })
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+
+    fn write_u8(&mut self, b: u8) -> io::Result<()> {
+        self.writer.write_all(&[b])
+    }
+
+    fn write_varint(&mut self, mut v: u64) -> io::Result<()> {
+        loop {
+            let byte = (v & 0x7f) as u8;
+            v >>= 7;
+            if v == 0 {
+                return self.write_u8(byte);
+            }
+            self.write_u8(byte | 0x80)?;
+        }
+    }
+
+    fn write_bytes(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.write_varint(bytes.len() as u64)?;
+        self.writer.write_all(bytes)
+    }
+
+    fn write_str(&mut self, s: &Ustr) -> io::Result<()> {
+        if let Some(&index) = self.strings.get(s) {
+            return self.write_varint(index);
+        }
+        let index = self.strings.len() as u64;
+        self.strings.insert(*s, index);
+        self.write_varint(index)?;
+        self.write_bytes(s.as_bytes())
+    }
+
+    fn write_opt_str(&mut self, s: &Option<Ustr>) -> io::Result<()> {
+        match s {
+            Some(s) => {
+                self.write_u8(1)?;
+                self.write_str(s)
+            }
+            None => self.write_u8(0),
+        }
+    }
+
+    fn write_str_vec(&mut self, strs: &[Ustr]) -> io::Result<()> {
+        self.write_varint(strs.len() as u64)?;
+        for s in strs {
+            self.write_str(s)?;
+        }
+        Ok(())
+    }
+
+    fn write_source_ranges(&mut self, ranges: &[SourceRange]) -> io::Result<()> {
+        self.write_varint(ranges.len() as u64)?;
+        for range in ranges {
+            self.write_source_range(range)?;
+        }
+        Ok(())
+    }
+
+    fn write_source_range(&mut self, range: &SourceRange) -> io::Result<()> {
+        self.write_varint(range.start_lineno as u64)?;
+        self.write_varint(range.start_col as u64)?;
+        self.write_varint(range.end_lineno as u64)?;
+        self.write_varint(range.end_col as u64)
+    }
+
+    pub fn write_record(&mut self, loc: &Location, record: &AnalysisUnion) -> io::Result<()> {
+        self.write_u8(match record {
+            AnalysisUnion::Target(_) => RECORD_TARGET,
+            AnalysisUnion::Source(_) => RECORD_SOURCE,
+            AnalysisUnion::Structured(_) => RECORD_STRUCTURED,
+        })?;
+        self.write_varint(loc.lineno as u64)?;
+        self.write_varint(loc.col_start as u64)?;
+        self.write_varint(loc.col_end as u64)?;
+
+        match record {
+            AnalysisUnion::Target(tgt) => self.write_target(tgt),
+            AnalysisUnion::Source(src) => self.write_source(src),
+            AnalysisUnion::Structured(structured) => self.write_structured(structured),
+        }
+    }
+
+    fn write_target(&mut self, tgt: &AnalysisTarget) -> io::Result<()> {
+        // Destructure exhaustively so that adding a field to the record fails to
+        // compile until it's handled here.
+        let AnalysisTarget {
+            target: _,
+            kind,
+            pretty,
+            sym,
+            context,
+            contextsym,
+            peek_range,
+            arg_ranges,
+        } = tgt;
+        self.write_u8(analysis_kind_to_u8(*kind))?;
+        self.write_str(pretty)?;
+        self.write_str(sym)?;
+        self.write_str(context)?;
+        self.write_str(contextsym)?;
+        self.write_varint(peek_range.start_lineno as u64)?;
+        self.write_varint(peek_range.end_lineno as u64)?;
+        self.write_source_ranges(arg_ranges)
+    }
+
+    fn write_source(&mut self, src: &AnalysisSource) -> io::Result<()> {
+        let AnalysisSource {
+            source: _,
+            syntax,
+            pretty,
+            sym,
+            no_crossref,
+            nesting_range,
+            type_pretty,
+            type_sym,
+            arg_ranges,
+            expansion_info,
+            confidence,
+        } = src;
+        self.write_str_vec(syntax)?;
+        self.write_str(pretty)?;
+        self.write_str_vec(sym)?;
+        self.write_u8(*no_crossref as u8)?;
+        self.write_source_range(nesting_range)?;
+        self.write_opt_str(type_pretty)?;
+        self.write_opt_str(type_sym)?;
+        self.write_source_ranges(arg_ranges)?;
+        match expansion_info {
+            Some(info) => {
+                self.write_u8(1)?;
+                self.write_bytes(&serde_json::to_vec(info)?)?;
+            }
+            None => self.write_u8(0)?,
+        }
+        match confidence {
+            Some(levels) => {
+                self.write_u8(1)?;
+                self.write_varint(levels.len() as u64)?;
+                for level in levels {
+                    self.write_u8(confidence_to_u8(*level))?;
+                }
+            }
+            None => self.write_u8(0)?,
+        }
+        Ok(())
+    }
+
+    fn write_structured(&mut self, structured: &AnalysisStructured) -> io::Result<()> {
+        self.write_bytes(&serde_json::to_vec(structured)?)
+    }
+}
+
+pub struct BinaryAnalysisReader<R: Read> {
+    reader: R,
+    strings: Vec<Ustr>,
+    records_read: usize,
+}
+
+impl<R: Read> BinaryAnalysisReader<R> {
+    /// Creates a reader for a file whose `BINARY_ANALYSIS_MAGIC` header has
+    /// already been consumed, as by `sniff_binary_analysis`.
+    pub fn new(reader: R) -> Self {
+        BinaryAnalysisReader {
+            reader,
+            strings: vec![],
+            records_read: 0,
+        }
+    }
+
+    /// The number of records successfully read so far, for error reporting.
+    pub fn records_read(&self) -> usize {
+        self.records_read
+    }
+
+    fn read_u8(&mut self) -> io::Result<u8> {
+        let mut buf = [0; 1];
+        self.reader.read_exact(&mut buf)?;
+        Ok(buf[0])
+    }
+
+    fn read_varint(&mut self) -> io::Result<u64> {
+        let mut v = 0;
+        let mut shift = 0;
+        loop {
+            let byte = self.read_u8()?;
+            if shift >= 64 {
+                return Err(invalid_data("varint too long".to_string()));
+            }
+            v |= ((byte & 0x7f) as u64) << shift;
+            if byte & 0x80 == 0 {
+                return Ok(v);
+            }
+            shift += 7;
+        }
+    }
+
+    fn read_u32(&mut self) -> io::Result<u32> {
+        let v = self.read_varint()?;
+        u32::try_from(v).map_err(|_| invalid_data(format!("value {} too large", v)))
+    }
+
+    fn read_bool(&mut self) -> io::Result<bool> {
+        match self.read_u8()? {
+            0 => Ok(false),
+            1 => Ok(true),
+            b => Err(invalid_data(format!("bad boolean {}", b))),
+        }
+    }
+
+    fn read_bytes(&mut self) -> io::Result<Vec<u8>> {
+        let len = self.read_varint()?;
+        let mut bytes = Vec::new();
+        (&mut self.reader).take(len).read_to_end(&mut bytes)?;
+        if bytes.len() as u64 != len {
+            return Err(ErrorKind::UnexpectedEof.into());
+        }
+        Ok(bytes)
+    }
+
+    fn read_str(&mut self) -> io::Result<Ustr> {
+        let index = self.read_varint()? as usize;
+        if let Some(s) = self.strings.get(index) {
+            return Ok(*s);
+        }
+        if index != self.strings.len() {
+            return Err(invalid_data(format!(
+                "string index {} past end of table of {}",
+                index,
+                self.strings.len()
+            )));
+        }
+        let bytes = self.read_bytes()?;
+        let s = std::str::from_utf8(&bytes).map_err(|e| invalid_data(e.to_string()))?;
+        let s = Ustr::from(s);
+        self.strings.push(s);
+        Ok(s)
+    }
+
+    fn read_opt_str(&mut self) -> io::Result<Option<Ustr>> {
+        Ok(if self.read_bool()? {
+            Some(self.read_str()?)
+        } else {
+            None
+        })
+    }
+
+    fn read_str_vec(&mut self) -> io::Result<Vec<Ustr>> {
+        let len = self.read_varint()?;
+        (0..len).map(|_| self.read_str()).collect()
+    }
+
+    fn read_source_range(&mut self) -> io::Result<SourceRange> {
+        Ok(SourceRange {
+            start_lineno: self.read_u32()?,
+            start_col: self.read_u32()?,
+            end_lineno: self.read_u32()?,
+            end_col: self.read_u32()?,
+        })
+    }
+
+    fn read_source_ranges(&mut self) -> io::Result<Vec<SourceRange>> {
+        let len = self.read_varint()?;
+        (0..len).map(|_| self.read_source_range()).collect()
+    }
+
+    /// Read the next record, returning None at the end of the file.
+    pub fn read_record(&mut self) -> io::Result<Option<(Location, AnalysisUnion)>> {
+        let mut tag = [0; 1];
+        if self.reader.read(&mut tag)? == 0 {
+            return Ok(None);
+        }
+        let loc = Location {
+            lineno: self.read_u32()?,
+            col_start: self.read_u32()?,
+            col_end: self.read_u32()?,
+        };
+        let record = match tag[0] {
+            RECORD_TARGET => AnalysisUnion::Target(self.read_target()?),
+            RECORD_SOURCE => AnalysisUnion::Source(self.read_source()?),
+            RECORD_STRUCTURED => AnalysisUnion::Structured(self.read_structured()?),
+            t => return Err(invalid_data(format!("bad record type {}", t))),
+        };
+        self.records_read += 1;
+        Ok(Some((loc, record)))
+    }
+
+    fn read_target(&mut self) -> io::Result<AnalysisTarget> {
+        Ok(AnalysisTarget {
+            target: TargetTag::Target,
+            kind: analysis_kind_from_u8(self.read_u8()?)?,
+            pretty: self.read_str()?,
+            sym: self.read_str()?,
+            context: self.read_str()?,
+            contextsym: self.read_str()?,
+            peek_range: LineRange {
+                start_lineno: self.read_u32()?,
+                end_lineno: self.read_u32()?,
+            },
+            arg_ranges: self.read_source_ranges()?,
+        })
+    }
+
+    fn read_source(&mut self) -> io::Result<AnalysisSource> {
+        Ok(AnalysisSource {
+            source: SourceTag::Source,
+            syntax: self.read_str_vec()?,
+            pretty: self.read_str()?,
+            sym: self.read_str_vec()?,
+            no_crossref: self.read_bool()?,
+            nesting_range: self.read_source_range()?,
+            type_pretty: self.read_opt_str()?,
+            type_sym: self.read_opt_str()?,
+            arg_ranges: self.read_source_ranges()?,
+            expansion_info: if self.read_bool()? {
+                Some(serde_json::from_slice::<ExpansionInfo>(
+                    &self.read_bytes()?,
+                )?)
+            } else {
+                None
+            },
+            confidence: if self.read_bool()? {
+                let len = self.read_varint()?;
+                Some(
+                    (0..len)
+                        .map(|_| confidence_from_u8(self.read_u8()?))
+                        .collect::<io::Result<_>>()?,
+                )
+            } else {
+                None
+            },
+        })
+    }
+
+    fn read_structured(&mut self) -> io::Result<AnalysisStructured> {
+        Ok(serde_json::from_slice(&self.read_bytes()?)?)
+    }
+}
+
+#[test]
+fn test_binary_round_trip() {
+    let lines = [
+        r#"{"loc":"00001:4","target":1,"kind":"def","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00002:4-7","source":1,"syntax":"def,function","pretty":"function foo","sym":"_Z3foov,_Z3foo","nestingRange":"2:10-4:0","type":"int ()","confidence":["cppTemplateHeuristic","concrete"]}"#,
+        r#"{"loc":"00002:4-7","target":1,"kind":"def","pretty":"foo","sym":"_Z3foov","context":"ns","contextsym":"NS_ns","peekRange":"2-4","argRanges":["2:8-2:9"]}"#,
+        r#"{"loc":"00003:9","source":1,"syntax":"use,macro","pretty":"macro FOO","sym":"M_FOO","expandsTo":{"":{"":"1"}}}"#,
+        r#"{"loc":"00004:0-3","structured":1,"pretty":"ns::S","sym":"T_ns::S","kind":"struct","sizeBytes":8,"fields":[],"platforms":["linux64"]}"#,
+    ];
+
+    let json_records: Vec<(Location, AnalysisUnion)> = lines
+        .iter()
+        .map(|line| {
+            let mut value: serde_json::Value = serde_json::from_str(line).unwrap();
+            let loc = value.as_object_mut().unwrap().remove("loc").unwrap();
+            (
+                super::analysis::parse_location(loc.as_str().unwrap()),
+                serde_json::from_value(value).unwrap(),
+            )
+        })
+        .collect();
+
+    let mut writer = BinaryAnalysisWriter::new(vec![]).unwrap();
+    for (loc, record) in &json_records {
+        writer.write_record(loc, record).unwrap();
+    }
+    let bytes = writer.into_inner();
+
+    let (is_binary, reader) = sniff_binary_analysis(Box::new(Cursor::new(bytes))).unwrap();
+    assert!(is_binary);
+    let mut reader = BinaryAnalysisReader::new(reader);
+    for (loc, record) in &json_records {
+        let (decoded_loc, decoded) = reader.read_record().unwrap().unwrap();
+        assert_eq!(&decoded_loc, loc);
+        assert_eq!(
+            serde_json::to_string(&decoded).unwrap(),
+            serde_json::to_string(record).unwrap()
+        );
+    }
+    assert!(reader.read_record().unwrap().is_none());
+
+    let json = lines.join("\n").into_bytes();
+    let (is_binary, mut reader) =
+        sniff_binary_analysis(Box::new(Cursor::new(json.clone()))).unwrap();
+    assert!(!is_binary);
+    let mut roundtripped = vec![];
+    reader.read_to_end(&mut roundtripped).unwrap();
+    assert_eq!(roundtripped, json);
+}
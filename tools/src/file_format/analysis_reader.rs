@@ -0,0 +1,398 @@
+//! Typed analysis reading with predicate push-down.
+//!
+//! `read_analyses` hands every record to a filter function and groups the
+//! results by location, which suits the indexing binaries.  Consumers that
+//! want the records of a single file in file order (`LocalIndex`,
+//! `RemoteServer`, `filter-analysis` and crossref's reader threads) use this
+//! instead so they all get the same typed `AnalysisUnion` records and the same
+//! handling of bad records: malformed lines are skipped with a warning (see
+//! `read_analysis_records_from`) and records that don't match the typed
+//! definitions are dropped, like the `read_*` filters do.  Use
+//! `validate-analysis` to find out why records were dropped.
+//!
+//! The location and record type predicates are checked before a JSON record
+//! is deserialized into its typed representation, so they're cheap.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, Read};
+use std::ops::RangeInclusive;
+
+use clap::ValueEnum;
+use flate2::read::GzDecoder;
+use serde_json::{Value, from_str, to_value};
+
+use super::analysis::{
+    AnalysisRecord, AnalysisStructured, AnalysisTarget, AnalysisUnion, Location, WithLocation,
+    read_analysis_records, read_analysis_records_from, try_parse_location,
+};
+use super::analysis_binary::sniff_binary_analysis;
+
+#[derive(Clone, Copy, Debug, PartialEq, ValueEnum)]
+pub enum RecordType {
+    Source,
+    Target,
+    Structured,
+}
+
+impl RecordType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RecordType::Source => "source",
+            RecordType::Target => "target",
+            RecordType::Structured => "structured",
+        }
+    }
+
+    /// Determine the type of a record without deserializing it.  JSON records
+    /// indicate their type via the presence of a "source", "target", or
+    /// "structured" key.
+    pub fn of_record(record: &AnalysisRecord) -> Option<RecordType> {
+        match record {
+            AnalysisRecord::Json(obj) => [
+                RecordType::Source,
+                RecordType::Target,
+                RecordType::Structured,
+            ]
+            .into_iter()
+            .find(|rt| obj.get(rt.name()).is_some()),
+            AnalysisRecord::Typed(union) => match **union {
+                AnalysisUnion::Source(_) => Some(RecordType::Source),
+                AnalysisUnion::Target(_) => Some(RecordType::Target),
+                AnalysisUnion::Structured(_) => Some(RecordType::Structured),
+            },
+        }
+    }
+}
+
+/// Conditions a record must meet to be returned by the readers in this module.
+/// Unset fields match everything.
+#[derive(Clone, Debug, Default)]
+pub struct AnalysisPredicate {
+    pub record_types: Option<Vec<RecordType>>,
+    /// For targets this is the "kind", for sources it's one of the "syntax"
+    /// pieces.  Structured records never match a kind.
+    pub kind: Option<String>,
+    /// Exact match against any of the record's symbols.
+    pub symbol: Option<String>,
+    /// Prefix match against any of the record's symbols.
+    pub symbol_prefix: Option<String>,
+    /// Lines the record's location must start on.
+    pub lines: Option<RangeInclusive<u32>>,
+}
+
+impl AnalysisPredicate {
+    pub fn for_record_types(record_types: &[RecordType]) -> Self {
+        AnalysisPredicate {
+            record_types: Some(record_types.to_vec()),
+            ..AnalysisPredicate::default()
+        }
+    }
+
+    fn matches_loc(&self, loc: &Location) -> bool {
+        match &self.lines {
+            Some(lines) => lines.contains(&loc.lineno),
+            None => true,
+        }
+    }
+
+    fn matches_record_type(&self, record_type: RecordType) -> bool {
+        match &self.record_types {
+            Some(record_types) => record_types.contains(&record_type),
+            None => true,
+        }
+    }
+
+    fn matches_syms(&self, syms: &[&str]) -> bool {
+        self.symbol
+            .as_ref()
+            .is_none_or(|symbol| syms.iter().any(|s| s == symbol))
+            && self
+                .symbol_prefix
+                .as_ref()
+                .is_none_or(|prefix| syms.iter().any(|s| s.starts_with(prefix.as_str())))
+    }
+
+    /// Check the predicates that need the typed record.
+    fn matches_typed(&self, record: &AnalysisUnion) -> bool {
+        match record {
+            AnalysisUnion::Target(tgt) => {
+                self.kind
+                    .as_ref()
+                    .is_none_or(|kind| tgt.kind.to_ustr().as_str() == kind)
+                    && self.matches_syms(&[tgt.sym.as_str()])
+            }
+            AnalysisUnion::Source(src) => {
+                self.kind
+                    .as_ref()
+                    .is_none_or(|kind| src.syntax.iter().any(|k| k.as_str() == kind))
+                    && self.matches_syms(&src.sym.iter().map(|s| s.as_str()).collect::<Vec<_>>())
+            }
+            AnalysisUnion::Structured(structured) => {
+                self.kind.is_none() && self.matches_syms(&[structured.sym.as_str()])
+            }
+        }
+    }
+
+    /// Check the record against the predicate, converting it to its typed
+    /// representation if it matches and it's well-formed.
+    pub fn filter(&self, record: AnalysisRecord, loc: &Location) -> Option<AnalysisUnion> {
+        if !self.matches_loc(loc) {
+            return None;
+        }
+        let typed = match RecordType::of_record(&record)? {
+            rt if !self.matches_record_type(rt) => return None,
+            RecordType::Source => record.into_source().map(AnalysisUnion::Source),
+            RecordType::Target => record.into_target().map(AnalysisUnion::Target),
+            RecordType::Structured => record.into_structured().map(AnalysisUnion::Structured),
+        }?;
+        if !self.matches_typed(&typed) {
+            return None;
+        }
+        Some(typed)
+    }
+
+    /// Check a raw JSON record, including its "loc", against the predicate
+    /// without converting it to its typed representation, so records that
+    /// don't match the typed definitions are kept.
+    pub fn matches_json(&self, value: &Value) -> bool {
+        let loc = value["loc"].as_str().and_then(try_parse_location);
+        if self.lines.is_some() && !loc.is_some_and(|loc| self.matches_loc(&loc)) {
+            return false;
+        }
+        let record_type = [
+            RecordType::Source,
+            RecordType::Target,
+            RecordType::Structured,
+        ]
+        .into_iter()
+        .find(|rt| value.get(rt.name()).is_some());
+        if self.record_types.is_some()
+            && !record_type.is_some_and(|rt| self.matches_record_type(rt))
+        {
+            return false;
+        }
+        // kind varies by record type:
+        // - target: "kind" is a single valued attribute
+        // - source: kind is baked into the comma-delimited "syntax"
+        if let Some(kind) = &self.kind {
+            let matches = match record_type {
+                Some(RecordType::Source) => value["syntax"]
+                    .as_str()
+                    .is_some_and(|syntax| syntax.split(',').any(|k| k == kind)),
+                Some(RecordType::Target) => value["kind"].as_str() == Some(kind.as_str()),
+                _ => false,
+            };
+            if !matches {
+                return false;
+            }
+        }
+        if self.symbol.is_none() && self.symbol_prefix.is_none() {
+            return true;
+        }
+        match value["sym"].as_str() {
+            Some(syms) => self.matches_syms(&syms.split(',').collect::<Vec<_>>()),
+            None => false,
+        }
+    }
+}
+
+/// Read the records matching `predicate` from the already-decompressed
+/// analysis contents of `reader` in file order.  `filename` is only used for
+/// warnings.
+pub fn read_matching_analysis_from(
+    reader: Box<dyn Read>,
+    filename: &str,
+    predicate: &AnalysisPredicate,
+) -> io::Result<Vec<WithLocation<AnalysisUnion>>> {
+    let mut records = vec![];
+    read_analysis_records_from(reader, filename, &mut |record, loc| {
+        if let Some(data) = predicate.filter(record, &loc) {
+            records.push(WithLocation { loc, data });
+        }
+    })?;
+    Ok(records)
+}
+
+/// Read the records matching `predicate` from the analysis file `filename` in
+/// file order.  Like `read_analyses`, if `filename` ends in .gz but doesn't
+/// exist then the uncompressed file is read instead.
+pub fn read_matching_analysis(
+    filename: &str,
+    predicate: &AnalysisPredicate,
+) -> io::Result<Vec<WithLocation<AnalysisUnion>>> {
+    let used_filename = match filename.strip_suffix(".gz") {
+        Some(uncompressed) if !std::fs::exists(filename).unwrap_or(false) => uncompressed,
+        _ => filename,
+    };
+    let mut records = vec![];
+    read_analysis_records(used_filename, &mut |record, loc| {
+        if let Some(data) = predicate.filter(record, &loc) {
+            records.push(WithLocation { loc, data });
+        }
+    })?;
+    Ok(records)
+}
+
+/// Read the records matching `predicate` from the already-decompressed
+/// analysis contents of `reader` in file order as the JSON objects they're
+/// stored as, "loc" included.  Unlike `read_matching_analysis_from`, records
+/// that don't match the typed definitions are returned as-is; this is what
+/// `filter-analysis` shows.  Binary analysis records have no JSON form on disk,
+/// so they're serialized from their typed representation.
+pub fn read_matching_raw_analysis_from(
+    reader: Box<dyn Read>,
+    filename: &str,
+    predicate: &AnalysisPredicate,
+) -> io::Result<Vec<Value>> {
+    let (is_binary, reader) = sniff_binary_analysis(reader)?;
+    let mut records = vec![];
+    if is_binary {
+        for record in read_matching_analysis_from(reader, filename, predicate)? {
+            records.push(to_value(record)?);
+        }
+        return Ok(records);
+    }
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        match from_str::<Value>(&line) {
+            Ok(value) if predicate.matches_json(&value) => records.push(value),
+            Ok(_) => {}
+            Err(e) => warn!(
+                "Error [{}] trying to read analysis from file [{}] line [{}]: [{}]",
+                e,
+                filename,
+                i + 1,
+                &line
+            ),
+        }
+    }
+    Ok(records)
+}
+
+/// Read the raw records matching `predicate` from the analysis file
+/// `filename`, like `read_matching_raw_analysis_from`, with the same .gz
+/// fallback as `read_matching_analysis`.
+pub fn read_matching_raw_analysis(
+    filename: &str,
+    predicate: &AnalysisPredicate,
+) -> io::Result<Vec<Value>> {
+    let used_filename = match filename.strip_suffix(".gz") {
+        Some(uncompressed) if !std::fs::exists(filename).unwrap_or(false) => uncompressed,
+        _ => filename,
+    };
+    let file = File::open(used_filename)?;
+    let reader: Box<dyn Read> = if used_filename.ends_with(".gz") {
+        Box::new(GzDecoder::new(file))
+    } else {
+        Box::new(file)
+    };
+    read_matching_raw_analysis_from(reader, used_filename, predicate)
+}
+
+/// Split the records of a file into its targets and structured records,
+/// discarding sources.
+pub fn split_targets_and_structured(
+    records: Vec<WithLocation<AnalysisUnion>>,
+) -> (
+    Vec<WithLocation<AnalysisTarget>>,
+    Vec<WithLocation<AnalysisStructured>>,
+) {
+    let mut targets = vec![];
+    let mut structured = vec![];
+    for WithLocation { loc, data } in records {
+        match data {
+            AnalysisUnion::Target(data) => targets.push(WithLocation { loc, data }),
+            AnalysisUnion::Structured(data) => structured.push(WithLocation { loc, data }),
+            AnalysisUnion::Source(_) => {}
+        }
+    }
+    (targets, structured)
+}
+
+#[test]
+fn test_predicate_filter() {
+    use serde_json::from_str;
+
+    let lines = [
+        r#"{"loc":"00001:4","target":1,"kind":"def","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00002:4-7","source":1,"syntax":"def,function","pretty":"function foo","sym":"_Z3foov,_Z3foo"}"#,
+        r#"{"loc":"00003:9","target":1,"kind":"use","pretty":"foo","sym":"_Z3foov"}"#,
+        r#"{"loc":"00004:0-3","structured":1,"pretty":"S","sym":"T_S","kind":"struct"}"#,
+        r#"{"loc":"00005:0","target":1,"kind":"bogus","pretty":"y","sym":"y"}"#,
+    ];
+    let matching = |predicate: &AnalysisPredicate| -> Vec<u32> {
+        lines
+            .iter()
+            .filter_map(|line| {
+                let mut value: serde_json::Value = from_str(line).unwrap();
+                let loc = value.as_object_mut().unwrap().remove("loc").unwrap();
+                let loc = super::analysis::parse_location(loc.as_str().unwrap());
+                predicate
+                    .filter(AnalysisRecord::Json(value), &loc)
+                    .map(|_| loc.lineno)
+            })
+            .collect()
+    };
+
+    // The bogus kind doesn't match the schema so it's always dropped.
+    assert_eq!(matching(&AnalysisPredicate::default()), vec![1, 2, 3, 4]);
+    assert_eq!(
+        matching(&AnalysisPredicate::for_record_types(&[
+            RecordType::Target,
+            RecordType::Structured
+        ])),
+        vec![1, 3, 4]
+    );
+    assert_eq!(
+        matching(&AnalysisPredicate {
+            kind: Some("def".to_string()),
+            ..AnalysisPredicate::default()
+        }),
+        vec![1, 2]
+    );
+    assert_eq!(
+        matching(&AnalysisPredicate {
+            symbol: Some("_Z3foo".to_string()),
+            ..AnalysisPredicate::default()
+        }),
+        vec![2]
+    );
+    assert_eq!(
+        matching(&AnalysisPredicate {
+            symbol_prefix: Some("_Z3".to_string()),
+            lines: Some(3..=4),
+            ..AnalysisPredicate::default()
+        }),
+        vec![3]
+    );
+
+    // Raw records keep the bogus kind and are matched the same way otherwise.
+    let raw_matching = |predicate: &AnalysisPredicate| -> Vec<usize> {
+        lines
+            .iter()
+            .enumerate()
+            .filter(|(_, line)| predicate.matches_json(&from_str(line).unwrap()))
+            .map(|(i, _)| i + 1)
+            .collect()
+    };
+    assert_eq!(
+        raw_matching(&AnalysisPredicate::default()),
+        vec![1, 2, 3, 4, 5]
+    );
+    assert_eq!(
+        raw_matching(&AnalysisPredicate {
+            kind: Some("def".to_string()),
+            ..AnalysisPredicate::default()
+        }),
+        vec![1, 2]
+    );
+    assert_eq!(
+        raw_matching(&AnalysisPredicate {
+            symbol_prefix: Some("_Z3".to_string()),
+            lines: Some(3..=4),
+            ..AnalysisPredicate::default()
+        }),
+        vec![3]
+    );
+}
@@ -0,0 +1,470 @@
+//! Strict validation of analysis files.
+//!
+//! `read_analyses` is intentionally forgiving: malformed lines get a `warn!`
+//! and are skipped, and records that don't match the typed definitions are
+//! dropped by the `read_*` filters.  That's what we want when building an
+//! index, but it means a new indexer can produce records that silently vanish.
+//! This module instead checks every line against the typed `AnalysisTarget`,
+//! `AnalysisSource` and `AnalysisStructured` definitions and checks locations
+//! against the source file, producing a diagnostics report per file.
+
+use std::fs::File;
+use std::io::{BufRead, BufReader, Read};
+
+use flate2::read::GzDecoder;
+use serde::Serialize;
+use serde_json::{Map, Value, from_str, from_value, to_string, to_value};
+use ustr::Ustr;
+
+use super::analysis::{
+    AnalysisSource, AnalysisStructured, AnalysisTarget, LineRange, Location, SourceRange,
+    WithLocation, try_parse_location,
+};
+use super::analysis_binary::{BinaryAnalysisReader, sniff_binary_analysis};
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum DiagnosticSeverity {
+    /// The record will be dropped or will cause a panic when read.
+    Error,
+    /// The record will be read but some of its data is suspect or ignored.
+    Warning,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnalysisDiagnostic {
+    /// 1-based line number in the analysis file.
+    pub line: usize,
+    pub severity: DiagnosticSeverity,
+    /// Short machine-friendly category like "json" or "location".
+    pub check: &'static str,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct AnalysisFileReport {
+    pub file: String,
+    pub records: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub diagnostics: Vec<AnalysisDiagnostic>,
+}
+
+impl AnalysisFileReport {
+    fn report(
+        &mut self,
+        line: usize,
+        severity: DiagnosticSeverity,
+        check: &'static str,
+        message: String,
+    ) {
+        match severity {
+            DiagnosticSeverity::Error => self.errors += 1,
+            DiagnosticSeverity::Warning => self.warnings += 1,
+        }
+        self.diagnostics.push(AnalysisDiagnostic {
+            line,
+            severity,
+            check,
+            message,
+        });
+    }
+}
+
+/// The byte length of each line of the source file, used to check that
+/// locations actually point into the file.
+struct SourceLines {
+    lengths: Vec<usize>,
+}
+
+impl SourceLines {
+    fn new(contents: &str) -> Self {
+        SourceLines {
+            lengths: contents.lines().map(|l| l.len()).collect(),
+        }
+    }
+
+    /// Check a 1-based line and 0-based column against the source, returning a
+    /// description of the problem if it's out of bounds.
+    fn check(&self, lineno: u32, col: u32) -> Option<String> {
+        if lineno == 0 {
+            return Some("line 0 is invalid; lines are 1-based".to_string());
+        }
+        let Some(len) = self.lengths.get(lineno as usize - 1) else {
+            return Some(format!(
+                "line {} is past the end of the {}-line source file",
+                lineno,
+                self.lengths.len()
+            ));
+        };
+        if col as usize > *len {
+            return Some(format!(
+                "column {} is past the end of line {} which is {} bytes long",
+                col, lineno, len
+            ));
+        }
+        None
+    }
+}
+
+/// Validate the analysis file at `analysis_path`, reporting it as `file`.
+/// If `source` is provided, locations are checked against its contents.
+///
+/// Analysis files ending in .gz are decompressed as they are read, and binary
+/// analysis files are detected and decoded.
+pub fn validate_analysis_file(
+    file: &str,
+    analysis_path: &str,
+    source: Option<&str>,
+) -> AnalysisFileReport {
+    let mut report = AnalysisFileReport {
+        file: file.to_string(),
+        ..AnalysisFileReport::default()
+    };
+
+    let reader: Box<dyn Read> = match File::open(analysis_path) {
+        Ok(f) if analysis_path.ends_with(".gz") => Box::new(GzDecoder::new(f)),
+        Ok(f) => Box::new(f),
+        Err(e) => {
+            report.report(
+                0,
+                DiagnosticSeverity::Error,
+                "file",
+                format!("unable to open analysis file [{}]: {}", analysis_path, e),
+            );
+            return report;
+        }
+    };
+    let source_lines = source.map(SourceLines::new);
+
+    let (is_binary, reader) = match sniff_binary_analysis(reader) {
+        Ok(sniffed) => sniffed,
+        Err(e) => {
+            report.report(
+                0,
+                DiagnosticSeverity::Error,
+                "file",
+                format!("unable to read analysis file [{}]: {}", analysis_path, e),
+            );
+            return report;
+        }
+    };
+    if is_binary {
+        // Binary records are already typed, so the schema checks can't fail,
+        // but re-checking them as JSON still catches bad locations.  "Lines"
+        // are record numbers.
+        let mut reader = BinaryAnalysisReader::new(BufReader::new(reader));
+        loop {
+            match reader.read_record() {
+                Ok(Some((loc, data))) => {
+                    report.records += 1;
+                    let record_no = report.records;
+                    let line = to_string(&WithLocation { loc, data }).unwrap();
+                    validate_record(&mut report, record_no, &line, source_lines.as_ref());
+                }
+                Ok(None) => break,
+                Err(e) => {
+                    report.report(
+                        report.records + 1,
+                        DiagnosticSeverity::Error,
+                        "file",
+                        format!("unable to decode binary record: {}", e),
+                    );
+                    break;
+                }
+            }
+        }
+        return report;
+    }
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = match line {
+            Ok(line) => line,
+            Err(e) => {
+                report.report(
+                    i + 1,
+                    DiagnosticSeverity::Error,
+                    "file",
+                    format!("unable to read line: {}", e),
+                );
+                break;
+            }
+        };
+        report.records += 1;
+        validate_record(&mut report, i + 1, &line, source_lines.as_ref());
+    }
+
+    report
+}
+
+fn validate_record(
+    report: &mut AnalysisFileReport,
+    lineno: usize,
+    line: &str,
+    source_lines: Option<&SourceLines>,
+) {
+    use DiagnosticSeverity::*;
+
+    let mut obj = match from_str::<Value>(line) {
+        Ok(Value::Object(obj)) => obj,
+        Ok(_) => {
+            report.report(lineno, Error, "json", "record is not an object".to_string());
+            return;
+        }
+        Err(e) => {
+            report.report(lineno, Error, "json", format!("malformed JSON: {}", e));
+            return;
+        }
+    };
+
+    let loc = match obj.remove("loc") {
+        Some(Value::String(s)) => match try_parse_location(&s) {
+            Some(loc) => loc,
+            None => {
+                report.report(lineno, Error, "location", format!("malformed loc [{}]", s));
+                return;
+            }
+        },
+        Some(_) => {
+            report.report(lineno, Error, "location", "loc is not a string".to_string());
+            return;
+        }
+        None => {
+            report.report(lineno, Error, "location", "missing loc".to_string());
+            return;
+        }
+    };
+    if loc.col_start > loc.col_end {
+        report.report(
+            lineno,
+            Error,
+            "location",
+            format!("loc starts after it ends ({:?})", loc),
+        );
+    }
+    // File targets use 1:0 even for empty files.
+    if let Some(source_lines) = source_lines
+        && !loc.is_file_target()
+    {
+        check_location(report, lineno, source_lines, &loc);
+    }
+
+    let tags: Vec<&str> = ["target", "source", "structured"]
+        .into_iter()
+        .filter(|tag| obj.contains_key(*tag))
+        .collect();
+    let (tag, orig) = match tags.as_slice() {
+        [tag] => (*tag, Value::Object(obj)),
+        [] => {
+            report.report(
+                lineno,
+                Error,
+                "record-type",
+                "record has none of the target/source/structured tags".to_string(),
+            );
+            return;
+        }
+        _ => {
+            report.report(
+                lineno,
+                Error,
+                "record-type",
+                format!("record has multiple tags: {}", tags.join(", ")),
+            );
+            return;
+        }
+    };
+
+    // Deserialize into the typed representation and then re-serialize so we
+    // can find any fields that the typed representation drops on the floor.
+    // Structured records keep unknown fields in `extra` so we only need to
+    // check that they deserialize.
+    let typed = match tag {
+        "target" => from_value::<AnalysisTarget<Ustr>>(orig.clone()).map(|t| {
+            if let Some(source_lines) = source_lines {
+                check_line_range(report, lineno, source_lines, "peekRange", &t.peek_range);
+                for range in &t.arg_ranges {
+                    check_source_range(report, lineno, source_lines, "argRanges", range);
+                }
+            }
+            to_value(t).ok()
+        }),
+        "source" => from_value::<AnalysisSource<Ustr>>(orig.clone()).map(|s| {
+            if let Some(source_lines) = source_lines {
+                check_source_range(
+                    report,
+                    lineno,
+                    source_lines,
+                    "nestingRange",
+                    &s.nesting_range,
+                );
+                for range in &s.arg_ranges {
+                    check_source_range(report, lineno, source_lines, "argRanges", range);
+                }
+            }
+            to_value(s).ok()
+        }),
+        _ => from_value::<AnalysisStructured<Ustr>>(orig.clone()).map(|_| None),
+    };
+    match typed {
+        Ok(Some(Value::Object(roundtripped))) => {
+            if let Value::Object(orig) = &orig {
+                check_dropped_fields(report, lineno, tag, orig, &roundtripped);
+            }
+        }
+        Ok(_) => {}
+        Err(e) => {
+            report.report(
+                lineno,
+                Error,
+                "schema",
+                format!("{} record doesn't match the schema: {}", tag, e),
+            );
+        }
+    }
+}
+
+/// Report any fields with meaningful values that didn't survive a round-trip
+/// through the typed representation.
+fn check_dropped_fields(
+    report: &mut AnalysisFileReport,
+    lineno: usize,
+    tag: &str,
+    orig: &Map<String, Value>,
+    roundtripped: &Map<String, Value>,
+) {
+    for (key, value) in orig {
+        if roundtripped.contains_key(key) {
+            continue;
+        }
+        // Fields at their default value are omitted when serializing.
+        let is_default = match value {
+            Value::Null => true,
+            Value::Bool(b) => !b,
+            Value::Number(n) => n.as_f64() == Some(0.0),
+            Value::String(s) => s.is_empty(),
+            Value::Array(a) => a.is_empty(),
+            Value::Object(o) => o.is_empty(),
+        };
+        if !is_default {
+            report.report(
+                lineno,
+                DiagnosticSeverity::Warning,
+                "unknown-field",
+                format!(
+                    "{} record field [{}] is not part of the schema and is ignored",
+                    tag, key
+                ),
+            );
+        }
+    }
+}
+
+fn check_location(
+    report: &mut AnalysisFileReport,
+    lineno: usize,
+    source_lines: &SourceLines,
+    loc: &Location,
+) {
+    if let Some(problem) = source_lines.check(loc.lineno, loc.col_end) {
+        report.report(
+            lineno,
+            DiagnosticSeverity::Error,
+            "location",
+            format!("loc: {}", problem),
+        );
+    }
+}
+
+fn check_line_range(
+    report: &mut AnalysisFileReport,
+    lineno: usize,
+    source_lines: &SourceLines,
+    what: &str,
+    range: &LineRange,
+) {
+    if range.is_empty() {
+        return;
+    }
+    let problem = if range.start_lineno > range.end_lineno {
+        Some("starts after it ends".to_string())
+    } else {
+        source_lines
+            .check(range.start_lineno, 0)
+            .or_else(|| source_lines.check(range.end_lineno, 0))
+    };
+    if let Some(problem) = problem {
+        report.report(
+            lineno,
+            DiagnosticSeverity::Warning,
+            "location",
+            format!("{}: {}", what, problem),
+        );
+    }
+}
+
+fn check_source_range(
+    report: &mut AnalysisFileReport,
+    lineno: usize,
+    source_lines: &SourceLines,
+    what: &str,
+    range: &SourceRange,
+) {
+    if range.is_empty() {
+        return;
+    }
+    let problem = if (range.start_lineno, range.start_col) > (range.end_lineno, range.end_col) {
+        Some("starts after it ends".to_string())
+    } else {
+        source_lines
+            .check(range.start_lineno, range.start_col)
+            .or_else(|| source_lines.check(range.end_lineno, range.end_col))
+    };
+    if let Some(problem) = problem {
+        report.report(
+            lineno,
+            DiagnosticSeverity::Warning,
+            "location",
+            format!("{}: {}", what, problem),
+        );
+    }
+}
+
+#[test]
+fn test_validate_record() {
+    let source = SourceLines::new("int x;\nint foo() {\n  return x;\n}\n");
+    let mut report = AnalysisFileReport::default();
+    let lines = [
+        r#"{"loc":"00001:4","target":1,"kind":"def","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00002:4-7","source":1,"syntax":"def,function","pretty":"function foo","sym":"_Z3foov","nestingRange":"2:10-4:0"}"#,
+        r#"{"loc":"00001:4","target":1,"kind":"def","pretty":"x","sym":"x""#,
+        r#"{"target":1,"kind":"def","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00009:4","target":1,"kind":"use","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00003:9-20","target":1,"kind":"use","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00003:9","target":1,"kind":"frobnicate","pretty":"x","sym":"x"}"#,
+        r#"{"loc":"00003:9","target":1,"kind":"use","pretty":"x","sym":"x","typo":"oops"}"#,
+        r#"{"loc":"00003:9","kind":"use","pretty":"x","sym":"x"}"#,
+    ];
+    for (i, line) in lines.iter().enumerate() {
+        validate_record(&mut report, i + 1, line, Some(&source));
+    }
+    let found: Vec<(usize, &str)> = report
+        .diagnostics
+        .iter()
+        .map(|d| (d.line, d.check))
+        .collect();
+    assert_eq!(
+        found,
+        vec![
+            (3, "json"),
+            (4, "location"),
+            (5, "location"),
+            (6, "location"),
+            (7, "schema"),
+            (8, "unknown-field"),
+            (9, "record-type"),
+        ]
+    );
+    assert_eq!(report.errors, 6);
+    assert_eq!(report.warnings, 1);
+}
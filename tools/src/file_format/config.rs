@@ -11,6 +11,7 @@ use serde::{Deserialize, Serialize};
 use git2::{Oid, Repository};
 use thread_local::ThreadLocal;
 
+use crate::revision_mapper::{CinnabarMapper, RevisionMapper, lookup_hg_prefix, open_revision_map};
 use crate::url_encode_path::url_encode_path;
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
@@ -101,6 +102,10 @@ pub struct TreeConfigPaths {
     /// If this is actually a mercurial repo, the URL of the hg server, no
     /// trailing `/`.
     pub hg_root: Option<String>,
+    /// Path to a git/hg revision mapping to use instead of git-cinnabar; either
+    /// a mapping file or a sorted map directory as written by
+    /// `build-revision-map`.  See `revision_mapper.rs`.
+    pub revision_map: Option<String>,
     /// Relative path within the source tree that's really a WPT root.
     pub wpt_root: Option<String>,
     /// If this is actually a git repo hosted on github, its URL.  If the repo
@@ -120,6 +125,18 @@ pub struct TreeConfigPaths {
     /// that the build script will handle downloading or generating the indexes.
     #[serde(default)]
     pub scip_subtrees: BTreeMap<String, ScipSubtreeConfig>,
+    /// Absolute path to a directory of `<name>.scm` tree-sitter queries that
+    /// override the built-in ones in `languages/nesting_queries` that
+    /// scip-indexer uses to derive nesting ranges and contextsyms, and
+    /// optionally a `pretty_rules.toml` overriding how it derives pretty names.
+    /// This allows a config repo to tune these without a rebuild.
+    pub scip_nesting_queries_path: Option<String>,
+    /// Namespaces of the `[topics.<namespace>]` rules in `ontology-mapping.toml`
+    /// that crossref should process for this tree, like `"observer"`.  Topic
+    /// processing re-reads analysis files for the matching calls, so trees must
+    /// opt in.
+    #[serde(default)]
+    pub ontology_topics: Vec<String>,
 }
 
 impl TreeConfigPaths {
@@ -249,9 +266,14 @@ pub struct GitData {
     pub coverage_repo: Option<ThreadLocalRepository>,
 
     pub blame_map: HashMap<Oid, Oid>, // Maps repo OID to blame_repo OID.
-    // Maps repo OID to Hg rev.  This comes from our blame commits, but cinnabar
-    // can also tell us this bidirectionally via `git2hg` and `hg2git`.
+    // Maps repo OID to Hg rev.  This comes from our blame commits; use
+    // `git_to_hg` and `hg_to_git` which also consult `revision_mapper`.
     pub hg_map: HashMap<Oid, String>,
+    /// The reverse of `hg_map`, sorted so abbreviated hg revs can be found.
+    pub hg_rev_map: BTreeMap<String, Oid>,
+    /// The tree's configured `revision_map`, or git-cinnabar if the tree has an
+    /// `hg_root`.  Trees that aren't hg-backed have neither.
+    pub revision_mapper: Option<Box<dyn RevisionMapper>>,
     pub old_map: HashMap<Oid, Oid>, // Maps oldgit OID to repo OID.
 
     pub mailmap: Mailmap,
@@ -460,6 +482,78 @@ pub fn index_blame(
     (blame_map, hg_map, oldrev_map)
 }
 
+/// Helper to parse the contents of a marks file written by git-fast-import's
+/// `--export-marks=` argument for a repo whose commits were written in the
+/// `index_blame` format, like the blame, syntax, and timeline repos.
+///
+/// This is used by our blame/history preprocessing logic in order to be able to
+///  handle restarting processing when a (large) merge wasn't fully processing
+/// in a single run.  The problem that can happen in those cases is that
+/// `index_blame`/similar methods only revwalk a single branch of our
+/// transformed blame and until the merge commit is ingested, we will lose track
+/// of the first parent of the merge if we've begun processing the second parent
+/// of the merge (modulo any commits they may share).
+///
+/// As the most dramatic example, our revwalk of the blink repository's history
+/// up until the point of the merge of the "chromium" and "link trees" looks
+/// like: [(289k commits of chromium), (182k commits of blink), (merge commit)].
+/// As long as our import process doesn't fail, we're fine, but if we crash or
+/// stop because of our "limit" mechanism once we've moved onto the blink
+/// commits, then they won't be referenced by the single branch tag we're using
+/// in our processing.
+///
+/// To this end, we load up the marks file to augment the information from
+/// index_blame and make sure to pass `--import-marks-if-exists=` when we kick
+/// off git-fast-import.  Note that we currently don't actually reference the
+/// marker identifiers we get from the file since we do have the hashes
+/// available and it makes sense to just use that, so the main reason we want
+/// to tell git-fast-import to import the marks file is so that the contents of
+/// the file are maintained through successive runs rather than only containing
+/// revisions processed in the last run.
+///
+/// Note that another alternative would have been some kind of complex branch
+/// management.  See bug 1782285 for more context.
+///
+/// Any source revisions missing from `derived_map` are added, mapping to
+/// `make_commit` of the derived repo's commit.  Returns the number of revisions
+/// in the marks file and the number of those that were needed to fill in
+/// `derived_map`.  If the latter is zero it means that `index_blame` already
+/// knew about everything.
+pub fn ingest_git_fast_import_marks_file<T>(
+    derived_repo: &Repository,
+    derived_map: &mut HashMap<Oid, T>,
+    marks_file: &str,
+    make_commit: impl Fn(Oid) -> T,
+) -> (u32, u32) {
+    // The number of revisions the marker file told us about.
+    let mut marker_revs_exist: u32 = 0;
+    // The number of marker revisions we used to fill in the derived_map.
+    let mut marker_revs_needed: u32 = 0;
+
+    for line in marks_file.lines() {
+        let line_pieces = line.split_whitespace().collect::<Vec<_>>();
+        let marker_oid = Oid::from_str(line_pieces[1]).unwrap();
+
+        marker_revs_exist += 1;
+
+        let commit = derived_repo.find_commit(marker_oid).unwrap();
+
+        let msg = commit.message().unwrap();
+        // (This must match what's in `index_blame`.)
+        let pieces = msg.split_whitespace().collect::<Vec<_>>();
+
+        let orig_oid = Oid::from_str(pieces[1]).unwrap();
+        derived_map.entry(orig_oid).or_insert_with(|| {
+            marker_revs_needed += 1;
+            make_commit(marker_oid)
+        });
+
+        // (we don't care about pieces[3] which is the hg commit if it's there)
+    }
+
+    (marker_revs_exist, marker_revs_needed)
+}
+
 pub struct BlameCommitInfo {
     pub sourcerev: Oid,
     // we're horribly inconsistent about whether this is a string or an oid
@@ -546,12 +640,24 @@ pub fn git_data(paths: &TreeConfigPaths, need_indexes: bool) -> Option<GitData>
         .as_deref()
         .map(|path| Repository::open(path).unwrap());
 
+    let hg_rev_map = hg_map
+        .iter()
+        .map(|(git_rev, hg_rev)| (hg_rev.clone(), *git_rev))
+        .collect();
+    let revision_mapper: Option<Box<dyn RevisionMapper>> = match paths.revision_map.as_deref() {
+        Some(path) => Some(open_revision_map(path).unwrap()),
+        None if paths.hg_root.is_some() => Some(Box::new(CinnabarMapper::new(repo.path()))),
+        None => None,
+    };
+
     Some(GitData {
         repo: repo.into(),
         blame_repo: blame_repo.map(Into::into),
         coverage_repo: coverage_repo.map(Into::into),
         blame_map,
         hg_map,
+        hg_rev_map,
+        revision_mapper,
         old_map,
         mailmap,
         blame_ignore,
@@ -746,6 +852,22 @@ impl BlameIgnoreList {
 }
 
 impl GitData {
+    /// Map a git revision to its hg revision, preferring what the blame repo
+    /// told us.
+    pub fn git_to_hg(&self, rev: &Oid) -> Option<String> {
+        match self.hg_map.get(rev) {
+            Some(hg_rev) => Some(hg_rev.clone()),
+            None => self.revision_mapper.as_ref()?.git_to_hg(*rev),
+        }
+    }
+
+    /// Map a (potentially abbreviated) hg revision to its git revision,
+    /// preferring what the blame repo told us.
+    pub fn hg_to_git(&self, hg_rev: &str) -> Option<Oid> {
+        lookup_hg_prefix(&self.hg_rev_map, hg_rev)
+            .or_else(|| self.revision_mapper.as_ref()?.hg_to_git(hg_rev))
+    }
+
     pub fn should_ignore_for_blame(&self, rev: &str) -> bool {
         // TODO: we might want to pull the commit message and check for
         // special annotations like "#skip-blame" or backouts as well.
@@ -13,6 +13,10 @@ pub struct Callee {
     #[serde(default, skip_serializing_if = "Option::is_none")]
     pub pretty: Option<Ustr>,
     pub sym: Ustr,
+    /// For callees synthesized by jumping through an IDL symbol's binding
+    /// slots (which will have a `kind` of "binding"), the IDL symbol.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub via: Option<Ustr>,
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize)]
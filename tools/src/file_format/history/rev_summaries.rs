@@ -20,12 +20,20 @@
 //! The revision summary is primarily an aggregation of the individual file
 //! deltas.  We only write out a single JSON blob so we only need a record and
 //! there's no need for a header.
+//!
+//! The one thing the file deltas can't tell us on their own is when a symbol
+//! moved between files, so renames are detected at this level across all of
+//! the revision's files and also listed in the summary's `renames`.
 
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 
 use serde::{Deserialize, Serialize};
 
-use super::timeline_common::SymbolSyntaxDeltaGroup;
+use super::syntax_files_struct::FileStructureRow;
+use super::timeline_common::{
+    ChangeKind, RenameCandidate, SymbolRenameRef, SymbolSyntaxDelta, SymbolSyntaxDeltaGroup,
+    TokenDeltaDetails, pair_renames,
+};
 
 /// Intended to be analogous to the payload of the `FileDeltaDetailRecord` for
 /// the given file.  If that changes to be more than just a `symbol_group` then
@@ -37,6 +45,73 @@ pub struct RevFileSummaryRecord {
     pub symbol_group: SymbolSyntaxDeltaGroup,
 }
 
+impl RevFileSummaryRecord {
+    /// Derive the symbol deltas for a single file from the lines removed from
+    /// and added to its tokenized "files" entry in the syntax repo, where each
+    /// line is a `<scope pretty> <token>` pair.  A token removed and added in
+    /// the same scope is counted as moved.  The file's structure rows from
+    /// before and after the change determine whether a scope was added or
+    /// removed rather than changed.
+    pub fn from_token_lines<'a>(
+        removed_lines: impl IntoIterator<Item = &'a str>,
+        added_lines: impl IntoIterator<Item = &'a str>,
+        old_rows: &[FileStructureRow],
+        new_rows: &[FileStructureRow],
+    ) -> Self {
+        let mut counts: BTreeMap<&str, BTreeMap<&str, (u32, u32)>> = BTreeMap::new();
+        for line in removed_lines {
+            if let Some((scope, token)) = line.split_once(' ') {
+                counts.entry(scope).or_default().entry(token).or_default().1 += 1;
+            }
+        }
+        for line in added_lines {
+            if let Some((scope, token)) = line.split_once(' ') {
+                counts.entry(scope).or_default().entry(token).or_default().0 += 1;
+            }
+        }
+
+        let symbol_deltas = counts
+            .into_iter()
+            .map(|(scope, tokens)| {
+                let existed = old_rows.iter().any(|row| row.pretty == scope);
+                let exists = new_rows.iter().any(|row| row.pretty == scope);
+                let change = match (existed, exists) {
+                    (false, true) => ChangeKind::Added,
+                    (true, false) => ChangeKind::Removed,
+                    _ => ChangeKind::Changed,
+                };
+                let token_changes = tokens
+                    .into_iter()
+                    .map(|(token, (added, removed))| {
+                        let moved = added.min(removed);
+                        (
+                            token.to_string(),
+                            TokenDeltaDetails {
+                                added: added - moved,
+                                moved,
+                                removed: removed - moved,
+                            },
+                        )
+                    })
+                    .collect();
+                (
+                    scope.to_string(),
+                    SymbolSyntaxDelta {
+                        change,
+                        token_changes,
+                        renamed_from: None,
+                        renamed_to: None,
+                    },
+                )
+            })
+            .collect();
+
+        RevFileSummaryRecord {
+            symbol_group: SymbolSyntaxDeltaGroup { symbol_deltas },
+        }
+    }
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct RevSummaryRecord {
     /// The source git repo revision we're describing; this should also be our
@@ -66,4 +141,173 @@ pub struct RevSummaryRecord {
     /// Basically the contents of all the `FileDetlaDetailRecords` for all the
     /// files changed in this revision.
     pub file_deltas: BTreeMap<String, RevFileSummaryRecord>,
+
+    /// Symbols renamed and/or moved between files by this revision, as found
+    /// by `detect_renames`.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub renames: Vec<RevRenameRecord>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RevRenameRecord {
+    pub from_path: String,
+    pub from_pretty: String,
+    pub to_path: String,
+    pub to_pretty: String,
+    pub similarity: f32,
+}
+
+impl RevSummaryRecord {
+    /// Pair up the symbols removed and added by this revision that look like
+    /// they are the same symbol under a new name and/or in a new file (see
+    /// `pair_renames`).  Both sides of each pair are changed to
+    /// `ChangeKind::Renamed` and linked to each other, and the pair is added to
+    /// `renames`.
+    ///
+    /// `structure_rows` maps (path, pretty) to the symbol's `FileStructureRow`,
+    /// which for removed symbols comes from the parent revision.  Symbols
+    /// without a row are never considered renamed.
+    pub fn detect_renames(&mut self, structure_rows: &HashMap<(String, String), FileStructureRow>) {
+        let mut removed = vec![];
+        let mut added = vec![];
+        for (path, file) in &self.file_deltas {
+            for (pretty, delta) in &file.symbol_group.symbol_deltas {
+                let candidates = match delta.change {
+                    ChangeKind::Removed => &mut removed,
+                    ChangeKind::Added => &mut added,
+                    _ => continue,
+                };
+                if let Some(row) = structure_rows.get(&(path.clone(), pretty.clone())) {
+                    candidates.push(RenameCandidate {
+                        path,
+                        pretty,
+                        row,
+                        delta,
+                    });
+                }
+            }
+        }
+
+        let renames = pair_renames(&removed, &added)
+            .into_iter()
+            .map(|(i_removed, i_added, similarity)| RevRenameRecord {
+                from_path: removed[i_removed].path.to_string(),
+                from_pretty: removed[i_removed].pretty.to_string(),
+                to_path: added[i_added].path.to_string(),
+                to_pretty: added[i_added].pretty.to_string(),
+                similarity,
+            })
+            .collect::<Vec<_>>();
+
+        for rename in &renames {
+            let moved = rename.from_path != rename.to_path;
+            if let Some(delta) = self
+                .file_deltas
+                .get_mut(&rename.from_path)
+                .and_then(|f| f.symbol_group.symbol_deltas.get_mut(&rename.from_pretty))
+            {
+                delta.change = ChangeKind::Renamed;
+                delta.renamed_to = Some(SymbolRenameRef {
+                    path: moved.then(|| rename.to_path.clone()),
+                    pretty: rename.to_pretty.clone(),
+                    similarity: rename.similarity,
+                });
+            }
+            if let Some(delta) = self
+                .file_deltas
+                .get_mut(&rename.to_path)
+                .and_then(|f| f.symbol_group.symbol_deltas.get_mut(&rename.to_pretty))
+            {
+                delta.change = ChangeKind::Renamed;
+                delta.renamed_from = Some(SymbolRenameRef {
+                    path: moved.then(|| rename.from_path.clone()),
+                    pretty: rename.from_pretty.clone(),
+                    similarity: rename.similarity,
+                });
+            }
+        }
+
+        self.renames.extend(renames);
+    }
+}
+
+#[test]
+fn test_detect_renames() {
+    fn row(pretty: &str) -> FileStructureRow {
+        FileStructureRow {
+            pretty: pretty.to_string(),
+            is_def: true,
+            kind: "method".to_string(),
+        }
+    }
+    let body = ["return", "mFoo", "+", "aBar", ";"];
+    let old_lines = std::iter::once("C::GetFoo GetFoo".to_string())
+        .chain(body.iter().map(|t| format!("C::GetFoo {}", t)))
+        .collect::<Vec<_>>();
+    let new_lines = std::iter::once("C::GetFooPlusBar GetFooPlusBar".to_string())
+        .chain(body.iter().map(|t| format!("C::GetFooPlusBar {}", t)))
+        .collect::<Vec<_>>();
+    let old_rows = [row("C::GetFoo"), row("C::Keep")];
+    let new_rows = [row("C::GetFooPlusBar")];
+
+    let mut summary = RevSummaryRecord {
+        source_rev: "abcd".to_string(),
+        syntax_rev: "ef01".to_string(),
+        timeline_rev: "2345".to_string(),
+        message: "Move and rename GetFoo".to_string(),
+        iso_date: "2024-01-01T00:00:00+00:00".to_string(),
+        unmapped_author: "someone".to_string(),
+        file_deltas: BTreeMap::new(),
+        renames: vec![],
+    };
+    summary.file_deltas.insert(
+        "a.cpp".to_string(),
+        RevFileSummaryRecord::from_token_lines(
+            old_lines.iter().map(String::as_str).chain(["C::Keep x"]),
+            ["C::Keep y"],
+            &old_rows,
+            &[row("C::Keep")],
+        ),
+    );
+    summary.file_deltas.insert(
+        "b.cpp".to_string(),
+        RevFileSummaryRecord::from_token_lines(
+            [],
+            new_lines.iter().map(String::as_str),
+            &[],
+            &new_rows,
+        ),
+    );
+
+    let mut structure_rows = HashMap::new();
+    for r in &old_rows {
+        structure_rows.insert(("a.cpp".to_string(), r.pretty.clone()), r.clone());
+    }
+    for r in &new_rows {
+        structure_rows.insert(("b.cpp".to_string(), r.pretty.clone()), r.clone());
+    }
+    summary.detect_renames(&structure_rows);
+
+    assert_eq!(summary.renames.len(), 1);
+    let rename = &summary.renames[0];
+    assert_eq!(
+        (rename.from_path.as_str(), rename.from_pretty.as_str()),
+        ("a.cpp", "C::GetFoo")
+    );
+    assert_eq!(
+        (rename.to_path.as_str(), rename.to_pretty.as_str()),
+        ("b.cpp", "C::GetFooPlusBar")
+    );
+
+    let a_deltas = &summary.file_deltas["a.cpp"].symbol_group.symbol_deltas;
+    let old = &a_deltas["C::GetFoo"];
+    assert_eq!(old.change, ChangeKind::Renamed);
+    let renamed_to = old.renamed_to.as_ref().unwrap();
+    assert_eq!(renamed_to.path.as_deref(), Some("b.cpp"));
+    assert_eq!(renamed_to.pretty, "C::GetFooPlusBar");
+    assert_eq!(a_deltas["C::Keep"].change, ChangeKind::Changed);
+
+    let new = &summary.file_deltas["b.cpp"].symbol_group.symbol_deltas["C::GetFooPlusBar"];
+    assert_eq!(new.change, ChangeKind::Renamed);
+    assert_eq!(new.renamed_from.as_ref().unwrap().pretty, "C::GetFoo");
 }
@@ -2,6 +2,8 @@ use std::collections::BTreeMap;
 
 use serde::{Deserialize, Serialize};
 
+use super::syntax_files_struct::FileStructureRow;
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct DetailRecordRef {
     /// Source revision this record contains details for.
@@ -62,11 +64,12 @@ pub struct TokenDeltaDetails {
     pub removed: u32,
 }
 
-/// Indicate whether a symbol was added/changed/removed.
-/// TODO: Figure out how to express a symbol being renamed.  While we could add
-/// a "Renamed" here, it might instead make sense to stick with "Changed" and
-/// instead have a list of change details (beyond the token_changes) which could
-/// express the rename.
+/// Indicate whether a symbol was added/changed/removed/renamed.
+///
+/// Renames are expressed as a `Renamed` on both the symbol that went away and
+/// the symbol that replaced it, with `renamed_to` / `renamed_from` on the
+/// `SymbolSyntaxDelta` linking the two so that history can be followed in
+/// either direction.
 #[derive(Clone, Copy, Debug, Eq, PartialEq, Ord, PartialOrd, Serialize, Deserialize)]
 #[serde(rename_all = "lowercase")]
 pub enum ChangeKind {
@@ -78,6 +81,22 @@ pub enum ChangeKind {
     Changed,
     /// The symbol/whatever was removed.
     Removed,
+    /// The symbol/whatever was renamed and/or moved to another file; exactly
+    /// one of `renamed_from` and `renamed_to` will be set.
+    Renamed,
+}
+
+/// The other side of a rename.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SymbolRenameRef {
+    /// The file the other symbol lives in, if the rename also moved the symbol
+    /// between files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    pub pretty: String,
+    /// The token similarity score that caused us to believe this was a rename,
+    /// from 0 to 1.
+    pub similarity: f32,
 }
 
 /// Summarized changes at symbol granularity, with the "pretty" being assumed to
@@ -90,6 +109,15 @@ pub struct SymbolSyntaxDelta {
     /// Changes to tokens within the owning scope corresponding to this pretty
     /// identifier.
     pub token_changes: BTreeMap<String, TokenDeltaDetails>,
+
+    /// For a `Renamed` symbol that came into existence, the symbol it used to
+    /// be.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_from: Option<SymbolRenameRef>,
+
+    /// For a `Renamed` symbol that went away, the symbol it became.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub renamed_to: Option<SymbolRenameRef>,
 }
 
 /// Holds aggregated changes to symbols.
@@ -100,3 +128,169 @@ pub struct SymbolSyntaxDeltaGroup {
     /// which is arbitrarily derived from prior blame processing logic.
     pub symbol_deltas: BTreeMap<String, SymbolSyntaxDelta>,
 }
+
+/// The minimum `token_similarity` for us to consider a removed symbol and an
+/// added symbol to be the same symbol.  This is intentionally fairly high
+/// because a false rename is more confusing than a missed one.
+pub const RENAME_SIMILARITY_THRESHOLD: f32 = 0.6;
+
+/// Compute the weighted Jaccard similarity of the tokens removed from one
+/// symbol and the tokens added to another.  Tokens that were "moved" within
+/// the symbol are not considered because the diff delta already explained
+/// them.
+pub fn token_similarity(removed: &SymbolSyntaxDelta, added: &SymbolSyntaxDelta) -> f32 {
+    let mut shared = 0;
+    let mut total = 0;
+    for (token, details) in &removed.token_changes {
+        let other = added.token_changes.get(token).map_or(0, |d| d.added);
+        shared += details.removed.min(other);
+        total += details.removed.max(other);
+    }
+    for (token, details) in &added.token_changes {
+        if !removed.token_changes.contains_key(token) {
+            total += details.added;
+        }
+    }
+    if total == 0 {
+        return 0.0;
+    }
+    shared as f32 / total as f32
+}
+
+/// A symbol that was added or removed and could be one side of a rename.
+pub struct RenameCandidate<'a> {
+    pub path: &'a str,
+    /// The key of the symbol in its `SymbolSyntaxDeltaGroup`.
+    pub pretty: &'a str,
+    /// The structure row for the symbol as of the revision it existed in.
+    pub row: &'a FileStructureRow,
+    pub delta: &'a SymbolSyntaxDelta,
+}
+
+/// Greedily pair up removed and added symbols of the same kind, best scores
+/// first, returning `(removed index, added index, similarity)` tuples for
+/// pairs that meet `RENAME_SIMILARITY_THRESHOLD`.  Each candidate is used at
+/// most once.
+pub fn pair_renames(
+    removed: &[RenameCandidate],
+    added: &[RenameCandidate],
+) -> Vec<(usize, usize, f32)> {
+    let mut scored = vec![];
+    for (i_removed, r) in removed.iter().enumerate() {
+        for (i_added, a) in added.iter().enumerate() {
+            if r.row.kind != a.row.kind || r.row.is_def != a.row.is_def {
+                continue;
+            }
+            let similarity = token_similarity(r.delta, a.delta);
+            if similarity >= RENAME_SIMILARITY_THRESHOLD {
+                scored.push((i_removed, i_added, similarity));
+            }
+        }
+    }
+    // Break ties by preferring the same file and then the original ordering so
+    // that our output is deterministic.
+    scored.sort_by(|x, y| {
+        y.2.total_cmp(&x.2)
+            .then_with(|| {
+                let x_same = removed[x.0].path == added[x.1].path;
+                let y_same = removed[y.0].path == added[y.1].path;
+                y_same.cmp(&x_same)
+            })
+            .then_with(|| (x.0, x.1).cmp(&(y.0, y.1)))
+    });
+
+    let mut used_removed = vec![false; removed.len()];
+    let mut used_added = vec![false; added.len()];
+    let mut pairs = vec![];
+    for (i_removed, i_added, similarity) in scored {
+        if used_removed[i_removed] || used_added[i_added] {
+            continue;
+        }
+        used_removed[i_removed] = true;
+        used_added[i_added] = true;
+        pairs.push((i_removed, i_added, similarity));
+    }
+    pairs
+}
+
+#[test]
+fn test_pair_renames() {
+    fn delta(change: ChangeKind, tokens: &[(&str, u32)]) -> SymbolSyntaxDelta {
+        SymbolSyntaxDelta {
+            change,
+            token_changes: tokens
+                .iter()
+                .map(|(token, count)| {
+                    let (added, removed) = match change {
+                        ChangeKind::Added => (*count, 0),
+                        _ => (0, *count),
+                    };
+                    (
+                        token.to_string(),
+                        TokenDeltaDetails {
+                            added,
+                            moved: 0,
+                            removed,
+                        },
+                    )
+                })
+                .collect(),
+            renamed_from: None,
+            renamed_to: None,
+        }
+    }
+    fn row(pretty: &str, kind: &str) -> FileStructureRow {
+        FileStructureRow {
+            pretty: pretty.to_string(),
+            is_def: true,
+            kind: kind.to_string(),
+        }
+    }
+
+    let body: [(&str, u32); 4] = [("return", 1), ("mFoo", 2), ("+", 1), ("aBar", 1)];
+    let old_delta = delta(
+        ChangeKind::Removed,
+        &[&[("GetFoo", 1)][..], &body[..]].concat(),
+    );
+    let new_delta = delta(
+        ChangeKind::Added,
+        &[&[("GetFooPlusBar", 1)][..], &body[..]].concat(),
+    );
+    let other_delta = delta(ChangeKind::Added, &[("Unrelated", 1), ("return", 1)]);
+    let old_row = row("C::GetFoo", "method");
+    let new_row = row("C::GetFooPlusBar", "method");
+    let field_row = row("C::mGetFooPlusBar", "field");
+    let other_row = row("C::Unrelated", "method");
+
+    let removed = [RenameCandidate {
+        path: "a.cpp",
+        pretty: "C::GetFoo",
+        row: &old_row,
+        delta: &old_delta,
+    }];
+    let added = [
+        RenameCandidate {
+            path: "b.cpp",
+            pretty: "C::Unrelated",
+            row: &other_row,
+            delta: &other_delta,
+        },
+        RenameCandidate {
+            path: "b.cpp",
+            pretty: "C::mGetFooPlusBar",
+            row: &field_row,
+            delta: &new_delta,
+        },
+        RenameCandidate {
+            path: "b.cpp",
+            pretty: "C::GetFooPlusBar",
+            row: &new_row,
+            delta: &new_delta,
+        },
+    ];
+
+    let pairs = pair_renames(&removed, &added);
+    assert_eq!(pairs.len(), 1);
+    assert_eq!((pairs[0].0, pairs[0].1), (0, 2));
+    assert!((pairs[0].2 - 5.0 / 7.0).abs() < 0.001);
+}
@@ -8,13 +8,12 @@ use std::hash::Hasher;
 
 extern crate regex;
 use serde_json::to_value;
-use serde_json::{Value, from_value, json, to_string};
-
-use ustr::Ustr;
+use serde_json::{json, to_string};
 
 use super::analysis::AnalysisUnion;
 use super::analysis::{
-    AnalysisSource, AnalysisStructured, ExpansionInfo, Location, WithLocation, read_analyses,
+    AnalysisRecord, AnalysisSource, AnalysisStructured, ExpansionInfo, Location, WithLocation,
+    read_analyses,
 };
 
 #[derive(Debug)]
@@ -40,8 +39,8 @@ pub fn merge_files<W: std::io::Write>(filenames: &[String], platforms: &[String]
 
     let src_data = read_analyses(
         filenames,
-        &mut |obj: Value, loc: &Location, i_file: usize| {
-            if let Ok(unified) = from_value::<AnalysisUnion<Ustr>>(obj) {
+        &mut |record: AnalysisRecord, loc: &Location, i_file: usize| {
+            if let Some(unified) = record.into_union() {
                 match unified {
                     AnalysisUnion::Source(mut src) => {
                         // return source objects so that they come out of `read_analyses` for
@@ -159,8 +158,23 @@ pub fn merge_files<W: std::io::Write>(filenames: &[String], platforms: &[String]
 
     for (_id, mut hmap) in structured_syms {
         if hmap.len() == 1 {
-            // There was only one variant of the structured info, so we can just use it as-is.
-            let (_hash, hs) = hmap.drain().next().unwrap();
+            // There was only one variant of the structured info, so we can just use it as-is,
+            // but if the symbol wasn't defined on every platform we note which platforms it was
+            // defined on so that platform-specific symbols can be identified.
+            let (_hash, mut hs) = hmap.drain().next().unwrap();
+            // Records are read a file at a time so repeats from the same file are adjacent.
+            hs.platforms.dedup();
+            if hs.platforms.len() < platforms.len() {
+                hs.data.extra.insert(
+                    "platforms".to_string(),
+                    json!(
+                        hs.platforms
+                            .iter()
+                            .map(|x| platforms[*x].clone())
+                            .collect::<Vec<String>>()
+                    ),
+                );
+            }
             writeln!(
                 writer,
                 "{}",
@@ -248,3 +262,72 @@ pub fn merge_files<W: std::io::Write>(filenames: &[String], platforms: &[String]
         }
     }
 }
+
+#[test]
+fn test_merge_structured_platforms() {
+    use serde_json::{Value, from_str};
+    use std::fs;
+
+    let dir = std::env::temp_dir().join(format!("merge-platforms-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let linux = dir.join("linux64");
+    let win = dir.join("win64");
+    fs::write(
+        &linux,
+        concat!(
+            r#"{"loc":"00001:7-13","structured":1,"pretty":"Shared","sym":"T_Shared","kind":"struct","sizeBytes":4}"#,
+            "\n",
+            r#"{"loc":"00002:7-16","structured":1,"pretty":"LinuxOnly","sym":"T_LinuxOnly","kind":"struct","sizeBytes":1}"#,
+            "\n",
+            r#"{"loc":"00003:7-13","structured":1,"pretty":"Layout","sym":"T_Layout","kind":"struct","sizeBytes":16}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+    fs::write(
+        &win,
+        concat!(
+            r#"{"loc":"00001:7-13","structured":1,"pretty":"Shared","sym":"T_Shared","kind":"struct","sizeBytes":4}"#,
+            "\n",
+            r#"{"loc":"00003:7-13","structured":1,"pretty":"Layout","sym":"T_Layout","kind":"struct","sizeBytes":8}"#,
+            "\n",
+        ),
+    )
+    .unwrap();
+
+    let mut output = Vec::new();
+    merge_files(
+        &[
+            linux.to_str().unwrap().to_string(),
+            win.to_str().unwrap().to_string(),
+        ],
+        &["linux64".to_string(), "win64".to_string()],
+        &mut output,
+    );
+    fs::remove_dir_all(&dir).unwrap();
+
+    let records: BTreeMap<String, Value> = std::str::from_utf8(&output)
+        .unwrap()
+        .lines()
+        .map(|line| {
+            let value: Value = from_str(line).unwrap();
+            (value["sym"].as_str().unwrap().to_string(), value)
+        })
+        .collect();
+
+    // Identical on every platform, so the record gets no platform info.
+    assert_eq!(records["T_Shared"]["sizeBytes"], json!(4));
+    assert!(records["T_Shared"].get("platforms").is_none());
+    assert_eq!(records["T_Shared"]["variants"], json!([]));
+    // Only defined on some platforms.
+    assert_eq!(records["T_LinuxOnly"]["platforms"], json!(["linux64"]));
+    assert_eq!(records["T_LinuxOnly"]["variants"], json!([]));
+    // Differs across platforms, so the highest platform's variant is canonical
+    // and the others are kept as variants.
+    assert_eq!(records["T_Layout"]["sizeBytes"], json!(8));
+    assert_eq!(records["T_Layout"]["platforms"], json!(["win64"]));
+    let variants = records["T_Layout"]["variants"].as_array().unwrap();
+    assert_eq!(variants.len(), 1);
+    assert_eq!(variants[0]["sizeBytes"], json!(16));
+    assert_eq!(variants[0]["platforms"], json!(["linux64"]));
+}
@@ -4,9 +4,15 @@ pub mod ontology_pointer_kind;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod history;
 
+#[cfg(not(target_arch = "wasm32"))]
+pub mod analysis_binary;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod analysis_manglings;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod analysis_reader;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod analysis_validator;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod bisectable_mmap;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod code_coverage_report;
@@ -1,8 +1,12 @@
+use regex::Regex;
 use serde::Deserialize;
 use ustr::{Ustr, UstrMap, ustr};
 
 use crate::symbol_graph_edge_kind::EdgeKind;
 
+use super::analysis::AnalysisStructured;
+use super::analysis_manglings::mangle_file;
+
 pub use super::ontology_pointer_kind::OntologyPointerKind;
 
 #[derive(Deserialize)]
@@ -11,6 +15,199 @@ pub struct OntologyMappingConfig {
     pub pretty: UstrMap<OntologyRule>,
     #[serde(default)]
     pub types: UstrMap<OntologyType>,
+    /// Rules that select the symbols they apply to via an `OntologySelector`
+    /// rather than an exact pretty identifier.  These are expressed in TOML as
+    /// `[[rules]]` array entries so that they can be named and so that they
+    /// are processed in the order they are declared.
+    #[serde(default)]
+    pub rules: Vec<OntologySelectorRule>,
+    /// Rules that bind string literal arguments into synthetic topic symbols,
+    /// keyed by the topic namespace (ex: "observer") which becomes part of the
+    /// synthetic symbols' names.
+    #[serde(default)]
+    pub topics: UstrMap<OntologyTopicRule>,
+}
+
+/// Describes a string-keyed dispatch mechanism like the observer service where
+/// the control flow from the code that notifies a topic to the code that
+/// handles it is only visible by looking at string literals.  Each distinct
+/// string literal seen as a topic argument becomes a synthetic `TOPIC_` symbol
+/// that the emitting functions call and which has `TopicHandler` ontology
+/// slots pointing at the handlers.
+#[derive(Deserialize)]
+pub struct OntologyTopicRule {
+    /// Calls to these methods notify/dispatch the topic named by their string
+    /// literal argument.  The calling function is treated as calling the
+    /// topic symbol.
+    #[serde(default)]
+    pub emitters: Vec<OntologyTopicArgRule>,
+    /// Calls to these methods register interest in the topic named by their
+    /// string literal argument.  The calling function is treated as a handler
+    /// of the topic because that is the best approximation we have without
+    /// tracking the registered callback.
+    #[serde(default)]
+    pub subscribers: Vec<OntologyTopicArgRule>,
+    /// Overrides of these methods are treated as handlers of any emitted
+    /// topic whose string literal appears in their body, like an
+    /// `nsIObserver::Observe` implementation comparing against the topic.
+    #[serde(default)]
+    pub handlers: Vec<OntologyTopicHandlerRule>,
+}
+
+#[derive(Deserialize)]
+pub struct OntologyTopicArgRule {
+    /// The exact pretty identifier of the method.  Calls to overrides of the
+    /// method also count.
+    pub pretty: Ustr,
+    /// The 0-based index of the argument which holds the topic string literal.
+    /// For C++ method calls this does not include the implicit `this`.
+    pub arg_index: u32,
+}
+
+#[derive(Deserialize)]
+pub struct OntologyTopicHandlerRule {
+    /// The exact pretty identifier of the method whose overrides handle topics.
+    pub overrides_of: Ustr,
+}
+
+/// Build the synthetic symbol for a topic.
+pub fn make_topic_sym(namespace: &str, topic: &str) -> String {
+    format!("TOPIC_{}_{}", mangle_file(namespace), mangle_file(topic))
+}
+
+/// Build the pretty identifier for a topic, ex: `observer:xpcom-shutdown`.
+pub fn make_topic_pretty(namespace: &str, topic: &str) -> String {
+    format!("{}:{}", namespace, topic)
+}
+
+/// Parse a string literal at the start of `text`, returning its contents and
+/// the number of bytes the literal occupies in `text`.  C++ encoding prefixes
+/// (`u8`, `u`, `U`, `L`) and user-defined literal suffixes like `_ns` are
+/// accepted.  Escape sequences are left as-is and raw strings are not
+/// supported.
+pub fn parse_string_literal(text: &str) -> Option<(String, usize)> {
+    let prefix_len = text
+        .char_indices()
+        .find(|(_, c)| !matches!(c, 'u' | 'U' | 'L' | '8'))
+        .map(|(i, _)| i)?;
+    // Only accept the prefixes that actually exist.
+    if !matches!(&text[..prefix_len], "" | "u" | "u8" | "U" | "L") {
+        return None;
+    }
+
+    let mut chars = text[prefix_len..].char_indices();
+    let (_, quote) = chars.next()?;
+    if !matches!(quote, '"' | '\'' | '`') {
+        return None;
+    }
+    let mut contents = String::new();
+    let mut escaped = false;
+    for (i, c) in chars {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == quote {
+            let mut end = prefix_len + i + c.len_utf8();
+            // Consume any user-defined literal suffix.
+            end += text[end..]
+                .find(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+                .unwrap_or(text.len() - end);
+            return Some((contents, end));
+        } else if c == '\n' {
+            return None;
+        }
+        contents.push(c);
+    }
+    None
+}
+
+/// Find all of the (non-empty) string literals in the given source text.
+/// This is a lexical approximation that does not understand comments.
+pub fn find_string_literals(text: &str) -> Vec<String> {
+    let mut literals = vec![];
+    let mut offset = 0;
+    while let Some(quote_pos) = text[offset..].find(['"', '\'']) {
+        let start = offset + quote_pos;
+        match parse_string_literal(&text[start..]) {
+            Some((contents, len)) => {
+                if !contents.is_empty() {
+                    literals.push(contents);
+                }
+                offset = start + len;
+            }
+            None => {
+                offset = start + 1;
+            }
+        }
+    }
+    literals
+}
+
+/// An `OntologyRule` that is applied to every symbol matched by its selector.
+/// The matched symbols are treated exactly like the symbols that an exact
+/// `[pretty."..."]` key resolves to, so a `runnable` rule should select the
+/// base methods and not their overrides (the overrides are found by the rule
+/// itself).
+#[derive(Deserialize)]
+pub struct OntologySelectorRule {
+    /// A human-readable name for the rule used in diagnostics.
+    pub name: Ustr,
+    #[serde(rename = "match")]
+    pub selector: OntologySelector,
+    #[serde(flatten)]
+    pub rule: OntologyRule,
+}
+
+/// Criteria for selecting symbols from the structured records known to
+/// crossref.  All specified criteria must match.  At least one of
+/// `overrides_of`, `subclasses_of`, `pretty_regex`, or `subsystem` must be
+/// specified; `kind` alone is not allowed because it would match too much.
+#[derive(Default, Deserialize)]
+pub struct OntologySelector {
+    /// Regular expression which must match the symbol's entire pretty
+    /// identifier.  (The expression is anchored for you.)
+    pub pretty_regex: Option<String>,
+    /// Select all methods that transitively override the method(s) with this
+    /// exact pretty identifier.  The method itself is not selected.
+    pub overrides_of: Option<Ustr>,
+    /// Select all classes that transitively subclass the class(es) with this
+    /// exact pretty identifier.  The class itself is not selected.
+    pub subclasses_of: Option<Ustr>,
+    /// Select symbols whose structured record was found in a file belonging
+    /// to this exact subsystem as determined by `per-file-info.toml`.
+    pub subsystem: Option<Ustr>,
+    /// Select symbols whose structured record has this kind, like "class",
+    /// "struct", "method", or "field".
+    pub kind: Option<Ustr>,
+    /// The compiled version of `pretty_regex`, populated by
+    /// `OntologyMappingIngestion::new`.
+    #[serde(skip)]
+    pub compiled_pretty_regex: Option<Regex>,
+}
+
+impl OntologySelector {
+    /// Check the criteria that can be evaluated against the structured record
+    /// in isolation.  The `overrides_of` and `subclasses_of` criteria require
+    /// traversing the hierarchy and are the responsibility of the caller.
+    pub fn matches_meta(&self, meta: &AnalysisStructured) -> bool {
+        if let Some(re) = &self.compiled_pretty_regex
+            && !re.is_match(&meta.pretty)
+        {
+            return false;
+        }
+        if let Some(subsystem) = &self.subsystem
+            && meta.subsystem.as_ref() != Some(subsystem)
+        {
+            return false;
+        }
+        if let Some(kind) = &self.kind
+            && meta.kind != *kind
+        {
+            return false;
+        }
+        true
+    }
 }
 
 #[derive(Deserialize)]
@@ -121,9 +318,32 @@ pub struct OntologyMappingIngestion {
 
 impl OntologyMappingIngestion {
     pub fn new(config_str: &str) -> Result<Self, String> {
-        let config: OntologyMappingConfig =
+        let mut config: OntologyMappingConfig =
             toml::from_str(config_str).map_err(|err| err.to_string())?;
 
+        for selector_rule in &mut config.rules {
+            let selector = &mut selector_rule.selector;
+            if selector.pretty_regex.is_none()
+                && selector.overrides_of.is_none()
+                && selector.subclasses_of.is_none()
+                && selector.subsystem.is_none()
+            {
+                return Err(format!(
+                    "Rule '{}' needs a pretty_regex, overrides_of, subclasses_of, or subsystem selector",
+                    selector_rule.name
+                ));
+            }
+            if let Some(pattern) = &selector.pretty_regex {
+                let re = Regex::new(&format!("^(?:{})$", pattern)).map_err(|err| {
+                    format!(
+                        "Rule '{}' has a bad pretty_regex: {}",
+                        selector_rule.name, err
+                    )
+                })?;
+                selector.compiled_pretty_regex = Some(re);
+            }
+        }
+
         Ok(OntologyMappingIngestion { config })
     }
 }
@@ -294,10 +514,34 @@ impl OntologyMappingConfig {
         &self,
         type_str: &str,
     ) -> (Vec<(OntologyPointerKind, Ustr)>, Vec<Ustr>) {
+        self.parse_type_as_pointer(type_str, false)
+    }
+
+    /// Like `maybe_parse_type_as_pointer` but for type strings from languages
+    /// like Rust where class types aren't marked with a "class " or "struct "
+    /// prefix, so every named type is assumed to be one.  This means natives
+    /// like `u32` will be returned too, so the caller needs to filter the
+    /// results by whether they map to a type symbol.
+    pub fn maybe_parse_untagged_type_as_pointer(
+        &self,
+        type_str: &str,
+    ) -> (Vec<(OntologyPointerKind, Ustr)>, Vec<Ustr>) {
+        self.parse_type_as_pointer(type_str, true)
+    }
+
+    fn parse_type_as_pointer(
+        &self,
+        type_str: &str,
+        untagged: bool,
+    ) -> (Vec<(OntologyPointerKind, Ustr)>, Vec<Ustr>) {
+        let new_type = || ShoddyType {
+            is_tag: untagged,
+            ..ShoddyType::default()
+        };
         let mut c = type_str.chars();
         let mut state = TypeParseState::Typish;
         let mut type_stack: Vec<ShoddyType> = vec![];
-        let mut cur_type = ShoddyType::default();
+        let mut cur_type = new_type();
         let mut token = String::new();
 
         let mut results: Vec<(OntologyPointerKind, Ustr)> = vec![];
@@ -372,7 +616,7 @@ impl OntologyMappingConfig {
                     token = String::new();
 
                     type_stack.push(cur_type);
-                    cur_type = ShoddyType::default();
+                    cur_type = new_type();
                 }
                 (TypeParseState::Typish, Some(',')) => {
                     if !cur_type.identifier.is_empty() {
@@ -400,7 +644,7 @@ impl OntologyMappingConfig {
 
                     if let Some(container_type) = type_stack.last_mut() {
                         container_type.args.push(cur_type);
-                        cur_type = ShoddyType::default();
+                        cur_type = new_type();
                     } else {
                         info!(type_str, "Hit comma with no parent type!");
                         return (results, labels_to_apply);
@@ -543,7 +787,7 @@ impl OntologyMappingConfig {
                 (TypeParseState::Closing, Some(',')) => {
                     if let Some(container_type) = type_stack.last_mut() {
                         container_type.args.push(cur_type);
-                        cur_type = ShoddyType::default();
+                        cur_type = new_type();
                     } else {
                         info!(type_str, "Hit comma with no parent type!");
                         return (results, labels_to_apply);
@@ -768,4 +1012,102 @@ kind = "contains"
         ),
         (vec![], vec![])
     );
+
+    // Untagged types as we get from SCIP.
+    assert_eq!(
+        c.maybe_parse_untagged_type_as_pointer("RefPtr<Actual>"),
+        (vec![(OntologyPointerKind::Strong, ustr("Actual"))], vec![])
+    );
+    assert_eq!(
+        c.maybe_parse_untagged_type_as_pointer("nsTArray<UniquePtr<Poodle>>"),
+        (vec![(OntologyPointerKind::Unique, ustr("Poodle"))], vec![])
+    );
+    assert_eq!(
+        c.maybe_parse_untagged_type_as_pointer("Actual"),
+        (
+            vec![(OntologyPointerKind::Contains, ustr("Actual"))],
+            vec![]
+        )
+    );
+}
+
+#[test]
+fn test_selector_rules() {
+    let test_config = r#"
+[[rules]]
+name = "dom-runnables"
+match.pretty_regex = "mozilla::dom::.*Runnable::Run"
+runnable = "constructor"
+
+[[rules]]
+name = "refcounted-subclasses"
+match.subclasses_of = "mozilla::RefCounted"
+match.kind = "class"
+labels = ["rc"]
+"#;
+    let ingestion = OntologyMappingIngestion::new(test_config).unwrap();
+    let rules = &ingestion.config.rules;
+    assert_eq!(rules.len(), 2);
+
+    let re = rules[0].selector.compiled_pretty_regex.as_ref().unwrap();
+    assert!(re.is_match("mozilla::dom::WorkerRunnable::Run"));
+    // The expression is anchored at both ends.
+    assert!(!re.is_match("mozilla::dom::WorkerRunnable::RunInternal"));
+    assert!(!re.is_match("not::mozilla::dom::WorkerRunnable::Run"));
+    assert!(rules[0].rule.runnable == Some(OntologyRunnableMode::Constructor));
+
+    assert_eq!(
+        rules[1].selector.subclasses_of,
+        Some(ustr("mozilla::RefCounted"))
+    );
+    assert_eq!(rules[1].rule.labels, vec![ustr("rc")]);
+
+    // A kind alone is too broad.
+    assert!(
+        OntologyMappingIngestion::new(
+            r#"
+[[rules]]
+name = "everything"
+match.kind = "class"
+labels = ["oops"]
+"#
+        )
+        .is_err()
+    );
+
+    // Bad regexes are reported rather than ignored.
+    assert!(
+        OntologyMappingIngestion::new(
+            r#"
+[[rules]]
+name = "broken"
+match.pretty_regex = "foo("
+labels = ["oops"]
+"#
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn test_string_literals() {
+    assert_eq!(
+        parse_string_literal(r#""xpcom-shutdown", nullptr);"#),
+        Some(("xpcom-shutdown".to_string(), 16))
+    );
+    assert_eq!(
+        parse_string_literal(r#"u"load"_ns)"#),
+        Some(("load".to_string(), 10))
+    );
+    assert_eq!(
+        parse_string_literal("'click')"),
+        Some(("click".to_string(), 7))
+    );
+    assert_eq!(parse_string_literal(r#"kTopic, "nope""#), None);
+    assert_eq!(parse_string_literal(r#""unterminated"#), None);
+
+    assert_eq!(
+        find_string_literals(r#"if (!strcmp(aTopic, "xpcom-shutdown")) { Log("it's \"over\""); }"#),
+        vec!["xpcom-shutdown".to_string(), r#"it's \"over\""#.to_string()]
+    );
 }
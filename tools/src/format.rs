@@ -4,6 +4,7 @@ use std::io::Write;
 use std::ops::Deref;
 use std::path::Path;
 use std::process::Command;
+use std::sync::{Mutex, OnceLock};
 use std::time::Instant;
 
 use crate::abstract_server::FileMatch;
@@ -12,6 +13,7 @@ use crate::file_format::analysis_manglings::make_file_sym_from_path;
 use crate::file_format::bisectable_mmap::BisectableMmap;
 use crate::file_format::code_coverage_report;
 use crate::file_format::coverage::InterpolatedCoverage;
+use crate::file_format::identifiers::IdentMap;
 use crate::file_format::jumpref::{
     JumprefData, JumprefTraversals, determine_desired_extra_syms_from_jumpref,
     extra_syms_next_step_lookups,
@@ -21,8 +23,10 @@ use crate::git_ops::{self, coverage_history, coverage_summary, git_time_to_chron
 use crate::languages;
 use crate::languages::FormatAs;
 use crate::links;
+use crate::markdown::{MarkdownContext, RenderedMarkdown, render_markdown};
 use crate::templating::builder::{build_and_parse_coverage_history, build_and_parse_dir_listing};
 use crate::tokenize;
+use crate::tree_sitter_support::highlighter::highlight_source;
 use crate::utils::OwnedOrBorrowed;
 
 use crate::file_format::analysis::{
@@ -49,6 +53,34 @@ pub struct FormattedLine {
     pub pop_nest_count: u32,
 }
 
+/// Tokenize the input with the hand-rolled tokenizer for the given format.
+pub fn tokenize_with_format(format: &FormatAs, input: &str) -> Vec<tokenize::Token> {
+    match *format {
+        FormatAs::Binary => panic!("Unexpected binary file"),
+        FormatAs::CSS => tokenize::tokenize_css(input),
+        FormatAs::Plain | FormatAs::Markdown => tokenize::tokenize_plain(input),
+        FormatAs::YAML => tokenize::tokenize_yaml(input),
+        FormatAs::FormatCLike(spec) => tokenize::tokenize_c_like(input, spec),
+        FormatAs::FormatXPIDL(spec, cdata_spec) => {
+            tokenize::tokenize_xpidl(input, spec, cdata_spec)
+        }
+        FormatAs::FormatTagLike(script_spec) => tokenize::tokenize_tag_like(input, script_spec),
+    }
+}
+
+/// Tokenize the input for `format_code`.  If "MOZSEARCH_TREE_SITTER_HIGHLIGHT"
+/// is set we use tree-sitter where we have a grammar for the file and it parses
+/// cleanly, falling back to the hand-rolled tokenizers otherwise.
+fn tokenize_for_format(format: &FormatAs, path: &str, input: &str) -> Vec<tokenize::Token> {
+    static USE_TREE_SITTER: OnceLock<bool> = OnceLock::new();
+    if *USE_TREE_SITTER.get_or_init(|| env::var("MOZSEARCH_TREE_SITTER_HIGHLIGHT").is_ok())
+        && let Some(tokens) = highlight_source(path, input)
+    {
+        return tokens;
+    }
+    tokenize_with_format(format, input)
+}
+
 /// Renders source code into a Vec of HTML-formatted lines wrapped in `FormattedLine` objects that
 /// provide the metadata for the position:sticky post-processing step.  Caller is responsible
 /// for generating line numbers and any blame information.
@@ -60,17 +92,7 @@ pub fn format_code(
     input: &str,
     analysis: &[WithLocation<Vec<AnalysisSource>>],
 ) -> (Vec<FormattedLine>, String) {
-    let tokens = match format {
-        FormatAs::Binary => panic!("Unexpected binary file"),
-        FormatAs::CSS => tokenize::tokenize_css(input),
-        FormatAs::Plain => tokenize::tokenize_plain(input),
-        FormatAs::YAML => tokenize::tokenize_yaml(input),
-        FormatAs::FormatCLike(spec) => tokenize::tokenize_c_like(input, spec),
-        FormatAs::FormatXPIDL(spec, cdata_spec) => {
-            tokenize::tokenize_xpidl(input, spec, cdata_spec)
-        }
-        FormatAs::FormatTagLike(script_spec) => tokenize::tokenize_tag_like(input, script_spec),
-    };
+    let tokens = tokenize_for_format(&format, path, input);
 
     let mut output_lines = Vec::new();
     let mut output = String::new();
@@ -404,19 +426,7 @@ pub fn format_code(
         let expansion_to_html = |key: &str, platform: &str, input: &str| {
             let mut html = String::new();
 
-            let tokens = match format {
-                FormatAs::Binary => panic!("Unexpected binary file"),
-                FormatAs::CSS => tokenize::tokenize_css(input),
-                FormatAs::Plain => tokenize::tokenize_plain(input),
-                FormatAs::YAML => tokenize::tokenize_yaml(input),
-                FormatAs::FormatCLike(spec) => tokenize::tokenize_c_like(input, spec),
-                FormatAs::FormatXPIDL(spec, cdata_spec) => {
-                    tokenize::tokenize_xpidl(input, spec, cdata_spec)
-                }
-                FormatAs::FormatTagLike(script_spec) => {
-                    tokenize::tokenize_tag_like(input, script_spec)
-                }
-            };
+            let tokens = tokenize_for_format(&format, path, input);
 
             let datum_in_expansion: HashMap<_, _> = datum
                 .iter()
@@ -627,6 +637,15 @@ pub fn format_file_data(
     };
 
     let slug = format_to_slug_attribute(&format);
+    let rendered_markdown = match format {
+        FormatAs::Markdown => Some(render_markdown_for_tree(
+            tree_config,
+            tree_name,
+            path,
+            &data,
+        )),
+        _ => None,
+    };
     let pre_format_code = Instant::now();
     let (output_lines, sym_json) =
         format_code(Some(cfg), jumpref_lookup, format, path, &data, analysis);
@@ -712,6 +731,10 @@ pub fn format_file_data(
         output::generate_svg_preview(writer, &url)?
     }
 
+    if let Some(ref rendered) = rendered_markdown {
+        output::generate_markdown_preview(writer, &rendered.html)?
+    }
+
     let f = F::Seq(vec![F::T(format!(
         "<div id=\"file\" class=\"file\" role=\"table\"{}>",
         slug
@@ -893,6 +916,27 @@ pub fn format_file_data(
 
     writeln!(writer, "<script>var SYM_INFO = {};</script>", sym_json,).unwrap();
 
+    // Symbols linked from the rendered Markdown won't have been seen by
+    // format_code, so add their jumprefs too.
+    if let Some(ref rendered) = rendered_markdown
+        && let Some(lookup) = jumpref_lookup
+    {
+        let mut markdown_sym_info = BTreeMap::new();
+        for sym in &rendered.symbols {
+            if let Ok(Some(jumpref)) = lookup.lookup(sym) {
+                markdown_sym_info.insert(*sym, jumpref);
+            }
+        }
+        if !markdown_sym_info.is_empty() {
+            writeln!(
+                writer,
+                "<script>Object.assign(SYM_INFO, {});</script>",
+                to_string(&json!(markdown_sym_info)).unwrap()
+            )
+            .unwrap();
+        }
+    }
+
     output::generate_footer(&opt, tree_name, path, writer).unwrap();
 
     format_perf.format_mixing_duration_us = pre_format_mixing.elapsed().as_micros() as u64;
@@ -900,6 +944,54 @@ pub fn format_file_data(
     Ok(format_perf)
 }
 
+/// The tree's identifiers file, which is only opened the first time each tree
+/// renders a Markdown document.
+fn markdown_ident_map(tree_config: &TreeConfig) -> Option<IdentMap> {
+    static IDENT_MAPS: OnceLock<Mutex<HashMap<String, Option<IdentMap>>>> = OnceLock::new();
+    let filename = format!("{}/identifiers", tree_config.paths.index_path);
+    IDENT_MAPS
+        .get_or_init(Default::default)
+        .lock()
+        .unwrap()
+        .entry(filename)
+        .or_insert_with_key(|filename| IdentMap::new(filename))
+        .clone()
+}
+
+/// Render a Markdown document, linking identifiers in code to their symbols
+/// using the tree's identifiers file.
+fn render_markdown_for_tree(
+    tree_config: &TreeConfig,
+    tree_name: &str,
+    path: &str,
+    data: &str,
+) -> RenderedMarkdown {
+    let ident_map = markdown_ident_map(tree_config);
+    // Identifiers with more symbols than this are too ambiguous to be worth
+    // linking.
+    const MAX_SYMBOLS: usize = 3;
+    let lookup_symbols = |ident: &str| -> Vec<Ustr> {
+        let Some(ref ident_map) = ident_map else {
+            return vec![];
+        };
+        let results = ident_map.lookup(ident, true, false, MAX_SYMBOLS + 1);
+        if results.len() > MAX_SYMBOLS {
+            return vec![];
+        }
+        results.into_iter().map(|r| r.symbol).unique().collect()
+    };
+    let raw_url = |path: &str| tree_config.paths.make_raw_resource_branch_url(path);
+    render_markdown(
+        data,
+        &MarkdownContext {
+            tree_name,
+            path,
+            lookup_symbols: &lookup_symbols,
+            raw_url: &raw_url,
+        },
+    )
+}
+
 pub fn add_coverage_panel_item(
     panel: &mut Vec<PanelSection>,
     coverage_summary: Option<&code_coverage_report::NodeMetadata>,
@@ -947,6 +1039,7 @@ fn format_to_slug_attribute(format: &FormatAs) -> String {
         FormatAs::FormatTagLike(spec) => spec.markdown_slug,
         FormatAs::FormatCLike(spec) => spec.markdown_slug,
         FormatAs::FormatXPIDL(_, _) => "",
+        FormatAs::Markdown => "md",
         _ => "",
     };
 
@@ -1220,12 +1313,14 @@ fn format_blob(
 
     let analysis = Vec::new();
 
-    let hg_rev: &str = tree_config
+    // Only hg trees care about the hg revision, so don't bother mapping it
+    // otherwise.
+    let hg_rev = tree_config
         .git
         .as_ref()
-        .and_then(|git| git.hg_map.get(&commit.id()))
-        .map(|rev| rev.as_ref()) // &String to &str conversion
-        .unwrap_or("default");
+        .filter(|_| tree_config.paths.hg_root.is_some())
+        .and_then(|git| git.git_to_hg(&commit.id()))
+        .unwrap_or_else(|| "default".to_string());
 
     let encoded_path = url_encode_path(path);
 
@@ -1276,7 +1371,7 @@ fn format_blob(
     if let Some(link) =
         tree_config
             .paths
-            .make_raw_resource_rev_url(&commit.id().to_string(), hg_rev, path)
+            .make_raw_resource_rev_url(&commit.id().to_string(), &hg_rev, path)
     {
         vcs_panel_items.push(PanelItem {
             label: PanelItemLabel::Plaintext("Raw".to_owned()),
@@ -1742,14 +1837,14 @@ fn generate_commit_info(
         vec![]
     };
 
-    let hg = match git.hg_map.get(&commit.id()) {
-        Some(hg_id) => {
-            let hg_link = format!(
-                "<a href=\"{}/rev/{}\">{}</a>",
-                tree_config.paths.hg_root.as_ref().unwrap(),
-                hg_id,
-                hg_id
-            );
+    let hg_id = tree_config
+        .paths
+        .hg_root
+        .as_ref()
+        .and_then(|hg_root| Some((hg_root, git.git_to_hg(&commit.id())?)));
+    let hg = match hg_id {
+        Some((hg_root, hg_id)) => {
+            let hg_link = format!("<a href=\"{}/rev/{}\">{}</a>", hg_root, hg_id, hg_id);
             vec![F::T(format!("<tr><td>hg</td><td>{}</td></tr>", hg_link))]
         }
 
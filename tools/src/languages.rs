@@ -5,6 +5,9 @@ use std::path::Path;
 pub struct LanguageSpec {
     pub reserved_words: HashMap<String, String>,
     pub hash_comment: bool,
+    // Like shells, only treat `#` as a comment at the start of a word, so that
+    // `$#` and `${#array[@]}` aren't comments.
+    pub hash_comment_at_word_start: bool,
     // In JS, private symbols are now a thing.
     pub hash_identifier: bool,
     pub c_style_comments: bool,
@@ -12,9 +15,14 @@ pub struct LanguageSpec {
     pub regexp_literals: bool,
     pub triple_quote_literals: bool,
     pub c_preprocessor: bool,
+    // Objective-C's `@interface` and friends.
+    pub at_directives: bool,
     // Rust is mostly C-like, with a couple of differences.
     pub rust_tweaks: bool,
     pub cxx14_digit_separators: bool,
+    // Describe files using their leading `//` comments if there's no `/* */`
+    // comment, for languages where that's the idiom.
+    pub describe_line_comments: bool,
     pub markdown_slug: &'static str,
 }
 
@@ -569,6 +577,186 @@ static RESERVED_WORDS_KOTLIN: &[&str] = &[
     "it",
 ];
 
+// https://go.dev/ref/spec#Keywords plus the predeclared constants
+static RESERVED_WORDS_GO: &[&str] = &[
+    "break",
+    "case",
+    "chan",
+    "const",
+    "continue",
+    "default",
+    "defer",
+    "else",
+    "fallthrough",
+    "for",
+    "func",
+    "go",
+    "goto",
+    "if",
+    "import",
+    "interface",
+    "map",
+    "package",
+    "range",
+    "return",
+    "select",
+    "struct",
+    "switch",
+    "type",
+    "var",
+    "true",
+    "false",
+    "nil",
+    "iota",
+];
+
+// https://docs.swift.org/swift-book/documentation/the-swift-programming-language/lexicalstructure/#Keywords-and-Punctuation
+static RESERVED_WORDS_SWIFT: &[&str] = &[
+    "associatedtype",
+    "class",
+    "deinit",
+    "enum",
+    "extension",
+    "fileprivate",
+    "func",
+    "import",
+    "init",
+    "inout",
+    "internal",
+    "let",
+    "open",
+    "operator",
+    "private",
+    "precedencegroup",
+    "protocol",
+    "public",
+    "rethrows",
+    "static",
+    "struct",
+    "subscript",
+    "typealias",
+    "var",
+    "break",
+    "case",
+    "catch",
+    "continue",
+    "default",
+    "defer",
+    "do",
+    "else",
+    "fallthrough",
+    "for",
+    "guard",
+    "if",
+    "in",
+    "repeat",
+    "return",
+    "throw",
+    "switch",
+    "where",
+    "while",
+    "Any",
+    "as",
+    "await",
+    "false",
+    "is",
+    "nil",
+    "self",
+    "Self",
+    "super",
+    "throws",
+    "true",
+    "try",
+    "async",
+    "actor",
+    "convenience",
+    "dynamic",
+    "final",
+    "indirect",
+    "lazy",
+    "mutating",
+    "nonmutating",
+    "optional",
+    "override",
+    "required",
+    "some",
+    "unowned",
+    "weak",
+    "willSet",
+    "didSet",
+    "get",
+    "set",
+];
+
+// Objective-C's additions to C++ are its `@` directives, which are only
+// reserved words with the `@`, like the C preprocessor's `#` directives.
+static RESERVED_WORDS_OBJC: &[&str] = &[
+    "@interface",
+    "@implementation",
+    "@end",
+    "@protocol",
+    "@property",
+    "@synthesize",
+    "@dynamic",
+    "@selector",
+    "@encode",
+    "@autoreleasepool",
+    "@optional",
+    "@required",
+    "@class",
+    "@import",
+    "@public",
+    "@protected",
+    "@private",
+    "@package",
+    "@try",
+    "@catch",
+    "@finally",
+    "@throw",
+    "@synchronized",
+];
+
+// POSIX shell reserved words plus the bash builtins that read like them
+static RESERVED_WORDS_SHELL: &[&str] = &[
+    "if", "then", "else", "elif", "fi", "case", "esac", "for", "while", "until", "do", "done",
+    "in", "function", "select", "time", "return", "export", "local", "readonly", "declare", "set",
+    "unset", "shift", "exit", "break", "continue", "source", "trap", "eval", "exec",
+];
+
+// CMake commands are case-insensitive, but lowercase is the common style
+static RESERVED_WORDS_CMAKE: &[&str] = &[
+    "if",
+    "elseif",
+    "else",
+    "endif",
+    "foreach",
+    "endforeach",
+    "while",
+    "endwhile",
+    "function",
+    "endfunction",
+    "macro",
+    "endmacro",
+    "return",
+    "break",
+    "continue",
+    "set",
+    "unset",
+    "option",
+    "include",
+    "project",
+    "add_executable",
+    "add_library",
+    "add_subdirectory",
+    "target_link_libraries",
+    "target_include_directories",
+    "target_compile_definitions",
+    "find_package",
+    "list",
+    "string",
+    "message",
+];
+
 lazy_static! {
     static ref JS_SPEC : LanguageSpec = LanguageSpec {
         reserved_words: make_reserved(RESERVED_WORDS_JS),
@@ -649,6 +837,46 @@ lazy_static! {
         .. LanguageSpec::default()
     };
 
+    static ref OBJC_SPEC : LanguageSpec = LanguageSpec {
+        reserved_words: make_reserved(&[RESERVED_WORDS_CPP, RESERVED_WORDS_OBJC].concat()),
+        at_directives: true,
+        markdown_slug: "objc",
+        .. CPP_SPEC.clone()
+    };
+
+    static ref GO_SPEC : LanguageSpec = LanguageSpec {
+        reserved_words: make_reserved(RESERVED_WORDS_GO),
+        c_style_comments: true,
+        // Go's raw strings
+        backtick_strings: true,
+        describe_line_comments: true,
+        markdown_slug: "go",
+        .. LanguageSpec::default()
+    };
+
+    static ref SWIFT_SPEC : LanguageSpec = LanguageSpec {
+        reserved_words: make_reserved(RESERVED_WORDS_SWIFT),
+        c_style_comments: true,
+        describe_line_comments: true,
+        markdown_slug: "swift",
+        .. LanguageSpec::default()
+    };
+
+    static ref SHELL_SPEC : LanguageSpec = LanguageSpec {
+        reserved_words: make_reserved(RESERVED_WORDS_SHELL),
+        hash_comment: true,
+        hash_comment_at_word_start: true,
+        markdown_slug: "sh",
+        .. LanguageSpec::default()
+    };
+
+    static ref CMAKE_SPEC : LanguageSpec = LanguageSpec {
+        reserved_words: make_reserved(RESERVED_WORDS_CMAKE),
+        hash_comment: true,
+        markdown_slug: "cmake",
+        .. LanguageSpec::default()
+    };
+
     static ref TOML_SPEC : LanguageSpec = LanguageSpec {
         hash_comment: true,
         triple_quote_literals: true,
@@ -664,19 +892,25 @@ pub enum FormatAs {
     FormatXPIDL(&'static LanguageSpec, &'static LanguageSpec),
     CSS,
     Plain,
+    Markdown,
     YAML,
     Binary,
 }
 
 pub fn select_formatting(filename: &str) -> FormatAs {
-    let ext = match Path::new(filename).extension() {
+    let path = Path::new(filename);
+    if path.file_name().and_then(|name| name.to_str()) == Some("CMakeLists.txt") {
+        return FormatAs::FormatCLike(&CMAKE_SPEC);
+    }
+    let ext = match path.extension() {
         Some(ext) => ext.to_str().unwrap(),
         None => "",
     };
     match ext {
-        "c" | "cc" | "cpp" | "cxx" | "h" | "hh" | "hxx" | "hpp" | "inc" | "mm" | "m" | "msg" => {
+        "c" | "cc" | "cpp" | "cxx" | "h" | "hh" | "hxx" | "hpp" | "inc" | "msg" => {
             FormatAs::FormatCLike(&CPP_SPEC)
         }
+        "m" | "mm" => FormatAs::FormatCLike(&OBJC_SPEC),
         "aidl" => FormatAs::FormatCLike(&AIDL_SPEC),
         "ipdl" | "ipdlh" => FormatAs::FormatCLike(&IPDL_SPEC),
         "idl" => FormatAs::FormatXPIDL(&IDL_SPEC, &CPP_SPEC),
@@ -689,6 +923,12 @@ pub fn select_formatting(filename: &str) -> FormatAs {
         "java" => FormatAs::FormatCLike(&JAVA_SPEC),
         "kt" => FormatAs::FormatCLike(&KOTLIN_SPEC),
         "toml" => FormatAs::FormatCLike(&TOML_SPEC),
+        "go" => FormatAs::FormatCLike(&GO_SPEC),
+        "swift" => FormatAs::FormatCLike(&SWIFT_SPEC),
+        "sh" | "bash" | "zsh" => FormatAs::FormatCLike(&SHELL_SPEC),
+        "cmake" => FormatAs::FormatCLike(&CMAKE_SPEC),
+
+        "md" | "markdown" => FormatAs::Markdown,
 
         "html" | "htm" | "xhtml" | "xht" | "xml" | "xul" => FormatAs::FormatTagLike(&HTML_SPEC),
 
@@ -704,3 +944,31 @@ pub fn select_formatting(filename: &str) -> FormatAs {
         _ => FormatAs::Plain,
     }
 }
+
+/// Map the info string slug of a fenced Markdown code block to the formatting
+/// to use for its contents.
+pub fn select_formatting_for_markdown_slug(slug: &str) -> Option<FormatAs> {
+    Some(match slug.to_ascii_lowercase().as_str() {
+        "c" | "cpp" | "c++" | "cxx" => FormatAs::FormatCLike(&CPP_SPEC),
+        "objc" | "objective-c" | "objcpp" => FormatAs::FormatCLike(&OBJC_SPEC),
+        "js" | "javascript" | "ts" | "typescript" | "json" | "mjs" => {
+            FormatAs::FormatCLike(&JS_SPEC)
+        }
+        "py" | "python" => FormatAs::FormatCLike(&PYTHON_SPEC),
+        "rs" | "rust" => FormatAs::FormatCLike(&RUST_SPEC),
+        "java" => FormatAs::FormatCLike(&JAVA_SPEC),
+        "kt" | "kotlin" => FormatAs::FormatCLike(&KOTLIN_SPEC),
+        "go" => FormatAs::FormatCLike(&GO_SPEC),
+        "swift" => FormatAs::FormatCLike(&SWIFT_SPEC),
+        "sh" | "bash" | "shell" | "zsh" | "console" => FormatAs::FormatCLike(&SHELL_SPEC),
+        "cmake" => FormatAs::FormatCLike(&CMAKE_SPEC),
+        "toml" => FormatAs::FormatCLike(&TOML_SPEC),
+        "webidl" => FormatAs::FormatCLike(&WEBIDL_SPEC),
+        "idl" | "xpidl" => FormatAs::FormatXPIDL(&IDL_SPEC, &CPP_SPEC),
+        "ipdl" => FormatAs::FormatCLike(&IPDL_SPEC),
+        "html" | "xml" | "xhtml" => FormatAs::FormatTagLike(&HTML_SPEC),
+        "css" => FormatAs::CSS,
+        "yml" | "yaml" => FormatAs::YAML,
+        _ => return None,
+    })
+}
@@ -71,8 +71,12 @@ pub mod links;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod logging;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod markdown;
+#[cfg(not(target_arch = "wasm32"))]
 pub mod output;
 #[cfg(not(target_arch = "wasm32"))]
+pub mod revision_mapper;
+#[cfg(not(target_arch = "wasm32"))]
 mod symbol_graph_edge_kind;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod tokenize;
@@ -80,5 +84,7 @@ pub mod tokenize;
 pub mod url_encode_path;
 #[cfg(not(target_arch = "wasm32"))]
 pub mod url_map_handler;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod web_routes;
 
 pub mod utils;
@@ -1,4 +1,8 @@
-use std::{collections::HashMap, sync::Mutex};
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
 
 use serde_json::{Map, Value, json};
 use tokio::{
@@ -174,3 +178,95 @@ pub fn init_logging() {
         *global_opt = Some(LogGlobal { handle });
     }
 }
+
+/// Initialize logging for synchronous batch processes like
+/// `build-syntax-token-tree.rs` which don't have a tokio runtime for
+/// `init_logging()` above.  Their existing `log` crate output is forwarded into
+/// tracing, and we log at info level unless `RUST_LOG` says otherwise.
+pub fn init_build_logging() {
+    let env_filter = match std::env::var("RUST_LOG") {
+        Ok(rustlog) if !rustlog.is_empty() => EnvFilter::new(rustlog),
+        _ => EnvFilter::new("info"),
+    };
+    tracing_subscriber::fmt()
+        .with_ansi(false)
+        .with_env_filter(env_filter)
+        .init();
+}
+
+/// Machine-readable progress reporting for long-running batch processes like
+/// `build-syntax-token-tree.rs`, which should have called
+/// `init_build_logging()`.  Each report is logged at info level to the
+/// "progress" target as a single line JSON object like:
+///
+/// `{"task":"syntax","done":100,"total":5000,"rev":"abc...","elapsedSecs":20.0,"perSec":5.0,"etaSecs":980}`
+///
+/// so that it can be grepped out of the log.  `etaSecs` is null until we have
+/// a rate.
+pub struct BuildProgress {
+    task: &'static str,
+    total: usize,
+    done: usize,
+    started: Instant,
+    last_report: Option<Instant>,
+    report_interval: Duration,
+}
+
+impl BuildProgress {
+    pub fn new(task: &'static str, total: usize) -> Self {
+        BuildProgress {
+            task,
+            total,
+            done: 0,
+            started: Instant::now(),
+            last_report: None,
+            report_interval: Duration::from_secs(10),
+        }
+    }
+
+    /// Note that another unit of work completed, reporting progress if we
+    /// haven't reported recently or if this was the last unit of work.
+    pub fn advance(&mut self, rev: &str) {
+        self.done += 1;
+        let now = Instant::now();
+        let due = match self.last_report {
+            Some(last) => now.duration_since(last) >= self.report_interval,
+            None => true,
+        };
+        if due || self.done == self.total {
+            self.last_report = Some(now);
+            info!(target: "progress", "{}", self.to_json(rev, now));
+        }
+    }
+
+    fn to_json(&self, rev: &str, now: Instant) -> Value {
+        let elapsed = now.duration_since(self.started).as_secs_f64();
+        let (per_sec, eta) = progress_rate(self.done, self.total, elapsed);
+        json!({
+            "task": self.task,
+            "done": self.done,
+            "total": self.total,
+            "rev": rev,
+            "elapsedSecs": elapsed,
+            "perSec": per_sec,
+            "etaSecs": eta,
+        })
+    }
+}
+
+/// Compute the rate in units per second and the estimated seconds remaining.
+fn progress_rate(done: usize, total: usize, elapsed_secs: f64) -> (f64, Option<u64>) {
+    if done == 0 || elapsed_secs <= 0.0 {
+        return (0.0, None);
+    }
+    let per_sec = done as f64 / elapsed_secs;
+    let remaining = total.saturating_sub(done) as f64;
+    (per_sec, Some((remaining / per_sec).ceil() as u64))
+}
+
+#[test]
+fn test_progress_rate() {
+    assert_eq!(progress_rate(0, 10, 5.0), (0.0, None));
+    assert_eq!(progress_rate(10, 30, 5.0), (2.0, Some(10)));
+    assert_eq!(progress_rate(30, 30, 5.0), (6.0, Some(0)));
+}
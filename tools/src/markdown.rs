@@ -0,0 +1,641 @@
+//! Renders (GitHub flavored) Markdown documentation in our trees for the
+//! rendered view of `.md` files.  Parsing is left to `pulldown-cmark`; this
+//! module only turns its events into HTML with a few searchfox specifics:
+//! - Links and images are resolved relative to the document into the tree's
+//!   `/source/` URLs.
+//! - Fenced code blocks whose info string is a slug we know (see
+//!   `select_formatting_for_markdown_slug`) are tokenized with the same
+//!   tokenizers as source listings.
+//! - Identifiers in code that the tree's `identifiers` file knows about get
+//!   `data-symbols` so that the context menu works in the rendered view.
+//!
+//! Raw HTML is escaped rather than passed through, and only `http`, `https`
+//! and `mailto` URLs are linked so that a document can't smuggle in a
+//! `javascript:` or `data:` URL.
+
+use std::collections::BTreeSet;
+
+use pulldown_cmark::{
+    Alignment, CodeBlockKind, CowStr, Event, LinkType, Options, Parser, Tag, TagEnd,
+};
+use ustr::Ustr;
+
+use crate::format::tokenize_with_format;
+use crate::languages::select_formatting_for_markdown_slug;
+use crate::tokenize::TokenKind;
+use crate::url_encode_path::url_encode_path;
+
+pub struct MarkdownContext<'a> {
+    pub tree_name: &'a str,
+    /// Tree-relative path of the document, used to resolve relative links.
+    pub path: &'a str,
+    /// Returns the symbols for an identifier, or an empty Vec if there are
+    /// none or too many to be useful.
+    pub lookup_symbols: &'a dyn Fn(&str) -> Vec<Ustr>,
+    /// Returns the URL to use for the raw contents of a tree-relative path so
+    /// that images can be displayed.
+    pub raw_url: &'a dyn Fn(&str) -> Option<String>,
+}
+
+pub struct RenderedMarkdown {
+    pub html: String,
+    /// Every symbol we emitted in a `data-symbols` attribute.
+    pub symbols: BTreeSet<Ustr>,
+}
+
+pub fn render_markdown(input: &str, ctx: &MarkdownContext) -> RenderedMarkdown {
+    let mut renderer = Renderer {
+        ctx,
+        symbols: BTreeSet::new(),
+        html: String::new(),
+        headings: vec![],
+        link_ends: vec![],
+        table_aligns: vec![],
+        table_cell: 0,
+        in_table_head: false,
+    };
+    renderer.render(Parser::new_ext(
+        input,
+        Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH,
+    ));
+    RenderedMarkdown {
+        html: renderer.html,
+        symbols: renderer.symbols,
+    }
+}
+
+fn escape_html(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+/// Returns the URL's scheme if it has one.
+fn url_scheme(url: &str) -> Option<&str> {
+    let (scheme, _) = url.split_once(':')?;
+    let mut chars = scheme.chars();
+    (chars.next()?.is_ascii_alphabetic()
+        && chars.all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.')))
+    .then_some(scheme)
+}
+
+/// Whether the URL is absolute with one of the given schemes.
+fn has_scheme(url: &str, schemes: &[&str]) -> bool {
+    url_scheme(url).is_some_and(|scheme| schemes.iter().any(|s| scheme.eq_ignore_ascii_case(s)))
+}
+
+const LINK_SCHEMES: &[&str] = &["http", "https", "mailto"];
+const IMAGE_SCHEMES: &[&str] = &["http", "https"];
+
+fn heading_id(text: &str) -> String {
+    text.chars()
+        .filter_map(|c| {
+            if c.is_alphanumeric() || c == '_' || c == '-' {
+                Some(c.to_ascii_lowercase())
+            } else if c == ' ' {
+                Some('-')
+            } else {
+                None
+            }
+        })
+        .collect()
+}
+
+/// Normalize `.` and `..` segments, returning None for paths that escape the
+/// tree root.
+fn normalize_path(path: &str) -> Option<String> {
+    let mut segments: Vec<&str> = vec![];
+    for segment in path.split('/') {
+        match segment {
+            "" | "." => {}
+            ".." => {
+                segments.pop()?;
+            }
+            _ => segments.push(segment),
+        }
+    }
+    let mut normalized = segments.join("/");
+    if path.ends_with('/') && !normalized.is_empty() {
+        normalized.push('/');
+    }
+    Some(normalized)
+}
+
+fn is_identifier_like(s: &str) -> bool {
+    s.starts_with(|c: char| c.is_alphabetic() || c == '_')
+        && s.chars()
+            .all(|c| c.is_alphanumeric() || matches!(c, '_' | ':' | '.' | '#' | '~'))
+}
+
+fn align_attr(align: Alignment) -> &'static str {
+    match align {
+        Alignment::None => "",
+        Alignment::Left => r#" style="text-align: left""#,
+        Alignment::Center => r#" style="text-align: center""#,
+        Alignment::Right => r#" style="text-align: right""#,
+    }
+}
+
+/// Collect the plain text of the events up to the end of the current tag, for
+/// image alt text.
+fn collect_text<'e>(events: &mut impl Iterator<Item = Event<'e>>) -> String {
+    let mut text = String::new();
+    let mut depth = 0;
+    for event in events {
+        match event {
+            Event::Start(_) => depth += 1,
+            Event::End(_) if depth == 0 => break,
+            Event::End(_) => depth -= 1,
+            Event::Text(s) | Event::Code(s) | Event::InlineHtml(s) | Event::Html(s) => {
+                text.push_str(&s)
+            }
+            Event::SoftBreak | Event::HardBreak => text.push(' '),
+            _ => {}
+        }
+    }
+    text
+}
+
+struct Renderer<'a> {
+    ctx: &'a MarkdownContext<'a>,
+    symbols: BTreeSet<Ustr>,
+    html: String,
+    /// For each open heading, where its contents start in `html` and the
+    /// plain text seen so far, from which the heading's id is derived.
+    headings: Vec<(usize, String)>,
+    /// What to close each open link with, which depends on whether we let the
+    /// link through.
+    link_ends: Vec<&'static str>,
+    table_aligns: Vec<Alignment>,
+    table_cell: usize,
+    in_table_head: bool,
+}
+
+impl Renderer<'_> {
+    /// Block level tags start on their own line.
+    fn start_block(&mut self) {
+        if !self.html.is_empty() && !self.html.ends_with('\n') {
+            self.html.push('\n');
+        }
+    }
+
+    fn push_text(&mut self, text: &str) {
+        if let Some((_, heading_text)) = self.headings.last_mut() {
+            heading_text.push_str(text);
+        }
+        self.html.push_str(&escape_html(text));
+    }
+
+    fn render<'e>(&mut self, mut events: impl Iterator<Item = Event<'e>>) {
+        while let Some(event) = events.next() {
+            match event {
+                Event::Start(Tag::CodeBlock(kind)) => {
+                    let slug = match kind {
+                        CodeBlockKind::Fenced(ref info) => {
+                            info.split_whitespace().next().unwrap_or("")
+                        }
+                        CodeBlockKind::Indented => "",
+                    };
+                    let mut code = String::new();
+                    for event in events.by_ref() {
+                        match event {
+                            Event::Text(s) => code.push_str(&s),
+                            Event::End(TagEnd::CodeBlock) => break,
+                            _ => {}
+                        }
+                    }
+                    if code.ends_with('\n') {
+                        code.pop();
+                    }
+                    self.start_block();
+                    self.render_code_block(slug, &code);
+                }
+                Event::Start(Tag::Image {
+                    dest_url, title, ..
+                }) => {
+                    let alt = collect_text(&mut events);
+                    self.render_image(&dest_url, &title, &alt);
+                }
+                Event::Start(tag) => self.start_tag(tag),
+                Event::End(tag) => self.end_tag(tag),
+                Event::Text(text) => self.push_text(&text),
+                Event::Code(code) => {
+                    if let Some((_, heading_text)) = self.headings.last_mut() {
+                        heading_text.push_str(&code);
+                    }
+                    let symbols = if is_identifier_like(&code) {
+                        self.symbols_attr(&code)
+                    } else {
+                        None
+                    };
+                    self.html.push_str(&format!(
+                        "<code{}>{}</code>",
+                        symbols.unwrap_or_default(),
+                        escape_html(&code)
+                    ));
+                }
+                // Raw HTML is shown as text.
+                Event::Html(html) | Event::InlineHtml(html) => self.push_text(&html),
+                Event::SoftBreak => self.html.push('\n'),
+                Event::HardBreak => self.html.push_str("<br>\n"),
+                Event::Rule => {
+                    self.start_block();
+                    self.html.push_str("<hr>\n");
+                }
+                // We don't enable the extensions that produce these.
+                Event::InlineMath(text)
+                | Event::DisplayMath(text)
+                | Event::FootnoteReference(text) => self.push_text(&text),
+                Event::TaskListMarker(_) => {}
+            }
+        }
+    }
+
+    fn start_tag(&mut self, tag: Tag) {
+        match tag {
+            Tag::Paragraph | Tag::HtmlBlock => {
+                self.start_block();
+                self.html.push_str("<p>");
+            }
+            Tag::Heading { .. } => {
+                self.start_block();
+                self.headings.push((self.html.len(), String::new()));
+            }
+            Tag::BlockQuote(_) => {
+                self.start_block();
+                self.html.push_str("<blockquote>\n");
+            }
+            Tag::List(Some(1)) => {
+                self.start_block();
+                self.html.push_str("<ol>\n");
+            }
+            Tag::List(Some(start)) => {
+                self.start_block();
+                self.html.push_str(&format!("<ol start=\"{}\">\n", start));
+            }
+            Tag::List(None) => {
+                self.start_block();
+                self.html.push_str("<ul>\n");
+            }
+            Tag::Item => {
+                self.start_block();
+                self.html.push_str("<li>");
+            }
+            Tag::Table(aligns) => {
+                self.start_block();
+                self.table_aligns = aligns;
+                self.html.push_str("<table>\n");
+            }
+            Tag::TableHead => {
+                self.in_table_head = true;
+                self.table_cell = 0;
+                self.html.push_str("<thead>\n<tr>");
+            }
+            Tag::TableRow => {
+                self.table_cell = 0;
+                self.html.push_str("<tr>");
+            }
+            Tag::TableCell => {
+                let align = self
+                    .table_aligns
+                    .get(self.table_cell)
+                    .copied()
+                    .unwrap_or(Alignment::None);
+                let cell = if self.in_table_head { "th" } else { "td" };
+                self.html
+                    .push_str(&format!("<{}{}>", cell, align_attr(align)));
+            }
+            Tag::Emphasis => self.html.push_str("<em>"),
+            Tag::Strong => self.html.push_str("<strong>"),
+            Tag::Strikethrough => self.html.push_str("<del>"),
+            Tag::Link {
+                link_type,
+                dest_url,
+                ..
+            } => {
+                let url = match link_type {
+                    LinkType::Email => Some(format!("mailto:{}", dest_url)),
+                    _ => self.resolve_link(&dest_url).map(|(url, _)| url),
+                };
+                match (url, link_type) {
+                    (Some(url), _) => {
+                        self.html
+                            .push_str(&format!(r#"<a href="{}">"#, escape_html(&url)));
+                        self.link_ends.push("</a>");
+                    }
+                    // An autolink we won't link is left as the text it was.
+                    (None, LinkType::Autolink) => {
+                        self.html.push_str("&lt;");
+                        self.link_ends.push("&gt;");
+                    }
+                    (None, _) => self.link_ends.push(""),
+                }
+            }
+            // Handled in `render`, or by extensions we don't enable.
+            Tag::CodeBlock(_)
+            | Tag::Image { .. }
+            | Tag::FootnoteDefinition(_)
+            | Tag::DefinitionList
+            | Tag::DefinitionListTitle
+            | Tag::DefinitionListDefinition
+            | Tag::Superscript
+            | Tag::Subscript
+            | Tag::MetadataBlock(_) => {}
+        }
+    }
+
+    fn end_tag(&mut self, tag: TagEnd) {
+        match tag {
+            TagEnd::Paragraph | TagEnd::HtmlBlock => {
+                if self.html.ends_with('\n') {
+                    self.html.pop();
+                }
+                self.html.push_str("</p>\n");
+            }
+            TagEnd::Heading(level) => {
+                let (start, text) = self.headings.pop().unwrap_or_default();
+                let contents = self.html.split_off(start);
+                self.html.push_str(&format!(
+                    "<h{0} id=\"{1}\">{2}</h{0}>\n",
+                    level as usize,
+                    escape_html(&heading_id(&text)),
+                    contents
+                ));
+            }
+            TagEnd::BlockQuote(_) => {
+                self.start_block();
+                self.html.push_str("</blockquote>\n");
+            }
+            TagEnd::List(ordered) => {
+                self.start_block();
+                self.html
+                    .push_str(if ordered { "</ol>\n" } else { "</ul>\n" });
+            }
+            TagEnd::Item => {
+                if self.html.ends_with('\n') {
+                    self.html.pop();
+                }
+                self.html.push_str("</li>\n");
+            }
+            TagEnd::Table => self.html.push_str("</tbody>\n</table>\n"),
+            TagEnd::TableHead => {
+                self.in_table_head = false;
+                self.html.push_str("</tr>\n</thead>\n<tbody>\n");
+            }
+            TagEnd::TableRow => self.html.push_str("</tr>\n"),
+            TagEnd::TableCell => {
+                self.html
+                    .push_str(if self.in_table_head { "</th>" } else { "</td>" });
+                self.table_cell += 1;
+            }
+            TagEnd::Emphasis => self.html.push_str("</em>"),
+            TagEnd::Strong => self.html.push_str("</strong>"),
+            TagEnd::Strikethrough => self.html.push_str("</del>"),
+            TagEnd::Link => {
+                let end = self.link_ends.pop().unwrap_or_default();
+                self.html.push_str(end);
+            }
+            TagEnd::CodeBlock
+            | TagEnd::Image
+            | TagEnd::FootnoteDefinition
+            | TagEnd::DefinitionList
+            | TagEnd::DefinitionListTitle
+            | TagEnd::DefinitionListDefinition
+            | TagEnd::Superscript
+            | TagEnd::Subscript
+            | TagEnd::MetadataBlock(_) => {}
+        }
+    }
+
+    /// Returns the `data-symbols` attribute for the identifier if it's one we
+    /// know about.
+    fn symbols_attr(&mut self, ident: &str) -> Option<String> {
+        let syms = (self.ctx.lookup_symbols)(ident);
+        if syms.is_empty() {
+            return None;
+        }
+        let joined = syms
+            .iter()
+            .map(|s| s.as_str())
+            .collect::<Vec<_>>()
+            .join(",");
+        self.symbols.extend(syms);
+        Some(format!(r#" data-symbols="{}""#, escape_html(&joined)))
+    }
+
+    fn render_image(&mut self, dest: &str, title: &CowStr, alt: &str) {
+        let resolved = self.resolve_link(dest);
+        let src = match &resolved {
+            Some((_, Some(tree_path))) => (self.ctx.raw_url)(tree_path),
+            Some((url, None)) if has_scheme(url, IMAGE_SCHEMES) => Some(url.clone()),
+            _ => None,
+        };
+        let title = if title.is_empty() {
+            String::new()
+        } else {
+            format!(r#" title="{}""#, escape_html(title))
+        };
+        match (resolved, src) {
+            (Some((url, _)), Some(src)) => self.html.push_str(&format!(
+                r#"<a href="{}"><img src="{}" alt="{}"{}></a>"#,
+                escape_html(&url),
+                escape_html(&src),
+                escape_html(alt),
+                title
+            )),
+            (Some((url, _)), None) => self.html.push_str(&format!(
+                r#"<a href="{}">{}</a>"#,
+                escape_html(&url),
+                escape_html(alt)
+            )),
+            (None, _) => self.push_text(alt),
+        }
+    }
+
+    fn render_code_block(&mut self, slug: &str, code: &str) {
+        self.html.push_str(r#"<pre class="markdown-code""#);
+        if !slug.is_empty() {
+            self.html
+                .push_str(&format!(r#" data-markdown-slug="{}""#, escape_html(slug)));
+        }
+        self.html.push_str("><code>");
+
+        match select_formatting_for_markdown_slug(slug) {
+            Some(format) => {
+                let mut last = 0;
+                for token in tokenize_with_format(&format, code) {
+                    self.html.push_str(&escape_html(&code[last..token.start]));
+                    let text = &code[token.start..token.end];
+                    let class = match token.kind {
+                        TokenKind::Identifier(Some(ref style)) => Some(style.clone()),
+                        TokenKind::StringLiteral => Some(r#"class="syn_string" "#.to_string()),
+                        TokenKind::Comment => Some(r#"class="syn_comment" "#.to_string()),
+                        TokenKind::RegularExpressionLiteral => {
+                            Some(r#"class="syn_regex" "#.to_string())
+                        }
+                        TokenKind::TagName | TokenKind::TagAttrName | TokenKind::EndTagName => {
+                            Some(r#"class="syn_tag" "#.to_string())
+                        }
+                        _ => None,
+                    };
+                    let symbols = match token.kind {
+                        TokenKind::Identifier(None) if text.len() >= 3 => self.symbols_attr(text),
+                        _ => None,
+                    };
+                    if class.is_some() || symbols.is_some() {
+                        self.html.push_str(&format!(
+                            "<span {}{}>{}</span>",
+                            class.unwrap_or_default(),
+                            symbols.unwrap_or_default().trim_start(),
+                            escape_html(text)
+                        ));
+                    } else {
+                        self.html.push_str(&escape_html(text));
+                    }
+                    last = token.end;
+                }
+                self.html.push_str(&escape_html(&code[last..]));
+            }
+            None => self.html.push_str(&escape_html(code)),
+        }
+
+        self.html.push_str("</code></pre>\n");
+    }
+
+    /// Resolve a link destination into the tree, returning the URL and, for
+    /// links into the tree, the tree-relative path.  Returns None for absolute
+    /// URLs with schemes we don't allow.
+    fn resolve_link(&self, dest: &str) -> Option<(String, Option<String>)> {
+        if dest.starts_with('#') || has_scheme(dest, LINK_SCHEMES) {
+            return Some((dest.to_string(), None));
+        }
+        if url_scheme(dest).is_some() {
+            return None;
+        }
+        let (path, fragment) = match dest.find(['#', '?']) {
+            Some(pos) => dest.split_at(pos),
+            None => (dest, ""),
+        };
+        let joined = match path.strip_prefix('/') {
+            Some(absolute) => absolute.to_string(),
+            None => match self.ctx.path.rsplit_once('/') {
+                Some((dir, _)) => format!("{}/{}", dir, path),
+                None => path.to_string(),
+            },
+        };
+        Some(match normalize_path(&joined) {
+            Some(normalized) => (
+                format!(
+                    "/{}/source/{}{}",
+                    self.ctx.tree_name,
+                    url_encode_path(&normalized),
+                    fragment
+                ),
+                Some(normalized),
+            ),
+            None => (dest.to_string(), None),
+        })
+    }
+}
+
+#[test]
+fn test_render_markdown() {
+    let lookup = |ident: &str| match ident {
+        "nsIFoo" => vec![ustr::ustr("#nsIFoo")],
+        _ => vec![],
+    };
+    let raw_url = |path: &str| Some(format!("https://raw.example/{}", path));
+    let ctx = MarkdownContext {
+        tree_name: "tree",
+        path: "docs/dom/index.md",
+        lookup_symbols: &lookup,
+        raw_url: &raw_url,
+    };
+    let source = "\
+Intro to `nsIFoo`
+=================
+
+See [the README](../../README.md#usage), [MDN][mdn], and *snake_case_is_fine*.
+
+- one
+- **two**
+  1. nested
+
+```cpp
+nsIFoo* foo; // hi
+```
+
+| a | b |
+|:--|--:|
+| 1 | 2 |
+
+![diagram](img/d.png)
+
+[mdn]: https://developer.mozilla.org/
+";
+    let rendered = render_markdown(source, &ctx);
+    assert_eq!(
+        rendered.html,
+        "\
+<h1 id=\"intro-to-nsifoo\">Intro to <code data-symbols=\"#nsIFoo\">nsIFoo</code></h1>
+<p>See <a href=\"/tree/source/README.md#usage\">the README</a>, <a href=\"https://developer.mozilla.org/\">MDN</a>, and <em>snake_case_is_fine</em>.</p>
+<ul>
+<li>one</li>
+<li><strong>two</strong>
+<ol>
+<li>nested</li>
+</ol></li>
+</ul>
+<pre class=\"markdown-code\" data-markdown-slug=\"cpp\"><code><span data-symbols=\"#nsIFoo\">nsIFoo</span>* foo; <span class=\"syn_comment\" >// hi</span></code></pre>
+<table>
+<thead>
+<tr><th style=\"text-align: left\">a</th><th style=\"text-align: right\">b</th></tr>
+</thead>
+<tbody>
+<tr><td style=\"text-align: left\">1</td><td style=\"text-align: right\">2</td></tr>
+</tbody>
+</table>
+<p><a href=\"/tree/source/docs/dom/img/d.png\"><img src=\"https://raw.example/docs/dom/img/d.png\" alt=\"diagram\"></a></p>
+"
+    );
+    assert_eq!(
+        rendered.symbols.into_iter().collect::<Vec<_>>(),
+        vec![ustr::ustr("#nsIFoo")]
+    );
+}
+
+#[test]
+fn test_render_markdown_unsafe_urls() {
+    let lookup = |_: &str| vec![];
+    let raw_url = |path: &str| Some(format!("https://raw.example/{}", path));
+    let ctx = MarkdownContext {
+        tree_name: "tree",
+        path: "docs/index.md",
+        lookup_symbols: &lookup,
+        raw_url: &raw_url,
+    };
+    let render = |source: &str| render_markdown(source, &ctx).html;
+
+    assert_eq!(
+        render("[x](javascript://%0aalert(1)) [y](JavaScript:alert(1))"),
+        "<p>x y</p>\n"
+    );
+    assert_eq!(render("[x][bad]\n\n[bad]: data:text/html,hi"), "<p>x</p>\n");
+    assert_eq!(render("![alt](vbscript:msgbox)"), "<p>alt</p>\n");
+    assert_eq!(
+        render("<javascript://%0aalert(1)>"),
+        "<p>&lt;javascript://%0aalert(1)&gt;</p>\n"
+    );
+    assert_eq!(
+        render("[a](HTTPS://example.com/) <mailto:a@example.com> ![i](http://example.com/i.png)"),
+        "<p><a href=\"HTTPS://example.com/\">a</a> \
+         <a href=\"mailto:a@example.com\">mailto:a@example.com</a> \
+         <a href=\"http://example.com/i.png\"><img src=\"http://example.com/i.png\" alt=\"i\"></a></p>\n"
+    );
+    // A mailto link isn't an image source.
+    assert_eq!(
+        render("![m](mailto:a@example.com)"),
+        "<p><a href=\"mailto:a@example.com\">m</a></p>\n"
+    );
+}
@@ -564,3 +564,19 @@ pub fn generate_svg_preview(writer: &mut dyn Write, url: &str) -> Result<(), &'s
     generate_formatted(writer, &f, 0)?;
     Ok(())
 }
+
+pub fn generate_markdown_preview(writer: &mut dyn Write, html: &str) -> Result<(), &'static str> {
+    let f = F::Seq(vec![
+        F::S(r#"<details class="markdown-preview" open>"#),
+        F::Indent(vec![
+            F::S("<summary>Rendered Markdown</summary>"),
+            F::S(r#"<div class="markdown-body">"#),
+            F::T(html.to_string()),
+            F::S("</div>"),
+        ]),
+        F::S("</details>"),
+    ]);
+
+    generate_formatted(writer, &f, 0)?;
+    Ok(())
+}
@@ -0,0 +1,432 @@
+//! Mapping between git revisions and the hg revisions they correspond to.
+//!
+//! Historically everything that needed this shelled out to git-cinnabar, which
+//! means that trees on machines without cinnabar installed could not offer hg
+//! permalinks.  The `RevisionMapper` trait lets callers not care where the
+//! mapping comes from:
+//! - `CinnabarMapper` asks `git cinnabar git2hg/hg2git --batch`.
+//! - `FlatMapper` holds an in-memory map, loaded from a mapping file where each
+//!   line looks like `<git rev> <hg rev>` (the same layout as build-blame's
+//!   `--old-revision-map`), or built from pairs we already have in hand like
+//!   the ones recorded in blame commits.
+//! - `SortedMapMapper` bisects a precomputed on-disk map written by
+//!   `SortedMapMapper::write` so that huge maps don't need to be loaded.  The
+//!   `build-revision-map` tool converts a mapping file into one.
+
+use std::collections::{BTreeMap, HashMap};
+use std::fs::{self, File};
+use std::io::{BufRead, BufReader, BufWriter, Write};
+use std::ops::Bound;
+use std::path::{Path, PathBuf};
+use std::process::{Child, Command, Stdio};
+use std::sync::Mutex;
+
+use git2::Oid;
+use memmap::Mmap;
+
+pub trait RevisionMapper: Send + Sync {
+    /// Map a git revision to its full hg revision.
+    fn git_to_hg(&self, git_rev: Oid) -> Option<String>;
+
+    /// Map a full or abbreviated hg revision to its git revision.
+    fn hg_to_git(&self, hg_rev: &str) -> Option<Oid>;
+}
+
+/// Open a mapping given a path that is either a `SortedMapMapper` directory or
+/// a `FlatMapper` mapping file.
+pub fn open_revision_map(path: &str) -> Result<Box<dyn RevisionMapper>, String> {
+    if Path::new(path).is_dir() {
+        Ok(Box::new(SortedMapMapper::open(path)?))
+    } else {
+        Ok(Box::new(FlatMapper::load(path)?))
+    }
+}
+
+/// Cinnabar reports unknown revisions as all zeroes.
+fn non_null_rev(rev: &str) -> Option<&str> {
+    let rev = rev.trim();
+    if rev.is_empty() || rev.chars().all(|c| c == '0') {
+        return None;
+    }
+    Some(rev)
+}
+
+/// Uses long-lived `git cinnabar ... --batch` helpers, spawned the first time
+/// each direction is needed.  If talking to a helper fails we respawn it and
+/// retry once, and if that fails too we log an error and report the revision
+/// as unmapped.  We must not panic here because callers hold the helper lock
+/// and a poisoned lock would break every later hg lookup.
+pub struct CinnabarMapper {
+    git_path: PathBuf,
+    git2hg: Mutex<Option<Child>>,
+    hg2git: Mutex<Option<Child>>,
+}
+
+impl CinnabarMapper {
+    pub fn new(git_path: &Path) -> Self {
+        CinnabarMapper {
+            git_path: git_path.to_owned(),
+            git2hg: Mutex::new(None),
+            hg2git: Mutex::new(None),
+        }
+    }
+
+    fn spawn_helper(&self, subcommand: &str) -> std::io::Result<Child> {
+        Command::new("git")
+            .arg("cinnabar")
+            .arg(subcommand)
+            .arg("--batch")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .current_dir(&self.git_path)
+            .spawn()
+    }
+
+    fn query_helper(child: &mut Child, rev: &str) -> std::io::Result<String> {
+        let stdin = child.stdin.as_mut().ok_or(std::io::ErrorKind::BrokenPipe)?;
+        writeln!(stdin, "{}", rev)?;
+        let stdout = child
+            .stdout
+            .as_mut()
+            .ok_or(std::io::ErrorKind::BrokenPipe)?;
+        let mut result = String::new();
+        if BufReader::new(stdout).read_line(&mut result)? == 0 {
+            return Err(std::io::ErrorKind::UnexpectedEof.into());
+        }
+        Ok(result)
+    }
+
+    fn query(&self, helper: &Mutex<Option<Child>>, subcommand: &str, rev: &str) -> Option<String> {
+        // Nothing below panics, but don't let a poisoned lock take down every
+        // later lookup if that ever changes.
+        let mut guard = helper.lock().unwrap_or_else(|e| e.into_inner());
+        for attempt in 0..2 {
+            let child = match guard.as_mut() {
+                Some(child) => child,
+                None => match self.spawn_helper(subcommand) {
+                    Ok(child) => guard.insert(child),
+                    Err(e) => {
+                        error!("Unable to spawn git cinnabar {}: {}", subcommand, e);
+                        return None;
+                    }
+                },
+            };
+            match Self::query_helper(child, rev) {
+                Ok(result) => return non_null_rev(&result).map(str::to_string),
+                Err(e) => {
+                    // The helper is wedged or gone; reap it so the next attempt
+                    // gets a fresh one.
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    *guard = None;
+                    if attempt == 0 {
+                        warn!(
+                            "git cinnabar {} failed for {}, retrying: {}",
+                            subcommand, rev, e
+                        );
+                    } else {
+                        error!("git cinnabar {} failed for {}: {}", subcommand, rev, e);
+                    }
+                }
+            }
+        }
+        None
+    }
+}
+
+impl RevisionMapper for CinnabarMapper {
+    fn git_to_hg(&self, git_rev: Oid) -> Option<String> {
+        self.query(&self.git2hg, "git2hg", &git_rev.to_string())
+    }
+
+    fn hg_to_git(&self, hg_rev: &str) -> Option<Oid> {
+        let git_rev = self.query(&self.hg2git, "hg2git", hg_rev)?;
+        Oid::from_str(&git_rev).ok()
+    }
+}
+
+impl Drop for CinnabarMapper {
+    fn drop(&mut self) {
+        for helper in [&self.git2hg, &self.hg2git] {
+            if let Ok(mut guard) = helper.lock()
+                && let Some(child) = guard.as_mut()
+            {
+                let _ = child.kill();
+            }
+        }
+    }
+}
+
+/// Find the git revision for the unique hg revision in `hg2git` starting with
+/// `hg_rev`, which may be abbreviated.
+pub fn lookup_hg_prefix(hg2git: &BTreeMap<String, Oid>, hg_rev: &str) -> Option<Oid> {
+    if hg_rev.is_empty() {
+        return None;
+    }
+    let mut matches = hg2git
+        .range::<str, _>((Bound::Included(hg_rev), Bound::Unbounded))
+        .take_while(|(full, _)| full.starts_with(hg_rev));
+    match (matches.next(), matches.next()) {
+        (Some((_, git_rev)), None) => Some(*git_rev),
+        // An exact match wins even if it's also a prefix of something else.
+        (Some((full, git_rev)), Some(_)) if full == hg_rev => Some(*git_rev),
+        _ => None,
+    }
+}
+
+/// Parse the `<git rev> <hg rev>` lines of a mapping file, skipping any that
+/// are malformed.
+pub fn parse_mapping_pairs(contents: &str) -> impl Iterator<Item = (Oid, String)> + '_ {
+    contents.lines().filter_map(|line| {
+        let (git_rev, hg_rev) = line.trim().split_once(' ')?;
+        Some((Oid::from_str(git_rev).ok()?, hg_rev.trim().to_string()))
+    })
+}
+
+#[derive(Default)]
+pub struct FlatMapper {
+    git2hg: HashMap<Oid, String>,
+    hg2git: BTreeMap<String, Oid>,
+}
+
+impl FlatMapper {
+    pub fn from_pairs(pairs: impl IntoIterator<Item = (Oid, String)>) -> Self {
+        let mut mapper = FlatMapper::default();
+        for (git_rev, hg_rev) in pairs {
+            mapper.hg2git.insert(hg_rev.clone(), git_rev);
+            mapper.git2hg.insert(git_rev, hg_rev);
+        }
+        mapper
+    }
+
+    pub fn parse(contents: &str) -> Self {
+        FlatMapper::from_pairs(parse_mapping_pairs(contents))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let contents = fs::read_to_string(path)
+            .map_err(|e| format!("Unable to read revision map {}: {}", path, e))?;
+        Ok(FlatMapper::parse(&contents))
+    }
+
+    pub fn len(&self) -> usize {
+        self.git2hg.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.git2hg.is_empty()
+    }
+}
+
+impl RevisionMapper for FlatMapper {
+    fn git_to_hg(&self, git_rev: Oid) -> Option<String> {
+        self.git2hg.get(&git_rev).cloned()
+    }
+
+    fn hg_to_git(&self, hg_rev: &str) -> Option<Oid> {
+        lookup_hg_prefix(&self.hg2git, hg_rev)
+    }
+}
+
+/// Each direction of the map is a file of fixed-width `<key> <value>\n` records
+/// sorted by key, where both are 40 character lowercase hex revisions.
+const SORTED_REV_LEN: usize = 40;
+const SORTED_RECORD_LEN: usize = SORTED_REV_LEN * 2 + 2;
+const SORTED_GIT2HG_NAME: &str = "git2hg";
+const SORTED_HG2GIT_NAME: &str = "hg2git";
+
+pub struct SortedMapMapper {
+    git2hg: Mmap,
+    hg2git: Mmap,
+}
+
+fn open_sorted_map_file(path: &Path) -> Result<Mmap, String> {
+    let file = File::open(path).map_err(|e| format!("Unable to open {}: {}", path.display(), e))?;
+    let mm = unsafe { Mmap::map(&file) }
+        .map_err(|e| format!("Unable to map {}: {}", path.display(), e))?;
+    if mm.len() % SORTED_RECORD_LEN != 0 {
+        return Err(format!("Corrupt sorted revision map {}", path.display()));
+    }
+    Ok(mm)
+}
+
+/// Find the value for the unique key starting with `prefix`.
+fn bisect_sorted_map<'a>(mm: &'a [u8], prefix: &str) -> Option<&'a str> {
+    let prefix = prefix.to_ascii_lowercase();
+    let prefix = prefix.as_bytes();
+    if prefix.is_empty() || prefix.len() > SORTED_REV_LEN {
+        return None;
+    }
+    let record = |i: usize| &mm[i * SORTED_RECORD_LEN..(i + 1) * SORTED_RECORD_LEN];
+    let key = |i: usize| &record(i)[..SORTED_REV_LEN];
+
+    let count = mm.len() / SORTED_RECORD_LEN;
+    // Find the first key that is >= the prefix.
+    let (mut lo, mut hi) = (0, count);
+    while lo < hi {
+        let mid = (lo + hi) / 2;
+        if key(mid) < prefix {
+            lo = mid + 1;
+        } else {
+            hi = mid;
+        }
+    }
+    if lo >= count || !key(lo).starts_with(prefix) {
+        return None;
+    }
+    if prefix.len() < SORTED_REV_LEN && lo + 1 < count && key(lo + 1).starts_with(prefix) {
+        return None;
+    }
+    std::str::from_utf8(&record(lo)[SORTED_REV_LEN + 1..SORTED_RECORD_LEN - 1]).ok()
+}
+
+impl SortedMapMapper {
+    pub fn open(dir: &str) -> Result<Self, String> {
+        let dir = Path::new(dir);
+        Ok(SortedMapMapper {
+            git2hg: open_sorted_map_file(&dir.join(SORTED_GIT2HG_NAME))?,
+            hg2git: open_sorted_map_file(&dir.join(SORTED_HG2GIT_NAME))?,
+        })
+    }
+
+    /// The number of revisions in the map.
+    pub fn len(&self) -> usize {
+        self.git2hg.len() / SORTED_RECORD_LEN
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.git2hg.is_empty()
+    }
+
+    /// Write out the map for the given (git, hg) pairs into `dir`, which must
+    /// exist.  Pairs with an hg revision that isn't a full 40 character hex
+    /// revision are skipped.
+    pub fn write(dir: &str, pairs: impl IntoIterator<Item = (Oid, String)>) -> std::io::Result<()> {
+        let mut git2hg = vec![];
+        let mut hg2git = vec![];
+        for (git_rev, hg_rev) in pairs {
+            let hg_rev = hg_rev.to_ascii_lowercase();
+            if hg_rev.len() != SORTED_REV_LEN || !hg_rev.chars().all(|c| c.is_ascii_hexdigit()) {
+                continue;
+            }
+            git2hg.push(format!("{} {}\n", git_rev, hg_rev));
+            hg2git.push(format!("{} {}\n", hg_rev, git_rev));
+        }
+
+        let dir = Path::new(dir);
+        for (name, mut lines) in [(SORTED_GIT2HG_NAME, git2hg), (SORTED_HG2GIT_NAME, hg2git)] {
+            lines.sort();
+            lines.dedup();
+            let mut writer = BufWriter::new(File::create(dir.join(name))?);
+            for line in lines {
+                writer.write_all(line.as_bytes())?;
+            }
+            writer.flush()?;
+        }
+        Ok(())
+    }
+}
+
+/// Convert the mapping file at `mapping_path` into a `SortedMapMapper`
+/// directory at `dir`, creating it if needed, and return how many revisions
+/// the written map contains.  The map is read back to make sure it's usable.
+pub fn build_sorted_map(mapping_path: &str, dir: &str) -> Result<usize, String> {
+    let contents = fs::read_to_string(mapping_path)
+        .map_err(|e| format!("Unable to read revision map {}: {}", mapping_path, e))?;
+    fs::create_dir_all(dir).map_err(|e| format!("Unable to create {}: {}", dir, e))?;
+    SortedMapMapper::write(dir, parse_mapping_pairs(&contents))
+        .map_err(|e| format!("Unable to write sorted revision map {}: {}", dir, e))?;
+    let mapper = SortedMapMapper::open(dir)?;
+    Ok(mapper.len())
+}
+
+impl RevisionMapper for SortedMapMapper {
+    fn git_to_hg(&self, git_rev: Oid) -> Option<String> {
+        bisect_sorted_map(&self.git2hg, &git_rev.to_string()).map(str::to_string)
+    }
+
+    fn hg_to_git(&self, hg_rev: &str) -> Option<Oid> {
+        Oid::from_str(bisect_sorted_map(&self.hg2git, hg_rev)?).ok()
+    }
+}
+
+#[test]
+fn test_flat_and_sorted_mappers() {
+    let git_a = Oid::from_str("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+    let git_b = Oid::from_str("bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb").unwrap();
+    let hg_a = "1234567890123456789012345678901234567890";
+    let hg_b = "1234999999999999999999999999999999999999";
+
+    let flat = FlatMapper::parse(&format!("{} {}\n{} {}\n", git_a, hg_a, git_b, hg_b));
+    assert_eq!(flat.len(), 2);
+    assert_eq!(flat.git_to_hg(git_b).as_deref(), Some(hg_b));
+    assert_eq!(flat.hg_to_git(hg_a), Some(git_a));
+    assert_eq!(flat.hg_to_git("123456"), Some(git_a));
+    // Ambiguous prefix.
+    assert_eq!(flat.hg_to_git("1234"), None);
+    assert_eq!(flat.hg_to_git(""), None);
+    assert_eq!(flat.hg_to_git("ffff"), None);
+
+    let dir = std::env::temp_dir().join(format!("sorted-rev-map-{}", std::process::id()));
+    fs::create_dir_all(&dir).unwrap();
+    let dir_str = dir.to_str().unwrap();
+    SortedMapMapper::write(
+        dir_str,
+        vec![(git_b, hg_b.to_string()), (git_a, hg_a.to_string())],
+    )
+    .unwrap();
+    let sorted = open_revision_map(dir_str).unwrap();
+    assert_eq!(sorted.git_to_hg(git_a).as_deref(), Some(hg_a));
+    assert_eq!(sorted.hg_to_git(hg_b), Some(git_b));
+    assert_eq!(sorted.hg_to_git("123456"), Some(git_a));
+    assert_eq!(sorted.hg_to_git("1234"), None);
+    assert_eq!(sorted.hg_to_git("ffff"), None);
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_build_sorted_map() {
+    let git_a = "aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa";
+    let git_b = "bbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbbb";
+    let hg_a = "1234567890ABCDEF789012345678901234567890";
+
+    let dir = std::env::temp_dir().join(format!("build-rev-map-{}", std::process::id()));
+    let mapping_path =
+        std::env::temp_dir().join(format!("build-rev-map-{}.txt", std::process::id()));
+    // The second line's hg revision is abbreviated so it can't be bisected and
+    // the third line is malformed; both are skipped.
+    fs::write(
+        &mapping_path,
+        format!("{} {}\n{} 1234\nnot a mapping\n", git_a, hg_a, git_b),
+    )
+    .unwrap();
+    let dir_str = dir.join("map").to_str().unwrap().to_string();
+    assert_eq!(
+        build_sorted_map(mapping_path.to_str().unwrap(), &dir_str),
+        Ok(1)
+    );
+
+    let sorted = open_revision_map(&dir_str).unwrap();
+    let git_a = Oid::from_str(git_a).unwrap();
+    assert_eq!(
+        sorted.git_to_hg(git_a).as_deref(),
+        Some(hg_a.to_ascii_lowercase().as_str())
+    );
+    assert_eq!(sorted.hg_to_git("1234567890abcdef"), Some(git_a));
+    assert_eq!(sorted.hg_to_git("1234567890ABCDEF"), Some(git_a));
+    assert_eq!(sorted.git_to_hg(Oid::from_str(git_b).unwrap()), None);
+    fs::remove_dir_all(&dir).unwrap();
+    fs::remove_file(&mapping_path).unwrap();
+}
+
+#[test]
+fn test_cinnabar_failure_is_unmapped() {
+    // There's no cinnabar clone here (and maybe no git-cinnabar at all), so
+    // every query fails.  That must report no hg revision rather than panic,
+    // including on later queries that reuse the helper lock.
+    let mapper = CinnabarMapper::new(&std::env::temp_dir());
+    let git_rev = Oid::from_str("aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaa").unwrap();
+    assert_eq!(mapper.git_to_hg(git_rev), None);
+    assert_eq!(mapper.git_to_hg(git_rev), None);
+    assert_eq!(mapper.hg_to_git("1234567890"), None);
+}
@@ -631,7 +631,14 @@ pub fn tokenize_c_like(string: &str, spec: &LanguageSpec) -> Vec<Token> {
             });
         } else if ch == ' ' || ch == '\t' || ch == '\r' {
             // Skip it.
-        } else if ch == '#' && spec.hash_comment {
+        } else if ch == '#'
+            && spec.hash_comment
+            && (!spec.hash_comment_at_word_start
+                || string[..start]
+                    .chars()
+                    .next_back()
+                    .is_none_or(|prev| prev.is_whitespace() || "|&;()<>".contains(prev)))
+        {
             loop {
                 if peek_pos() == string.len() {
                     tokens.push(Token {
@@ -671,6 +678,20 @@ pub fn tokenize_c_like(string: &str, spec: &LanguageSpec) -> Vec<Token> {
             let word = "#".to_owned() + &string[id_start..peek_pos()];
             let class = spec.reserved_words.get(&word).cloned();
 
+            tokens.push(Token {
+                start,
+                end: peek_pos(),
+                kind: TokenKind::Identifier(class),
+            });
+            next_token_maybe_regexp_literal = false;
+        } else if ch == '@' && spec.at_directives && is_ident(peek_char()) {
+            while is_ident(peek_char()) {
+                get_char();
+            }
+
+            let word = &string[start..peek_pos()];
+            let class = spec.reserved_words.get(word).cloned();
+
             tokens.push(Token {
                 start,
                 end: peek_pos(),
@@ -2374,6 +2395,74 @@ mod tests {
         );
     }
 
+    #[test]
+    fn test_at_directives_objc() {
+        let objc_spec = match select_formatting("test.mm") {
+            FormatAs::FormatCLike(spec) => spec,
+            _ => panic!("wrong spec"),
+        };
+        let reserved = || TokenKind::Identifier(Some("class=\"syn_reserved\" ".to_string()));
+
+        check_tokens(
+            "@interface Foo : NSObject @end",
+            &[
+                ("@interface", reserved()),
+                ("Foo", TokenKind::Identifier(None)),
+                (":", TokenKind::Punctuation),
+                ("NSObject", TokenKind::Identifier(None)),
+                ("@end", reserved()),
+            ],
+            objc_spec,
+        );
+
+        // Without the `@` these are just identifiers in C++ code.
+        check_tokens(
+            "int end = interface;",
+            &[
+                ("int", reserved()),
+                ("end", TokenKind::Identifier(None)),
+                ("=", TokenKind::Punctuation),
+                ("interface", TokenKind::Identifier(None)),
+                (";", TokenKind::Punctuation),
+            ],
+            objc_spec,
+        );
+    }
+
+    #[test]
+    fn test_hash_comment_shell() {
+        let shell_spec = match select_formatting("test.sh") {
+            FormatAs::FormatCLike(spec) => spec,
+            _ => panic!("wrong spec"),
+        };
+
+        check_tokens(
+            "echo $# ${#arr} # count",
+            &[
+                ("echo", TokenKind::Identifier(None)),
+                ("$", TokenKind::Punctuation),
+                ("#", TokenKind::Punctuation),
+                ("$", TokenKind::Punctuation),
+                ("{", TokenKind::Punctuation),
+                ("#", TokenKind::Punctuation),
+                ("arr", TokenKind::Identifier(None)),
+                ("}", TokenKind::Punctuation),
+                ("# count", TokenKind::Comment),
+            ],
+            shell_spec,
+        );
+
+        check_tokens(
+            "true;# done",
+            &[
+                ("true", TokenKind::Identifier(None)),
+                (";", TokenKind::Punctuation),
+                ("# done", TokenKind::Comment),
+            ],
+            shell_spec,
+        );
+    }
+
     #[test]
     fn test_css() {
         check_css_tokens(
@@ -4,6 +4,8 @@ use std::path::Path;
 use include_dir::{Dir, include_dir};
 
 use crate::file_format::history::syntax_files_struct::FileStructureRow;
+use crate::languages::{FormatAs, select_formatting};
+use crate::tokenize::{Token, TokenKind, tokenize_c_like, tokenize_xpidl};
 
 use tree_sitter::StreamingIterator as _;
 
@@ -115,6 +117,36 @@ pub fn hypertokenize_source_file(
             vec![],
             vec![],
         ),
+        "java" => {
+            let ts_lang: tree_sitter::Language = tree_sitter_java::LANGUAGE.into();
+            let string_literal = ts_lang.id_for_node_kind("string_literal", true);
+            let char_literal = ts_lang.id_for_node_kind("character_literal", true);
+            (
+                "java",
+                ts_lang,
+                "java",
+                vec![string_literal, char_literal],
+                vec![],
+            )
+        }
+        "kt" => {
+            let ts_lang: tree_sitter::Language = tree_sitter_kotlin_ng::LANGUAGE.into();
+            let string_literal = ts_lang.id_for_node_kind("string_literal", true);
+            ("kotlin", ts_lang, "kotlin", vec![string_literal], vec![])
+        }
+        // There are no tree-sitter grammars for our IDL dialects, but they are
+        // simple enough that the tokenizers we use for formatting suffice.
+        "webidl" | "idl" | "ipdl" | "ipdlh" => {
+            let lang = if ext == "ipdlh" { "ipdl" } else { ext };
+            let tokens = match select_formatting(filename) {
+                FormatAs::FormatCLike(spec) => tokenize_c_like(source_contents, spec),
+                FormatAs::FormatXPIDL(spec, cdata_spec) => {
+                    tokenize_xpidl(source_contents, spec, cdata_spec)
+                }
+                _ => return Err(format!("No tokenizer for lang: {}", lang)),
+            };
+            return Ok(hypertokenize_idl_like(lang, source_contents, &tokens));
+        }
         // Explicitly skip things we know are binary; this list copied from "langauages.rs"
         "ogg" | "ttf" | "xpi" | "png" | "bcmap" | "gif" | "ogv" | "jpg" | "jpeg" | "bmp"
         | "icns" | "ico" | "mp4" | "sqlite" | "jar" | "webm" | "webp" | "woff" | "class"
@@ -251,15 +283,20 @@ pub fn hypertokenize_source_file(
                 });
                 id_stack.push(next_container_id);
 
-                next_container_match = query_matches.next();
-                if let Some(container_match) = &next_container_match {
-                    next_container_id = container_match
-                        .nodes_for_capture_index(container_capture_ix)
-                        .next()
-                        .unwrap()
-                        .id();
-                } else {
-                    next_container_id = usize::MAX;
+                // A container can match more than once, like a field declaration
+                // with multiple declarators, but we only want the first match.
+                let handled_container_id = next_container_id;
+                while next_container_id == handled_container_id {
+                    next_container_match = query_matches.next();
+                    if let Some(container_match) = &next_container_match {
+                        next_container_id = container_match
+                            .nodes_for_capture_index(container_capture_ix)
+                            .next()
+                            .unwrap()
+                            .id();
+                    } else {
+                        next_container_id = usize::MAX;
+                    }
                 }
             }
             let node_kind_id = node.kind_id();
@@ -299,3 +336,353 @@ pub fn hypertokenize_source_file(
         structure,
     })
 }
+
+/// Map the keywords that introduce a named braced block in our IDL dialects to
+/// the `FileStructureRow` kind we use for the equivalent C++ construct.
+fn idl_container_kind(lang: &str, keyword: &str) -> Option<&'static str> {
+    match (lang, keyword) {
+        ("webidl", "interface") | ("idl", "interface") | ("ipdl", "protocol") => Some("class"),
+        ("webidl", "dictionary") | ("ipdl", "struct") => Some("struct"),
+        ("webidl", "enum") | ("idl", "cenum") => Some("enum"),
+        ("ipdl", "union") => Some("union"),
+        ("webidl", "namespace") | ("ipdl", "namespace") => Some("namespace"),
+        _ => None,
+    }
+}
+
+/// Keywords that can sit between the container keyword and its name, as in
+/// WebIDL's `interface mixin Foo {`.
+const IDL_NAME_PREFIXES: &[&str] = &["mixin"];
+
+/// Keywords that make a member statement without parens a field, as in
+/// `readonly attribute long foo;` or `const long FOO = 1;`.
+const IDL_FIELD_KEYWORDS: &[&str] = &["attribute", "const"];
+
+/// Produce the same representation as `hypertokenize_source_file` from the
+/// output of our formatting tokenizers for WebIDL, XPIDL, and IPDL.  A
+/// container begins at its keyword like it would for a tree-sitter node, but is
+/// only committed once we see its opening brace, so forward declarations like
+/// `interface nsIFoo;` don't create structure.
+///
+/// Members directly inside a container are found per statement: the identifier
+/// before the first paren names a method, and attributes, consts, and the
+/// members of dictionaries and structs are fields named by the identifier
+/// before their `=` or `;`.  Extended attributes like `[Throws]` are skipped.
+fn hypertokenize_idl_like(lang: &str, source_contents: &str, tokens: &[Token]) -> HyperTokenized {
+    let mut tokenized = Vec::new();
+    let mut structure = Vec::new();
+
+    let empty_context = "%".to_string();
+    let mut context_stack: Vec<String> = vec![];
+    // The kind of each container in `context_stack`.
+    let mut kind_stack: Vec<&'static str> = vec![];
+    // One entry per open brace, true if the brace opened a container.
+    let mut brace_stack: Vec<bool> = vec![];
+    let context_pretty = |context_stack: &Vec<String>| {
+        if context_stack.is_empty() {
+            empty_context.clone()
+        } else {
+            context_stack.join("::")
+        }
+    };
+
+    let mut pending_kind: Option<&'static str> = None;
+    let mut pending_name: Option<String> = None;
+    // Tokens seen since the container keyword that we can't assign a context
+    // until we know whether this is a definition.
+    let mut pending_tokens: Vec<&str> = vec![];
+
+    // State for the member statement we're in, if we're directly inside a
+    // container.
+    let mut bracket_depth = 0;
+    let mut paren_depth = 0;
+    let mut member_idents: Vec<&str> = vec![];
+    let mut member_name: Option<&str> = None;
+    let mut member_is_method = false;
+
+    for token in tokens {
+        let text = source_contents[token.start..token.end].trim();
+        if text.is_empty() {
+            continue;
+        }
+        let mut pieces: Vec<&str> = match token.kind {
+            TokenKind::Comment => text.split_whitespace().collect(),
+            _ => vec![text],
+        };
+
+        let in_members = pending_kind.is_none()
+            && brace_stack.last() == Some(&true)
+            && kind_stack.last() != Some(&"enum");
+        if in_members {
+            match (&token.kind, text) {
+                (TokenKind::Punctuation, "[") => bracket_depth += 1,
+                (TokenKind::Punctuation, "]") => bracket_depth -= 1,
+                _ if bracket_depth > 0 => {}
+                (TokenKind::Punctuation, "(") => {
+                    if paren_depth == 0
+                        && !member_is_method
+                        && member_name.is_none()
+                        && let Some(name) = member_idents.last()
+                    {
+                        member_is_method = true;
+                        context_stack.push(name.to_string());
+                        structure.push(FileStructureRow {
+                            pretty: context_pretty(&context_stack),
+                            is_def: true,
+                            kind: "method".to_string(),
+                        });
+                        context_stack.pop();
+                    }
+                    paren_depth += 1;
+                }
+                (TokenKind::Punctuation, ")") => paren_depth -= 1,
+                _ if paren_depth > 0 => {}
+                (TokenKind::Identifier(_), _) => member_idents.push(text),
+                (TokenKind::Punctuation, "=") => {
+                    member_name = member_name.or(member_idents.last().copied());
+                }
+                (TokenKind::Punctuation, ";") => {
+                    let is_field = !member_is_method
+                        && (matches!(kind_stack.last(), Some(&"struct") | Some(&"union"))
+                            || member_idents
+                                .iter()
+                                .any(|ident| IDL_FIELD_KEYWORDS.contains(ident)));
+                    if is_field && let Some(name) = member_name.or(member_idents.last().copied()) {
+                        context_stack.push(name.to_string());
+                        structure.push(FileStructureRow {
+                            pretty: context_pretty(&context_stack),
+                            is_def: true,
+                            kind: "field".to_string(),
+                        });
+                        context_stack.pop();
+                    }
+                }
+                _ => {}
+            }
+        }
+        if matches!(token.kind, TokenKind::Punctuation)
+            && matches!(text, ";" | "{" | "}")
+            && paren_depth == 0
+        {
+            member_idents.clear();
+            member_name = None;
+            member_is_method = false;
+        }
+
+        match token.kind {
+            TokenKind::Identifier(_) => {
+                if pending_kind.is_some() && pending_name.is_none() {
+                    if !IDL_NAME_PREFIXES.contains(&text) {
+                        pending_name = Some(text.to_string());
+                    }
+                } else if pending_kind.is_none()
+                    && let Some(kind) = idl_container_kind(lang, text)
+                {
+                    pending_kind = Some(kind);
+                }
+            }
+            TokenKind::Punctuation if text == "{" => {
+                let is_container =
+                    if let (Some(kind), Some(name)) = (pending_kind.take(), pending_name.take()) {
+                        context_stack.push(name);
+                        kind_stack.push(kind);
+                        structure.push(FileStructureRow {
+                            pretty: context_pretty(&context_stack),
+                            is_def: true,
+                            kind: kind.to_string(),
+                        });
+                        true
+                    } else {
+                        false
+                    };
+                brace_stack.push(is_container);
+            }
+            TokenKind::Punctuation if text == ";" => {
+                pending_kind = None;
+                pending_name = None;
+            }
+            _ => {}
+        }
+
+        if pending_kind.is_some() {
+            pending_tokens.append(&mut pieces);
+            continue;
+        }
+
+        let context = context_pretty(&context_stack);
+        for piece in pending_tokens.drain(..).chain(pieces) {
+            tokenized.push(format!("{} {}", context, piece));
+        }
+
+        // The closing brace belongs to the container, so pop after emitting.
+        if matches!(token.kind, TokenKind::Punctuation)
+            && text == "}"
+            && brace_stack.pop() == Some(true)
+        {
+            context_stack.pop();
+            kind_stack.pop();
+        }
+    }
+
+    let context = context_pretty(&context_stack);
+    for piece in pending_tokens {
+        tokenized.push(format!("{} {}", context, piece));
+    }
+
+    HyperTokenized {
+        lang: lang.to_string(),
+        tokenized,
+        structure,
+    }
+}
+
+#[test]
+fn test_hypertokenize_idl_like() {
+    let source = "interface nsIBar;\n\
+                  [scriptable] interface nsIFoo : nsISupports {\n\
+                  \x20 cenum Mode : 8 { A = 0, };\n\
+                  \x20 const long LIMIT = 4;\n\
+                  \x20 [infallible] readonly attribute boolean ready;\n\
+                  \x20 void go(); // Go!\n\
+                  \x20 [noscript] void stop(in unsigned long aReason);\n\
+                  };\n";
+    let result = hypertokenize_source_file("nsIFoo.idl", source).unwrap();
+    assert_eq!(result.lang, "idl");
+    assert_eq!(
+        outline_of(&result),
+        vec![
+            ("nsIFoo", "class"),
+            ("nsIFoo::Mode", "enum"),
+            ("nsIFoo::LIMIT", "field"),
+            ("nsIFoo::ready", "field"),
+            ("nsIFoo::go", "method"),
+            ("nsIFoo::stop", "method"),
+        ]
+    );
+    assert_eq!(result.tokenized[0], "% interface");
+    assert!(result.tokenized.contains(&"nsIFoo interface".to_string()));
+    assert!(result.tokenized.contains(&"nsIFoo::Mode A".to_string()));
+    assert!(result.tokenized.contains(&"nsIFoo Go!".to_string()));
+    assert_eq!(result.tokenized.last().unwrap(), "% ;");
+}
+
+#[test]
+fn test_hypertokenize_webidl_members() {
+    let source = "[Exposed=(Window,Worker)]\n\
+                  interface Foo {\n\
+                  \x20 constructor(optional FooInit init = {});\n\
+                  \x20 [Throws] Promise<sequence<long>> fetch(DOMString url);\n\
+                  \x20 readonly attribute DOMString name;\n\
+                  };\n\
+                  dictionary FooInit {\n\
+                  \x20 required DOMString name;\n\
+                  \x20 long count = 0;\n\
+                  };\n\
+                  enum FooMode { \"a\", \"b\" };\n";
+    let result = hypertokenize_source_file("Foo.webidl", source).unwrap();
+    assert_eq!(result.lang, "webidl");
+    assert_eq!(
+        outline_of(&result),
+        vec![
+            ("Foo", "class"),
+            ("Foo::constructor", "method"),
+            ("Foo::fetch", "method"),
+            ("Foo::name", "field"),
+            ("FooInit", "struct"),
+            ("FooInit::name", "field"),
+            ("FooInit::count", "field"),
+            ("FooMode", "enum"),
+        ]
+    );
+}
+
+#[test]
+fn test_hypertokenize_ipdl_members() {
+    let source = "namespace mozilla {\n\
+                  struct FooArgs { nsCString url; uint32_t flags; };\n\
+                  protocol PFoo {\n\
+                  \x20 manager PBar;\n\
+                  parent:\n\
+                  \x20 async Start(FooArgs aArgs);\n\
+                  \x20 sync Query() returns (bool ok);\n\
+                  };\n\
+                  }\n";
+    let result = hypertokenize_source_file("PFoo.ipdl", source).unwrap();
+    assert_eq!(result.lang, "ipdl");
+    assert_eq!(
+        outline_of(&result),
+        vec![
+            ("mozilla", "namespace"),
+            ("mozilla::FooArgs", "struct"),
+            ("mozilla::FooArgs::url", "field"),
+            ("mozilla::FooArgs::flags", "field"),
+            ("mozilla::PFoo", "class"),
+            ("mozilla::PFoo::Start", "method"),
+            ("mozilla::PFoo::Query", "method"),
+        ]
+    );
+}
+
+#[cfg(test)]
+fn outline_of(result: &HyperTokenized) -> Vec<(&str, &str)> {
+    result
+        .structure
+        .iter()
+        .map(|row| (row.pretty.as_str(), row.kind.as_str()))
+        .collect()
+}
+
+#[test]
+fn test_hypertokenize_java() {
+    let source = "package org.example;\n\
+                  public class Foo {\n\
+                  \x20 int a, b;\n\
+                  \x20 Foo() {}\n\
+                  \x20 enum Mode { A, B }\n\
+                  \x20 interface Listener { void onThing(); }\n\
+                  \x20 void run() { int local = 1; }\n\
+                  }\n";
+    let result = hypertokenize_source_file("Foo.java", source).unwrap();
+    assert_eq!(result.lang, "java");
+    assert_eq!(
+        outline_of(&result),
+        vec![
+            ("Foo", "class"),
+            ("Foo::a", "field"),
+            ("Foo::Foo", "method"),
+            ("Foo::Mode", "enum"),
+            ("Foo::Listener", "class"),
+            ("Foo::Listener::onThing", "method"),
+            ("Foo::run", "method"),
+        ]
+    );
+    assert!(result.tokenized.contains(&"Foo::run local".to_string()));
+}
+
+#[test]
+fn test_hypertokenize_kotlin() {
+    let source = "package org.example\n\
+                  class Foo {\n\
+                  \x20 fun run() { val local = 1 }\n\
+                  }\n\
+                  object Registry {\n\
+                  \x20 fun register(foo: Foo) {}\n\
+                  }\n";
+    let result = hypertokenize_source_file("Foo.kt", source).unwrap();
+    assert_eq!(result.lang, "kotlin");
+    assert_eq!(
+        outline_of(&result),
+        vec![
+            ("Foo", "class"),
+            ("Foo::run", "method"),
+            ("Registry", "class"),
+            ("Registry::register", "method"),
+        ]
+    );
+    assert!(result.tokenized.contains(&"Foo::run local".to_string()));
+    assert!(
+        result
+            .tokenized
+            .contains(&"Registry::register foo".to_string())
+    );
+}
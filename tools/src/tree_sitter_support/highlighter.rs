@@ -0,0 +1,290 @@
+//! Tree-sitter based replacement for the hand-rolled tokenizers in
+//! `tokenize.rs`, producing the same `Token` stream so that `format_code` can
+//! use either.
+//!
+//! The queries in `languages/highlight_queries` only need to capture the nodes
+//! whose entire extent should be a single token:
+//! - `@comment` becomes `TokenKind::Comment`
+//! - `@string` becomes `TokenKind::StringLiteral`
+//! - `@regex` becomes `TokenKind::RegularExpressionLiteral`
+//! - `@identifier` becomes `TokenKind::Identifier(None)`, for things like Rust
+//!   lifetimes that would otherwise be split into punctuation and a name.
+//!
+//! Keywords don't need to be captured because grammars model them as anonymous
+//! nodes, so any anonymous leaf made up of word characters is a keyword.  Any
+//! other leaf is an identifier if it starts with a word character and
+//! punctuation otherwise.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::OnceLock;
+
+use include_dir::{Dir, include_dir};
+use tree_sitter::StreamingIterator as _;
+
+use crate::languages::SYN_RESERVED_CLASS;
+use crate::tokenize::{Token, TokenKind};
+
+static QUERIES_DIR: Dir = include_dir!("$CARGO_MANIFEST_DIR/languages/highlight_queries");
+
+fn highlight_language_for_filename(filename: &str) -> Option<&'static str> {
+    let ext = Path::new(filename).extension()?.to_str()?;
+    Some(match ext {
+        "c" | "cc" | "cpp" | "cxx" | "h" | "hh" | "hxx" | "hpp" => "cpp",
+        "js" | "jsm" | "mjs" | "sjs" | "jsx" => "javascript",
+        "ts" => "typescript",
+        "tsx" => "tsx",
+        "py" | "build" | "configure" => "python",
+        "rs" => "rust",
+        "java" => "java",
+        "kt" => "kotlin",
+        _ => return None,
+    })
+}
+
+/// The grammar for each highlight language, and the query file it uses.
+fn highlight_languages() -> Vec<(&'static str, tree_sitter::Language, &'static str)> {
+    vec![
+        ("cpp", tree_sitter_cpp::LANGUAGE.into(), "cpp"),
+        (
+            "javascript",
+            tree_sitter_javascript::LANGUAGE.into(),
+            "javascript",
+        ),
+        (
+            "typescript",
+            tree_sitter_typescript::LANGUAGE_TYPESCRIPT.into(),
+            "typescript",
+        ),
+        (
+            "tsx",
+            tree_sitter_typescript::LANGUAGE_TSX.into(),
+            "typescript",
+        ),
+        ("python", tree_sitter_python::LANGUAGE.into(), "python"),
+        ("rust", tree_sitter_rust::LANGUAGE.into(), "rust"),
+        ("java", tree_sitter_java::LANGUAGE.into(), "java"),
+        ("kotlin", tree_sitter_kotlin_ng::LANGUAGE.into(), "kotlin"),
+    ]
+}
+
+type LanguageAndQuery = (tree_sitter::Language, tree_sitter::Query);
+
+/// Compiling the queries is much slower than using them, so they're compiled
+/// once, the first time anything is highlighted.  A query that fails to compile
+/// is left out so its files fall back to the hand-rolled tokenizers.
+fn language_and_query(lang: &str) -> Option<&'static LanguageAndQuery> {
+    static QUERIES: OnceLock<HashMap<&'static str, LanguageAndQuery>> = OnceLock::new();
+    QUERIES
+        .get_or_init(|| {
+            let mut queries = HashMap::new();
+            for (lang, ts_lang, query_file) in highlight_languages() {
+                let Some(query_src) = QUERIES_DIR
+                    .get_file(format!("{}.scm", query_file))
+                    .and_then(|file| file.contents_utf8())
+                else {
+                    warn!("No highlight query for {}", lang);
+                    continue;
+                };
+                match tree_sitter::Query::new(&ts_lang, query_src) {
+                    Ok(query) => {
+                        queries.insert(lang, (ts_lang, query));
+                    }
+                    Err(err) => warn!("Bad highlight query for {}: {}", lang, err.message),
+                }
+            }
+            queries
+        })
+        .get(lang)
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '$'
+}
+
+/// Anonymous nodes that look like words are keywords.  Preprocessor directives
+/// like `#include` count too.
+fn is_keyword(node: &tree_sitter::Node, text: &str) -> bool {
+    let word = text.strip_prefix('#').unwrap_or(text);
+    !node.is_named() && word.starts_with(char::is_alphabetic) && word.chars().all(is_word_char)
+}
+
+fn capture_kind(name: &str) -> Option<TokenKind> {
+    match name {
+        "comment" => Some(TokenKind::Comment),
+        "string" => Some(TokenKind::StringLiteral),
+        "regex" => Some(TokenKind::RegularExpressionLiteral),
+        "identifier" => Some(TokenKind::Identifier(None)),
+        _ => None,
+    }
+}
+
+/// Tokenize the given file contents using tree-sitter, returning None if there
+/// is no grammar for the file or the parse had errors, in which case the caller
+/// should fall back to the hand-rolled tokenizers.
+pub fn highlight_source(filename: &str, input: &str) -> Option<Vec<Token>> {
+    let (ts_lang, query) = language_and_query(highlight_language_for_filename(filename)?)?;
+
+    let mut parser = tree_sitter::Parser::new();
+    parser.set_language(ts_lang).ok()?;
+    let parse_tree = parser.parse(input.as_bytes(), None)?;
+    // Error recovery can produce surprising trees and the hand-rolled
+    // tokenizers are at least predictable.
+    if parse_tree.root_node().has_error() {
+        return None;
+    }
+
+    // Gather the captures, keeping only the outermost where they overlap.
+    let mut captured: Vec<Token> = vec![];
+    let mut query_cursor = tree_sitter::QueryCursor::new();
+    let mut captures = query_cursor.captures(query, parse_tree.root_node(), input.as_bytes());
+    while let Some((query_match, capture_ix)) = captures.next() {
+        let capture = &query_match.captures[*capture_ix];
+        let Some(kind) = capture_kind(query.capture_names()[capture.index as usize]) else {
+            continue;
+        };
+        let range = capture.node.byte_range();
+        captured.push(Token {
+            start: range.start,
+            end: range.end,
+            kind,
+        });
+    }
+    captured.sort_by_key(|t| (t.start, std::cmp::Reverse(t.end)));
+    let mut outermost: Vec<Token> = vec![];
+    for token in captured {
+        if outermost.last().is_none_or(|last| token.start >= last.end) {
+            outermost.push(token);
+        }
+    }
+
+    // Walk the leaves, emitting the capture instead of any leaves it covers.
+    let mut tokens = vec![];
+    let mut pending_captures = outermost.into_iter().peekable();
+    let mut cursor = parse_tree.walk();
+    let mut visited_children = false;
+    loop {
+        if visited_children {
+            if cursor.goto_next_sibling() {
+                visited_children = false;
+            } else if !cursor.goto_parent() {
+                break;
+            }
+            continue;
+        }
+        if cursor.goto_first_child() {
+            continue;
+        }
+        visited_children = true;
+
+        let node = cursor.node();
+        let (start, end) = (node.start_byte(), node.end_byte());
+        while let Some(capture) = pending_captures.next_if(|c| c.start <= start) {
+            tokens.push(capture);
+        }
+        if start == end || tokens.last().is_some_and(|last| start < last.end) {
+            continue;
+        }
+
+        let text = &input[start..end];
+        let kind = if is_keyword(&node, text) {
+            TokenKind::Identifier(Some(SYN_RESERVED_CLASS.to_string()))
+        } else if text.starts_with(is_word_char) {
+            TokenKind::Identifier(None)
+        } else {
+            TokenKind::Punctuation
+        };
+        tokens.push(Token { start, end, kind });
+    }
+    tokens.extend(pending_captures);
+
+    Some(split_lines(input, tokens))
+}
+
+/// `format_code` expects a `TokenKind::Newline` for every newline and no token
+/// to span lines, so split multi-line tokens like block comments.
+fn split_lines(input: &str, tokens: Vec<Token>) -> Vec<Token> {
+    let mut result = vec![];
+    let mut pos = 0;
+    let push_newlines = |result: &mut Vec<Token>, from: usize, to: usize| {
+        for (offset, _) in input[from..to].match_indices('\n') {
+            result.push(Token {
+                start: from + offset,
+                end: from + offset + 1,
+                kind: TokenKind::Newline,
+            });
+        }
+    };
+
+    for token in tokens {
+        push_newlines(&mut result, pos, token.start);
+        let mut piece_start = token.start;
+        for (offset, _) in input[token.start..token.end].match_indices('\n') {
+            let newline = token.start + offset;
+            if newline > piece_start {
+                result.push(Token {
+                    start: piece_start,
+                    end: newline,
+                    kind: token.kind.clone(),
+                });
+            }
+            result.push(Token {
+                start: newline,
+                end: newline + 1,
+                kind: TokenKind::Newline,
+            });
+            piece_start = newline + 1;
+        }
+        if token.end > piece_start {
+            result.push(Token {
+                start: piece_start,
+                end: token.end,
+                kind: token.kind,
+            });
+        }
+        pos = token.end;
+    }
+    push_newlines(&mut result, pos, input.len());
+
+    result
+}
+
+#[test]
+fn test_highlight_source() {
+    let source = "/* a\n   b */\nfn f<'a>(x: &'a str) -> String {\n    format!(\"{}\", x)\n}\n";
+    let tokens = highlight_source("test.rs", source).unwrap();
+    let described: Vec<(&str, TokenKind)> = tokens
+        .into_iter()
+        .filter(|t| t.kind != TokenKind::Newline)
+        .map(|t| (&source[t.start..t.end], t.kind))
+        .take(8)
+        .collect();
+    let reserved = || TokenKind::Identifier(Some(SYN_RESERVED_CLASS.to_string()));
+    assert_eq!(
+        described,
+        vec![
+            ("/* a", TokenKind::Comment),
+            ("   b */", TokenKind::Comment),
+            ("fn", reserved()),
+            ("f", TokenKind::Identifier(None)),
+            ("<", TokenKind::Punctuation),
+            ("'a", TokenKind::Identifier(None)),
+            (">", TokenKind::Punctuation),
+            ("(", TokenKind::Punctuation),
+        ]
+    );
+
+    assert!(highlight_source("test.txt", source).is_none());
+}
+
+#[test]
+fn test_highlight_javascript_grammar() {
+    // JSX is only valid in JS, and TypeScript type annotations only in TS.
+    let jsx = "let el = <div>{x}</div>;\n";
+    assert!(highlight_source("test.js", jsx).is_some());
+    assert!(highlight_source("test.ts", jsx).is_none());
+
+    let ts = "let x: number = 1;\n";
+    assert!(highlight_source("test.js", ts).is_none());
+    assert!(highlight_source("test.ts", ts).is_some());
+}
@@ -1 +1,2 @@
 pub mod cst_tokenizer;
+pub mod highlighter;
@@ -0,0 +1,328 @@
+//! Axum routes and middleware shared by `web-server.rs` and
+//! `pipeline-server.rs`.
+//!
+//! `pipeline-server.rs` only mounts the query routes while `web-server.rs`
+//! mounts them alongside its own routes so that a local searchfox can be run
+//! with just the one server.  In both cases the caller is responsible for
+//! providing the `LocalServers` via an `Extension` layer and for layering
+//! `conditional_get` over the merged router.
+
+use std::{collections::HashMap, sync::Arc};
+
+use axum::{
+    Extension, Json, Router,
+    extract::{Path, Query, Request},
+    http::{HeaderMap, HeaderValue, Method, StatusCode, header},
+    middleware::Next,
+    response::{Html, IntoResponse, Response},
+    routing::get,
+};
+use axum_macros::debug_handler;
+use liquid::Template;
+use serde_json::{Value, json};
+use tower::limit::GlobalConcurrencyLimitLayer;
+use tracing::Instrument;
+
+use crate::{
+    abstract_server::{ErrorDetails, ErrorLayer, LocalServerMap, ServerError},
+    cmd_pipeline::{PipelineValues, builder::build_pipeline_graph},
+    logging::LoggedSpan,
+    query::chew_query::chew_query,
+    templating::builder::build_and_parse_query_results,
+};
+
+/// The per-tree servers as created by `make_all_local_servers`.
+pub type LocalServers = Arc<LocalServerMap>;
+
+struct QueryTemplates {
+    query_results: Template,
+}
+
+/// The query routes historically served by `pipeline-server.rs`.  Requests to
+/// these routes are limited to 4 in flight, independent of any other routes the
+/// router gets merged with.
+pub fn pipeline_router() -> Router {
+    let templates = Arc::new(QueryTemplates {
+        query_results: build_and_parse_query_results(),
+    });
+
+    Router::new()
+        .route("/{tree}/query/{preset}", get(handle_query))
+        .layer(Extension(templates))
+        .layer(GlobalConcurrencyLimitLayer::new(4))
+}
+
+#[debug_handler]
+async fn handle_query(
+    local_servers: Extension<LocalServers>,
+    templates: Extension<Arc<QueryTemplates>>,
+    headers: HeaderMap,
+    Path((tree, preset)): Path<(String, String)>,
+    Query(params): Query<HashMap<String, String>>,
+) -> Result<Response, ServerError> {
+    let server = match local_servers.get(&tree) {
+        Some(s) => s,
+        None => {
+            return Err(ServerError::StickyProblem(ErrorDetails {
+                layer: ErrorLayer::DataLayer,
+                message: format!("No such tree: {}", tree),
+            }));
+        }
+    };
+
+    if preset.as_str() != "default" {
+        return Err(ServerError::StickyProblem(ErrorDetails {
+            layer: ErrorLayer::DataLayer,
+            message: format!("No such preset: {}", preset),
+        }));
+    }
+
+    let maybe_log = params.contains_key("debug");
+    let logged_span: Option<LoggedSpan> = if maybe_log {
+        Some(LoggedSpan::new_logged_span("query"))
+    } else {
+        None
+    };
+
+    let query = match params.get("q") {
+        Some(q) => q,
+        None => {
+            return Err(ServerError::StickyProblem(ErrorDetails {
+                layer: ErrorLayer::BadInput,
+                message: "No 'q' parameter, no results!".to_string(),
+            }));
+        }
+    };
+
+    let graph = {
+        let _log_entered = logged_span
+            .as_ref()
+            .map(|lspan| lspan.span.clone().entered());
+
+        let pipeline_plan = chew_query(query)?;
+
+        build_pipeline_graph(server.clonify(), pipeline_plan)?
+    };
+
+    let result = match &logged_span {
+        Some(lspan) => graph.run(true).instrument(lspan.span.clone()).await?,
+        _ => graph.run(true).await?,
+    };
+
+    let make_html = !wants_json(&headers);
+
+    let logs = match logged_span {
+        Some(lspan) => lspan.retrieve_serde_json().await,
+        _ => Value::Null,
+    };
+
+    // There are a bunch of ways to return headers to axum; this is the most
+    // legible I found.
+    let mut header_map = HeaderMap::new();
+    header_map.insert(header::VARY, "Accept".parse().unwrap());
+
+    if make_html {
+        let sym_info_str = match &result {
+            PipelineValues::GraphResultsBundle(grb) => {
+                serde_json::to_string(&grb.symbols).unwrap_or_else(|_| "{}".to_string())
+            }
+            PipelineValues::GraphInput(graphs) => {
+                serde_json::to_string(&graphs.symbols).unwrap_or_else(|_| "{}".to_string())
+            }
+            PipelineValues::SymbolTreeTableList(sttl) => {
+                serde_json::to_string(&sttl.unioned_node_sets_as_jumprefs())
+                    .unwrap_or_else(|_| "{}".to_string())
+            }
+            _ => "{}".to_string(),
+        };
+
+        // For simplicity, the template expects "results" variable to always be
+        // an array.
+        // Use an empty array for the void result, which is used when the
+        // query is an empty string.
+        let result_value = match result {
+            PipelineValues::Void => json!([]),
+            _ => serde_json::to_value(result).unwrap(),
+        };
+
+        let globals = liquid::object!({
+            "results": result_value,
+            "query": query.clone(),
+            "preset": preset.clone(),
+            "tree": tree.clone(),
+            "logs": logs,
+            "SYM_INFO_STR": sym_info_str,
+        });
+
+        let output = templates.query_results.render(&globals)?;
+        Ok((header_map, Html(output)).into_response())
+    } else {
+        Ok((header_map, Json(result)).into_response())
+    }
+}
+
+fn wants_json(headers: &HeaderMap) -> bool {
+    matches!(
+        headers.get(header::ACCEPT).map(|x| x.to_str()),
+        Some(Ok("application/json"))
+    )
+}
+
+/// Build the ETag for a response derived from the given tree revision.  The
+/// query endpoint varies on the "Accept" header, so the representation is part
+/// of the tag.  The tag is weak because we make no promises that the bytes of
+/// the output are stable, just that they are equivalent for the revision.
+fn make_etag(rev: &str, headers: &HeaderMap) -> String {
+    let representation = if wants_json(headers) { "json" } else { "html" };
+    format!("W/\"{}-{}\"", rev, representation)
+}
+
+/// Check the "If-None-Match" header against our ETag using the weak comparison
+/// that is mandated for "If-None-Match".
+fn etag_matches(headers: &HeaderMap, etag: &str) -> bool {
+    let Some(Ok(if_none_match)) = headers.get(header::IF_NONE_MATCH).map(|v| v.to_str()) else {
+        return false;
+    };
+    let opaque = etag.trim_start_matches("W/");
+    if_none_match
+        .split(',')
+        .map(|candidate| candidate.trim())
+        .any(|candidate| candidate == "*" || candidate.trim_start_matches("W/") == opaque)
+}
+
+/// Middleware that implements conditional GET for any route whose first path
+/// segment is the name of a tree that has `commit_info`.  Everything we serve
+/// for a tree is derived from the indexed revision, so the revision is a
+/// sufficient validator.  Debug queries capture logs which are different every
+/// time, so they are excluded.
+pub async fn conditional_get(
+    Extension(local_servers): Extension<LocalServers>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let is_debug = request.uri().query().is_some_and(|q| {
+        q.split('&')
+            .any(|kv| kv == "debug" || kv.starts_with("debug="))
+    });
+    let etag = match request.uri().path().split('/').nth(1) {
+        Some(tree) if request.method() == Method::GET && !is_debug => local_servers
+            .get(tree)
+            .and_then(|server| server.commit_info().ok().flatten())
+            .map(|info| make_etag(&info.rev, request.headers())),
+        _ => None,
+    };
+    let Some(etag) = etag else {
+        return next.run(request).await;
+    };
+
+    if etag_matches(request.headers(), &etag) {
+        return (StatusCode::NOT_MODIFIED, [(header::ETAG, etag)]).into_response();
+    }
+
+    let mut response = next.run(request).await;
+    if response.status() == StatusCode::OK
+        && let Ok(value) = HeaderValue::from_str(&etag)
+    {
+        response.headers_mut().insert(header::ETAG, value);
+    }
+    response
+}
+
+#[test]
+fn test_etag_matches() {
+    let mut headers = HeaderMap::new();
+    let etag = make_etag("abc123", &headers);
+    assert_eq!(etag, "W/\"abc123-html\"");
+    assert!(!etag_matches(&headers, &etag));
+
+    headers.insert(
+        header::IF_NONE_MATCH,
+        HeaderValue::from_static("\"zzz-html\", \"abc123-html\""),
+    );
+    assert!(etag_matches(&headers, &etag));
+
+    headers.insert(header::ACCEPT, HeaderValue::from_static("application/json"));
+    let json_etag = make_etag("abc123", &headers);
+    assert!(!etag_matches(&headers, &json_etag));
+
+    headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("*"));
+    assert!(etag_matches(&headers, &json_etag));
+}
+
+#[tokio::test]
+async fn test_query_errors_are_json() {
+    use axum::body::{Body, to_bytes};
+    use std::fs;
+    use tower::ServiceExt;
+
+    // Just enough of an index for `make_all_local_servers` to open.
+    let dir = std::env::temp_dir().join(format!("web-routes-{}", std::process::id()));
+    let index = dir.join("tests");
+    fs::create_dir_all(&index).unwrap();
+    for name in ["crossref", "crossref-extra", "jumpref", "jumpref-extra"] {
+        fs::write(index.join(name), "").unwrap();
+    }
+    fs::write(index.join("concise-per-file-info.json"), "{}").unwrap();
+    let config_path = dir.join("config.json");
+    let config = json!({
+        "mozsearch_path": concat!(env!("CARGO_MANIFEST_DIR"), "/.."),
+        "config_repo": dir,
+        "default_tree": "tests",
+        "trees": {
+            "tests": {
+                "priority": 100,
+                "on_error": "halt",
+                "cache": "everything",
+                "index_path": index,
+                "files_path": index,
+                "objdir_path": index,
+                "scip_subtrees": {},
+                "codesearch_path": index.join("livegrep.idx"),
+                "codesearch_port": 8080
+            }
+        }
+    });
+    fs::write(&config_path, config.to_string()).unwrap();
+    let local_servers =
+        crate::abstract_server::make_all_local_servers(config_path.to_str().unwrap()).unwrap();
+    let router = pipeline_router().layer(Extension(local_servers));
+
+    for (uri, status, layer, message) in [
+        (
+            "/nope/query/default?q=foo",
+            StatusCode::NOT_FOUND,
+            "DataLayer",
+            "No such tree: nope",
+        ),
+        (
+            "/tests/query/fancy?q=foo",
+            StatusCode::NOT_FOUND,
+            "DataLayer",
+            "No such preset: fancy",
+        ),
+        (
+            "/tests/query/default",
+            StatusCode::BAD_REQUEST,
+            "BadInput",
+            "No 'q' parameter, no results!",
+        ),
+    ] {
+        let request = Request::builder().uri(uri).body(Body::empty()).unwrap();
+        let response = router.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), status, "{}", uri);
+        assert_eq!(
+            response.headers().get(header::CONTENT_TYPE).unwrap(),
+            "application/json"
+        );
+        let body = to_bytes(response.into_body(), usize::MAX).await.unwrap();
+        let body: Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body,
+            json!({ "error": { "kind": "sticky", "layer": layer, "message": message } }),
+            "{}",
+            uri
+        );
+    }
+
+    fs::remove_dir_all(&dir).unwrap();
+}